@@ -0,0 +1,303 @@
+use crate::{
+    cache::SongCache,
+    error::ParseError,
+    library::{read_song_directory, read_song_folder},
+    origin::{check_song_updates, update_songs, SongUpdate},
+    pack::{read_packs, update_pack},
+    song::Song,
+    versions::VersionStore,
+};
+
+use async_std::sync::{Arc, Mutex};
+use serde::Serialize;
+
+use std::path::PathBuf;
+
+/// What a planned update reinstalls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateKind {
+    Song,
+    Pack,
+}
+
+/// A song or pack which has changed at its origin.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlannedUpdate {
+    pub kind: UpdateKind,
+    pub name: String,
+    /// Library root the song or pack is in.
+    pub root: PathBuf,
+    /// Absolute path to the song or pack folder.
+    pub path: PathBuf,
+    /// Url or path the update is installed from.
+    pub url: String,
+    /// Songs whose chart changed, relative to the library root.
+    pub songs: Vec<String>,
+    /// Songs whose chart changed, with the chart hash at the origin.
+    #[serde(skip)]
+    pub changes: Vec<SongUpdate>,
+}
+
+/// Updates found by `plan_updates`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct UpdatePlan {
+    pub updates: Vec<PlannedUpdate>,
+}
+
+impl UpdatePlan {
+    pub fn is_empty(&self) -> bool {
+        self.updates.is_empty()
+    }
+}
+
+/// What `apply_updates` changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct UpdateSummary {
+    /// Names of the updated songs.
+    pub songs: Vec<String>,
+    /// Names of the updated packs.
+    pub packs: Vec<String>,
+    /// Names of the songs and packs which failed to update, with the reason.
+    pub failed: Vec<(String, String)>,
+}
+
+impl UpdateSummary {
+    pub fn is_empty(&self) -> bool {
+        self.songs.is_empty() && self.packs.is_empty() && self.failed.is_empty()
+    }
+}
+
+/// Checks the origin of every song in `roots` and plans the updates.
+///
+/// Changed songs inside a pack with a source update the whole pack, other
+/// songs are updated on their own.
+pub async fn plan_updates(
+    song_cache: Option<Arc<Mutex<SongCache>>>,
+    roots: Vec<PathBuf>,
+) -> Result<UpdatePlan, ParseError> {
+    let mut plan = UpdatePlan::default();
+
+    for root in roots {
+        let songs = read_song_directory(song_cache.clone(), &root).await?;
+        let packs = read_packs(&root).await?;
+
+        let with_origin: Vec<_> = songs.into_iter().filter(|s| s.origin.is_some()).collect();
+        let changed = check_song_updates(with_origin.clone()).await?;
+
+        for change in changed {
+            let song = match with_origin.iter().find(|s| s.path == change.path) {
+                Some(song) => song,
+                None => continue,
+            };
+
+            let pack = packs
+                .iter()
+                .find(|p| p.info.source.is_some() && p.contains(&song.path));
+
+            if let Some(pack) = pack {
+                if let Some(update) = plan.updates.iter_mut().find(|u| u.path == pack.path) {
+                    update.songs.push(song.id.clone());
+                    update.changes.push(change);
+                    continue;
+                }
+
+                plan.updates.push(PlannedUpdate {
+                    kind: UpdateKind::Pack,
+                    name: pack.name().to_owned(),
+                    root: root.clone(),
+                    path: pack.path.clone(),
+                    url: pack.info.source.clone().unwrap_or_default(),
+                    songs: vec![song.id.clone()],
+                    changes: vec![change],
+                });
+            } else {
+                plan.updates.push(PlannedUpdate {
+                    kind: UpdateKind::Song,
+                    name: song_name(song),
+                    root: root.clone(),
+                    path: song.path.clone(),
+                    url: song
+                        .origin
+                        .as_ref()
+                        .map(|o| o.url.clone())
+                        .unwrap_or_default(),
+                    songs: vec![song.id.clone()],
+                    changes: vec![change],
+                });
+            }
+        }
+    }
+
+    log::debug!("planned {} song and pack updates", plan.updates.len());
+
+    Ok(plan)
+}
+
+/// Installs the planned updates.
+///
+/// A failed update is recorded in the summary and doesn't stop the others.
+pub async fn apply_updates(
+    song_cache: Option<Arc<Mutex<SongCache>>>,
+    version_store: Option<Arc<Mutex<VersionStore>>>,
+    plan: UpdatePlan,
+) -> UpdateSummary {
+    let mut summary = UpdateSummary::default();
+
+    for update in plan.updates {
+        let result = match update.kind {
+            UpdateKind::Song => {
+                let song = read_song_folder(&update.root, &update.path);
+                match update_songs(song_cache.clone(), version_store.clone(), vec![song]).await {
+                    Ok(result) => match result.failed.into_iter().next() {
+                        Some((_, reason)) => Err(reason),
+                        None => Ok(()),
+                    },
+                    Err(error) => Err(error.to_string()),
+                }
+            }
+            UpdateKind::Pack => match read_packs(&update.root).await {
+                Ok(packs) => match packs.into_iter().find(|p| p.path == update.path) {
+                    Some(pack) => {
+                        update_pack(song_cache.clone(), version_store.clone(), pack, None)
+                            .await
+                            .map(|_| ())
+                            .map_err(|e| e.to_string())
+                    }
+                    None => Err("Pack no longer exists".to_owned()),
+                },
+                Err(error) => Err(error.to_string()),
+            },
+        };
+
+        match (result, update.kind) {
+            (Ok(_), UpdateKind::Song) => summary.songs.push(update.name),
+            (Ok(_), UpdateKind::Pack) => summary.packs.push(update.name),
+            (Err(error), _) => {
+                log::error!("failed to update {:?}: {}", &update.path, &error);
+                summary.failed.push((update.name, error));
+            }
+        }
+    }
+
+    summary
+}
+
+fn song_name(song: &Song) -> String {
+    match song.artist() {
+        Some(artist) => format!("{} - {}", artist, song.title()),
+        None => song.title().to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::library::read_song_folder;
+    use crate::origin::{chart_hash, write_song_origin, SongOrigin};
+    use crate::pack::install_pack;
+    use crate::versions::{rollback_song, VersionStore};
+
+    use async_std::sync::{Arc, Mutex};
+    use async_std::task;
+    use std::fs::File;
+    use std::io::Write;
+    use std::path::Path;
+
+    fn write_archive(path: &Path, files: &[(&str, &str)]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+
+        for (name, contents) in files {
+            zip.start_file(*name, Default::default()).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_plan_and_apply_updates() {
+        task::block_on(async {
+            let downloads = tempfile::tempdir().unwrap();
+            let root = tempfile::tempdir().unwrap();
+            let store_dir = tempfile::tempdir().unwrap();
+
+            let pack_archive = downloads.path().join("Pack.zip");
+            write_archive(
+                &pack_archive,
+                &[("One/notes.chart", "one"), ("Two/notes.chart", "two")],
+            );
+            install_pack(
+                None,
+                None,
+                pack_archive.to_string_lossy().to_string(),
+                root.path().to_owned(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+            let song_archive = downloads.path().join("Single.zip");
+            write_archive(&song_archive, &[("notes.chart", "single")]);
+
+            let single = root.path().join("Single");
+            std::fs::create_dir_all(&single).unwrap();
+            std::fs::write(single.join("notes.chart"), "single").unwrap();
+            write_song_origin(
+                &single,
+                &SongOrigin {
+                    url: song_archive.to_string_lossy().to_string(),
+                    hash: chart_hash(&single),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+            let roots = vec![root.path().to_owned()];
+            assert!(plan_updates(None, roots.clone()).await.unwrap().is_empty());
+
+            write_archive(
+                &pack_archive,
+                &[("One/notes.chart", "1"), ("Two/notes.chart", "2")],
+            );
+            write_archive(&song_archive, &[("notes.chart", "new")]);
+
+            let plan = plan_updates(None, roots.clone()).await.unwrap();
+            assert_eq!(plan.updates.len(), 2);
+
+            let pack = plan
+                .updates
+                .iter()
+                .find(|u| u.kind == UpdateKind::Pack)
+                .unwrap();
+            assert_eq!(pack.songs.len(), 2);
+
+            let store = VersionStore::open(store_dir.path().to_owned(), u64::MAX).unwrap();
+            let store = Arc::new(Mutex::new(store));
+
+            let summary = apply_updates(None, Some(store.clone()), plan).await;
+            assert_eq!(summary.packs, vec!["Pack"]);
+            assert_eq!(summary.songs, vec!["Single"]);
+            assert!(summary.failed.is_empty());
+
+            assert_eq!(
+                std::fs::read_to_string(single.join("notes.chart")).unwrap(),
+                "new"
+            );
+            assert!(plan_updates(None, roots.clone()).await.unwrap().is_empty());
+
+            // A rolled back song isn't updated again.
+            let song = read_song_folder(root.path(), &single);
+            let version = store.lock().await.versions(&song)[0].id.clone();
+            rollback_song(None, store, song, version).await.unwrap();
+
+            assert_eq!(
+                std::fs::read_to_string(single.join("notes.chart")).unwrap(),
+                "single"
+            );
+            assert!(plan_updates(None, roots).await.unwrap().is_empty());
+        });
+    }
+}
@@ -1,30 +1,61 @@
 use crate::error::FilesystemError;
 use crate::fs::backup::{Backup, ZipBackup, ZstdBackup};
+use crate::fs::encryption::{is_encrypted, open_archive, SeekableArchive, ENCRYPTED_EXTENSION};
 use crate::repository::CompressionFormat;
+use crate::s3::{list_remote_backups, S3Destination};
+use crate::snapshot::{latest_snapshot_time, read_hash, SnapshotStore, SNAPSHOT_DIR};
 
-use chrono::{Local, NaiveDateTime};
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 
 /// Creates a .zip archive from the list of source folders and
-/// saves it to the dest folder.
+/// saves it to the dest folder. The archive is encrypted with `passphrase`
+/// if one is given.
+///
+/// An archive which fails verification is removed again, so it's never
+/// mistaken for a backup.
 pub async fn backup_folders(
     src_folders: Vec<BackupFolder>,
     mut dest: PathBuf,
     compression: CompressionFormat,
     zstd_level: i32,
+    passphrase: Option<String>,
 ) -> Result<NaiveDateTime, FilesystemError> {
     let now = Local::now();
 
-    dest.push(format!(
+    let mut name = format!(
         "ajour_backup_{}.{}",
         now.format("%Y-%m-%d_%H-%M-%S"),
         compression.file_ext(),
-    ));
+    );
+    if passphrase.is_some() {
+        name.push('.');
+        name.push_str(ENCRYPTED_EXTENSION);
+    }
+    dest.push(name);
 
     match compression {
-        CompressionFormat::Zip => ZipBackup::new(src_folders, &dest).backup()?,
-        CompressionFormat::Zstd => ZstdBackup::new(src_folders, &dest, zstd_level).backup()?,
+        CompressionFormat::Zip => {
+            ZipBackup::new(src_folders, &dest, passphrase.clone()).backup()?
+        }
+        CompressionFormat::Zstd => {
+            ZstdBackup::new(src_folders, &dest, zstd_level, passphrase.clone()).backup()?
+        }
+    }
+
+    // Read the archive back to make sure it can be restored.
+    let verification = verify_backup(dest.clone(), passphrase).await?;
+    if !verification.is_ok() {
+        remove_archive(&dest)?;
+
+        return Err(FilesystemError::CorruptBackup {
+            path: dest,
+            issue: verification.issues[0].to_string(),
+        });
     }
 
     // Won't fail since we pass it the correct format
@@ -33,27 +64,699 @@ pub async fn backup_folders(
     Ok(as_of)
 }
 
-/// Finds the latest archive in the supplied backup folder and returns
-/// the datetime it was saved
-pub async fn latest_backup(backup_dir: PathBuf) -> Option<NaiveDateTime> {
+/// Name of the manifest at the root of every backup archive.
+pub const MANIFEST_NAME: &str = "chmon_manifest.json";
+
+/// Lists the files in a backup archive, for verifying it.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct BackupManifest {
+    pub files: Vec<ManifestFile>,
+}
+
+/// A file in a backup archive.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ManifestFile {
+    /// Path inside the archive, with `/` separators.
+    pub path: String,
+    pub size: u64,
+    /// Lowercase hex encoded SHA-256 checksum of the contents.
+    pub hash: String,
+}
+
+/// A problem found while verifying a backup archive.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub enum VerifyIssue {
+    /// The archive couldn't be read to the end.
+    Unreadable {
+        error: String,
+    },
+    /// The archive holds another number of files than the manifest lists.
+    EntryCount {
+        expected: usize,
+        found: usize,
+    },
+    Missing {
+        path: String,
+    },
+    Size {
+        path: String,
+        expected: u64,
+        found: u64,
+    },
+    Checksum {
+        path: String,
+    },
+}
+
+impl std::fmt::Display for VerifyIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifyIssue::Unreadable { error } => write!(f, "archive can't be read: {}", error),
+            VerifyIssue::EntryCount { expected, found } => {
+                write!(f, "expected {} files, found {}", expected, found)
+            }
+            VerifyIssue::Missing { path } => write!(f, "{} is missing", path),
+            VerifyIssue::Size {
+                path,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} is {} bytes instead of {} bytes",
+                path, found, expected
+            ),
+            VerifyIssue::Checksum { path } => write!(f, "{} has a wrong checksum", path),
+        }
+    }
+}
+
+/// Result of verifying a backup archive, saved next to it.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Verification {
+    pub verified_at: NaiveDateTime,
+    /// Number of files read from the archive.
+    pub files: usize,
+    /// False for archives made before manifests were embedded, which can
+    /// only be checked for being readable.
+    pub has_manifest: bool,
+    pub issues: Vec<VerifyIssue>,
+}
+
+impl Verification {
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Returns the path the verification of an archive is saved to.
+fn verification_path(archive: &Path) -> PathBuf {
+    let mut name = archive.file_name().unwrap_or_default().to_owned();
+    name.push(".verify.json");
+
+    archive.with_file_name(name)
+}
+
+/// Returns the saved verification of an archive, if it was verified.
+fn load_verification(archive: &Path) -> Option<Verification> {
+    let contents = std::fs::read(verification_path(archive)).ok()?;
+
+    serde_json::from_slice(&contents).ok()
+}
+
+/// Removes an archive and its saved verification.
+fn remove_archive(archive: &Path) -> Result<(), FilesystemError> {
+    std::fs::remove_file(archive)?;
+
+    let verification = verification_path(archive);
+    if verification.exists() {
+        std::fs::remove_file(verification)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the compression format of an archive from its file name.
+pub(crate) fn archive_format(path: &Path) -> CompressionFormat {
+    if path
+        .to_string_lossy()
+        .trim_end_matches(&format!(".{}", ENCRYPTED_EXTENSION))
+        .ends_with(".tar.zst")
+    {
+        CompressionFormat::Zstd
+    } else {
+        CompressionFormat::Zip
+    }
+}
+
+/// Reads every file in the archive, returning their size and checksum by
+/// path and the embedded manifest. Stops at the first error.
+fn read_archive_files(
+    path: &Path,
+    format: CompressionFormat,
+    passphrase: Option<&str>,
+    files: &mut HashMap<String, (u64, String)>,
+    manifest: &mut Option<BackupManifest>,
+) -> Result<(), FilesystemError> {
+    let mut add = |name: String, reader: &mut dyn Read| -> Result<(), FilesystemError> {
+        if name == MANIFEST_NAME {
+            let mut contents = vec![];
+            reader.read_to_end(&mut contents)?;
+            *manifest = Some(serde_json::from_slice(&contents)?);
+        } else {
+            let (hash, size) = read_hash(reader)?;
+            files.insert(name, (size, hash));
+        }
+
+        Ok(())
+    };
+
+    match format {
+        CompressionFormat::Zip => {
+            let mut zip = zip::ZipArchive::new(SeekableArchive::open(path, passphrase)?)?;
+
+            for idx in 0..zip.len() {
+                let mut file = zip.by_index(idx)?;
+
+                if file.is_file() {
+                    let name = file.name().to_owned();
+                    add(name, &mut file)?;
+                }
+            }
+        }
+        CompressionFormat::Zstd => {
+            let decoder = zstd::stream::read::Decoder::new(open_archive(path, passphrase)?)?;
+            let mut tar = tar::Archive::new(decoder);
+
+            for entry in tar.entries()? {
+                let mut entry = entry?;
+
+                if entry.header().entry_type().is_file() {
+                    let name = entry.path()?.to_string_lossy().replace('\\', "/");
+                    add(name, &mut entry)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads a backup archive back and checks its files against the embedded
+/// manifest. The result is saved next to the archive. Encrypted archives
+/// need the passphrase they were encrypted with.
+pub async fn verify_backup(
+    path: PathBuf,
+    passphrase: Option<String>,
+) -> Result<Verification, FilesystemError> {
+    if !path.is_file() {
+        return Err(FilesystemError::FileDoesntExist { path });
+    }
+    if is_encrypted(&path) && passphrase.is_none() {
+        return Err(FilesystemError::PassphraseRequired { path });
+    }
+
+    let mut files = HashMap::new();
+    let mut manifest = None;
+    let mut issues = vec![];
+
+    match read_archive_files(
+        &path,
+        archive_format(&path),
+        passphrase.as_deref(),
+        &mut files,
+        &mut manifest,
+    ) {
+        // A wrong passphrase doesn't mean the archive is corrupt.
+        Err(FilesystemError::WrongPassphrase) => return Err(FilesystemError::WrongPassphrase),
+        Err(error) => issues.push(VerifyIssue::Unreadable {
+            error: error.to_string(),
+        }),
+        Ok(()) => {}
+    }
+
+    if let (Some(manifest), true) = (&manifest, issues.is_empty()) {
+        if manifest.files.len() != files.len() {
+            issues.push(VerifyIssue::EntryCount {
+                expected: manifest.files.len(),
+                found: files.len(),
+            });
+        }
+
+        for file in manifest.files.iter() {
+            match files.get(&file.path) {
+                None => issues.push(VerifyIssue::Missing {
+                    path: file.path.clone(),
+                }),
+                Some((size, _)) if *size != file.size => issues.push(VerifyIssue::Size {
+                    path: file.path.clone(),
+                    expected: file.size,
+                    found: *size,
+                }),
+                Some((_, hash)) if hash != &file.hash => issues.push(VerifyIssue::Checksum {
+                    path: file.path.clone(),
+                }),
+                Some(_) => {}
+            }
+        }
+    }
+
+    let verification = Verification {
+        verified_at: Local::now().naive_local(),
+        files: files.len(),
+        has_manifest: manifest.is_some(),
+        issues,
+    };
+
+    std::fs::write(
+        verification_path(&path),
+        serde_json::to_vec_pretty(&verification)?,
+    )?;
+
+    log::debug!(
+        "verified backup {:?}: {} files, {} issues",
+        &path,
+        verification.files,
+        verification.issues.len()
+    );
+
+    Ok(verification)
+}
+
+/// When the latest backups were saved. Archives and song library snapshots
+/// are scheduled separately, so each is due on its own.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LatestBackups {
+    pub archive: Option<NaiveDateTime>,
+    pub snapshot: Option<NaiveDateTime>,
+}
+
+impl LatestBackups {
+    /// Returns when any backup was last saved.
+    pub fn any(&self) -> Option<NaiveDateTime> {
+        self.archive.max(self.snapshot)
+    }
+}
+
+/// Finds the latest archive and song library snapshot in the supplied backup
+/// folder, counting archives uploaded to `remote` if it's configured, and
+/// returns the datetimes they were saved. Archives which failed verification
+/// don't count.
+pub async fn latest_backups(backup_dir: PathBuf, remote: S3Destination) -> LatestBackups {
+    let mut latest = LatestBackups {
+        archive: list_backups(&backup_dir)
+            .into_iter()
+            .rev()
+            .find(|archive| !archive.is_corrupt())
+            .map(|archive| archive.as_of),
+        snapshot: latest_snapshot_time(&backup_dir),
+    };
+
+    if !remote.is_configured() {
+        return latest;
+    }
+
+    // The local backups are still meaningful when the bucket can't be reached.
+    match list_remote_backups(remote).await {
+        Ok(mut backups) => {
+            latest.archive = latest.archive.max(backups.pop().map(|backup| backup.as_of));
+        }
+        Err(error) => log::error!("listing remote backups failed: {}", error),
+    }
+
+    latest
+}
+
+/// A backup archive saved on the filesystem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupArchive {
+    pub path: PathBuf,
+    pub as_of: NaiveDateTime,
+    pub format: CompressionFormat,
+    /// Size of the archive in bytes.
+    pub size: u64,
+    /// Saved result of the last verification.
+    pub verification: Option<Verification>,
+}
+
+impl BackupArchive {
+    /// Returns true if the archive is encrypted and needs a passphrase to be
+    /// read.
+    pub fn is_encrypted(&self) -> bool {
+        is_encrypted(&self.path)
+    }
+
+    /// Returns true if the last verification found issues.
+    pub fn is_corrupt(&self) -> bool {
+        matches!(&self.verification, Some(verification) if !verification.is_ok())
+    }
+}
+
+impl std::fmt::Display for BackupArchive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({}{})",
+            self.as_of.format("%Y-%m-%d %H:%M:%S"),
+            self.format,
+            if self.is_encrypted() {
+                ", encrypted"
+            } else {
+                ""
+            }
+        )
+    }
+}
+
+/// Returns the archives in the supplied backup folder, oldest first.
+pub fn list_backups(backup_dir: &Path) -> Vec<BackupArchive> {
     let zip_pattern = format!("{}/ajour_backup_[0-9][0-9][0-9][0-9]-[0-9][0-9]-[0-9][0-9]_[0-9][0-9]-[0-9][0-9]-[0-9][0-9].zip", backup_dir.display());
     let zstd_pattern = format!("{}/ajour_backup_[0-9][0-9][0-9][0-9]-[0-9][0-9]-[0-9][0-9]_[0-9][0-9]-[0-9][0-9]-[0-9][0-9].tar.zst", backup_dir.display());
 
     let mut backups = vec![];
 
-    for path in glob::glob(&zip_pattern)
-        .unwrap()
-        .chain(glob::glob(&zstd_pattern).unwrap())
-        .flatten()
+    // Encrypted archives have `.enc` appended to the name.
+    let patterns = [
+        (zip_pattern.clone(), CompressionFormat::Zip),
+        (zstd_pattern.clone(), CompressionFormat::Zstd),
+        (
+            format!("{}.{}", zip_pattern, ENCRYPTED_EXTENSION),
+            CompressionFormat::Zip,
+        ),
+        (
+            format!("{}.{}", zstd_pattern, ENCRYPTED_EXTENSION),
+            CompressionFormat::Zstd,
+        ),
+    ];
+
+    for (path, format) in patterns.iter().flat_map(|(pattern, format)| {
+        glob::glob(pattern)
+            .unwrap()
+            .flatten()
+            .map(move |path| (path, *format))
+    }) {
+        if let Ok(archive) = Archive::try_from(path.clone()) {
+            let size = path.metadata().map(|m| m.len()).unwrap_or_default();
+
+            let verification = load_verification(&path);
+
+            backups.push(BackupArchive {
+                path,
+                as_of: archive.as_of,
+                format,
+                size,
+                verification,
+            });
+        }
+    }
+
+    backups.sort_by_key(|archive| archive.as_of);
+    backups
+}
+
+/// Rules deciding which backup archives and song library snapshots are kept.
+/// A backup is kept if any of `keep_last`, `keep_daily` or `keep_weekly`
+/// keeps it, everything is kept when none of them are set. `max_size_mb` then
+/// removes the oldest of the kept archives until the rest fit. It doesn't
+/// apply to snapshots, which share most of their files. The latest archive
+/// and the latest snapshot are always kept. Archives which failed
+/// verification don't count towards the rules and are removed.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BackupRetention {
+    /// Keep the latest N archives.
+    pub keep_last: Option<usize>,
+    /// Keep the latest archive of each of the last N days.
+    pub keep_daily: Option<u32>,
+    /// Keep the latest archive of each of the last N weeks.
+    pub keep_weekly: Option<u32>,
+    /// Maximum size of all archives together, in megabytes.
+    pub max_size_mb: Option<u64>,
+}
+
+impl BackupRetention {
+    /// Returns true if any rule is set, otherwise nothing is ever pruned.
+    pub fn is_enabled(&self) -> bool {
+        self.keep_last.is_some()
+            || self.keep_daily.is_some()
+            || self.keep_weekly.is_some()
+            || self.max_size_mb.is_some()
+    }
+}
+
+/// When backups are made automatically.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BackupSchedule {
+    /// Back up when CHmon starts.
+    #[serde(default)]
+    pub on_startup: bool,
+
+    /// Days between two backups, 1 backs up daily and 7 weekly.
+    #[serde(default)]
+    pub interval_days: Option<u32>,
+
+    /// Back up before updating or deleting several songs or addons at once.
+    #[serde(default)]
+    pub before_bulk_changes: bool,
+}
+
+impl BackupSchedule {
+    pub const DAILY: u32 = 1;
+    pub const WEEKLY: u32 = 7;
+
+    /// Returns true if a backup interval is set and it passed since
+    /// `last_backup`.
+    pub fn is_due(&self, last_backup: Option<NaiveDateTime>, now: NaiveDateTime) -> bool {
+        match (self.interval_days, last_backup) {
+            (Some(days), Some(last_backup)) => {
+                now - last_backup >= Duration::days(days.max(1) as i64)
+            }
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+}
+
+/// Returns the Monday of the week `date` is in.
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+/// Returns the archives `retention` would remove as of `now`, oldest first.
+/// `archives` are sorted oldest first, as returned by `list_backups`.
+pub fn plan_prune(
+    archives: &[BackupArchive],
+    retention: &BackupRetention,
+    now: NaiveDateTime,
+) -> Vec<BackupArchive> {
+    if !retention.is_enabled() {
+        return vec![];
+    }
+
+    // Archives which can't be restored aren't kept in place of good ones.
+    let verified = archives
+        .iter()
+        .filter(|archive| !archive.is_corrupt())
+        .collect::<Vec<_>>();
+    let backups = verified
+        .iter()
+        .map(|archive| (archive.as_of, archive.size))
+        .collect::<Vec<_>>();
+
+    let kept = verified
+        .iter()
+        .zip(kept_backups(&backups, retention, now))
+        .filter(|(_, keep)| *keep)
+        .map(|(archive, _)| archive.path.as_path())
+        .collect::<HashSet<_>>();
+
+    archives
+        .iter()
+        .filter(|archive| !kept.contains(archive.path.as_path()))
+        .cloned()
+        .collect()
+}
+
+/// Returns which of `backups`, given by when they were made and their size
+/// and sorted oldest first, `retention` keeps as of `now`.
+fn kept_backups(
+    backups: &[(NaiveDateTime, u64)],
+    retention: &BackupRetention,
+    now: NaiveDateTime,
+) -> Vec<bool> {
+    if !retention.is_enabled() || backups.is_empty() {
+        return vec![true; backups.len()];
+    }
+
+    let newest_first = backups.iter().rev().collect::<Vec<_>>();
+
+    let count_rules = retention.keep_last.is_some()
+        || retention.keep_daily.is_some()
+        || retention.keep_weekly.is_some();
+    let mut keep = vec![!count_rules; newest_first.len()];
+
+    if let Some(last) = retention.keep_last {
+        keep.iter_mut().take(last).for_each(|keep| *keep = true);
+    }
+
+    if let Some(days) = retention.keep_daily {
+        let mut seen = HashSet::new();
+
+        for (idx, (as_of, _)) in newest_first.iter().enumerate() {
+            let date = as_of.date();
+
+            if (now.date() - date).num_days() < days as i64 && seen.insert(date) {
+                keep[idx] = true;
+            }
+        }
+    }
+
+    if let Some(weeks) = retention.keep_weekly {
+        let mut seen = HashSet::new();
+
+        for (idx, (as_of, _)) in newest_first.iter().enumerate() {
+            let week = week_start(as_of.date());
+
+            if (week_start(now.date()) - week).num_days() < weeks as i64 * 7 && seen.insert(week) {
+                keep[idx] = true;
+            }
+        }
+    }
+
+    keep[0] = true;
+
+    if let Some(max_size_mb) = retention.max_size_mb {
+        let max_size = max_size_mb * 1_000_000;
+        let mut size = 0;
+        let mut full = false;
+
+        for (idx, (_, backup_size)) in newest_first.iter().enumerate() {
+            if !keep[idx] {
+                continue;
+            }
+
+            if idx > 0 && (full || size + backup_size > max_size) {
+                // Once a backup doesn't fit, all older ones go as well.
+                full = true;
+                keep[idx] = false;
+            } else {
+                size += backup_size;
+            }
+        }
+    }
+
+    keep.reverse();
+    keep
+}
+
+/// A song library snapshot removed by pruning.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrunedSnapshot {
+    pub id: String,
+    pub as_of: NaiveDateTime,
+}
+
+impl std::fmt::Display for PrunedSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (Songs)", self.as_of.format("%Y-%m-%d %H:%M:%S"))
+    }
+}
+
+/// Backups the retention rules remove.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PrunedBackups {
+    pub archives: Vec<BackupArchive>,
+    pub snapshots: Vec<PrunedSnapshot>,
+    /// Size of the snapshot files no other snapshot references, in bytes.
+    pub snapshot_size: u64,
+}
+
+impl PrunedBackups {
+    /// Returns the number of archives and snapshots.
+    pub fn len(&self) -> usize {
+        self.archives.len() + self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the size freed in bytes.
+    pub fn size(&self) -> u64 {
+        self.archives
+            .iter()
+            .map(|archive| archive.size)
+            .sum::<u64>()
+            + self.snapshot_size
+    }
+}
+
+/// Returns the archives and snapshots in the backup folder `retention` would
+/// remove as of `now`.
+fn plan_backup_prune(
+    backup_dir: &Path,
+    retention: &BackupRetention,
+    now: NaiveDateTime,
+) -> Result<PrunedBackups, FilesystemError> {
+    let mut pruned = PrunedBackups {
+        archives: plan_prune(&list_backups(backup_dir), retention, now),
+        ..Default::default()
+    };
+
+    if !backup_dir.join(SNAPSHOT_DIR).is_dir() {
+        return Ok(pruned);
+    }
+
+    let snapshots = SnapshotStore::in_backup_directory(backup_dir)?.snapshots()?;
+    let backups = snapshots
+        .iter()
+        .map(|snapshot| (snapshot.created.with_timezone(&Local).naive_local(), 0))
+        .collect::<Vec<_>>();
+
+    let mut kept_files = HashSet::new();
+    let mut removed_files = HashMap::new();
+
+    for ((snapshot, (as_of, _)), keep) in snapshots
+        .iter()
+        .zip(backups.iter())
+        .zip(kept_backups(&backups, retention, now))
     {
-        if let Ok(archive) = Archive::try_from(path) {
-            backups.push(archive.as_of);
+        let files = snapshot.roots.iter().flat_map(|root| root.files.iter());
+
+        if keep {
+            kept_files.extend(files.map(|file| file.hash.as_str()));
+        } else {
+            removed_files.extend(files.map(|file| (file.hash.as_str(), file.size)));
+
+            pruned.snapshots.push(PrunedSnapshot {
+                id: snapshot.id.clone(),
+                as_of: *as_of,
+            });
         }
     }
 
-    // Apparently NaiveDateTime sorts in Desc order by default, no need to reverse
-    backups.sort();
-    backups.pop()
+    pruned.snapshot_size = removed_files
+        .iter()
+        .filter(|(hash, _)| !kept_files.contains(*hash))
+        .map(|(_, size)| size)
+        .sum();
+
+    Ok(pruned)
+}
+
+/// Returns the archives and snapshots in the backup folder `retention` would
+/// remove.
+pub async fn preview_prune(
+    backup_dir: PathBuf,
+    retention: BackupRetention,
+) -> Result<PrunedBackups, FilesystemError> {
+    plan_backup_prune(&backup_dir, &retention, Local::now().naive_local())
+}
+
+/// Removes the archives and snapshots in the backup folder `retention`
+/// doesn't keep, and the snapshot files only they referenced, and returns
+/// them.
+pub async fn prune_backups(
+    backup_dir: PathBuf,
+    retention: BackupRetention,
+) -> Result<PrunedBackups, FilesystemError> {
+    let mut pruned = plan_backup_prune(&backup_dir, &retention, Local::now().naive_local())?;
+
+    for archive in pruned.archives.iter() {
+        remove_archive(&archive.path)?;
+
+        log::debug!("pruned backup {:?}", &archive.path);
+    }
+
+    if !pruned.snapshots.is_empty() {
+        let ids = pruned
+            .snapshots
+            .iter()
+            .map(|snapshot| snapshot.id.clone())
+            .collect::<Vec<_>>();
+
+        pruned.snapshot_size = SnapshotStore::in_backup_directory(&backup_dir)?.remove(&ids)?;
+    }
+
+    Ok(pruned)
 }
 
 /// Specifies a folder that we want backed up. `prefix` will get stripped out of
@@ -74,7 +777,7 @@ impl BackupFolder {
 
 /// Metadata for our archive saved on the filesystem. Converted from a `PathBuf` with
 /// the correct naming convention
-struct Archive {
+pub(crate) struct Archive {
     pub as_of: NaiveDateTime,
 }
 
@@ -101,3 +804,366 @@ impl TryFrom<PathBuf> for Archive {
         Ok(Archive { as_of })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn archive(as_of: &str, size: u64) -> BackupArchive {
+        BackupArchive {
+            path: PathBuf::from(format!("{}.zip", as_of)),
+            as_of: NaiveDateTime::parse_from_str(as_of, "%Y-%m-%d %H:%M").unwrap(),
+            format: CompressionFormat::Zip,
+            size,
+            verification: None,
+        }
+    }
+
+    fn corrupt(as_of: NaiveDateTime) -> Verification {
+        Verification {
+            verified_at: as_of,
+            files: 0,
+            has_manifest: true,
+            issues: vec![VerifyIssue::Checksum {
+                path: "Songs/One/notes.chart".to_owned(),
+            }],
+        }
+    }
+
+    fn pruned(archives: &[BackupArchive], retention: BackupRetention) -> Vec<String> {
+        let now = NaiveDateTime::parse_from_str("2021-06-16 12:00", "%Y-%m-%d %H:%M").unwrap();
+
+        plan_prune(archives, &retention, now)
+            .into_iter()
+            .map(|archive| archive.as_of.format("%Y-%m-%d %H:%M").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_plan_prune() {
+        // 2021-06-16 is a Wednesday.
+        let archives = vec![
+            archive("2021-05-30 10:00", 100),
+            archive("2021-06-02 10:00", 100),
+            archive("2021-06-09 10:00", 100),
+            archive("2021-06-14 10:00", 100),
+            archive("2021-06-15 09:00", 100),
+            archive("2021-06-15 18:00", 100),
+            archive("2021-06-16 08:00", 100),
+        ];
+
+        assert!(pruned(&archives, BackupRetention::default()).is_empty());
+
+        let keep_last = BackupRetention {
+            keep_last: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(pruned(&archives, keep_last).len(), 5);
+
+        let keep_daily = BackupRetention {
+            keep_daily: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(
+            pruned(&archives, keep_daily),
+            vec![
+                "2021-05-30 10:00",
+                "2021-06-02 10:00",
+                "2021-06-09 10:00",
+                "2021-06-15 09:00"
+            ]
+        );
+
+        let keep_weekly = BackupRetention {
+            keep_weekly: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            pruned(&archives, keep_weekly),
+            vec![
+                "2021-05-30 10:00",
+                "2021-06-02 10:00",
+                "2021-06-14 10:00",
+                "2021-06-15 09:00",
+                "2021-06-15 18:00"
+            ]
+        );
+
+        // The latest archive is kept even if it's too large by itself.
+        let max_size = BackupRetention {
+            max_size_mb: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(pruned(&archives, max_size).len(), 6);
+
+        let combined = BackupRetention {
+            keep_last: Some(1),
+            keep_weekly: Some(4),
+            max_size_mb: Some(1),
+            ..Default::default()
+        };
+        let archives = archives
+            .into_iter()
+            .map(|archive| BackupArchive {
+                size: 400_000,
+                ..archive
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pruned(&archives, combined),
+            vec![
+                "2021-05-30 10:00",
+                "2021-06-02 10:00",
+                "2021-06-14 10:00",
+                "2021-06-15 09:00",
+                "2021-06-15 18:00"
+            ]
+        );
+
+        // A corrupt latest archive is removed instead of the one before it.
+        let mut archives = archives;
+        let latest = archives.last_mut().unwrap();
+        latest.verification = Some(corrupt(latest.as_of));
+
+        assert!(pruned(&archives, BackupRetention::default()).is_empty());
+        assert_eq!(
+            pruned(&archives, keep_last),
+            vec![
+                "2021-05-30 10:00",
+                "2021-06-02 10:00",
+                "2021-06-09 10:00",
+                "2021-06-14 10:00",
+                "2021-06-16 08:00"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_backup_schedule_is_due() {
+        let now = NaiveDateTime::parse_from_str("2021-06-16 12:00", "%Y-%m-%d %H:%M").unwrap();
+        let mut schedule = BackupSchedule::default();
+
+        assert!(!schedule.is_due(None, now));
+
+        schedule.interval_days = Some(BackupSchedule::DAILY);
+        assert!(schedule.is_due(None, now));
+        assert!(!schedule.is_due(Some(now - Duration::hours(23)), now));
+        assert!(schedule.is_due(Some(now - Duration::hours(24)), now));
+
+        schedule.interval_days = Some(BackupSchedule::WEEKLY);
+        assert!(!schedule.is_due(Some(now - Duration::days(6)), now));
+        assert!(schedule.is_due(Some(now - Duration::days(8)), now));
+    }
+
+    #[test]
+    fn test_latest_backups() {
+        let backup_dir = tempfile::tempdir().unwrap();
+        let latest = |dir: &Path| {
+            async_std::task::block_on(latest_backups(dir.to_owned(), S3Destination::default()))
+        };
+
+        assert_eq!(latest(backup_dir.path()), LatestBackups::default());
+
+        std::fs::write(
+            backup_dir
+                .path()
+                .join("ajour_backup_2021-06-01_10-00-00.tar.zst"),
+            b"backup",
+        )
+        .unwrap();
+        assert_eq!(
+            latest(backup_dir.path()).archive.unwrap().to_string(),
+            "2021-06-01 10:00:00"
+        );
+        assert_eq!(latest(backup_dir.path()).snapshot, None);
+
+        // An archive which failed verification isn't a backup.
+        let corrupt_archive = backup_dir
+            .path()
+            .join("ajour_backup_2021-06-03_10-00-00.tar.zst");
+        std::fs::write(&corrupt_archive, b"backup").unwrap();
+        std::fs::write(
+            verification_path(&corrupt_archive),
+            serde_json::to_vec(&corrupt(Local::now().naive_local())).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            latest(backup_dir.path()).archive.unwrap().to_string(),
+            "2021-06-01 10:00:00"
+        );
+
+        // Song library snapshots are tracked on their own.
+        let manifests = backup_dir.path().join("chmon_songs").join("snapshots");
+        std::fs::create_dir_all(&manifests).unwrap();
+        std::fs::write(manifests.join("2021-06-02_08-30-00_1.yml"), b"").unwrap();
+
+        let latest = latest(backup_dir.path());
+        assert_eq!(latest.archive.unwrap().to_string(), "2021-06-01 10:00:00");
+        assert_eq!(latest.snapshot.unwrap().to_string(), "2021-06-02 08:30:00");
+        assert_eq!(latest.any(), latest.snapshot);
+    }
+
+    #[test]
+    fn test_verify_backup() {
+        let backup_dir = tempfile::tempdir().unwrap();
+        let source = tempfile::tempdir().unwrap();
+
+        let folder = source.path().join("WTF");
+        std::fs::create_dir_all(folder.join("Account")).unwrap();
+        std::fs::write(folder.join("Config.wtf"), b"SET a 1").unwrap();
+        std::fs::write(folder.join("Account").join("bindings.wtf"), b"bind").unwrap();
+
+        for format in CompressionFormat::ALL.iter() {
+            let as_of = async_std::task::block_on(backup_folders(
+                vec![BackupFolder::new(&folder, source.path())],
+                backup_dir.path().to_owned(),
+                *format,
+                3,
+                None,
+            ))
+            .unwrap();
+
+            let archive = list_backups(backup_dir.path())
+                .into_iter()
+                .find(|archive| archive.as_of == as_of && archive.format == *format)
+                .unwrap();
+
+            // The verification made after the backup is saved next to it.
+            let verification = archive.verification.unwrap();
+            assert!(verification.is_ok());
+            assert!(verification.has_manifest);
+            assert_eq!(verification.files, 2);
+
+            // Archives have a timestamp with second precision.
+            std::thread::sleep(std::time::Duration::from_millis(1100));
+        }
+
+        // A manifest which doesn't match the files.
+        let corrupt = backup_dir
+            .path()
+            .join("ajour_backup_2021-06-01_10-00-00.zip");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&corrupt).unwrap());
+        let options = zip::write::FileOptions::default();
+        zip.start_file("WTF/Config.wtf", options).unwrap();
+        std::io::Write::write_all(&mut zip, b"SET a 2").unwrap();
+        zip.start_file(MANIFEST_NAME, options).unwrap();
+        let manifest = BackupManifest {
+            files: vec![
+                ManifestFile {
+                    path: "WTF/Config.wtf".to_owned(),
+                    size: 7,
+                    hash: read_hash(&b"SET a 1"[..]).unwrap().0,
+                },
+                ManifestFile {
+                    path: "WTF/Account/bindings.wtf".to_owned(),
+                    size: 4,
+                    hash: read_hash(&b"bind"[..]).unwrap().0,
+                },
+            ],
+        };
+        std::io::Write::write_all(&mut zip, &serde_json::to_vec(&manifest).unwrap()).unwrap();
+        zip.finish().unwrap();
+
+        let verification = async_std::task::block_on(verify_backup(corrupt, None)).unwrap();
+        assert_eq!(
+            verification.issues,
+            vec![
+                VerifyIssue::EntryCount {
+                    expected: 2,
+                    found: 1
+                },
+                VerifyIssue::Checksum {
+                    path: "WTF/Config.wtf".to_owned()
+                },
+                VerifyIssue::Missing {
+                    path: "WTF/Account/bindings.wtf".to_owned()
+                },
+            ]
+        );
+
+        // A truncated archive.
+        let truncated = backup_dir
+            .path()
+            .join("ajour_backup_2021-06-02_10-00-00.tar.zst");
+        let zstd = list_backups(backup_dir.path())
+            .into_iter()
+            .find(|archive| archive.format == CompressionFormat::Zstd)
+            .unwrap();
+        let contents = std::fs::read(&zstd.path).unwrap();
+        std::fs::write(&truncated, &contents[..contents.len() / 2]).unwrap();
+
+        let verification = async_std::task::block_on(verify_backup(truncated, None)).unwrap();
+        assert!(matches!(
+            verification.issues[..],
+            [VerifyIssue::Unreadable { .. }]
+        ));
+    }
+
+    #[test]
+    fn test_prune_backups() {
+        let backup_dir = tempfile::tempdir().unwrap();
+
+        for name in [
+            "ajour_backup_2021-06-01_10-00-00.zip",
+            "ajour_backup_2021-06-02_10-00-00.tar.zst",
+            "ajour_backup_2021-06-03_10-00-00.zip",
+            "notes.txt",
+        ]
+        .iter()
+        {
+            std::fs::write(backup_dir.path().join(name), b"backup").unwrap();
+        }
+
+        let retention = BackupRetention {
+            keep_last: Some(1),
+            ..Default::default()
+        };
+
+        // Two snapshots sharing a file, the older one also has a file the
+        // newer one doesn't.
+        let library = tempfile::tempdir().unwrap();
+        let roots = [library.path().join("Songs")];
+        std::fs::create_dir_all(roots[0].join("One")).unwrap();
+        std::fs::write(roots[0].join("One/song.ini"), b"kept").unwrap();
+        std::fs::write(roots[0].join("One/notes.chart"), b"removed").unwrap();
+
+        let store = SnapshotStore::in_backup_directory(backup_dir.path()).unwrap();
+        store.create(&roots, &Default::default()).unwrap();
+        std::fs::remove_file(roots[0].join("One/notes.chart")).unwrap();
+        let latest = store.create(&roots, &Default::default()).unwrap();
+
+        let preview =
+            async_std::task::block_on(preview_prune(backup_dir.path().to_owned(), retention))
+                .unwrap();
+        assert_eq!(preview.len(), 3);
+        assert_eq!(preview.snapshot_size, 7);
+
+        let pruned =
+            async_std::task::block_on(prune_backups(backup_dir.path().to_owned(), retention))
+                .unwrap();
+
+        assert_eq!(pruned, preview);
+        assert_eq!(pruned.archives.len(), 2);
+        assert_eq!(pruned.archives[1].format, CompressionFormat::Zstd);
+
+        let snapshots = store.snapshots().unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].id, latest.id);
+        assert_eq!(
+            walkdir::WalkDir::new(backup_dir.path().join(SNAPSHOT_DIR).join("objects"))
+                .into_iter()
+                .filter_map(Result::ok)
+                .filter(|entry| entry.file_type().is_file())
+                .count(),
+            1
+        );
+
+        let remaining = list_backups(backup_dir.path());
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(
+            remaining[0].path.file_name().unwrap(),
+            "ajour_backup_2021-06-03_10-00-00.zip"
+        );
+        assert!(backup_dir.path().join("notes.txt").exists());
+    }
+}
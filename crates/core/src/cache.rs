@@ -1,7 +1,10 @@
 use crate::error::{CacheError, FilesystemError};
 use crate::fs::{config_dir, PersistentData};
+use crate::library::song_folder_modified;
+use crate::origin::SongOrigin;
 use crate::parse::Fingerprint;
 use crate::repository::RepositoryKind;
+use crate::song::{Song, SongMetadata};
 use crate::{
     addon::{Addon, AddonFolder},
     catalog::{download_catalog, Catalog},
@@ -15,7 +18,7 @@ use serde::{Deserialize, Serialize};
 
 use std::collections::HashMap;
 use std::convert::TryFrom;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Serialize, Deserialize, Default, Debug)]
 pub struct FingerprintCache(HashMap<Flavor, Vec<Fingerprint>>);
@@ -240,6 +243,86 @@ pub enum ExternalReleaseId {
     Version(String),
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub enum SongCache {
+    V1(HashMap<PathBuf, Vec<SongCacheEntry>>),
+}
+
+impl Default for SongCache {
+    fn default() -> Self {
+        SongCache::V1(Default::default())
+    }
+}
+
+impl SongCache {
+    pub(crate) fn get_mut_for_root(&mut self, root: &Path) -> &mut Vec<SongCacheEntry> {
+        match self {
+            SongCache::V1(cache) => cache.entry(root.to_owned()).or_default(),
+        }
+    }
+
+    /// Replaces the entry of a song whose folder changed on disk.
+    pub(crate) fn replace_song(&mut self, song: &Song) {
+        let entries = self.get_mut_for_root(&song.root);
+        entries.retain(|entry| entry.path != song.path);
+
+        if let Some(modified) = song_folder_modified(&song.path) {
+            entries.push(SongCacheEntry {
+                path: song.path.clone(),
+                hash: song.hash.clone(),
+                metadata: song.metadata.clone(),
+                modified,
+                origin: song.origin.clone(),
+            });
+        }
+    }
+}
+
+impl PersistentData for SongCache {
+    fn relative_path() -> PathBuf {
+        PathBuf::from("cache/songs.yml")
+    }
+}
+
+pub async fn load_song_cache() -> Result<SongCache, CacheError> {
+    Ok(SongCache::load_or_default()?)
+}
+
+pub async fn remove_song_cache_entry(
+    song_cache: Arc<Mutex<SongCache>>,
+    root: PathBuf,
+    path: PathBuf,
+) -> Result<Option<SongCacheEntry>, CacheError> {
+    // Lock mutex to get mutable access and block other tasks from trying to update
+    let mut song_cache = song_cache.lock().await;
+
+    // Get entries for library root
+    let entries = song_cache.get_mut_for_root(&root);
+
+    if let Some(idx) = entries.iter().position(|e| e.path == path) {
+        let entry = entries.remove(idx);
+
+        // Persist changes to filesystem
+        song_cache.save()?;
+
+        Ok(Some(entry))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Metadata of a parsed song folder. The entry is reused on the next scan
+/// as long as the folder hasn't been modified since.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SongCacheEntry {
+    pub path: PathBuf,
+    pub hash: Option<String>,
+    pub metadata: SongMetadata,
+    pub modified: DateTime<Utc>,
+    #[serde(default)]
+    pub origin: Option<SongOrigin>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CatalogCache {
     etag: String,
@@ -0,0 +1,193 @@
+use crate::error::{FilesystemError, ParseError};
+use crate::library::CHART_FILES;
+use crate::song::{Song, SongState};
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const FILE_NAME: &str = "badsongs.txt";
+
+/// Reason used when Clone Hero didn't write one.
+const UNKNOWN_REASON: &str = "Clone Hero failed to load this song";
+
+/// Songs Clone Hero failed to load during its last scan, keyed by the
+/// normalized song folder.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BadSongs {
+    pub songs: HashMap<PathBuf, String>,
+}
+
+impl BadSongs {
+    /// Puts every song listed in `badsongs.txt` into an error state with the
+    /// reason Clone Hero gave.
+    ///
+    /// Songs already in an error state keep their own error.
+    pub fn apply(&self, songs: &mut [Song]) {
+        for song in songs.iter_mut() {
+            if matches!(song.state, SongState::Error(_)) {
+                continue;
+            }
+
+            if let Some(reason) = self.songs.get(&normalize(&song.path)) {
+                song.state = SongState::Error(reason.clone());
+            }
+        }
+    }
+}
+
+/// Parses the contents of `badsongs.txt`.
+///
+/// Every unindented line is the path of a song folder or chart file, the
+/// indented or `-` prefixed lines below it are the reasons it failed to
+/// load.
+pub fn parse_bad_songs(contents: &str) -> BadSongs {
+    let mut songs = HashMap::new();
+    let mut current: Option<(PathBuf, Vec<String>)> = None;
+
+    let mut finish = |entry: Option<(PathBuf, Vec<String>)>| {
+        if let Some((folder, reasons)) = entry {
+            let reason = if reasons.is_empty() {
+                UNKNOWN_REASON.to_owned()
+            } else {
+                reasons.join(", ")
+            };
+
+            songs.insert(normalize(&folder), reason);
+        }
+    };
+
+    for line in contents.trim_start_matches('\u{feff}').lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let is_reason = line.starts_with(char::is_whitespace) || line.starts_with('-');
+
+        if is_reason {
+            if let Some((_, reasons)) = current.as_mut() {
+                let reason = line.trim().trim_start_matches('-').trim();
+
+                if !reason.is_empty() {
+                    reasons.push(reason.to_owned());
+                }
+            }
+        } else {
+            finish(current.take());
+            current = Some((song_folder(line.trim()), vec![]));
+        }
+    }
+
+    finish(current.take());
+
+    BadSongs { songs }
+}
+
+/// Clone Hero lists either the song folder or the chart file inside it.
+fn song_folder(path: &str) -> PathBuf {
+    let path = PathBuf::from(path);
+
+    let is_song_file = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| {
+            let name = name.to_lowercase();
+            CHART_FILES.contains(&name.as_str()) || name == "song.ini"
+        })
+        .unwrap_or_default();
+
+    match path.parent() {
+        Some(parent) if is_song_file => parent.to_owned(),
+        _ => path,
+    }
+}
+
+/// Normalizes a path for comparing it with song folders, dropping `.`,
+/// repeated and trailing separators. Paths are compared case insensitive on
+/// Windows.
+fn normalize(path: &Path) -> PathBuf {
+    let path = path.components().collect::<PathBuf>();
+
+    if cfg!(windows) {
+        PathBuf::from(path.to_string_lossy().to_lowercase())
+    } else {
+        path
+    }
+}
+
+/// Loads `badsongs.txt` from the Clone Hero data directory.
+///
+/// A missing file means Clone Hero had no problems loading songs.
+pub async fn load_bad_songs(data_directory: impl AsRef<Path>) -> Result<BadSongs, ParseError> {
+    let path = data_directory.as_ref().join(FILE_NAME);
+
+    if !path.exists() {
+        log::debug!("no bad songs found at {:?}", path);
+        return Ok(BadSongs::default());
+    }
+
+    let data = async_std::fs::read(&path)
+        .await
+        .map_err(FilesystemError::Io)?;
+
+    let bad_songs = parse_bad_songs(&String::from_utf8_lossy(&data));
+
+    log::debug!("loaded {} bad songs", bad_songs.songs.len());
+
+    Ok(bad_songs)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_bad_songs() {
+        let contents = "\u{feff}/songs/Broken/notes.chart\r\n\
+                        \t- Failed to parse chart\r\n\
+                        \t- No audio\r\n\
+                        \r\n\
+                        /songs/Other Song\r\n\
+                        /songs/Third/song.ini\r\n  Invalid song.ini\r\n\
+                        /songs/./Fourth//\r\n";
+
+        let bad_songs = parse_bad_songs(contents);
+
+        assert_eq!(bad_songs.songs.len(), 4);
+        assert_eq!(
+            bad_songs.songs[Path::new("/songs/Broken")],
+            "Failed to parse chart, No audio"
+        );
+        assert_eq!(
+            bad_songs.songs[Path::new("/songs/Other Song")],
+            UNKNOWN_REASON
+        );
+        assert_eq!(
+            bad_songs.songs[Path::new("/songs/Third")],
+            "Invalid song.ini"
+        );
+
+        let mut songs = vec![
+            Song::new(
+                "Broken".to_owned(),
+                PathBuf::from("/songs/Broken"),
+                PathBuf::from("/songs"),
+            ),
+            Song::new(
+                "Fine".to_owned(),
+                PathBuf::from("/songs/Fine"),
+                PathBuf::from("/songs"),
+            ),
+            Song::new(
+                "Fourth".to_owned(),
+                PathBuf::from("/songs/Fourth"),
+                PathBuf::from("/songs"),
+            ),
+        ];
+
+        bad_songs.apply(&mut songs);
+
+        assert!(matches!(songs[0].state, SongState::Error(_)));
+        assert_eq!(songs[1].state, SongState::Idle);
+        assert!(matches!(songs[2].state, SongState::Error(_)));
+    }
+}
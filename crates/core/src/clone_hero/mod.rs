@@ -0,0 +1,11 @@
+//! Readers for the binary files Clone Hero keeps in its data directory.
+pub mod badsongs;
+mod reader;
+pub mod scoredata;
+pub mod setlist;
+pub mod songcache;
+
+pub use badsongs::{load_bad_songs, BadSongs};
+pub use scoredata::{load_score_data, ScoreData};
+pub use setlist::export_setlist;
+pub use songcache::{load_song_cache_file, SongCacheFile};
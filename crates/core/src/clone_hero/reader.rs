@@ -0,0 +1,140 @@
+use crate::error::ParseError;
+
+use std::convert::TryInto;
+
+/// Upper bound for preallocating from counts read out of a file, so a corrupt
+/// count fails on the first missing record instead of allocating.
+pub(crate) const MAX_PREALLOCATED: usize = 65_536;
+
+/// Little-endian reader over the bytes of a Clone Hero binary file.
+pub(crate) struct Reader<'a> {
+    file: &'static str,
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(file: &'static str, data: &'a [u8]) -> Self {
+        Reader { file, data, pos: 0 }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    pub(crate) fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], ParseError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| ParseError::UnexpectedEndOfFile {
+                file: self.file.to_owned(),
+            })?;
+
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+
+        Ok(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], ParseError> {
+        // Can't fail, `read_bytes` returns exactly N bytes
+        Ok(self.read_bytes(N)?.try_into().unwrap())
+    }
+
+    pub(crate) fn read_u8(&mut self) -> Result<u8, ParseError> {
+        Ok(self.read_array::<1>()?[0])
+    }
+
+    pub(crate) fn read_u16(&mut self) -> Result<u16, ParseError> {
+        Ok(u16::from_le_bytes(self.read_array()?))
+    }
+
+    pub(crate) fn read_i8(&mut self) -> Result<i8, ParseError> {
+        Ok(i8::from_le_bytes(self.read_array()?))
+    }
+
+    pub(crate) fn read_i32(&mut self) -> Result<i32, ParseError> {
+        Ok(i32::from_le_bytes(self.read_array()?))
+    }
+
+    pub(crate) fn read_i64(&mut self) -> Result<i64, ParseError> {
+        Ok(i64::from_le_bytes(self.read_array()?))
+    }
+
+    /// Reads a string prefixed with its length in bytes, encoded 7 bits at
+    /// a time the way .NET's `BinaryWriter` does.
+    pub(crate) fn read_string(&mut self) -> Result<String, ParseError> {
+        let mut len = 0usize;
+        let mut shift = 0;
+
+        loop {
+            let byte = self.read_u8()?;
+            len |= ((byte & 0x7f) as usize) << shift;
+
+            if byte & 0x80 == 0 {
+                break;
+            }
+
+            shift += 7;
+
+            // A length never takes more than 5 bytes
+            if shift > 28 {
+                return Err(ParseError::UnexpectedEndOfFile {
+                    file: self.file.to_owned(),
+                });
+            }
+        }
+
+        Ok(String::from_utf8_lossy(self.read_bytes(len)?).into_owned())
+    }
+
+    /// Reads a 16 byte MD5 checksum as a lowercase hex string.
+    pub(crate) fn read_checksum(&mut self) -> Result<String, ParseError> {
+        Ok(self
+            .read_bytes(16)?
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read_checksum() {
+        let mut data = vec![0x01, 0x00];
+        data.extend(&[0xab; 16]);
+
+        let mut reader = Reader::new("test", &data);
+
+        assert_eq!(reader.read_u16().unwrap(), 1);
+        assert_eq!(
+            reader.read_checksum().unwrap(),
+            "abababababababababababababababab"
+        );
+        assert!(reader.is_empty());
+        assert!(reader.read_u8().is_err());
+    }
+
+    #[test]
+    fn test_read_string() {
+        let long = "a".repeat(200);
+
+        let mut data = vec![5];
+        data.extend(b"hello");
+        // 200 doesn't fit in 7 bits
+        data.extend(&[0xc8, 0x01]);
+        data.extend(long.as_bytes());
+        data.push(0);
+
+        let mut reader = Reader::new("test", &data);
+
+        assert_eq!(reader.read_string().unwrap(), "hello");
+        assert_eq!(reader.read_string().unwrap(), long);
+        assert_eq!(reader.read_string().unwrap(), "");
+        assert!(reader.is_empty());
+    }
+}
@@ -0,0 +1,223 @@
+use super::reader::{Reader, MAX_PREALLOCATED};
+use crate::error::{FilesystemError, ParseError};
+use crate::song::{Difficulty, Instrument, Song};
+
+use std::collections::HashMap;
+use std::path::Path;
+
+const FILE_NAME: &str = "scoredata.bin";
+
+/// Play history for all songs Clone Hero has recorded a score for, keyed by
+/// the chart checksum.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScoreData {
+    pub version: i32,
+    pub songs: HashMap<String, SongScores>,
+}
+
+/// Play history for a single song.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SongScores {
+    pub play_count: u32,
+    pub scores: Vec<InstrumentScore>,
+}
+
+impl SongScores {
+    /// Returns the highest score across all instruments and difficulties.
+    pub fn best(&self) -> Option<&InstrumentScore> {
+        self.scores.iter().max_by_key(|s| s.score)
+    }
+}
+
+/// Best score on a single instrument.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InstrumentScore {
+    pub instrument: Instrument,
+    pub difficulty: Difficulty,
+    /// Notes hit.
+    pub numerator: u16,
+    /// Total notes.
+    pub denominator: u16,
+    pub stars: u8,
+    pub score: u32,
+}
+
+impl InstrumentScore {
+    /// Returns the accuracy between 0 and 1.
+    pub fn accuracy(&self) -> Option<f32> {
+        if self.denominator == 0 {
+            None
+        } else {
+            Some(self.numerator as f32 / self.denominator as f32)
+        }
+    }
+}
+
+impl ScoreData {
+    /// Sets the play history on each song that has a matching chart checksum.
+    pub fn apply(&self, songs: &mut [Song]) {
+        for song in songs.iter_mut() {
+            song.scores = song
+                .hash
+                .as_ref()
+                .and_then(|hash| self.songs.get(hash))
+                .cloned();
+        }
+    }
+}
+
+/// Parses the contents of `scoredata.bin`.
+///
+/// The file starts with a version and the number of songs. Each song is
+/// its chart checksum, the number of instrument scores and the play count,
+/// followed by the scores themselves.
+pub fn parse_score_data(data: &[u8]) -> Result<ScoreData, ParseError> {
+    let mut reader = Reader::new(FILE_NAME, data);
+
+    let version = reader.read_i32()?;
+    let song_count = reader.read_i32()?.max(0) as usize;
+
+    let mut songs = HashMap::with_capacity(song_count.min(MAX_PREALLOCATED));
+
+    for _ in 0..song_count {
+        let hash = reader.read_checksum()?;
+        let instrument_count = reader.read_u8()?;
+        let play_count = reader.read_i32()?.max(0) as u32;
+
+        let mut scores = Vec::with_capacity((instrument_count as usize).min(Instrument::ALL.len()));
+
+        for _ in 0..instrument_count {
+            let instrument = reader.read_u16()?;
+            let difficulty = reader.read_u8()?;
+            let numerator = reader.read_u16()?;
+            let denominator = reader.read_u16()?;
+            let stars = reader.read_u8()?;
+            let _padding = reader.read_i32()?;
+            let score = reader.read_i32()?.max(0) as u32;
+
+            // Skip instruments added by newer versions of Clone Hero.
+            if let (Some(instrument), Some(difficulty)) = (
+                Instrument::from_id(instrument),
+                Difficulty::from_id(difficulty),
+            ) {
+                scores.push(InstrumentScore {
+                    instrument,
+                    difficulty,
+                    numerator,
+                    denominator,
+                    stars,
+                    score,
+                });
+            }
+        }
+
+        songs.insert(hash, SongScores { play_count, scores });
+    }
+
+    if !reader.is_empty() {
+        log::debug!("ignoring trailing bytes in {}", FILE_NAME);
+    }
+
+    Ok(ScoreData { version, songs })
+}
+
+/// Loads `scoredata.bin` from the Clone Hero data directory.
+///
+/// A missing file means nothing has been played yet, so an empty
+/// `ScoreData` is returned.
+pub async fn load_score_data(data_directory: impl AsRef<Path>) -> Result<ScoreData, ParseError> {
+    let path = data_directory.as_ref().join(FILE_NAME);
+
+    if !path.exists() {
+        log::debug!("no score data found at {:?}", path);
+        return Ok(ScoreData::default());
+    }
+
+    let data = async_std::fs::read(&path)
+        .await
+        .map_err(FilesystemError::Io)?;
+
+    let score_data = parse_score_data(&data)?;
+
+    log::debug!(
+        "loaded score data version {} with {} songs",
+        score_data.version,
+        score_data.songs.len()
+    );
+
+    Ok(score_data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn write_song(data: &mut Vec<u8>, hash: u8, play_count: i32, scores: &[(u16, u8, i32)]) {
+        data.extend(&[hash; 16]);
+        data.push(scores.len() as u8);
+        data.extend(&play_count.to_le_bytes());
+
+        for (instrument, stars, score) in scores {
+            data.extend(&instrument.to_le_bytes());
+            data.push(3);
+            data.extend(&90u16.to_le_bytes());
+            data.extend(&100u16.to_le_bytes());
+            data.push(*stars);
+            data.extend(&1i32.to_le_bytes());
+            data.extend(&score.to_le_bytes());
+        }
+    }
+
+    #[test]
+    fn test_parse_score_data() {
+        let mut data = vec![];
+        data.extend(&20_211_010i32.to_le_bytes());
+        data.extend(&2i32.to_le_bytes());
+        write_song(&mut data, 0xab, 7, &[(0, 5, 120_000), (6, 4, 150_000)]);
+        // Unknown instrument id is skipped
+        write_song(&mut data, 0x01, 1, &[(42, 3, 1_000), (9, 2, 500)]);
+
+        let score_data = parse_score_data(&data).unwrap();
+
+        assert_eq!(score_data.version, 20_211_010);
+        assert_eq!(score_data.songs.len(), 2);
+
+        let scores = &score_data.songs["abababababababababababababababab"];
+        assert_eq!(scores.play_count, 7);
+        assert_eq!(scores.scores.len(), 2);
+
+        let best = scores.best().unwrap();
+        assert_eq!(best.instrument, Instrument::Drums);
+        assert_eq!(best.difficulty, Difficulty::Expert);
+        assert_eq!(best.stars, 4);
+        assert_eq!(best.accuracy(), Some(0.9));
+
+        let scores = &score_data.songs["01010101010101010101010101010101"];
+        assert_eq!(scores.play_count, 1);
+        assert_eq!(scores.scores.len(), 1);
+        assert_eq!(scores.scores[0].instrument, Instrument::Vocals);
+    }
+
+    #[test]
+    fn test_parse_truncated_score_data() {
+        let mut data = vec![];
+        data.extend(&1i32.to_le_bytes());
+        data.extend(&1i32.to_le_bytes());
+        data.extend(&[0; 10]);
+
+        assert!(matches!(
+            parse_score_data(&data),
+            Err(ParseError::UnexpectedEndOfFile { .. })
+        ));
+
+        // A corrupt song count fails instead of allocating for it.
+        let mut data = vec![];
+        data.extend(&1i32.to_le_bytes());
+        data.extend(&i32::MAX.to_le_bytes());
+
+        assert!(matches!(
+            parse_score_data(&data),
+            Err(ParseError::UnexpectedEndOfFile { .. })
+        ));
+    }
+}
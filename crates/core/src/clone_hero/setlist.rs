@@ -0,0 +1,100 @@
+use crate::clone_hero::reader::Reader;
+use crate::error::{FilesystemError, ParseError};
+use crate::organizer::sanitize_name;
+
+use std::path::PathBuf;
+
+/// Folder in the Clone Hero data directory setlists are read from.
+const FOLDER_NAME: &str = "Setlists";
+
+const FILE_NAME: &str = "setlist";
+
+/// Encodes a setlist, which is the chart checksum of each song in order,
+/// written as strings the way .NET's `BinaryWriter` does.
+pub fn setlist_bytes(hashes: &[String]) -> Vec<u8> {
+    let mut data = vec![];
+
+    for hash in hashes {
+        let mut len = hash.len();
+
+        // The length is written 7 bits at a time.
+        while len >= 0x80 {
+            data.push((len as u8 & 0x7f) | 0x80);
+            len >>= 7;
+        }
+        data.push(len as u8);
+
+        data.extend(hash.as_bytes());
+    }
+
+    data
+}
+
+/// Parses a setlist into the chart checksums of its songs.
+pub fn parse_setlist(data: &[u8]) -> Result<Vec<String>, ParseError> {
+    let mut reader = Reader::new(FILE_NAME, data);
+    let mut hashes = vec![];
+
+    while !reader.is_empty() {
+        hashes.push(reader.read_string()?);
+    }
+
+    Ok(hashes)
+}
+
+/// Writes the songs with the chart checksums `hashes` as a setlist named
+/// `name` into the `Setlists` folder of the Clone Hero data directory,
+/// replacing a setlist with the same name. Returns the path written to.
+pub async fn export_setlist(
+    data_directory: PathBuf,
+    name: String,
+    hashes: Vec<String>,
+) -> Result<PathBuf, FilesystemError> {
+    let folder = data_directory.join(FOLDER_NAME);
+    async_std::fs::create_dir_all(&folder).await?;
+
+    let path = folder.join(format!("{}.{}", sanitize_name(&name), FILE_NAME));
+    async_std::fs::write(&path, setlist_bytes(&hashes)).await?;
+
+    log::debug!("exported {} songs to setlist {:?}", hashes.len(), &path);
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use async_std::task;
+
+    #[test]
+    fn test_export_setlist() {
+        let data_directory = tempfile::tempdir().unwrap();
+
+        let hashes = vec![
+            "0123456789abcdef0123456789abcdef".to_owned(),
+            "fedcba9876543210fedcba9876543210".to_owned(),
+        ];
+
+        let data = setlist_bytes(&hashes);
+        assert_eq!(data.len(), 66);
+        assert_eq!(data[0], 32);
+
+        let path = task::block_on(export_setlist(
+            data_directory.path().to_owned(),
+            "Metal: 90s".to_owned(),
+            hashes.clone(),
+        ))
+        .unwrap();
+
+        assert_eq!(
+            path.parent(),
+            Some(data_directory.path().join("Setlists").as_path())
+        );
+        assert_eq!(
+            parse_setlist(&std::fs::read(&path).unwrap()).unwrap(),
+            hashes
+        );
+        assert!(parse_setlist(&[32, b'a']).is_err());
+    }
+}
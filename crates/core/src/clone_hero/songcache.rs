@@ -0,0 +1,282 @@
+use super::reader::{Reader, MAX_PREALLOCATED};
+use crate::error::{FilesystemError, ParseError};
+use crate::song::{Instrument, SongMetadata};
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use std::path::{Path, PathBuf};
+
+const FILE_NAME: &str = "songcache.bin";
+
+/// Layout written by Clone Hero v1.0.
+const VERSION_20211010: i32 = 20_211_010;
+
+/// .NET ticks, 100 nanosecond intervals since 0001-01-01, at the Unix epoch.
+const UNIX_EPOCH_TICKS: i64 = 621_355_968_000_000_000;
+
+/// Number of string tables before the song records, in the order `name`,
+/// `artist`, `album`, `genre`, `year`, `charter` and `playlist`.
+const STRING_TABLE_COUNT: usize = 7;
+
+/// Songs found by Clone Hero's own scan.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SongCacheFile {
+    pub version: i32,
+    pub songs: Vec<CachedSong>,
+}
+
+/// A single song record from `songcache.bin`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedSong {
+    /// Absolute path to the song folder.
+    pub folder: PathBuf,
+    /// Last write time of the song folder when Clone Hero scanned it, in
+    /// .NET ticks.
+    pub modified_ticks: i64,
+    /// Lowercase hex encoded MD5 checksum of the chart file.
+    pub hash: String,
+    pub metadata: SongMetadata,
+}
+
+impl CachedSong {
+    /// Returns the last write time of the song folder when Clone Hero
+    /// scanned it.
+    pub fn modified(&self) -> Option<DateTime<Utc>> {
+        let ticks = self.modified_ticks.checked_sub(UNIX_EPOCH_TICKS)?;
+
+        Utc.timestamp_opt(
+            ticks.div_euclid(10_000_000),
+            (ticks.rem_euclid(10_000_000) * 100) as u32,
+        )
+        .single()
+    }
+}
+
+/// Converts a modification time to .NET ticks, the precision Clone Hero
+/// stores it in.
+pub fn to_ticks(time: DateTime<Utc>) -> i64 {
+    time.timestamp() * 10_000_000 + (time.timestamp_subsec_nanos() / 100) as i64 + UNIX_EPOCH_TICKS
+}
+
+/// Parses the contents of `songcache.bin`.
+///
+/// Returns `ParseError::UnsupportedVersion` for layouts we don't know,
+/// callers are expected to fall back to scanning the song folders.
+pub fn parse_song_cache_file(data: &[u8]) -> Result<SongCacheFile, ParseError> {
+    let mut reader = Reader::new(FILE_NAME, data);
+
+    let version = reader.read_i32()?;
+
+    let songs = match version {
+        VERSION_20211010 => parse_songs_20211010(&mut reader)?,
+        version => {
+            return Err(ParseError::UnsupportedVersion {
+                file: FILE_NAME.to_owned(),
+                version,
+            })
+        }
+    };
+
+    if !reader.is_empty() {
+        log::debug!("ignoring trailing bytes in {}", FILE_NAME);
+    }
+
+    Ok(SongCacheFile { version, songs })
+}
+
+/// The header is followed by a checksum of the song folders, the string
+/// tables and the song records. Each record references the string tables
+/// by index, with -1 meaning the value is missing.
+fn parse_songs_20211010(reader: &mut Reader) -> Result<Vec<CachedSong>, ParseError> {
+    let _checksum = reader.read_checksum()?;
+
+    let mut tables: Vec<Vec<String>> = Vec::with_capacity(STRING_TABLE_COUNT);
+
+    for _ in 0..STRING_TABLE_COUNT {
+        let count = reader.read_i32()?.max(0) as usize;
+
+        let mut table = Vec::with_capacity(count.min(MAX_PREALLOCATED));
+        for _ in 0..count {
+            table.push(reader.read_string()?);
+        }
+
+        tables.push(table);
+    }
+
+    let song_count = reader.read_i32()?.max(0) as usize;
+    let mut songs = Vec::with_capacity(song_count.min(MAX_PREALLOCATED));
+
+    for _ in 0..song_count {
+        let folder = PathBuf::from(reader.read_string()?);
+        let modified_ticks = reader.read_i64()?;
+
+        let mut strings: Vec<Option<String>> = Vec::with_capacity(STRING_TABLE_COUNT);
+        for table in tables.iter() {
+            let index = reader.read_i32()?;
+
+            strings.push(if index < 0 {
+                None
+            } else {
+                table.get(index as usize).cloned()
+            });
+        }
+
+        let mut metadata = SongMetadata::default();
+
+        for instrument in Instrument::ALL.iter() {
+            // An intensity of -1 means the instrument isn't charted
+            let intensity = reader.read_i8()?;
+
+            if intensity >= 0 {
+                metadata.difficulties.insert(*instrument, intensity as i32);
+            }
+        }
+
+        let song_length = reader.read_i32()?;
+        let hash = reader.read_checksum()?;
+
+        let mut strings = strings.into_iter();
+        metadata.name = strings.next().flatten();
+        metadata.artist = strings.next().flatten();
+        metadata.album = strings.next().flatten();
+        metadata.genre = strings.next().flatten();
+        metadata.year = strings.next().flatten();
+        metadata.charter = strings.next().flatten();
+        metadata.song_length = if song_length > 0 {
+            Some(song_length as u64)
+        } else {
+            None
+        };
+
+        songs.push(CachedSong {
+            folder,
+            modified_ticks,
+            hash,
+            metadata,
+        });
+    }
+
+    Ok(songs)
+}
+
+/// Loads `songcache.bin` from the Clone Hero data directory.
+///
+/// Returns `None` if Clone Hero hasn't scanned any songs yet.
+pub async fn load_song_cache_file(
+    data_directory: impl AsRef<Path>,
+) -> Result<Option<SongCacheFile>, ParseError> {
+    let path = data_directory.as_ref().join(FILE_NAME);
+
+    if !path.exists() {
+        log::debug!("no song cache found at {:?}", path);
+        return Ok(None);
+    }
+
+    let data = async_std::fs::read(&path)
+        .await
+        .map_err(FilesystemError::Io)?;
+
+    let song_cache_file = parse_song_cache_file(&data)?;
+
+    log::debug!(
+        "loaded song cache version {} with {} songs",
+        song_cache_file.version,
+        song_cache_file.songs.len()
+    );
+
+    Ok(Some(song_cache_file))
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    fn write_string(data: &mut Vec<u8>, s: &str) {
+        assert!(s.len() < 128);
+        data.push(s.len() as u8);
+        data.extend(s.as_bytes());
+    }
+
+    /// Builds a `songcache.bin` with one song per `(folder, modified_ticks, name)`.
+    pub(crate) fn song_cache_file_bytes(songs: &[(&str, i64, &str)]) -> Vec<u8> {
+        let mut data = vec![];
+        data.extend(&VERSION_20211010.to_le_bytes());
+        data.extend(&[0; 16]);
+
+        // Names, then a single artist and empty tables for the rest
+        data.extend(&(songs.len() as i32).to_le_bytes());
+        for (_, _, name) in songs {
+            write_string(&mut data, name);
+        }
+        data.extend(&1i32.to_le_bytes());
+        write_string(&mut data, "Metallica");
+        for _ in 2..STRING_TABLE_COUNT {
+            data.extend(&0i32.to_le_bytes());
+        }
+
+        data.extend(&(songs.len() as i32).to_le_bytes());
+        for (idx, (folder, modified_ticks, _)) in songs.iter().enumerate() {
+            write_string(&mut data, folder);
+            data.extend(&modified_ticks.to_le_bytes());
+
+            data.extend(&(idx as i32).to_le_bytes());
+            data.extend(&0i32.to_le_bytes());
+            for _ in 2..STRING_TABLE_COUNT {
+                data.extend(&(-1i32).to_le_bytes());
+            }
+
+            for instrument in Instrument::ALL.iter() {
+                data.push(if *instrument == Instrument::Drums {
+                    6
+                } else {
+                    0xff
+                });
+            }
+
+            data.extend(&300_000i32.to_le_bytes());
+            data.extend(&[idx as u8; 16]);
+        }
+
+        data
+    }
+
+    #[test]
+    fn test_parse_song_cache_file() {
+        let data = song_cache_file_bytes(&[("/songs/One", UNIX_EPOCH_TICKS + 15, "One")]);
+
+        let song_cache_file = parse_song_cache_file(&data).unwrap();
+
+        assert_eq!(song_cache_file.version, VERSION_20211010);
+        assert_eq!(song_cache_file.songs.len(), 1);
+
+        let song = &song_cache_file.songs[0];
+        assert_eq!(song.folder, PathBuf::from("/songs/One"));
+        assert_eq!(song.hash, "00000000000000000000000000000000");
+        assert_eq!(song.metadata.name.as_deref(), Some("One"));
+        assert_eq!(song.metadata.artist.as_deref(), Some("Metallica"));
+        assert_eq!(song.metadata.album, None);
+        assert_eq!(song.metadata.song_length, Some(300_000));
+        assert_eq!(song.metadata.difficulties.len(), 1);
+        assert_eq!(song.metadata.difficulties.get(&Instrument::Drums), Some(&6));
+        assert_eq!(song.modified(), Utc.timestamp_opt(0, 1_500).single());
+        assert_eq!(to_ticks(song.modified().unwrap()), song.modified_ticks);
+    }
+
+    #[test]
+    fn test_parse_unsupported_song_cache_file() {
+        let mut data = song_cache_file_bytes(&[]);
+        data[..4].copy_from_slice(&1i32.to_le_bytes());
+
+        assert!(matches!(
+            parse_song_cache_file(&data),
+            Err(ParseError::UnsupportedVersion { version: 1, .. })
+        ));
+
+        let data = song_cache_file_bytes(&[("/songs/One", 0, "One")]);
+
+        assert!(matches!(
+            parse_song_cache_file(&data[..data.len() - 1]),
+            Err(ParseError::UnexpectedEndOfFile { .. })
+        ));
+    }
+}
@@ -1,17 +1,21 @@
+use crate::backup::{BackupRetention, BackupSchedule};
 use crate::catalog;
 use crate::error::FilesystemError;
 use crate::repository::CompressionFormat;
+use crate::s3::S3Destination;
 use glob::MatchOptions;
 use serde::{Deserialize, Serialize};
 use std::fs::create_dir_all;
 use std::path::{Path, PathBuf};
 
 mod addons;
+mod songs;
 mod wow;
 
 use crate::fs::PersistentData;
 
 pub use crate::config::addons::Addons;
+pub use crate::config::songs::{SmartFilter, SongAutoUpdate, SongInbox, Songs};
 pub use crate::config::wow::{Flavor, Wow};
 
 /// Config struct.
@@ -23,6 +27,9 @@ pub struct Config {
     #[serde(default)]
     pub addons: Addons,
 
+    #[serde(default)]
+    pub songs: Songs,
+
     pub theme: Option<String>,
 
     #[serde(default)]
@@ -49,6 +56,27 @@ pub struct Config {
     #[serde(default)]
     pub backup_fonts: bool,
 
+    /// Back up the song libraries incrementally, see `snapshot`.
+    #[serde(default)]
+    pub backup_songs: bool,
+
+    /// Which backup archives are kept after each backup.
+    #[serde(default)]
+    pub backup_retention: BackupRetention,
+
+    /// When backups are made automatically.
+    #[serde(default)]
+    pub backup_schedule: BackupSchedule,
+
+    /// Encrypt backup archives with a passphrase, which is never saved.
+    #[serde(default)]
+    pub backup_encrypt: bool,
+
+    /// S3-compatible storage backup archives are uploaded to after each
+    /// backup.
+    #[serde(default)]
+    pub backup_s3: S3Destination,
+
     #[serde(default)]
     pub hide_ignored_addons: bool,
 
@@ -0,0 +1,240 @@
+use crate::fs::config_dir;
+use crate::organizer::DEFAULT_TEMPLATE;
+use crate::versions;
+
+use chrono::{DateTime, Duration, Local, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Struct for song library specific settings.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Default)]
+pub struct Songs {
+    /// Library roots which are scanned for song folders.
+    #[serde(default)]
+    pub directories: Vec<PathBuf>,
+
+    /// Clone Hero data directory, holding `scoredata.bin`, `songcache.bin`
+    /// and `badsongs.txt`. If not set, the platform default is used.
+    #[serde(default)]
+    pub data_directory: Option<PathBuf>,
+
+    /// Directory broken songs are moved to. If not set, a `quarantine`
+    /// folder in the config directory is used.
+    #[serde(default)]
+    pub quarantine_directory: Option<PathBuf>,
+
+    /// Template the organizer moves song folders to, relative to their
+    /// library root.
+    #[serde(default)]
+    pub organize_template: Option<String>,
+
+    /// Automatic updates of songs and packs from their origin.
+    #[serde(default)]
+    pub auto_update: SongAutoUpdate,
+
+    /// Size cap of the store keeping previous versions of songs, in MB.
+    #[serde(default)]
+    pub versions_max_size: Option<u64>,
+
+    /// Folder watched for archives and song folders to import.
+    #[serde(default)]
+    pub inbox: SongInbox,
+
+    /// Saved search queries, shown as tabs in My Songs.
+    #[serde(default)]
+    pub smart_filters: Vec<SmartFilter>,
+}
+
+impl Songs {
+    /// Adds a library root, unless it has already been added.
+    pub fn add_directory(&mut self, path: PathBuf) {
+        if !self.directories.contains(&path) {
+            self.directories.push(path);
+        }
+    }
+
+    /// Returns the Clone Hero data directory, falling back to the
+    /// default location for the platform.
+    pub fn data_directory(&self) -> Option<PathBuf> {
+        if let Some(dir) = &self.data_directory {
+            return Some(dir.clone());
+        }
+
+        #[cfg(target_os = "windows")]
+        {
+            dirs_next::document_dir().map(|p| p.join("Clone Hero"))
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            dirs_next::data_dir().map(|p| p.join("com.srylain.CloneHero"))
+        }
+
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        {
+            dirs_next::home_dir().map(|p| p.join(".clonehero"))
+        }
+    }
+
+    /// Returns the directory broken songs are moved to.
+    pub fn quarantine_directory(&self) -> PathBuf {
+        self.quarantine_directory
+            .clone()
+            .unwrap_or_else(|| config_dir().join("quarantine"))
+    }
+
+    /// Returns the size cap of the versions store in bytes.
+    pub fn versions_max_size(&self) -> u64 {
+        self.versions_max_size
+            .unwrap_or(versions::DEFAULT_MAX_SIZE_MB)
+            * 1_000_000
+    }
+
+    /// Returns the organizer template, falling back to the default one.
+    pub fn organize_template(&self) -> String {
+        self.organize_template
+            .clone()
+            .unwrap_or_else(|| DEFAULT_TEMPLATE.to_owned())
+    }
+
+    /// Returns the library root inbox imports are installed into, falling
+    /// back to the first library root.
+    pub fn inbox_library(&self) -> Option<PathBuf> {
+        self.inbox
+            .library
+            .clone()
+            .or_else(|| self.directories.first().cloned())
+    }
+}
+
+/// Folder which songs are imported from as soon as they're dropped in.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Default)]
+pub struct SongInbox {
+    /// Folder which is watched. Watching is off if not set.
+    #[serde(default)]
+    pub directory: Option<PathBuf>,
+
+    /// Library root imported songs are installed into. If not set, the first
+    /// library root is used.
+    #[serde(default)]
+    pub library: Option<PathBuf>,
+}
+
+/// Named search query, like `genre:metal year:1990..1999`, whose songs are
+/// listed on their own.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct SmartFilter {
+    pub name: String,
+    pub query: String,
+}
+
+/// Schedule for updating songs and packs from their origin.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Default)]
+pub struct SongAutoUpdate {
+    /// Library roots which opted in to automatic updates.
+    #[serde(default)]
+    pub directories: Vec<PathBuf>,
+
+    /// Hours between two automatic updates.
+    #[serde(default)]
+    pub interval_hours: Option<u32>,
+
+    /// Hours of the day, in local time, during which no automatic updates
+    /// run, as `(start, end)`. The window may span midnight.
+    #[serde(default)]
+    pub quiet_hours: Option<(u32, u32)>,
+
+    /// When songs were last updated automatically.
+    #[serde(default)]
+    pub last_run: Option<DateTime<Utc>>,
+}
+
+impl SongAutoUpdate {
+    pub const DEFAULT_INTERVAL_HOURS: u32 = 24;
+
+    /// Returns the time between two automatic updates.
+    pub fn interval(&self) -> Duration {
+        Duration::hours(
+            self.interval_hours
+                .unwrap_or(Self::DEFAULT_INTERVAL_HOURS)
+                .max(1) as i64,
+        )
+    }
+
+    /// Returns true if the library root opted in to automatic updates.
+    pub fn is_enabled(&self, root: &Path) -> bool {
+        self.directories.iter().any(|dir| dir == root)
+    }
+
+    /// Opts a library root in or out of automatic updates.
+    pub fn set_enabled(&mut self, root: PathBuf, enabled: bool) {
+        self.directories.retain(|dir| dir != &root);
+
+        if enabled {
+            self.directories.push(root);
+        }
+    }
+
+    /// Returns true if `hour` is inside the quiet window.
+    pub fn is_quiet(&self, hour: u32) -> bool {
+        match self.quiet_hours {
+            Some((start, end)) if start <= end => hour >= start && hour < end,
+            Some((start, end)) => hour >= start || hour < end,
+            None => false,
+        }
+    }
+
+    /// Returns true if any library opted in, the interval passed since the
+    /// last run and `now` is outside the quiet window.
+    pub fn is_due(&self, now: DateTime<Local>) -> bool {
+        if self.directories.is_empty() || self.is_quiet(now.hour()) {
+            return false;
+        }
+
+        match self.last_run {
+            Some(last_run) => now.with_timezone(&Utc) - last_run >= self.interval(),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use chrono::{NaiveDate, TimeZone};
+
+    #[test]
+    fn test_auto_update_is_due() {
+        let mut auto_update = SongAutoUpdate::default();
+        let now = Local
+            .from_local_datetime(
+                &NaiveDate::from_ymd_opt(2021, 3, 1)
+                    .and_then(|date| date.and_hms_opt(12, 0, 0))
+                    .unwrap(),
+            )
+            .unwrap();
+
+        // Nothing opted in.
+        assert!(!auto_update.is_due(now));
+
+        auto_update.set_enabled(PathBuf::from("/songs"), true);
+        assert!(auto_update.is_enabled(Path::new("/songs")));
+        assert!(auto_update.is_due(now));
+
+        auto_update.last_run = Some((now - Duration::hours(2)).with_timezone(&Utc));
+        assert!(!auto_update.is_due(now));
+
+        auto_update.interval_hours = Some(1);
+        assert!(auto_update.is_due(now));
+
+        auto_update.quiet_hours = Some((22, 13));
+        assert!(auto_update.is_quiet(23));
+        assert!(auto_update.is_quiet(3));
+        assert!(!auto_update.is_quiet(13));
+        assert!(!auto_update.is_due(now));
+
+        auto_update.quiet_hours = Some((9, 11));
+        assert!(auto_update.is_due(now));
+    }
+}
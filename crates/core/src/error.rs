@@ -9,9 +9,13 @@ pub enum FilesystemError {
     #[error(transparent)]
     SerdeYaml(#[from] serde_yaml::Error),
     #[error(transparent)]
+    SerdeJson(#[from] serde_json::Error),
+    #[error(transparent)]
     Zip(#[from] zip::result::ZipError),
     #[error(transparent)]
     WalkDir(#[from] walkdir::Error),
+    #[error(transparent)]
+    Watch(#[from] notify::Error),
     #[error("File doesn't exist: {path:?}")]
     FileDoesntExist { path: PathBuf },
     #[cfg(target_os = "macos")]
@@ -21,6 +25,62 @@ pub enum FilesystemError {
     NormalizingPathSlash { path: PathBuf },
     #[error("Could not strip prefix {prefix:?} from {from:?}")]
     StripPrefix { prefix: String, from: String },
+    #[error(
+        "Not enough space in {path:?}: {required} bytes required, {available} bytes available"
+    )]
+    InsufficientSpace {
+        path: PathBuf,
+        required: u64,
+        available: u64,
+    },
+    #[error("Pack {name} has no source to update from")]
+    PackMissingSource { name: String },
+    #[error("{path:?} already exists and is not a pack")]
+    NotAPack { path: PathBuf },
+    #[error("No song found at {archive_path:?} in {url}")]
+    MissingFromArchive { archive_path: String, url: String },
+    #[error("The chart at {archive_path:?} in {url} is not the shared song")]
+    ChartMismatch { archive_path: String, url: String },
+    #[error("Version {hash} is not in the versions store")]
+    VersionNotFound { hash: String },
+    #[error("Snapshot {id} doesn't exist")]
+    SnapshotNotFound { id: String },
+    #[error("Don't know where {path} was backed up from, choose a folder to restore to")]
+    UnknownRestoreLocation { path: String },
+    #[error("Backup {path:?} failed verification: {issue}")]
+    CorruptBackup { path: PathBuf, issue: String },
+    #[error("Backup {path:?} is encrypted, a passphrase is required to read it")]
+    PassphraseRequired { path: PathBuf },
+    #[error("Wrong passphrase, the backup could not be decrypted")]
+    WrongPassphrase,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum RemoteBackupError {
+    #[error("Set AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY to back up to S3")]
+    MissingCredentials,
+    #[error("Invalid S3 endpoint {endpoint}")]
+    InvalidEndpoint { endpoint: String },
+    #[error("{path:?} is not a backup archive")]
+    NotABackup { path: PathBuf },
+    #[error("S3 request to {url} failed with status {code}: {message}")]
+    Request {
+        code: isahc::http::StatusCode,
+        url: String,
+        message: String,
+    },
+    #[error("Unexpected response from S3: {reason}")]
+    InvalidResponse { reason: String },
+    #[error(transparent)]
+    Download(#[from] DownloadError),
+    #[error(transparent)]
+    Filesystem(#[from] FilesystemError),
+}
+
+impl From<std::io::Error> for RemoteBackupError {
+    fn from(e: std::io::Error) -> Self {
+        RemoteBackupError::Filesystem(FilesystemError::Io(e))
+    }
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -47,6 +107,8 @@ pub enum DownloadError {
     MissingSelfUpdateRelease { bin_name: String },
     #[error("Catalog failed to download")]
     CatalogFailed,
+    #[error("{name} has no download location")]
+    MissingDownloadUrl { name: String },
     #[error("Self update for linux only works from AppImage")]
     SelfUpdateLinuxNonAppImage,
     #[error(transparent)]
@@ -127,6 +189,26 @@ impl From<isahc::Error> for RepositoryError {
 pub enum ParseError {
     #[error("Addon directory not found: {path:?}")]
     MissingAddonDirectory { path: PathBuf },
+    #[error("Song directory not found: {path:?}")]
+    MissingSongDirectory { path: PathBuf },
+    #[error("Unexpected end of file while reading {file}")]
+    UnexpectedEndOfFile { file: String },
+    #[error("Unsupported {file} version: {version}")]
+    UnsupportedVersion { file: String, version: i32 },
+    #[error("Unknown field in template: {{{field}}}")]
+    UnknownTemplateField { field: String },
+    #[error("Missing closing brace in template: {template}")]
+    UnclosedTemplateField { template: String },
+    #[error("Missing closing quote in query: {query}")]
+    UnclosedQueryQuote { query: String },
+    #[error("Missing value for {key} in query")]
+    MissingQueryValue { key: String },
+    #[error("Invalid value {value} for {key} in query, {reason}")]
+    InvalidQueryValue {
+        key: String,
+        value: String,
+        reason: &'static str,
+    },
     #[error("No folders passed to addon")]
     BuildAddonEmptyFolders,
     #[error("No parent directory for {dir:?}")]
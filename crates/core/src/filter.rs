@@ -0,0 +1,352 @@
+use crate::song::{Instrument, Song};
+
+/// Inclusive range of values. A missing bound is open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bounds<T> {
+    pub min: Option<T>,
+    pub max: Option<T>,
+}
+
+impl<T> Default for Bounds<T> {
+    fn default() -> Self {
+        Bounds {
+            min: None,
+            max: None,
+        }
+    }
+}
+
+impl<T: PartialOrd + Copy> Bounds<T> {
+    /// Returns true if neither bound is set.
+    pub fn is_open(&self) -> bool {
+        self.min.is_none() && self.max.is_none()
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        self.min.iter().all(|min| value >= *min) && self.max.iter().all(|max| value <= *max)
+    }
+
+    /// Parses `min..max`, `min..`, `..max` or a single value, with `parse`
+    /// used for each bound.
+    pub(crate) fn parse(s: &str, parse: impl Fn(&str) -> Option<T>) -> Option<Bounds<T>> {
+        let bound = |s: &str| match s.trim() {
+            "" => Some(None),
+            s => parse(s).map(Some),
+        };
+
+        let bounds = match s.find("..") {
+            Some(idx) => Bounds {
+                min: bound(&s[..idx])?,
+                max: bound(&s[idx + 2..])?,
+            },
+            None => {
+                let value = parse(s.trim())?;
+
+                Bounds {
+                    min: Some(value),
+                    max: Some(value),
+                }
+            }
+        };
+
+        if let (Some(min), Some(max)) = (bounds.min, bounds.max) {
+            if min > max {
+                return None;
+            }
+        }
+
+        Some(bounds)
+    }
+}
+
+/// Parses a difficulty range like `3..5`, `4..`, `..2` or `6`.
+pub fn parse_difficulty_bounds(s: &str) -> Result<Bounds<i32>, &'static str> {
+    Bounds::parse(s, |s| s.parse().ok().filter(|d| *d >= 0))
+        .ok_or("expected a difficulty range like 3..5, 4.. or ..2")
+}
+
+/// Parses a rating range like `4..`, `2..3` or `5`.
+pub fn parse_rating_bounds(s: &str) -> Result<Bounds<u8>, &'static str> {
+    Bounds::parse(s, |s| s.parse().ok()).ok_or("expected a rating range like 4.., 2..3 or 5")
+}
+
+/// Parses a length range like `2m..4m30s`, `..3:00` or `180..`, in
+/// milliseconds.
+pub fn parse_length_bounds(s: &str) -> Result<Bounds<u64>, &'static str> {
+    Bounds::parse(s, parse_length).ok_or("expected a length range like 2m..4m30s, ..3:00 or 180..")
+}
+
+/// Parses a song length as `m:ss`, with `h`, `m` and `s` units like `4m30s`
+/// or as plain seconds, and returns it in milliseconds. Lengths which don't
+/// fit are rejected.
+pub fn parse_length(s: &str) -> Option<u64> {
+    let s = s.trim().to_lowercase();
+
+    if let Some(idx) = s.find(':') {
+        let minutes = s[..idx].parse::<u64>().ok()?;
+        let seconds = s[idx + 1..].parse::<u64>().ok().filter(|s| *s < 60)?;
+
+        return minutes
+            .checked_mul(60)?
+            .checked_add(seconds)?
+            .checked_mul(1000);
+    }
+
+    if !s.is_empty() && s.chars().all(|c| c.is_ascii_digit()) {
+        return s.parse::<u64>().ok()?.checked_mul(1000);
+    }
+
+    let mut total = 0;
+    let mut number = String::new();
+    for c in s.chars() {
+        let unit = match c {
+            '0'..='9' => {
+                number.push(c);
+                continue;
+            }
+            'h' => 3_600_000,
+            'm' => 60_000,
+            's' => 1000,
+            _ => return None,
+        };
+
+        total = number
+            .parse::<u64>()
+            .ok()?
+            .checked_mul(unit)
+            .and_then(|length| length.checked_add(total))?;
+        number.clear();
+    }
+
+    if number.is_empty() && !s.is_empty() {
+        Some(total)
+    } else {
+        None
+    }
+}
+
+/// Filter on the songs of a library, used by `chmon list` and `chmon search`.
+/// Every criteria which is set has to match.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SongFilter {
+    /// Text which the artist, title, album or charter has to contain.
+    pub text: Option<String>,
+    /// Text which the artist has to contain.
+    pub artist: Option<String>,
+    /// Text which the charter has to contain.
+    pub charter: Option<String>,
+    /// Instrument the song has to be charted for.
+    pub instrument: Option<Instrument>,
+    /// Difficulty of `instrument`, or of any charted instrument if no
+    /// instrument is set.
+    pub difficulty: Bounds<i32>,
+    /// Length of the song in milliseconds.
+    pub length: Bounds<u64>,
+    /// Tag the song has to have, ignoring case.
+    pub tag: Option<String>,
+    /// Only match favorites.
+    pub favorite: bool,
+    /// Rating the user gave the song. Unrated songs only match open bounds.
+    pub rating: Bounds<u8>,
+}
+
+impl SongFilter {
+    /// Returns a filter matching songs whose artist, title, album or charter
+    /// contains `text`.
+    pub fn text(text: impl Into<String>) -> Self {
+        SongFilter {
+            text: Some(text.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn matches(&self, song: &Song) -> bool {
+        let contains = |field: Option<&str>, text: &str| matches!(field, Some(field) if field.to_lowercase().contains(&text.to_lowercase()));
+
+        if let Some(text) = &self.text {
+            let fields = [
+                song.artist(),
+                Some(song.title()),
+                song.metadata.album.as_deref(),
+                song.charter(),
+            ];
+
+            if !fields.iter().any(|field| contains(*field, text)) {
+                return false;
+            }
+        }
+
+        if let Some(artist) = &self.artist {
+            if !contains(song.artist(), artist) {
+                return false;
+            }
+        }
+
+        if let Some(charter) = &self.charter {
+            if !contains(song.charter(), charter) {
+                return false;
+            }
+        }
+
+        let difficulties = &song.metadata.difficulties;
+        let difficulty_matches = match self.instrument {
+            Some(instrument) => {
+                matches!(difficulties.get(&instrument), Some(d) if self.difficulty.contains(*d))
+            }
+            None => {
+                self.difficulty.is_open()
+                    || difficulties.values().any(|d| self.difficulty.contains(*d))
+            }
+        };
+
+        if !difficulty_matches {
+            return false;
+        }
+
+        let user_data = &song.user_data;
+
+        if matches!(&self.tag, Some(tag) if !user_data.has_tag(tag)) {
+            return false;
+        }
+
+        if self.favorite && !user_data.favorite {
+            return false;
+        }
+
+        if !self.rating.is_open()
+            && !matches!(user_data.rating, Some(rating) if self.rating.contains(rating))
+        {
+            return false;
+        }
+
+        self.length.is_open()
+            || matches!(song.metadata.song_length, Some(length) if self.length.contains(length))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    fn song(artist: &str, title: &str, length: u64, difficulties: &[(Instrument, i32)]) -> Song {
+        let mut song = Song::new(title.to_owned(), PathBuf::from(title), PathBuf::new());
+        song.metadata.name = Some(title.to_owned());
+        song.metadata.artist = Some(artist.to_owned());
+        song.metadata.charter = Some("Harmonix".to_owned());
+        song.metadata.song_length = Some(length);
+        song.metadata.difficulties = difficulties.iter().copied().collect();
+        song
+    }
+
+    #[test]
+    fn test_parse_length() {
+        assert_eq!(parse_length("4:05"), Some(245_000));
+        assert_eq!(parse_length("4m5s"), Some(245_000));
+        assert_eq!(parse_length("4M"), Some(240_000));
+        assert_eq!(parse_length("1h"), Some(3_600_000));
+        assert_eq!(parse_length("245"), Some(245_000));
+        assert_eq!(parse_length("4m5"), None);
+        assert_eq!(parse_length("4:75"), None);
+        assert_eq!(parse_length("four"), None);
+        assert_eq!(parse_length(""), None);
+        assert_eq!(parse_length("99999999999999999"), None);
+        assert_eq!(parse_length("99999999999999999:00"), None);
+        assert_eq!(parse_length("9999999999999h"), None);
+        assert_eq!(parse_length("5124095576030h5124095576030h"), None);
+    }
+
+    #[test]
+    fn test_parse_bounds() {
+        assert_eq!(
+            parse_difficulty_bounds("3..5"),
+            Ok(Bounds {
+                min: Some(3),
+                max: Some(5)
+            })
+        );
+        assert_eq!(
+            parse_difficulty_bounds("4.."),
+            Ok(Bounds {
+                min: Some(4),
+                max: None
+            })
+        );
+        assert_eq!(
+            parse_difficulty_bounds("6"),
+            Ok(Bounds {
+                min: Some(6),
+                max: Some(6)
+            })
+        );
+        assert!(parse_difficulty_bounds("5..3").is_err());
+        assert!(parse_difficulty_bounds("-1").is_err());
+
+        assert_eq!(
+            parse_length_bounds("..3:00"),
+            Ok(Bounds {
+                min: None,
+                max: Some(180_000)
+            })
+        );
+        assert!(parse_length_bounds("3m..x").is_err());
+    }
+
+    #[test]
+    fn test_song_filter() {
+        let one = song(
+            "Metallica",
+            "One",
+            446_000,
+            &[(Instrument::Guitar, 6), (Instrument::Drums, 5)],
+        );
+        let two = song(
+            "Daft Punk",
+            "One More Time",
+            320_000,
+            &[(Instrument::Bass, 2)],
+        );
+
+        let filter = SongFilter::text("one");
+        assert!(filter.matches(&one) && filter.matches(&two));
+
+        let filter = SongFilter {
+            artist: Some("METALLICA".to_owned()),
+            ..Default::default()
+        };
+        assert!(filter.matches(&one) && !filter.matches(&two));
+
+        let filter = SongFilter {
+            instrument: Some(Instrument::Drums),
+            difficulty: parse_difficulty_bounds("..4").unwrap(),
+            ..Default::default()
+        };
+        assert!(!filter.matches(&one) && !filter.matches(&two));
+
+        let filter = SongFilter {
+            difficulty: parse_difficulty_bounds("..4").unwrap(),
+            ..Default::default()
+        };
+        assert!(!filter.matches(&one) && filter.matches(&two));
+
+        let filter = SongFilter {
+            length: parse_length_bounds("..6m").unwrap(),
+            ..SongFilter::text("one")
+        };
+        assert!(!filter.matches(&one) && filter.matches(&two));
+
+        let mut favorite = one.clone();
+        favorite.user_data.favorite = true;
+        favorite.user_data.add_tag("Warmup");
+        favorite.user_data.set_rating(Some(4));
+
+        let filter = SongFilter {
+            tag: Some("warmup".to_owned()),
+            favorite: true,
+            rating: parse_rating_bounds("4..").unwrap(),
+            ..Default::default()
+        };
+        assert!(filter.matches(&favorite) && !filter.matches(&one));
+    }
+}
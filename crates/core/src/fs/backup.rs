@@ -1,6 +1,8 @@
+use super::encryption::{encrypt_file, EncryptWriter, TempArchive};
 use super::Result;
-use crate::backup::BackupFolder;
+use crate::backup::{BackupFolder, BackupManifest, ManifestFile, MANIFEST_NAME};
 use crate::error::FilesystemError;
+use crate::snapshot::read_hash;
 
 use path_slash::PathExt;
 use std::fs::File;
@@ -14,24 +16,43 @@ pub trait Backup {
     fn backup(&self) -> Result<()>;
 }
 
-/// Back up folders to a zip archive and save on the fs
+/// Back up folders to a zip archive and save on the fs, encrypted if a
+/// passphrase is given
 pub struct ZipBackup {
     src: Vec<BackupFolder>,
     dest: PathBuf,
+    passphrase: Option<String>,
 }
 
 impl ZipBackup {
-    pub(crate) fn new(src: Vec<BackupFolder>, dest: impl AsRef<Path>) -> ZipBackup {
+    pub(crate) fn new(
+        src: Vec<BackupFolder>,
+        dest: impl AsRef<Path>,
+        passphrase: Option<String>,
+    ) -> ZipBackup {
         ZipBackup {
             src,
             dest: dest.as_ref().to_owned(),
+            passphrase,
         }
     }
 }
 
 impl Backup for ZipBackup {
     fn backup(&self) -> Result<()> {
-        let output = BufWriter::new(File::create(&self.dest)?);
+        // Zip archives are seeked while written, so an encrypted archive is
+        // written to a temporary file next to it first and encrypted from
+        // there.
+        let (output, temp) = match self.passphrase {
+            Some(_) => {
+                let dir = self.dest.parent().unwrap_or_else(|| Path::new("."));
+                let (temp, file) = TempArchive::create(dir)?;
+                (file, Some(temp))
+            }
+            None => (File::create(&self.dest)?, None),
+        };
+
+        let output = BufWriter::new(output);
 
         let mut zip_writer = ZipWriter::new(output);
         let options = FileOptions::default()
@@ -39,12 +60,20 @@ impl Backup for ZipBackup {
             .unix_permissions(0o755);
 
         let mut buffer = vec![];
+        let mut manifest = BackupManifest::default();
 
         for folder in &self.src {
             let prefix = &folder.prefix;
             let path = &folder.path;
 
-            zip_write(path, prefix, &mut buffer, &mut zip_writer, options)?;
+            zip_write(
+                path,
+                prefix,
+                &mut buffer,
+                &mut zip_writer,
+                options,
+                &mut manifest,
+            )?;
 
             for entry in WalkDir::new(path)
                 .into_iter()
@@ -52,11 +81,26 @@ impl Backup for ZipBackup {
             {
                 let path = entry.path();
 
-                zip_write(path, prefix, &mut buffer, &mut zip_writer, options)?;
+                zip_write(
+                    path,
+                    prefix,
+                    &mut buffer,
+                    &mut zip_writer,
+                    options,
+                    &mut manifest,
+                )?;
             }
         }
 
-        zip_writer.finish()?;
+        // The manifest goes last, so it can be used to verify the archive.
+        zip_writer.start_file(MANIFEST_NAME, options)?;
+        zip_writer.write_all(&serde_json::to_vec_pretty(&manifest)?)?;
+
+        zip_writer.finish()?.flush()?;
+
+        if let (Some(temp), Some(passphrase)) = (&temp, &self.passphrase) {
+            encrypt_file(&temp.path, &self.dest, passphrase)?;
+        }
 
         Ok(())
     }
@@ -69,6 +113,7 @@ fn zip_write(
     buffer: &mut Vec<u8>,
     writer: &mut ZipWriter<BufWriter<File>>,
     options: FileOptions,
+    manifest: &mut BackupManifest,
 ) -> Result<()> {
     if !path.exists() {
         return Err(FilesystemError::FileDoesntExist {
@@ -76,6 +121,34 @@ fn zip_write(
         });
     }
 
+    let name = entry_name(path, prefix)?;
+
+    if path.is_dir() {
+        writer.add_directory(&name, options)?;
+    } else {
+        writer.start_file(&name, options)?;
+
+        let mut file = File::open(path)?;
+        file.read_to_end(buffer)?;
+
+        writer.write_all(buffer)?;
+
+        let (hash, size) = read_hash(&buffer[..])?;
+        manifest.files.push(ManifestFile {
+            path: name,
+            size,
+            hash,
+        });
+
+        buffer.clear();
+    }
+
+    Ok(())
+}
+
+/// Returns the name of `path` inside the archive, which is the path without
+/// `prefix` and with `/` separators.
+fn entry_name(path: &Path, prefix: &Path) -> Result<String> {
     // On windows, convers `\` to `/`
     let normalized_path = path
         .to_slash()
@@ -97,52 +170,90 @@ fn zip_write(
         })?
         .trim_start_matches('/');
 
-    if path.is_dir() {
-        writer.add_directory(name, options)?;
-    } else {
-        writer.start_file(name, options)?;
-
-        let mut file = File::open(path)?;
-        file.read_to_end(buffer)?;
-
-        writer.write_all(buffer)?;
-        buffer.clear();
-    }
-
-    Ok(())
+    Ok(name.to_owned())
 }
 
+/// Back up folders to a zstd compressed tar archive and save on the fs,
+/// encrypted if a passphrase is given
 pub struct ZstdBackup {
     src: Vec<BackupFolder>,
     dest: PathBuf,
     level: i32,
+    passphrase: Option<String>,
 }
 
 impl ZstdBackup {
-    pub(crate) fn new(src: Vec<BackupFolder>, dest: impl AsRef<Path>, level: i32) -> ZstdBackup {
+    pub(crate) fn new(
+        src: Vec<BackupFolder>,
+        dest: impl AsRef<Path>,
+        level: i32,
+        passphrase: Option<String>,
+    ) -> ZstdBackup {
         ZstdBackup {
             src,
             dest: dest.as_ref().to_owned(),
             level,
+            passphrase,
         }
     }
-}
 
-impl Backup for ZstdBackup {
-    fn backup(&self) -> Result<()> {
+    /// Writes the archive to `output`, returning it once the archive is
+    /// complete.
+    fn write_archive<W: Write>(&self, output: W) -> Result<W> {
         use zstd::stream::write::Encoder as ZstdEncoder;
 
-        let output = File::create(&self.dest)?;
         let mut enc = ZstdEncoder::new(output, self.level)?;
         enc.multithread(num_cpus::get() as u32)?;
-        let mut tar = tar::Builder::new(enc.auto_finish());
+        let mut tar = tar::Builder::new(enc);
+        let mut manifest = BackupManifest::default();
 
         for folder in &self.src {
             let path = folder.path.strip_prefix(&folder.prefix).unwrap();
             let src_path = folder.prefix.join(&folder.path);
+
+            // `append_dir_all` follows symlinks, so the manifest does as well.
+            for entry in WalkDir::new(&src_path).follow_links(true) {
+                let entry = entry?;
+
+                if entry.file_type().is_file() {
+                    let (hash, size) = read_hash(File::open(entry.path())?)?;
+
+                    manifest.files.push(ManifestFile {
+                        path: entry_name(entry.path(), &folder.prefix)?,
+                        size,
+                        hash,
+                    });
+                }
+            }
+
             tar.append_dir_all(path, src_path)?;
         }
-        tar.finish()?;
+
+        // The manifest goes last, so it can be used to verify the archive.
+        let manifest = serde_json::to_vec_pretty(&manifest)?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(manifest.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(chrono::Utc::now().timestamp() as u64);
+        header.set_cksum();
+        tar.append_data(&mut header, MANIFEST_NAME, &manifest[..])?;
+
+        Ok(tar.into_inner()?.finish()?)
+    }
+}
+
+impl Backup for ZstdBackup {
+    fn backup(&self) -> Result<()> {
+        let output = BufWriter::new(File::create(&self.dest)?);
+
+        // The compressed archive is encrypted while it's written.
+        match &self.passphrase {
+            Some(passphrase) => {
+                self.write_archive(EncryptWriter::new(output, passphrase)?)?
+                    .finish()?;
+            }
+            None => self.write_archive(output)?.flush()?,
+        }
 
         Ok(())
     }
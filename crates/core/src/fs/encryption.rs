@@ -0,0 +1,462 @@
+//! Passphrase based encryption of backup archives.
+//!
+//! An encrypted archive starts with a header holding the number of key
+//! derivation rounds, the salt, a check value for the derived key and the
+//! nonce prefix. The archive follows in chunks of `CHUNK_SIZE` bytes, each
+//! sealed with ChaCha20-Poly1305 using the STREAM construction, so reordered,
+//! changed or truncated chunks are noticed when decrypting. Only one chunk is
+//! held in memory at a time.
+//!
+//! zstd archives are encrypted and decrypted while streaming and never exist
+//! unencrypted on disk. Zip archives are seeked while written and read, so
+//! they're written to, or decrypted to, an unencrypted copy next to the
+//! archive while backing up, verifying or restoring. The copy is hidden, only
+//! readable by the current user on Unix and removed afterwards, but is left
+//! behind if CHmon is killed meanwhile.
+use super::Result;
+use crate::error::FilesystemError;
+
+use chacha20poly1305::aead::stream::{DecryptorBE32, EncryptorBE32};
+use chacha20poly1305::aead::NewAead;
+use chacha20poly1305::{ChaCha20Poly1305, Key};
+use hmac::Hmac;
+use sha2::Sha256;
+use std::ffi::OsStr;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Extension appended to the file name of encrypted archives.
+pub const ENCRYPTED_EXTENSION: &str = "enc";
+
+const MAGIC: &[u8; 8] = b"CHMONENC";
+const VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const CHECK_LEN: usize = 32;
+const NONCE_PREFIX_LEN: usize = 7;
+const TAG_LEN: usize = 16;
+const CHUNK_SIZE: usize = 64 * 1024;
+/// Headers asking for more rounds than this are rejected rather than
+/// spending minutes deriving a key.
+const MAX_ROUNDS: u32 = 10_000_000;
+
+#[cfg(not(test))]
+const ROUNDS: u32 = 200_000;
+// Key derivation is slow without optimizations.
+#[cfg(test)]
+const ROUNDS: u32 = 1_000;
+
+/// Returns true if `path` is an encrypted archive.
+pub fn is_encrypted(path: &Path) -> bool {
+    path.extension() == Some(OsStr::new(ENCRYPTED_EXTENSION))
+}
+
+/// Derives the key and the check value stored in the header from the
+/// passphrase.
+fn derive_key(passphrase: &str, salt: &[u8], rounds: u32) -> ([u8; 32], [u8; CHECK_LEN]) {
+    let mut output = [0; 32 + CHECK_LEN];
+    pbkdf2::pbkdf2::<Hmac<Sha256>>(passphrase.as_bytes(), salt, rounds, &mut output);
+
+    let mut key = [0; 32];
+    let mut check = [0; CHECK_LEN];
+    key.copy_from_slice(&output[..32]);
+    check.copy_from_slice(&output[32..]);
+
+    (key, check)
+}
+
+fn random_bytes(buffer: &mut [u8]) -> io::Result<()> {
+    getrandom::getrandom(buffer).map_err(io::Error::other)
+}
+
+fn sealing_failed() -> io::Error {
+    io::Error::other("failed to encrypt backup chunk")
+}
+
+fn corrupt_chunk() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "encrypted backup is corrupt or truncated",
+    )
+}
+
+/// Encrypts everything written to it. `finish` must be called once done,
+/// which seals the last chunk.
+pub struct EncryptWriter<W: Write> {
+    inner: W,
+    encryptor: Option<EncryptorBE32<ChaCha20Poly1305>>,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> EncryptWriter<W> {
+    /// Writes the header to `inner` and returns a writer encrypting with a
+    /// key derived from `passphrase`.
+    pub fn new(mut inner: W, passphrase: &str) -> io::Result<EncryptWriter<W>> {
+        let mut salt = [0; SALT_LEN];
+        let mut nonce_prefix = [0; NONCE_PREFIX_LEN];
+        random_bytes(&mut salt)?;
+        random_bytes(&mut nonce_prefix)?;
+
+        let (key, check) = derive_key(passphrase, &salt, ROUNDS);
+
+        inner.write_all(MAGIC)?;
+        inner.write_all(&[VERSION])?;
+        inner.write_all(&ROUNDS.to_be_bytes())?;
+        inner.write_all(&salt)?;
+        inner.write_all(&check)?;
+        inner.write_all(&nonce_prefix)?;
+
+        let aead = ChaCha20Poly1305::new(&Key::from(key));
+
+        Ok(EncryptWriter {
+            inner,
+            encryptor: Some(EncryptorBE32::from_aead(aead, (&nonce_prefix).into())),
+            buffer: Vec::with_capacity(CHUNK_SIZE + TAG_LEN),
+        })
+    }
+
+    /// Seals the buffered chunk and writes it out.
+    fn write_chunk(&mut self) -> io::Result<()> {
+        let encryptor = self.encryptor.as_mut().ok_or_else(sealing_failed)?;
+        encryptor
+            .encrypt_next_in_place(b"", &mut self.buffer)
+            .map_err(|_| sealing_failed())?;
+
+        self.inner.write_all(&self.buffer)?;
+        self.buffer.clear();
+
+        Ok(())
+    }
+
+    /// Seals the last chunk, which is always shorter than `CHUNK_SIZE` so the
+    /// reader can tell it apart, and returns the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let encryptor = self.encryptor.take().ok_or_else(sealing_failed)?;
+        encryptor
+            .encrypt_last_in_place(b"", &mut self.buffer)
+            .map_err(|_| sealing_failed())?;
+
+        self.inner.write_all(&self.buffer)?;
+        self.inner.flush()?;
+
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for EncryptWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(CHUNK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..len]);
+
+        if self.buffer.len() == CHUNK_SIZE {
+            self.write_chunk()?;
+        }
+
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Decrypts an archive written by `EncryptWriter` while reading it.
+pub struct DecryptReader<R: Read> {
+    inner: R,
+    decryptor: Option<DecryptorBE32<ChaCha20Poly1305>>,
+    buffer: Vec<u8>,
+    position: usize,
+}
+
+impl<R: Read> DecryptReader<R> {
+    /// Reads the header from `inner`. Fails with
+    /// `FilesystemError::WrongPassphrase` if `passphrase` isn't the one the
+    /// archive was encrypted with.
+    pub fn new(mut inner: R, passphrase: &str) -> Result<DecryptReader<R>> {
+        let mut magic = [0; 8];
+        let mut version = [0; 1];
+        let mut rounds = [0; 4];
+        let mut salt = [0; SALT_LEN];
+        let mut check = [0; CHECK_LEN];
+        let mut nonce_prefix = [0; NONCE_PREFIX_LEN];
+
+        inner.read_exact(&mut magic)?;
+        inner.read_exact(&mut version)?;
+
+        if &magic != MAGIC || version[0] != VERSION {
+            return Err(FilesystemError::Io(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not an encrypted backup",
+            )));
+        }
+
+        inner.read_exact(&mut rounds)?;
+        inner.read_exact(&mut salt)?;
+        inner.read_exact(&mut check)?;
+        inner.read_exact(&mut nonce_prefix)?;
+
+        let rounds = u32::from_be_bytes(rounds);
+        if rounds == 0 || rounds > MAX_ROUNDS {
+            return Err(FilesystemError::Io(corrupt_chunk()));
+        }
+
+        let (key, expected_check) = derive_key(passphrase, &salt, rounds);
+
+        if check != expected_check {
+            return Err(FilesystemError::WrongPassphrase);
+        }
+
+        let aead = ChaCha20Poly1305::new(&Key::from(key));
+
+        Ok(DecryptReader {
+            inner,
+            decryptor: Some(DecryptorBE32::from_aead(aead, (&nonce_prefix).into())),
+            buffer: Vec::with_capacity(CHUNK_SIZE + TAG_LEN),
+            position: 0,
+        })
+    }
+
+    /// Reads and decrypts the next chunk. A chunk shorter than a full one is
+    /// the last.
+    fn read_chunk(&mut self) -> io::Result<()> {
+        self.buffer.resize(CHUNK_SIZE + TAG_LEN, 0);
+        self.position = 0;
+
+        let mut len = 0;
+        while len < self.buffer.len() {
+            match self.inner.read(&mut self.buffer[len..]) {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        self.buffer.truncate(len);
+
+        if len == CHUNK_SIZE + TAG_LEN {
+            let decryptor = self.decryptor.as_mut().ok_or_else(corrupt_chunk)?;
+            decryptor
+                .decrypt_next_in_place(b"", &mut self.buffer)
+                .map_err(|_| corrupt_chunk())
+        } else {
+            let decryptor = self.decryptor.take().ok_or_else(corrupt_chunk)?;
+            decryptor
+                .decrypt_last_in_place(b"", &mut self.buffer)
+                .map_err(|_| corrupt_chunk())
+        }
+    }
+}
+
+impl<R: Read> Read for DecryptReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.buffer.len() {
+            if self.decryptor.is_none() {
+                return Ok(0);
+            }
+
+            self.read_chunk()?;
+        }
+
+        let len = buf.len().min(self.buffer.len() - self.position);
+        buf[..len].copy_from_slice(&self.buffer[self.position..self.position + len]);
+        self.position += len;
+
+        Ok(len)
+    }
+}
+
+/// Returns a hidden path in `dir` for a decrypted or not yet encrypted zip
+/// archive.
+fn temp_archive_path(dir: &Path) -> io::Result<PathBuf> {
+    let mut suffix = [0; 8];
+    random_bytes(&mut suffix)?;
+
+    let suffix: String = suffix.iter().map(|b| format!("{:02x}", b)).collect();
+
+    Ok(dir.join(format!(".chmon_backup_{}.zip.tmp", suffix)))
+}
+
+/// Creates a new file which only the current user can read and write.
+fn create_private(path: &Path) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.read(true).write(true).create_new(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    options.open(path)
+}
+
+/// Encrypts the file at `src` to `dest`.
+pub(crate) fn encrypt_file(src: &Path, dest: &Path, passphrase: &str) -> Result<()> {
+    let mut input = BufReader::new(File::open(src)?);
+    let mut writer = EncryptWriter::new(BufWriter::new(File::create(dest)?), passphrase)?;
+
+    io::copy(&mut input, &mut writer)?;
+    writer.finish()?;
+
+    Ok(())
+}
+
+/// Returns the passphrase for reading the archive at `path`, failing if it's
+/// encrypted and no passphrase was given.
+fn required_passphrase<'a>(path: &Path, passphrase: Option<&'a str>) -> Result<Option<&'a str>> {
+    if !is_encrypted(path) {
+        return Ok(None);
+    }
+
+    passphrase
+        .map(Some)
+        .ok_or_else(|| FilesystemError::PassphraseRequired {
+            path: path.to_owned(),
+        })
+}
+
+/// Opens an archive for reading it from start to end, decrypting it if it's
+/// encrypted.
+pub(crate) fn open_archive(path: &Path, passphrase: Option<&str>) -> Result<Box<dyn Read>> {
+    let file = BufReader::new(File::open(path)?);
+
+    Ok(match required_passphrase(path, passphrase)? {
+        Some(passphrase) => Box::new(DecryptReader::new(file, passphrase)?),
+        None => Box::new(file),
+    })
+}
+
+/// An archive which can be read in any order, as zip archives need. An
+/// encrypted archive is decrypted to a temporary file next to it, which is
+/// removed when this is dropped.
+pub(crate) struct SeekableArchive {
+    file: File,
+    temp: Option<PathBuf>,
+}
+
+impl SeekableArchive {
+    pub(crate) fn open(path: &Path, passphrase: Option<&str>) -> Result<SeekableArchive> {
+        let passphrase = match required_passphrase(path, passphrase)? {
+            Some(passphrase) => passphrase,
+            None => {
+                return Ok(SeekableArchive {
+                    file: File::open(path)?,
+                    temp: None,
+                })
+            }
+        };
+
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        let temp = temp_archive_path(dir)?;
+        let mut reader = DecryptReader::new(BufReader::new(File::open(path)?), passphrase)?;
+
+        // Removes the temporary file again if decrypting fails.
+        let mut archive = SeekableArchive {
+            file: create_private(&temp)?,
+            temp: Some(temp),
+        };
+        let mut writer = BufWriter::new(&mut archive.file);
+        io::copy(&mut reader, &mut writer)?;
+        writer.flush()?;
+        drop(writer);
+
+        archive.file.seek(SeekFrom::Start(0))?;
+
+        Ok(archive)
+    }
+}
+
+impl Read for SeekableArchive {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Seek for SeekableArchive {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+impl Drop for SeekableArchive {
+    fn drop(&mut self) {
+        if let Some(temp) = &self.temp {
+            let _ = std::fs::remove_file(temp);
+        }
+    }
+}
+
+/// A hidden file which is removed when dropped, used to write a zip archive
+/// before encrypting it.
+pub(crate) struct TempArchive {
+    pub path: PathBuf,
+}
+
+impl TempArchive {
+    /// Creates the file in `dir`, only readable by the current user, and
+    /// returns it opened for writing.
+    pub(crate) fn create(dir: &Path) -> Result<(TempArchive, File)> {
+        let path = temp_archive_path(dir)?;
+        let file = create_private(&path)?;
+
+        Ok((TempArchive { path }, file))
+    }
+}
+
+impl Drop for TempArchive {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn encrypt(data: &[u8], passphrase: &str) -> Vec<u8> {
+        let mut writer = EncryptWriter::new(vec![], passphrase).unwrap();
+        writer.write_all(data).unwrap();
+        writer.finish().unwrap()
+    }
+
+    fn decrypt(data: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+        let mut reader = DecryptReader::new(data, passphrase)?;
+        let mut output = vec![];
+        reader.read_to_end(&mut output)?;
+
+        Ok(output)
+    }
+
+    #[test]
+    fn test_encryption_round_trip() {
+        // Empty, shorter than a chunk, exactly two chunks and a partial chunk.
+        for len in [0, 1000, CHUNK_SIZE * 2, CHUNK_SIZE * 2 + 10].iter() {
+            let data: Vec<u8> = (0..*len).map(|i| (i % 251) as u8).collect();
+            let encrypted = encrypt(&data, "hunter2");
+
+            assert_eq!(decrypt(&encrypted, "hunter2").unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_decryption_errors() {
+        let data = vec![7; CHUNK_SIZE * 2];
+        let encrypted = encrypt(&data, "hunter2");
+
+        assert!(matches!(
+            decrypt(&encrypted, "hunter3"),
+            Err(FilesystemError::WrongPassphrase)
+        ));
+
+        // Dropping the last chunk is noticed even though it ends on a chunk.
+        let truncated = &encrypted[..encrypted.len() - TAG_LEN];
+        assert!(decrypt(truncated, "hunter2").is_err());
+
+        let mut changed = encrypted.clone();
+        let last = changed.len() - 1;
+        changed[last] ^= 1;
+        assert!(decrypt(&changed, "hunter2").is_err());
+
+        assert!(decrypt(&data, "hunter2").is_err());
+    }
+}
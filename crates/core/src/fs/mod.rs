@@ -9,12 +9,16 @@ use std::sync::Mutex;
 
 mod addon;
 pub mod backup;
+pub mod encryption;
 mod save;
+mod song;
 #[cfg(feature = "gui")]
 mod theme;
 
 pub use addon::{delete_addons, delete_saved_variables, install_addon};
 pub use save::PersistentData;
+pub use song::{check_available_space, folder_size, quarantine_song};
+pub(crate) use song::{copy_dir, move_dir, replace_dir, sibling_dir, unique_destination};
 #[cfg(feature = "gui")]
 pub use theme::{import_theme, load_user_themes};
 
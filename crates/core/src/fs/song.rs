@@ -0,0 +1,253 @@
+use super::Result;
+use crate::error::FilesystemError;
+use crate::song::Song;
+use crate::utility::rename;
+
+use std::fs::{copy, create_dir_all, remove_dir_all};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Moves a song folder into the quarantine directory, keeping its path
+/// relative to the library root. Returns the new location of the folder.
+///
+/// If a song with the same path has been quarantined before, a number is
+/// appended to the folder name.
+pub async fn quarantine_song(song: &Song, quarantine_directory: &Path) -> Result<PathBuf> {
+    let to = unique_destination(quarantine_directory.join(&song.id));
+
+    move_dir(&song.path, &to)?;
+
+    log::debug!("quarantined {:?} to {:?}", &song.path, &to);
+
+    Ok(to)
+}
+
+/// Returns `path`, or `path` with ` (1)`, ` (2)`, ... appended to the folder
+/// name if it already exists.
+pub(crate) fn unique_destination(path: PathBuf) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+
+    let mut to = path;
+    let mut idx = 1;
+
+    while to.exists() {
+        to.set_file_name(format!("{} ({})", file_name, idx));
+        idx += 1;
+    }
+
+    to
+}
+
+/// Moves a directory, copying it if it can't be renamed because `to` is on
+/// another filesystem.
+pub(crate) fn move_dir(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        create_dir_all(parent)?;
+    }
+
+    if rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    // A failed copy is cleaned up by `copy_dir`, leaving `from` untouched.
+    copy_dir(from, to)?;
+    remove_dir_all(from)?;
+
+    Ok(())
+}
+
+/// Returns a hidden folder next to `path`, named after it with `suffix`
+/// appended. Used to stage a replacement on the same filesystem.
+pub(crate) fn sibling_dir(path: &Path, suffix: &str) -> PathBuf {
+    path.with_file_name(format!(
+        ".{}.{}",
+        path.file_name().unwrap_or_default().to_string_lossy(),
+        suffix
+    ))
+}
+
+/// Replaces the directory `to` with `from`.
+///
+/// An existing `to` is moved aside and only deleted once `from` is in its
+/// place. If `from` can't be moved, the old directory is put back.
+pub(crate) fn replace_dir(from: &Path, to: &Path) -> Result<()> {
+    if !to.exists() {
+        rename(from, to)?;
+        return Ok(());
+    }
+
+    let old = sibling_dir(to, "chmon-old");
+
+    if old.exists() {
+        remove_dir_all(&old)?;
+    }
+
+    rename(to, &old)?;
+
+    if let Err(error) = rename(from, to) {
+        if let Err(error) = rename(&old, to) {
+            log::error!("failed to move {:?} back to {:?}: {}", &old, to, error);
+        }
+
+        return Err(error.into());
+    }
+
+    if let Err(error) = remove_dir_all(&old) {
+        log::error!("failed to remove {:?}: {}", &old, error);
+    }
+
+    Ok(())
+}
+
+/// Copies a directory and everything in it.
+///
+/// If the copy fails, whatever was copied so far is removed again, unless
+/// `to` existed before.
+pub(crate) fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    let existed = to.exists();
+    let result = copy_dir_entries(from, to);
+
+    if result.is_err() && !existed {
+        if let Err(error) = remove_dir_all(to) {
+            log::error!("failed to remove partial copy {:?}: {}", to, error);
+        }
+    }
+
+    result
+}
+
+fn copy_dir_entries(from: &Path, to: &Path) -> Result<()> {
+    for entry in WalkDir::new(from).into_iter() {
+        let entry = entry?;
+        let target = to.join(entry.path().strip_prefix(from).unwrap_or(entry.path()));
+
+        if entry.file_type().is_dir() {
+            create_dir_all(&target)?;
+        } else {
+            copy(entry.path(), &target)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the size in bytes of all files in a directory.
+pub fn folder_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(std::result::Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Checks that the filesystem of `target` has room for `folders`, returning
+/// the number of bytes required.
+///
+/// Moves within the same filesystem don't need any space, but are checked
+/// the same way since that can't be told apart on every platform.
+pub async fn check_available_space(folders: Vec<PathBuf>, target: PathBuf) -> Result<u64> {
+    let required = folders.iter().map(|folder| folder_size(folder)).sum();
+    let available = fs2::available_space(&target)?;
+
+    if required > available {
+        return Err(FilesystemError::InsufficientSpace {
+            path: target,
+            required,
+            available,
+        });
+    }
+
+    Ok(required)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use async_std::task;
+
+    #[test]
+    fn test_quarantine_song() {
+        let root = tempfile::tempdir().unwrap();
+        let quarantine = tempfile::tempdir().unwrap();
+
+        let quarantine_song_at = |id: &str| {
+            let path = root.path().join(id);
+            create_dir_all(&path).unwrap();
+            std::fs::write(path.join("notes.chart"), "").unwrap();
+
+            let song = Song::new(id.to_owned(), path, root.path().to_owned());
+            task::block_on(quarantine_song(&song, quarantine.path())).unwrap()
+        };
+
+        let first = quarantine_song_at("Artist/Song");
+        assert_eq!(first, quarantine.path().join("Artist/Song"));
+        assert!(first.join("notes.chart").is_file());
+        assert!(!root.path().join("Artist/Song").exists());
+
+        let second = quarantine_song_at("Artist/Song");
+        assert_eq!(second, quarantine.path().join("Artist/Song (1)"));
+    }
+
+    #[test]
+    fn test_replace_dir() {
+        let root = tempfile::tempdir().unwrap();
+        let to = root.path().join("Pack");
+        let staged = sibling_dir(&to, "chmon-staging");
+        assert_eq!(staged, root.path().join(".Pack.chmon-staging"));
+
+        create_dir_all(&staged).unwrap();
+        std::fs::write(staged.join("new.chart"), "").unwrap();
+        replace_dir(&staged, &to).unwrap();
+        assert!(to.join("new.chart").is_file());
+
+        create_dir_all(&staged).unwrap();
+        std::fs::write(staged.join("newer.chart"), "").unwrap();
+        replace_dir(&staged, &to).unwrap();
+        assert!(to.join("newer.chart").is_file());
+        assert!(!to.join("new.chart").exists());
+        assert!(!staged.exists());
+        assert!(!sibling_dir(&to, "chmon-old").exists());
+
+        // The old directory is kept if there's nothing to replace it with.
+        assert!(replace_dir(&staged, &to).is_err());
+        assert!(to.join("newer.chart").is_file());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_copy_dir_removes_partial_copy() {
+        let root = tempfile::tempdir().unwrap();
+        let from = root.path().join("from");
+        let to = root.path().join("to");
+
+        create_dir_all(&from).unwrap();
+        std::fs::write(from.join("notes.chart"), "").unwrap();
+        // Copying the broken link fails after the folder was created.
+        std::os::unix::fs::symlink(root.path().join("missing"), from.join("song.ogg")).unwrap();
+
+        assert!(copy_dir(&from, &to).is_err());
+        assert!(from.join("notes.chart").is_file());
+        assert!(!to.exists());
+    }
+
+    #[test]
+    fn test_check_available_space() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(root.path().join("notes.chart"), [0; 100]).unwrap();
+
+        let folders = vec![root.path().to_owned()];
+
+        assert_eq!(folder_size(root.path()), 100);
+        assert_eq!(
+            task::block_on(check_available_space(folders, root.path().to_owned())).unwrap(),
+            100
+        );
+    }
+}
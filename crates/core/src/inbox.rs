@@ -0,0 +1,381 @@
+use crate::{
+    cache::SongCache,
+    error::FilesystemError,
+    fs::{copy_dir, move_dir, unique_destination, PersistentData},
+    library::{chart_file, find_song_folders, read_song_folder, validate_song_folder},
+    organizer::sanitize_name,
+    pack::extract_archive,
+    song::{Song, SongState},
+    utility::rename,
+};
+
+use async_std::sync::{Arc, Mutex};
+
+use std::fs::{create_dir_all, remove_dir_all, File};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Folder in the inbox successfully imported items are moved to.
+pub const PROCESSED_FOLDER: &str = "processed";
+
+/// Folder in the inbox items which couldn't be imported are moved to.
+pub const FAILED_FOLDER: &str = "failed";
+
+/// Time the inbox has to be quiet before new items are imported, so
+/// downloads and copies can finish first.
+pub const INBOX_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// Archive formats songs are often shared as, which can't be extracted.
+/// They are moved to `failed/` instead of being left in the inbox.
+const UNSUPPORTED_ARCHIVES: [&str; 2] = ["rar", "7z"];
+
+/// Outcome of importing an archive or folder from the inbox.
+#[derive(Debug, Clone)]
+pub struct InboxImport {
+    /// File name of the archive or folder in the inbox.
+    pub name: String,
+    /// Where the original was moved to, in `processed/` or `failed/`.
+    pub moved_to: PathBuf,
+    /// Songs installed into the library.
+    pub songs: Vec<Song>,
+    /// Why the import failed, if it did. Nothing is installed then.
+    pub error: Option<String>,
+}
+
+/// Returns the archives and folders waiting in the inbox.
+pub fn inbox_items(inbox: &Path) -> Vec<PathBuf> {
+    let mut items: Vec<_> = inbox
+        .read_dir()
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| is_inbox_item(inbox, path))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    items.sort();
+    items
+}
+
+/// Returns the items of the inbox which `changes`, as reported by a
+/// recursive watcher, touched and which are still there.
+pub fn changed_inbox_items(inbox: &Path, changes: &[PathBuf]) -> Vec<PathBuf> {
+    let mut items: Vec<_> = changes
+        .iter()
+        .filter_map(|path| path.strip_prefix(inbox).ok()?.components().next())
+        .map(|name| inbox.join(name))
+        .filter(|path| path.exists() && is_inbox_item(inbox, path))
+        .collect();
+
+    items.sort();
+    items.dedup();
+    items
+}
+
+/// Returns true for folders and archives directly inside the inbox, other
+/// than the `processed` and `failed` folders. Hidden files and unfinished
+/// downloads are skipped.
+fn is_inbox_item(inbox: &Path, path: &Path) -> bool {
+    if path.parent() != Some(inbox) {
+        return false;
+    }
+
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    if name.starts_with('.') || name == PROCESSED_FOLDER || name == FAILED_FOLDER {
+        return false;
+    }
+
+    path.is_dir() || is_zip(path) || is_unsupported_archive(path)
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+fn is_zip(path: &Path) -> bool {
+    path.is_file() && extension(path) == "zip"
+}
+
+fn is_unsupported_archive(path: &Path) -> bool {
+    path.is_file() && UNSUPPORTED_ARCHIVES.contains(&extension(path).as_str())
+}
+
+/// Imports an archive or song folder from the inbox into `library`.
+///
+/// Every song in it is validated first, and nothing is installed if any
+/// song has problems. The songs are installed into a folder named after the
+/// item, or directly as a song folder if the item is a single song. The
+/// original is then moved to `processed/`, or to `failed/` if it couldn't
+/// be imported.
+pub async fn import_inbox_item(
+    song_cache: Option<Arc<Mutex<SongCache>>>,
+    inbox: PathBuf,
+    item: PathBuf,
+    library: PathBuf,
+) -> Result<InboxImport, FilesystemError> {
+    let name = item
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+
+    let result = install_item(&item, &library).await;
+
+    let folder = if result.is_ok() {
+        PROCESSED_FOLDER
+    } else {
+        FAILED_FOLDER
+    };
+    let moved_to = unique_destination(inbox.join(folder).join(&name));
+    move_item(&item, &moved_to)?;
+
+    let import = match result {
+        Ok(songs) => {
+            if let Some(song_cache) = song_cache {
+                let mut cache = song_cache.lock().await;
+                for song in songs.iter() {
+                    cache.replace_song(song);
+                }
+                cache.save()?;
+            }
+
+            log::debug!("imported {} songs from {:?}", songs.len(), &item);
+
+            InboxImport {
+                name,
+                moved_to,
+                songs,
+                error: None,
+            }
+        }
+        Err(error) => {
+            log::debug!("failed to import {:?}: {}", &item, &error);
+
+            InboxImport {
+                name,
+                moved_to,
+                songs: vec![],
+                error: Some(error),
+            }
+        }
+    };
+
+    Ok(import)
+}
+
+/// Validates the songs of an inbox item and installs them into `library`,
+/// returning the installed songs or why they weren't installed.
+async fn install_item(item: &Path, library: &Path) -> Result<Vec<Song>, String> {
+    if is_unsupported_archive(item) {
+        return Err(format!(
+            "{} archives aren't supported, extract it into the inbox instead",
+            extension(item)
+        ));
+    }
+
+    let stem = if item.is_dir() {
+        item.file_name()
+    } else {
+        item.file_stem()
+    };
+    let destination = unique_destination(
+        library.join(sanitize_name(&stem.unwrap_or_default().to_string_lossy())),
+    );
+
+    if item.is_dir() {
+        validate_songs(item).await?;
+
+        copy_dir(item, &destination).map_err(|e| e.to_string())?;
+    } else {
+        // Archives are extracted next to where they're installed, so they can
+        // be moved into place once they're valid.
+        let staging = unique_destination(library.join(format!(
+            ".inbox-{}",
+            destination.file_name().unwrap_or_default().to_string_lossy()
+        )));
+
+        let result = extract_zip(item, &staging).map_err(|e| e.to_string());
+        let result = match result {
+            Ok(_) => validate_songs(&staging).await,
+            Err(error) => Err(error),
+        };
+        let result =
+            result.and_then(|_| move_dir(&staging, &destination).map_err(|e| e.to_string()));
+
+        if staging.exists() {
+            let _ = remove_dir_all(&staging);
+        }
+
+        result?;
+    }
+
+    Ok(song_folders(&destination)
+        .iter()
+        .map(|folder| read_song_folder(library, folder))
+        .collect())
+}
+
+fn extract_zip(archive: &Path, to: &Path) -> Result<(), FilesystemError> {
+    let mut archive = zip::ZipArchive::new(File::open(archive)?)?;
+
+    extract_archive(&mut archive, Path::new(""), to)
+}
+
+/// Returns the song folders of an item, which can be a song folder itself.
+fn song_folders(path: &Path) -> Vec<PathBuf> {
+    if chart_file(path).is_some() {
+        vec![path.to_owned()]
+    } else {
+        find_song_folders(path)
+    }
+}
+
+/// Checks every song below `path`, failing with the problems found.
+async fn validate_songs(path: &Path) -> Result<(), String> {
+    let folders = song_folders(path);
+
+    if folders.is_empty() {
+        return Err("No songs found".to_owned());
+    }
+
+    let root = path.parent().unwrap_or(path).to_owned();
+
+    let mut problems = vec![];
+    for folder in folders {
+        let song = validate_song_folder(root.clone(), folder).await;
+
+        if let SongState::Error(error) = song.state {
+            problems.push(format!("{}: {}", song.id, error));
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(problems.join("; "))
+    }
+}
+
+/// Moves a file or folder, copying it if it can't be renamed because `to`
+/// is on another filesystem.
+fn move_item(from: &Path, to: &Path) -> Result<(), FilesystemError> {
+    if from.is_dir() {
+        return move_dir(from, to);
+    }
+
+    if let Some(parent) = to.parent() {
+        create_dir_all(parent)?;
+    }
+
+    if rename(from, to).is_err() {
+        std::fs::copy(from, to)?;
+        std::fs::remove_file(from)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use async_std::task;
+    use std::fs;
+    use std::io::Write;
+
+    fn write_song(folder: &Path) {
+        fs::create_dir_all(folder).unwrap();
+        fs::write(
+            folder.join("notes.chart"),
+            "[Song]\n{\n  Name = \"Song\"\n}\n",
+        )
+        .unwrap();
+        fs::write(folder.join("song.ogg"), "audio").unwrap();
+    }
+
+    #[test]
+    fn test_import_inbox_items() {
+        task::block_on(async {
+            let inbox = tempfile::tempdir().unwrap();
+            let library = tempfile::tempdir().unwrap();
+            let (inbox, library) = (inbox.path(), library.path());
+
+            // A zip with a song at its root.
+            let mut zip = zip::ZipWriter::new(File::create(inbox.join("Song.zip")).unwrap());
+            zip.start_file("notes.chart", Default::default()).unwrap();
+            zip.write_all(b"[Song]\n{\n}\n").unwrap();
+            zip.start_file("song.ogg", Default::default()).unwrap();
+            zip.write_all(b"audio").unwrap();
+            zip.finish().unwrap();
+
+            // A folder with two songs, and one without audio.
+            write_song(&inbox.join("Pack").join("One"));
+            write_song(&inbox.join("Pack").join("Two"));
+            write_song(&inbox.join("Broken"));
+            fs::remove_file(inbox.join("Broken").join("song.ogg")).unwrap();
+
+            fs::write(inbox.join("Song.zip.part"), "").unwrap();
+            fs::write(inbox.join("Other.rar"), "").unwrap();
+
+            let items = inbox_items(inbox);
+            assert_eq!(
+                items,
+                vec![
+                    inbox.join("Broken"),
+                    inbox.join("Other.rar"),
+                    inbox.join("Pack"),
+                    inbox.join("Song.zip"),
+                ]
+            );
+
+            let mut imports = vec![];
+            for item in items {
+                imports.push(
+                    import_inbox_item(None, inbox.to_owned(), item, library.to_owned())
+                        .await
+                        .unwrap(),
+                );
+            }
+
+            assert!(imports[0].error.as_ref().unwrap().contains("No audio"));
+            assert_eq!(
+                imports[0].moved_to,
+                inbox.join(FAILED_FOLDER).join("Broken")
+            );
+            assert!(imports[1].error.is_some());
+            assert_eq!(imports[2].songs.len(), 2);
+            assert_eq!(
+                imports[2].moved_to,
+                inbox.join(PROCESSED_FOLDER).join("Pack")
+            );
+            assert_eq!(imports[3].songs.len(), 1);
+            assert_eq!(imports[3].songs[0].path, library.join("Song"));
+
+            assert!(library
+                .join("Pack")
+                .join("Two")
+                .join("notes.chart")
+                .is_file());
+            assert!(!library.join("Broken").exists());
+            assert!(inbox.join(FAILED_FOLDER).join("Other.rar").is_file());
+            assert!(inbox.join(PROCESSED_FOLDER).join("Song.zip").is_file());
+
+            // Only the unfinished download is left.
+            assert!(inbox_items(inbox).is_empty());
+            assert_eq!(
+                changed_inbox_items(
+                    inbox,
+                    &[
+                        inbox.join(PROCESSED_FOLDER).join("Song.zip"),
+                        inbox.join("Song.zip.part")
+                    ]
+                ),
+                Vec::<PathBuf>::new()
+            );
+        });
+    }
+}
@@ -1,15 +1,34 @@
 pub mod addon;
+pub mod autoupdate;
 pub mod backup;
 pub mod cache;
 pub mod catalog;
+pub mod clone_hero;
 pub mod config;
 pub mod error;
+pub mod filter;
 pub mod fs;
+pub mod inbox;
+pub mod library;
 pub mod murmur2;
 pub mod network;
+pub mod organizer;
+pub mod origin;
+pub mod pack;
 pub mod parse;
+pub mod query;
+pub mod report;
 pub mod repository;
+pub mod restore;
+pub mod s3;
 pub mod share;
+pub mod snapshot;
+pub mod song;
+pub mod stats;
 #[cfg(feature = "gui")]
 pub mod theme;
+pub mod transfer;
+pub mod userdata;
 pub mod utility;
+pub mod versions;
+pub mod watch;
@@ -0,0 +1,787 @@
+use crate::{
+    cache::{SongCache, SongCacheEntry},
+    clone_hero::{
+        load_song_cache_file,
+        songcache::{to_ticks, SongCacheFile},
+    },
+    error::ParseError,
+    fs::PersistentData,
+    origin::read_song_origin,
+    song::{Instrument, Song, SongMetadata, SongState},
+};
+
+use async_std::sync::{Arc, Mutex};
+use chrono::{DateTime, Utc};
+use path_slash::PathExt;
+use rayon::prelude::*;
+use walkdir::WalkDir;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Files Clone Hero reads notes from, in order of preference.
+pub const CHART_FILES: [&str; 2] = ["notes.mid", "notes.chart"];
+
+/// Extensions of the audio files Clone Hero can play.
+pub const AUDIO_EXTENSIONS: [&str; 4] = ["ogg", "mp3", "opus", "wav"];
+
+/// Time a library root has to be quiet before changed song folders are read
+/// again.
+pub const LIBRARY_DEBOUNCE: Duration = Duration::from_secs(1);
+
+/// Scans a library root for song folders.
+///
+/// Song folders which haven't been modified since the last scan are
+/// read from the `SongCache`, everything else is parsed from disk.
+pub async fn read_song_directory<P: AsRef<Path>>(
+    song_cache: Option<Arc<Mutex<SongCache>>>,
+    root_dir: P,
+) -> Result<Vec<Song>, ParseError> {
+    let root_dir = root_dir.as_ref();
+
+    log::debug!("{:?} - parsing song folder", root_dir);
+
+    if !root_dir.is_dir() {
+        return Err(ParseError::MissingSongDirectory {
+            path: root_dir.to_owned(),
+        });
+    }
+
+    let folders = find_song_folders(root_dir);
+
+    log::debug!("{:?} - {} song folders found", root_dir, folders.len());
+
+    let cached_entries: HashMap<PathBuf, SongCacheEntry> = if let Some(song_cache) = &song_cache {
+        let mut cache = song_cache.lock().await;
+
+        cache
+            .get_mut_for_root(root_dir)
+            .iter()
+            .map(|entry| (entry.path.clone(), entry.clone()))
+            .collect()
+    } else {
+        HashMap::new()
+    };
+
+    let songs: Vec<(Song, Option<DateTime<Utc>>)> = folders
+        .par_iter()
+        .map(|path| {
+            let modified = song_folder_modified(path);
+
+            let song = match cached_entries.get(path) {
+                Some(entry) if Some(entry.modified) == modified => {
+                    song_from_cache_entry(root_dir, entry)
+                }
+                _ => read_song_folder(root_dir, path),
+            };
+
+            (song, modified)
+        })
+        .collect();
+
+    log::debug!(
+        "{:?} - {} songs read from cache",
+        root_dir,
+        songs
+            .iter()
+            .filter(|(song, modified)| cached_entries
+                .get(&song.path)
+                .map(|entry| Some(entry.modified) == *modified)
+                .unwrap_or_default())
+            .count()
+    );
+
+    if let Some(song_cache) = &song_cache {
+        let mut cache = song_cache.lock().await;
+
+        let entries = cache.get_mut_for_root(root_dir);
+        *entries = songs
+            .iter()
+            .filter(|(song, _)| !matches!(song.state, SongState::Error(_)))
+            .filter_map(|(song, modified)| {
+                modified.map(|modified| SongCacheEntry {
+                    path: song.path.clone(),
+                    hash: song.hash.clone(),
+                    metadata: song.metadata.clone(),
+                    modified,
+                    origin: song.origin.clone(),
+                })
+            })
+            .collect();
+
+        cache.save()?;
+    }
+
+    Ok(songs.into_iter().map(|(song, _)| song).collect())
+}
+
+/// Returns the folders of a library root which have to be read again after
+/// the paths in `changes`, as reported by a watcher, changed.
+///
+/// A change inside a song folder affects that song folder. A new folder
+/// without a chart file, like a pack which is being copied in, affects every
+/// song below it, and a removed path the songs which were in it. Changes in
+/// hidden folders, like the ones archives are extracted to, are ignored.
+pub fn changed_song_folders(root_dir: &Path, changes: &[PathBuf]) -> Vec<PathBuf> {
+    let mut folders = changes
+        .iter()
+        .filter(|path| match path.strip_prefix(root_dir) {
+            Ok(relative) => {
+                relative.components().next().is_some()
+                    && !relative
+                        .components()
+                        .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+            }
+            Err(_) => false,
+        })
+        .filter_map(|path| {
+            let song_folder = path
+                .ancestors()
+                .take_while(|folder| *folder != root_dir)
+                .find(|folder| chart_file(folder).is_some());
+
+            match song_folder {
+                Some(folder) => Some(folder.to_owned()),
+                None if path.is_dir() || !path.exists() => Some(path.to_owned()),
+                // A file outside of any song folder.
+                None => None,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // Folders below another changed folder are read with it.
+    folders.sort();
+    folders.dedup();
+    let mut changed: Vec<PathBuf> = vec![];
+    for folder in folders {
+        if !changed.iter().any(|parent| folder.starts_with(parent)) {
+            changed.push(folder);
+        }
+    }
+
+    changed
+}
+
+/// Reads the songs in and below `folders` of a library root again, after
+/// they changed on disk. The cached songs in and below the folders are
+/// replaced by the songs found there now.
+pub async fn reread_song_folders(
+    song_cache: Option<Arc<Mutex<SongCache>>>,
+    root_dir: PathBuf,
+    folders: Vec<PathBuf>,
+) -> Result<Vec<Song>, ParseError> {
+    let songs: Vec<Song> = folders
+        .iter()
+        .flat_map(|folder| {
+            if chart_file(folder).is_some() {
+                vec![folder.clone()]
+            } else if folder.is_dir() {
+                find_song_folders(folder)
+            } else {
+                vec![]
+            }
+        })
+        .collect::<Vec<_>>()
+        .par_iter()
+        .map(|path| read_song_folder(&root_dir, path))
+        .collect();
+
+    log::debug!(
+        "{:?} - {} songs read again from {} changed folders",
+        root_dir,
+        songs.len(),
+        folders.len()
+    );
+
+    if let Some(song_cache) = &song_cache {
+        let mut cache = song_cache.lock().await;
+
+        cache
+            .get_mut_for_root(&root_dir)
+            .retain(|entry| !folders.iter().any(|folder| entry.path.starts_with(folder)));
+
+        for song in songs
+            .iter()
+            .filter(|song| !matches!(song.state, SongState::Error(_)))
+        {
+            cache.replace_song(song);
+        }
+
+        cache.save()?;
+    }
+
+    Ok(songs)
+}
+
+/// Seeds the `SongCache` of a library root from Clone Hero's `songcache.bin`,
+/// so the first scan of a large library doesn't have to parse every folder.
+///
+/// Nothing is done if the root already has cached entries. Returns the
+/// number of songs seeded.
+pub async fn seed_song_cache<P: AsRef<Path>, D: AsRef<Path>>(
+    song_cache: Arc<Mutex<SongCache>>,
+    root_dir: P,
+    data_directory: D,
+) -> Result<usize, ParseError> {
+    let root_dir = root_dir.as_ref();
+
+    if !song_cache
+        .lock()
+        .await
+        .get_mut_for_root(root_dir)
+        .is_empty()
+    {
+        return Ok(0);
+    }
+
+    let song_cache_file = match load_song_cache_file(data_directory).await? {
+        Some(song_cache_file) => song_cache_file,
+        None => return Ok(0),
+    };
+
+    let mut cache = song_cache.lock().await;
+
+    let seeded = seed_song_cache_entries(&mut cache, root_dir, &song_cache_file);
+
+    if seeded > 0 {
+        cache.save()?;
+    }
+
+    log::debug!("{:?} - {} songs seeded from Clone Hero", root_dir, seeded);
+
+    Ok(seeded)
+}
+
+/// Adds an entry for every song of `song_cache_file` inside `root_dir`.
+///
+/// Songs whose folder has been modified since Clone Hero scanned it are
+/// skipped, `read_song_directory` parses those as usual.
+fn seed_song_cache_entries(
+    cache: &mut SongCache,
+    root_dir: &Path,
+    song_cache_file: &SongCacheFile,
+) -> usize {
+    let entries = cache.get_mut_for_root(root_dir);
+
+    let seeded: Vec<SongCacheEntry> = song_cache_file
+        .songs
+        .par_iter()
+        .filter(|song| song.folder.starts_with(root_dir))
+        .filter(|song| {
+            song.folder
+                .metadata()
+                .and_then(|m| m.modified())
+                .map(|modified| to_ticks(modified.into()) == song.modified_ticks)
+                .unwrap_or_default()
+        })
+        .filter_map(|song| {
+            Some(SongCacheEntry {
+                path: song.folder.clone(),
+                hash: Some(song.hash.clone()),
+                metadata: song.metadata.clone(),
+                modified: song_folder_modified(&song.folder)?,
+                origin: read_song_origin(&song.folder),
+            })
+        })
+        .collect();
+
+    let count = seeded.len();
+    entries.extend(seeded);
+
+    count
+}
+
+/// Returns every folder below `root_dir` which contains a chart file.
+pub fn find_song_folders(root_dir: &Path) -> Vec<PathBuf> {
+    WalkDir::new(root_dir)
+        .min_depth(1)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_dir())
+        .filter(|entry| chart_file(entry.path()).is_some())
+        .map(|entry| entry.path().to_owned())
+        .collect()
+}
+
+/// Returns the chart file of a song folder, if any.
+pub fn chart_file(folder: &Path) -> Option<PathBuf> {
+    CHART_FILES
+        .iter()
+        .map(|name| folder.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Returns the latest modification time of the folder and the files
+/// directly inside it. Editing `song.ini` in place doesn't always bump
+/// the modification time of the folder itself.
+pub fn song_folder_modified(folder: &Path) -> Option<DateTime<Utc>> {
+    let folder_modified = folder.metadata().and_then(|m| m.modified()).ok()?;
+
+    let files_modified = folder
+        .read_dir()
+        .ok()?
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.metadata().and_then(|m| m.modified()).ok());
+
+    files_modified
+        .chain(std::iter::once(folder_modified))
+        .max()
+        .map(DateTime::<Utc>::from)
+}
+
+/// Reads a single song folder from disk.
+pub fn read_song_folder(root_dir: &Path, folder: &Path) -> Song {
+    let mut song = Song::new(
+        song_id(root_dir, folder),
+        folder.to_owned(),
+        root_dir.to_owned(),
+    );
+
+    let chart = match chart_file(folder) {
+        Some(chart) => chart,
+        None => {
+            song.state = SongState::Error("No chart file found".to_owned());
+            return song;
+        }
+    };
+
+    match std::fs::read(&chart) {
+        Ok(data) => {
+            song.hash = Some(format!("{:x}", md5::compute(&data)));
+
+            if chart.extension().and_then(|e| e.to_str()) == Some("chart") {
+                song.metadata = parse_chart_metadata(&String::from_utf8_lossy(&data));
+            }
+        }
+        Err(error) => {
+            song.state = SongState::Error(error.to_string());
+            return song;
+        }
+    }
+
+    if let Ok(data) = std::fs::read(folder.join("song.ini")) {
+        let ini = parse_song_ini(&String::from_utf8_lossy(&data));
+        song.metadata = merge_metadata(ini, std::mem::take(&mut song.metadata));
+    }
+
+    song.origin = read_song_origin(folder);
+
+    song
+}
+
+/// Re-reads a song folder from disk and checks it for the problems which
+/// keep Clone Hero from loading a song.
+///
+/// The returned song is in an error state listing every problem found.
+pub async fn validate_song_folder(root_dir: PathBuf, folder: PathBuf) -> Song {
+    let mut song = read_song_folder(&root_dir, &folder);
+
+    if matches!(song.state, SongState::Error(_)) {
+        return song;
+    }
+
+    let problems = song_folder_problems(&folder);
+
+    if !problems.is_empty() {
+        song.state = SongState::Error(problems.join(", "));
+    }
+
+    song
+}
+
+/// Returns the problems found in a song folder which has a chart file.
+fn song_folder_problems(folder: &Path) -> Vec<String> {
+    let mut problems = vec![];
+
+    let files: Vec<PathBuf> = folder
+        .read_dir()
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| path.is_file())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let has_extension = |path: &Path, extensions: &[&str]| {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .map(|e| extensions.iter().any(|x| e.eq_ignore_ascii_case(x)))
+            .unwrap_or_default()
+    };
+
+    if !files.iter().any(|f| has_extension(f, &AUDIO_EXTENSIONS)) {
+        problems.push("No audio files found".to_owned());
+    }
+
+    if let Some(chart) = chart_file(folder) {
+        if chart.metadata().map(|m| m.len() == 0).unwrap_or(true) {
+            problems.push(format!(
+                "{} is empty",
+                chart.file_name().unwrap_or_default().to_string_lossy()
+            ));
+        }
+
+        // Midi charts carry no metadata, Clone Hero needs a song.ini for them
+        if has_extension(&chart, &["mid"]) && !folder.join("song.ini").is_file() {
+            problems.push("Missing song.ini".to_owned());
+        }
+    }
+
+    problems
+}
+
+fn song_from_cache_entry(root_dir: &Path, entry: &SongCacheEntry) -> Song {
+    let mut song = Song::new(
+        song_id(root_dir, &entry.path),
+        entry.path.clone(),
+        root_dir.to_owned(),
+    );
+    song.hash = entry.hash.clone();
+    song.metadata = entry.metadata.clone();
+    song.origin = entry.origin.clone();
+
+    song
+}
+
+fn song_id(root_dir: &Path, folder: &Path) -> String {
+    folder
+        .strip_prefix(root_dir)
+        .unwrap_or(folder)
+        .to_slash_lossy()
+}
+
+/// Fills any field missing in `primary` from `fallback`.
+fn merge_metadata(primary: SongMetadata, fallback: SongMetadata) -> SongMetadata {
+    SongMetadata {
+        name: primary.name.or(fallback.name),
+        artist: primary.artist.or(fallback.artist),
+        album: primary.album.or(fallback.album),
+        genre: primary.genre.or(fallback.genre),
+        year: primary.year.or(fallback.year),
+        charter: primary.charter.or(fallback.charter),
+        song_length: primary.song_length.or(fallback.song_length),
+        difficulties: if primary.difficulties.is_empty() {
+            fallback.difficulties
+        } else {
+            primary.difficulties
+        },
+    }
+}
+
+/// Parses the `[song]` section of a `song.ini`. Keys are case insensitive.
+pub(crate) fn parse_song_ini(contents: &str) -> SongMetadata {
+    let mut metadata = SongMetadata::default();
+    let mut in_song_section = false;
+    let mut frets = None;
+
+    for line in contents.trim_start_matches('\u{feff}').lines() {
+        let line = line.trim();
+
+        if line.starts_with('[') && line.ends_with(']') {
+            in_song_section = line[1..line.len() - 1].trim().eq_ignore_ascii_case("song");
+            continue;
+        }
+
+        if !in_song_section {
+            continue;
+        }
+
+        let (key, value) = match split_key_value(line) {
+            Some(pair) => pair,
+            None => continue,
+        };
+
+        let value = value.to_owned();
+
+        match key.to_lowercase().as_str() {
+            "name" => metadata.name = Some(value),
+            "artist" => metadata.artist = Some(value),
+            "album" => metadata.album = Some(value),
+            "genre" => metadata.genre = Some(value),
+            "year" => metadata.year = Some(value),
+            "charter" => metadata.charter = Some(value),
+            "frets" => frets = Some(value),
+            "song_length" => metadata.song_length = value.parse().ok(),
+            key => {
+                if let Some(instrument) = Instrument::ALL.iter().find(|i| i.ini_key() == key) {
+                    // A difficulty of -1 means the instrument isn't charted
+                    if let Ok(difficulty) = value.parse::<i32>() {
+                        if difficulty >= 0 {
+                            metadata.difficulties.insert(*instrument, difficulty);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // `frets` is what older charts used for the charter
+    if metadata.charter.is_none() {
+        metadata.charter = frets;
+    }
+
+    metadata
+}
+
+/// Parses the `[Song]` section of a `notes.chart`.
+pub(crate) fn parse_chart_metadata(contents: &str) -> SongMetadata {
+    let mut metadata = SongMetadata::default();
+    let mut in_song_section = false;
+
+    for line in contents.trim_start_matches('\u{feff}').lines() {
+        let line = line.trim();
+
+        if line.starts_with('[') {
+            if in_song_section {
+                break;
+            }
+
+            in_song_section = line.eq_ignore_ascii_case("[song]");
+            continue;
+        }
+
+        if !in_song_section {
+            continue;
+        }
+
+        let (key, value) = match split_key_value(line) {
+            Some(pair) => pair,
+            None => continue,
+        };
+
+        let value = value.trim_matches('"').to_owned();
+
+        match key {
+            "Name" => metadata.name = Some(value),
+            "Artist" => metadata.artist = Some(value),
+            "Album" => metadata.album = Some(value),
+            "Genre" => metadata.genre = Some(value),
+            // Years are stored as `", 2008"` by most charting tools
+            "Year" => metadata.year = Some(value.trim_start_matches(", ").to_owned()),
+            "Charter" => metadata.charter = Some(value),
+            _ => {}
+        }
+    }
+
+    metadata
+}
+
+fn split_key_value(line: &str) -> Option<(&str, &str)> {
+    let mut split = line.splitn(2, '=');
+
+    let key = split.next()?.trim();
+    let value = split.next()?.trim();
+
+    if key.is_empty() || value.is_empty() {
+        None
+    } else {
+        Some((key, value))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use async_std::task;
+
+    #[test]
+    fn test_parse_song_ini() {
+        let contents = "\u{feff}[Song]\r\nname = Master of Puppets\r\nARTIST = Metallica\r\n\
+                        frets = Harmonix\r\nsong_length = 515000\r\ndiff_guitar = 5\r\n\
+                        diff_drums = 6\r\ndiff_bass = -1\r\n\r\n[other]\r\nname = ignored\r\n";
+
+        let metadata = parse_song_ini(contents);
+
+        assert_eq!(metadata.name.as_deref(), Some("Master of Puppets"));
+        assert_eq!(metadata.artist.as_deref(), Some("Metallica"));
+        assert_eq!(metadata.charter.as_deref(), Some("Harmonix"));
+        assert_eq!(metadata.song_length, Some(515_000));
+        assert_eq!(metadata.difficulties.get(&Instrument::Guitar), Some(&5));
+        assert_eq!(metadata.difficulties.get(&Instrument::Drums), Some(&6));
+        assert_eq!(metadata.difficulties.get(&Instrument::Bass), None);
+    }
+
+    #[test]
+    fn test_parse_chart_metadata() {
+        let contents =
+            "[Song]\n{\n  Name = \"Song\"\n  Artist = \"Band\"\n  Year = \", 1999\"\n}\n\
+                        [SyncTrack]\n{\n  Name = \"ignored\"\n}\n";
+
+        let metadata = parse_chart_metadata(contents);
+
+        assert_eq!(metadata.name.as_deref(), Some("Song"));
+        assert_eq!(metadata.artist.as_deref(), Some("Band"));
+        assert_eq!(metadata.year.as_deref(), Some("1999"));
+    }
+
+    #[test]
+    fn test_read_song_directory() {
+        task::block_on(async {
+            let root = tempfile::tempdir().unwrap();
+
+            let song_dir = root.path().join("Metallica/One");
+            std::fs::create_dir_all(&song_dir).unwrap();
+            std::fs::write(
+                song_dir.join("notes.chart"),
+                "[Song]\n{\n  Name = \"One\"\n}\n",
+            )
+            .unwrap();
+            std::fs::write(song_dir.join("song.ini"), "[song]\nartist = Metallica\n").unwrap();
+
+            // Folders without a chart file are not songs
+            std::fs::create_dir_all(root.path().join("Empty")).unwrap();
+
+            let songs = read_song_directory(None, root.path()).await.unwrap();
+
+            assert_eq!(songs.len(), 1);
+
+            let song = &songs[0];
+            assert_eq!(song.id, "Metallica/One");
+            assert_eq!(song.title(), "One");
+            assert_eq!(song.artist(), Some("Metallica"));
+            assert_eq!(
+                song.hash.as_deref(),
+                Some(format!("{:x}", md5::compute("[Song]\n{\n  Name = \"One\"\n}\n")).as_str())
+            );
+
+            assert!(matches!(
+                read_song_directory(None, root.path().join("missing")).await,
+                Err(ParseError::MissingSongDirectory { .. })
+            ));
+        });
+    }
+
+    #[test]
+    fn test_changed_song_folders() {
+        task::block_on(async {
+            let root = tempfile::tempdir().unwrap();
+            let root = root.path();
+
+            let write_song = |folder: &Path| {
+                std::fs::create_dir_all(folder).unwrap();
+                std::fs::write(folder.join("notes.chart"), "[Song]\n{\n}\n").unwrap();
+            };
+
+            write_song(&root.join("Metallica/One"));
+            write_song(&root.join("Pack/Two"));
+            write_song(&root.join("Pack/Three"));
+            write_song(&root.join(".inbox-Song"));
+            std::fs::write(root.join("readme.txt"), "").unwrap();
+
+            let changes = vec![
+                root.join("Metallica/One/song.ini"),
+                root.join("Pack/Two/notes.chart"),
+                root.join("Pack"),
+                root.join("Removed"),
+                root.join(".inbox-Song/notes.chart"),
+                root.join("readme.txt"),
+                PathBuf::from("/elsewhere/song.ini"),
+            ];
+
+            let folders = changed_song_folders(root, &changes);
+            assert_eq!(
+                folders,
+                vec![
+                    root.join("Metallica/One"),
+                    root.join("Pack"),
+                    root.join("Removed")
+                ]
+            );
+
+            let song_cache = Arc::new(Mutex::new(SongCache::default()));
+            let songs = reread_song_folders(Some(song_cache.clone()), root.to_owned(), folders)
+                .await
+                .unwrap();
+
+            let mut ids = songs
+                .iter()
+                .map(|song| song.id.as_str())
+                .collect::<Vec<_>>();
+            ids.sort_unstable();
+            assert_eq!(ids, vec!["Metallica/One", "Pack/Three", "Pack/Two"]);
+            assert_eq!(song_cache.lock().await.get_mut_for_root(root).len(), 3);
+        });
+    }
+
+    #[test]
+    fn test_validate_song_folder() {
+        let root = tempfile::tempdir().unwrap();
+
+        let folder = root.path().join("Song");
+        std::fs::create_dir_all(&folder).unwrap();
+        std::fs::write(folder.join("notes.mid"), "MThd").unwrap();
+
+        let song = task::block_on(validate_song_folder(root.path().to_owned(), folder.clone()));
+        assert_eq!(
+            song.state,
+            SongState::Error("No audio files found, Missing song.ini".to_owned())
+        );
+
+        std::fs::write(folder.join("song.OGG"), "").unwrap();
+        std::fs::write(folder.join("song.ini"), "[song]\nname = Song\n").unwrap();
+
+        let song = task::block_on(validate_song_folder(root.path().to_owned(), folder));
+        assert_eq!(song.state, SongState::Idle);
+        assert_eq!(song.title(), "Song");
+    }
+
+    #[test]
+    fn test_seed_song_cache() {
+        use crate::clone_hero::songcache::{parse_song_cache_file, test::song_cache_file_bytes};
+
+        let root = tempfile::tempdir().unwrap();
+
+        let folder_ticks =
+            |folder: &Path| to_ticks(folder.metadata().unwrap().modified().unwrap().into());
+
+        let unchanged = root.path().join("Unchanged");
+        let changed = root.path().join("Changed");
+        for folder in [&unchanged, &changed].iter() {
+            std::fs::create_dir_all(folder).unwrap();
+            std::fs::write(folder.join("notes.chart"), "[Song]\n{\n}\n").unwrap();
+        }
+
+        let data = song_cache_file_bytes(&[
+            (
+                unchanged.to_str().unwrap(),
+                folder_ticks(&unchanged),
+                "Unchanged",
+            ),
+            (
+                changed.to_str().unwrap(),
+                folder_ticks(&changed) - 1,
+                "Changed",
+            ),
+            ("/elsewhere/Song", 0, "Elsewhere"),
+        ]);
+        let song_cache_file = parse_song_cache_file(&data).unwrap();
+
+        let mut cache = SongCache::default();
+
+        assert_eq!(
+            seed_song_cache_entries(&mut cache, root.path(), &song_cache_file),
+            1
+        );
+
+        let song_cache = Arc::new(Mutex::new(cache));
+        let songs = task::block_on(read_song_directory(Some(song_cache), root.path())).unwrap();
+
+        assert_eq!(songs.len(), 2);
+
+        // Metadata of the unchanged song comes from Clone Hero, the changed
+        // song is parsed from disk and has no name.
+        let unchanged = songs.iter().find(|s| s.path == unchanged).unwrap();
+        assert_eq!(unchanged.metadata.name.as_deref(), Some("Unchanged"));
+        assert_eq!(
+            unchanged.hash.as_deref(),
+            Some("00000000000000000000000000000000")
+        );
+
+        let changed = songs.iter().find(|s| s.path == changed).unwrap();
+        assert_eq!(changed.metadata.name, None);
+    }
+}
@@ -6,11 +6,11 @@ use async_std::{
     io::copy,
 };
 use isahc::config::RedirectPolicy;
-use isahc::http::header::CONTENT_LENGTH;
+use isahc::http::header::{CONTENT_LENGTH, ETAG, LAST_MODIFIED};
 use isahc::prelude::*;
 use isahc::{HttpClient, Request, Response};
 use once_cell::sync::Lazy;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 
 /// Shared `HttpClient`.
@@ -78,6 +78,16 @@ pub(crate) async fn post_json_async<T: ToString, D: Serialize>(
         .await?)
 }
 
+/// Sends a request with a body, e.g. an upload.
+pub(crate) async fn send_async(
+    request: isahc::http::request::Builder,
+    body: Vec<u8>,
+) -> Result<Response<isahc::AsyncBody>, DownloadError> {
+    let request = request.header("user-agent", &user_agent());
+
+    Ok(HTTP_CLIENT.send_async(request.body(body)?).await?)
+}
+
 /// Function to download a zip archive for a `Addon`.
 /// Note: Addon needs to have a `remote_url` to the file.
 pub async fn download_addon(
@@ -135,6 +145,14 @@ pub async fn download_addon(
     Ok(())
 }
 
+/// Validators a server sent along with a file, to ask it later whether the
+/// file changed since.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
 /// Download a file from the internet
 pub(crate) async fn download_file<T: ToString>(
     url: T,
@@ -145,6 +163,65 @@ pub(crate) async fn download_file<T: ToString>(
     log::debug!("downloading file from {}", &url);
 
     let resp = request_async(&url, vec![("ACCEPT", "application/octet-stream")], None).await?;
+
+    save_response(resp, dest_file).await
+}
+
+/// Downloads a file, unless the server reports it unchanged since
+/// `validators` were received. Returns the validators of the downloaded
+/// file, `None` if it's unchanged.
+pub(crate) async fn download_file_if_modified(
+    url: &str,
+    dest_file: &Path,
+    validators: &Validators,
+) -> Result<Option<Validators>, DownloadError> {
+    let mut headers = vec![("ACCEPT", "application/octet-stream")];
+    if let Some(etag) = validators.etag.as_deref() {
+        headers.push(("If-None-Match", etag));
+    }
+    if let Some(last_modified) = validators.last_modified.as_deref() {
+        headers.push(("If-Modified-Since", last_modified));
+    }
+
+    let resp = request_async(url, headers, None).await?;
+
+    match resp.status().as_u16() {
+        304 => {
+            log::debug!("{} is unchanged", url);
+            return Ok(None);
+        }
+        200..=299 => {}
+        _ => {
+            return Err(DownloadError::InvalidStatusCode {
+                code: resp.status(),
+                url: url.to_owned(),
+            })
+        }
+    }
+
+    let header = |name| {
+        resp.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(String::from)
+    };
+    let validators = Validators {
+        etag: header(ETAG),
+        last_modified: header(LAST_MODIFIED),
+    };
+
+    log::debug!("downloading file from {}", url);
+
+    save_response(resp, dest_file).await?;
+
+    Ok(Some(validators))
+}
+
+/// Writes the body of a response to `dest_file`.
+async fn save_response(
+    resp: Response<isahc::AsyncBody>,
+    dest_file: &Path,
+) -> Result<(), DownloadError> {
     let (parts, mut body) = resp.into_parts();
 
     // If response length doesn't equal content length, full file wasn't downloaded
@@ -0,0 +1,452 @@
+use crate::{
+    cache::SongCache,
+    error::{FilesystemError, ParseError},
+    fs::PersistentData,
+    library::song_folder_modified,
+    pack::is_in_pack,
+    song::Song,
+    utility::rename,
+};
+
+use async_std::sync::{Arc, Mutex};
+use path_slash::PathExt;
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Template used if the user hasn't set one.
+pub const DEFAULT_TEMPLATE: &str = "{artist}/{artist} - {name} ({charter})";
+
+/// Fields which can be used in a template.
+pub const TEMPLATE_FIELDS: [&str; 6] = ["name", "artist", "album", "genre", "year", "charter"];
+
+/// Names Windows reserves for devices, which can't be used for folders.
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Longest folder name we create, in UTF-16 units. FAT32 and NTFS allow 255,
+/// this leaves room for collision suffixes and the temporary organize name.
+const MAX_NAME_LEN: usize = 200;
+
+/// A single song folder move planned by the organizer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenamePlan {
+    /// Library root the song folder is in.
+    pub root: PathBuf,
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+impl RenamePlan {
+    /// Returns the new id of the song, its path relative to the root.
+    pub fn id(&self) -> String {
+        self.to
+            .strip_prefix(&self.root)
+            .unwrap_or(&self.to)
+            .to_slash_lossy()
+    }
+}
+
+/// Outcome of applying a list of `RenamePlan`.
+#[derive(Debug, Clone, Default)]
+pub struct OrganizeResult {
+    pub renamed: Vec<RenamePlan>,
+    /// Plans which couldn't be applied, with the reason.
+    pub failed: Vec<(RenamePlan, String)>,
+}
+
+/// Checks that a template only uses known fields and has no unclosed braces.
+pub fn validate_template(template: &str) -> Result<(), ParseError> {
+    render(template, |_| Some(String::new())).map(|_| ())
+}
+
+/// Renders the template for a song into a path relative to the library root.
+///
+/// Every path component is sanitised so it is valid on Windows and FAT32.
+pub fn render_template(template: &str, song: &Song) -> Result<PathBuf, ParseError> {
+    let metadata = &song.metadata;
+
+    let components = template
+        .split(&['/', '\\'][..])
+        .filter(|segment| !segment.trim().is_empty())
+        .map(|segment| {
+            render(segment, |field| match field {
+                "name" => Some(song.title().to_owned()),
+                "artist" => metadata.artist.clone(),
+                "album" => metadata.album.clone(),
+                "genre" => metadata.genre.clone(),
+                "year" => metadata.year.clone(),
+                "charter" => metadata.charter.clone(),
+                _ => None,
+            })
+            .map(|name| sanitize_name(&name))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(components.iter().collect())
+}
+
+/// Replaces every `{field}` in `segment` with the value returned by `value`.
+/// Missing values are rendered as `Unknown`, and `()` left empty by a missing
+/// value is removed.
+fn render<F>(segment: &str, value: F) -> Result<String, ParseError>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut rendered = String::new();
+    let mut rest = segment;
+
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| ParseError::UnclosedTemplateField {
+                template: segment.to_owned(),
+            })?;
+
+        let field = rest[start + 1..start + end].trim().to_lowercase();
+
+        if !TEMPLATE_FIELDS.contains(&field.as_str()) {
+            return Err(ParseError::UnknownTemplateField { field });
+        }
+
+        match value(&field).filter(|v| !v.trim().is_empty()) {
+            Some(value) => rendered.push_str(value.trim()),
+            // An unknown charter is better left out than shown as `(Unknown)`
+            None if field == "charter" => {}
+            None => rendered.push_str("Unknown"),
+        }
+
+        rest = &rest[start + end + 1..];
+    }
+
+    rendered.push_str(rest);
+
+    Ok(rendered.replace("()", "").replace("[]", ""))
+}
+
+/// Makes a single path component valid on Windows and FAT32.
+pub fn sanitize_name(name: &str) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    // Collapse whitespace left behind by removed fields
+    sanitized = sanitized.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if sanitized.encode_utf16().count() > MAX_NAME_LEN {
+        let mut len = 0;
+        sanitized = sanitized
+            .chars()
+            .take_while(|c| {
+                len += c.len_utf16();
+                len <= MAX_NAME_LEN
+            })
+            .collect();
+    }
+
+    // Windows strips trailing dots and spaces
+    let mut sanitized = sanitized.trim_end_matches(&['.', ' '][..]).to_owned();
+
+    if sanitized.is_empty() {
+        sanitized = "Unknown".to_owned();
+    }
+
+    let stem = sanitized.split('.').next().unwrap_or_default();
+    if RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(stem)) {
+        sanitized.insert(0, '_');
+    }
+
+    sanitized
+}
+
+/// Plans where every song of `songs` inside `root` is moved to.
+///
+/// Songs already at the right location, and songs inside a pack, are left
+/// out. A target which already exists, or is planned for another song, gets
+/// a ` (2)`, ` (3)`, ... suffix. Targets are compared case insensitively,
+/// since that is how Windows and FAT32 compare them.
+pub fn plan_organize(
+    template: &str,
+    root: &Path,
+    songs: &[Song],
+) -> Result<Vec<RenamePlan>, ParseError> {
+    validate_template(template)?;
+
+    let key = |path: &Path| path.to_slash_lossy().to_lowercase();
+
+    let mut plans = vec![];
+    let mut taken = HashSet::new();
+
+    // Songs which stay where they are keep their folder.
+    let mut targets = vec![];
+    for song in songs.iter().filter(|s| s.root == root && !is_in_pack(s)) {
+        let target = root.join(render_template(template, song)?);
+
+        if target == song.path {
+            taken.insert(key(&song.path));
+        } else {
+            targets.push((song, target));
+        }
+    }
+
+    for (song, target) in targets {
+        let mut to = target.clone();
+        let mut idx = 2;
+
+        while taken.contains(&key(&to)) || (to.exists() && key(&to) != key(&song.path)) {
+            let name = target.file_name().unwrap_or_default().to_string_lossy();
+            to.set_file_name(format!("{} ({})", name, idx));
+            idx += 1;
+        }
+
+        taken.insert(key(&to));
+
+        if to != song.path {
+            plans.push(RenamePlan {
+                root: root.to_owned(),
+                from: song.path.clone(),
+                to,
+            });
+        }
+    }
+
+    Ok(plans)
+}
+
+/// Moves the song folders as planned and updates the song cache in place.
+///
+/// Folders left empty by a move are removed, up to the library root.
+pub async fn apply_rename_plans(
+    song_cache: Option<Arc<Mutex<SongCache>>>,
+    plans: Vec<RenamePlan>,
+) -> Result<OrganizeResult, FilesystemError> {
+    let mut result = OrganizeResult::default();
+
+    for plan in plans {
+        match move_song_folder(&plan) {
+            Ok(_) => result.renamed.push(plan),
+            Err(error) => {
+                log::error!("failed to move {:?}: {}", &plan.from, error);
+                result.failed.push((plan, error.to_string()));
+            }
+        }
+    }
+
+    if let Some(song_cache) = song_cache {
+        let mut cache = song_cache.lock().await;
+
+        for plan in result.renamed.iter() {
+            let entries = cache.get_mut_for_root(&plan.root);
+
+            if let Some(entry) = entries.iter_mut().find(|e| e.path == plan.from) {
+                entry.path = plan.to.clone();
+
+                if let Some(modified) = song_folder_modified(&plan.to) {
+                    entry.modified = modified;
+                }
+            }
+        }
+
+        cache.save()?;
+    }
+
+    log::debug!(
+        "organized songs, {} moved and {} failed",
+        result.renamed.len(),
+        result.failed.len()
+    );
+
+    Ok(result)
+}
+
+fn move_song_folder(plan: &RenamePlan) -> Result<(), FilesystemError> {
+    let mut from = plan.from.clone();
+
+    // A folder can't be moved into itself, go through a temporary name
+    if plan.to.starts_with(&plan.from) {
+        let temp = plan.from.with_file_name(format!(
+            "{}.chmon-organize",
+            plan.from.file_name().unwrap_or_default().to_string_lossy()
+        ));
+
+        rename(&from, &temp)?;
+        from = temp;
+    }
+
+    if let Err(error) = move_folder(&from, &plan.to) {
+        // Put a folder which was moved to its temporary name back.
+        if from != plan.from {
+            remove_empty_parents(&plan.to, &plan.root);
+
+            if let Err(error) = move_folder(&from, &plan.from) {
+                log::error!(
+                    "failed to move {:?} back to {:?}: {}",
+                    &from,
+                    &plan.from,
+                    error
+                );
+            }
+        }
+
+        return Err(error);
+    }
+
+    remove_empty_parents(&plan.from, &plan.root);
+
+    Ok(())
+}
+
+/// Renames `from` to `to`, creating the parents of `to`.
+fn move_folder(from: &Path, to: &Path) -> Result<(), FilesystemError> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    rename(from, to)?;
+
+    Ok(())
+}
+
+/// Removes `path`'s parents, as long as they are empty and inside `root`.
+pub(crate) fn remove_empty_parents(path: &Path, root: &Path) {
+    let mut current = path.parent();
+
+    while let Some(dir) = current {
+        if dir == root || !dir.starts_with(root) {
+            break;
+        }
+
+        // Fails when the directory isn't empty
+        if std::fs::remove_dir(dir).is_err() {
+            break;
+        }
+
+        current = dir.parent();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use async_std::task;
+
+    fn song(
+        root: &Path,
+        id: &str,
+        name: &str,
+        artist: Option<&str>,
+        charter: Option<&str>,
+    ) -> Song {
+        let mut song = Song::new(id.to_owned(), root.join(id), root.to_owned());
+        song.metadata.name = Some(name.to_owned());
+        song.metadata.artist = artist.map(str::to_owned);
+        song.metadata.charter = charter.map(str::to_owned);
+        song
+    }
+
+    #[test]
+    fn test_sanitize_name() {
+        assert_eq!(sanitize_name("AC/DC: Live?"), "AC_DC_ Live_");
+        assert_eq!(sanitize_name("Song.  "), "Song");
+        assert_eq!(sanitize_name("con"), "_con");
+        assert_eq!(sanitize_name("Nul.txt"), "_Nul.txt");
+        assert_eq!(sanitize_name("  "), "Unknown");
+        assert_eq!(sanitize_name(&"a".repeat(300)).len(), MAX_NAME_LEN);
+        // Characters outside the BMP take two UTF-16 units.
+        assert_eq!(
+            sanitize_name(&"🎸".repeat(300)).encode_utf16().count(),
+            MAX_NAME_LEN
+        );
+    }
+
+    #[test]
+    fn test_render_template() {
+        let root = Path::new("/songs");
+        let one = song(root, "x", "One", Some("Metallica"), Some("Harmonix"));
+        let two = song(root, "y", "Two", None, None);
+
+        assert_eq!(
+            render_template(DEFAULT_TEMPLATE, &one).unwrap(),
+            Path::new("Metallica").join("Metallica - One (Harmonix)")
+        );
+        assert_eq!(
+            render_template(DEFAULT_TEMPLATE, &two).unwrap(),
+            Path::new("Unknown").join("Unknown - Two")
+        );
+        assert_eq!(
+            render_template("{Genre}\\{name}", &one).unwrap(),
+            Path::new("Unknown").join("One")
+        );
+
+        assert!(matches!(
+            render_template("{artist}/{title}", &one),
+            Err(ParseError::UnknownTemplateField { field }) if field == "title"
+        ));
+        assert!(matches!(
+            validate_template("{artist"),
+            Err(ParseError::UnclosedTemplateField { .. })
+        ));
+    }
+
+    #[test]
+    fn test_organize() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+
+        let songs = vec![
+            song(root, "Song (2)", "One", Some("Metallica"), None),
+            song(root, "chart_final_v3", "One", Some("Metallica"), None),
+            song(
+                root,
+                "Metallica/Metallica - Two",
+                "Two",
+                Some("Metallica"),
+                None,
+            ),
+            song(root, "nested/deep/x", "Three", Some("Band"), None),
+            // Moved into its own folder
+            song(root, "Four", "Four", Some("Four"), None),
+            // Stays in its pack
+            song(root, "Pack/Five", "Five", Some("Five"), None),
+        ];
+
+        for song in songs.iter() {
+            std::fs::create_dir_all(&song.path).unwrap();
+            std::fs::write(song.path.join("notes.chart"), &song.id).unwrap();
+        }
+        std::fs::write(root.join("Pack").join(crate::pack::PACK_FILE), "name: Pack").unwrap();
+
+        let plans = plan_organize(DEFAULT_TEMPLATE, root, &songs).unwrap();
+
+        // The third song is already in the right place
+        assert_eq!(plans.len(), 4);
+        assert_eq!(plans[0].id(), "Metallica/Metallica - One");
+        assert_eq!(plans[1].id(), "Metallica/Metallica - One (2)");
+        assert_eq!(plans[2].id(), "Band/Band - Three");
+        assert_eq!(plans[3].id(), "Four/Four - Four");
+
+        let result = task::block_on(apply_rename_plans(None, plans)).unwrap();
+
+        assert_eq!(result.renamed.len(), 4);
+        assert!(result.failed.is_empty());
+        assert_eq!(
+            std::fs::read_to_string(root.join("Metallica/Metallica - One (2)/notes.chart"))
+                .unwrap(),
+            "chart_final_v3"
+        );
+        assert!(!root.join("Song (2)").exists());
+        assert!(!root.join("nested").exists());
+        assert!(root.join("Four/Four - Four/notes.chart").is_file());
+        assert!(root.join("Pack/Five/notes.chart").is_file());
+    }
+}
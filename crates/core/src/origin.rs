@@ -0,0 +1,597 @@
+use crate::{
+    cache::SongCache,
+    error::{DownloadError, FilesystemError},
+    fs::{config_dir, replace_dir, sibling_dir, PersistentData},
+    library::{chart_file, read_song_folder},
+    network::{download_file, download_file_if_modified, Validators},
+    pack::{archive_chart_hashes, extract_archive},
+    song::{Song, SongState},
+    versions::{archive_songs, VersionStore},
+};
+
+use async_std::sync::{Arc, Mutex};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::fs::{remove_dir_all, remove_file, File};
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// File inside a song folder recording where the song was installed from.
+/// Clone Hero ignores it when scanning for songs.
+pub const ORIGIN_FILE: &str = ".chmon-origin.yml";
+
+/// Where a song was installed from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SongOrigin {
+    /// Id of the song in the catalog it was found in, if any.
+    #[serde(default)]
+    pub catalog_id: Option<String>,
+    /// Url or path of the archive the song was installed from.
+    pub url: String,
+    /// Folder of the song inside the archive, empty if the chart is at the
+    /// top of the archive.
+    #[serde(default)]
+    pub archive_path: String,
+    /// Chart hash when the song was installed.
+    pub hash: Option<String>,
+    pub installed: Option<DateTime<Utc>>,
+    /// Set when the song is rolled back to an archived version. Pinned songs
+    /// aren't checked for updates, so the rollback isn't undone by the next
+    /// automatic update.
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+/// A song whose chart changed at its origin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SongUpdate {
+    /// Absolute path to the song folder.
+    pub path: PathBuf,
+    /// Hash of the chart at the origin.
+    pub remote_hash: String,
+}
+
+/// Songs whose chart changed at their origin, keyed by the song folder.
+#[derive(Debug, Clone, Default)]
+pub struct SongUpdates {
+    pub songs: HashMap<PathBuf, String>,
+}
+
+impl SongUpdates {
+    /// Marks every song with a newer chart at its origin as updatable.
+    ///
+    /// Songs which are busy, in an error state or pinned are left alone.
+    pub fn apply(&self, songs: &mut [Song]) {
+        for song in songs.iter_mut() {
+            if song.state != SongState::Idle || is_pinned(song) {
+                continue;
+            }
+
+            if let Some(remote_hash) = self.songs.get(&song.path) {
+                if song.hash.as_ref() != Some(remote_hash) {
+                    song.state = SongState::Updatable;
+                }
+            }
+        }
+    }
+}
+
+/// Outcome of `update_songs`.
+#[derive(Debug, Clone, Default)]
+pub struct UpdateResult {
+    /// Songs read back from disk after they were updated.
+    pub updated: Vec<Song>,
+    /// Songs which couldn't be updated, with the reason.
+    pub failed: Vec<(Song, String)>,
+}
+
+/// Chart hashes of an archive at a song origin, with the validators the
+/// server sent along with it.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct CachedArchive {
+    pub validators: Validators,
+    pub hashes: HashMap<String, String>,
+}
+
+/// Chart hashes of the archives at song origins, keyed by url. An archive is
+/// only downloaded again once its server reports it changed.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct OriginCache {
+    pub archives: HashMap<String, CachedArchive>,
+}
+
+impl PersistentData for OriginCache {
+    fn relative_path() -> PathBuf {
+        PathBuf::from("cache/origins.yml")
+    }
+}
+
+/// Reads the origin of a song folder, if it has been installed by us.
+pub fn read_song_origin(folder: &Path) -> Option<SongOrigin> {
+    let path = folder.join(ORIGIN_FILE);
+
+    if !path.is_file() {
+        return None;
+    }
+
+    let file = File::open(&path).ok()?;
+
+    match serde_yaml::from_reader(file) {
+        Ok(origin) => Some(origin),
+        Err(error) => {
+            log::error!("failed to read {:?}: {}", &path, error);
+            None
+        }
+    }
+}
+
+/// Returns true if the song was rolled back and is kept out of updates.
+fn is_pinned(song: &Song) -> bool {
+    matches!(&song.origin, Some(origin) if origin.pinned)
+}
+
+/// Records the origin of a song folder.
+pub fn write_song_origin(folder: &Path, origin: &SongOrigin) -> Result<(), FilesystemError> {
+    let contents = serde_yaml::to_string(origin)?;
+    std::fs::write(folder.join(ORIGIN_FILE), contents)?;
+
+    Ok(())
+}
+
+/// Returns the chart hash of a song folder.
+pub(crate) fn chart_hash(folder: &Path) -> Option<String> {
+    let data = std::fs::read(chart_file(folder)?).ok()?;
+
+    Some(format!("{:x}", md5::compute(&data)))
+}
+
+/// An archive opened by `fetch_archive`. A downloaded archive is deleted
+/// once this is dropped.
+pub(crate) struct FetchedArchive {
+    archive: Option<zip::ZipArchive<File>>,
+    download: Option<PathBuf>,
+}
+
+impl Deref for FetchedArchive {
+    type Target = zip::ZipArchive<File>;
+
+    fn deref(&self) -> &Self::Target {
+        self.archive
+            .as_ref()
+            .expect("archive is only taken on drop")
+    }
+}
+
+impl DerefMut for FetchedArchive {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.archive
+            .as_mut()
+            .expect("archive is only taken on drop")
+    }
+}
+
+impl Drop for FetchedArchive {
+    fn drop(&mut self) {
+        // The file has to be closed before it can be deleted on Windows.
+        self.archive.take();
+
+        if let Some(download) = self.download.take() {
+            remove_download(&download);
+        }
+    }
+}
+
+/// Returns true for an http(s) url, anything else is a local path.
+pub(crate) fn is_url(url: &str) -> bool {
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+/// Returns a new path in the downloads folder to download `url` to.
+fn download_path(url: &str) -> Result<PathBuf, FilesystemError> {
+    let downloads = config_dir().join("cache").join("downloads");
+    std::fs::create_dir_all(&downloads)?;
+
+    // Unique per download, the same url can be fetched more than once at a time.
+    static DOWNLOADS: AtomicUsize = AtomicUsize::new(0);
+
+    Ok(downloads.join(format!(
+        "{:x}-{}-{}.zip",
+        md5::compute(url),
+        std::process::id(),
+        DOWNLOADS.fetch_add(1, Ordering::Relaxed)
+    )))
+}
+
+fn remove_download(path: &Path) {
+    if let Err(error) = remove_file(path) {
+        log::error!("failed to remove download {:?}: {}", path, error);
+    }
+}
+
+/// Opens the archive at `url`, downloading it first if it isn't a local
+/// path.
+pub(crate) async fn fetch_archive(url: &str) -> Result<FetchedArchive, DownloadError> {
+    let (path, download) = if is_url(url) {
+        let path = download_path(url)?;
+
+        if let Err(error) = download_file(url, &path).await {
+            if path.exists() {
+                remove_download(&path);
+            }
+
+            return Err(error);
+        }
+
+        (path.clone(), Some(path))
+    } else {
+        (PathBuf::from(url), None)
+    };
+
+    // Deletes the download if it can't be opened.
+    let mut fetched = FetchedArchive {
+        archive: None,
+        download,
+    };
+
+    let file = File::open(&path).map_err(FilesystemError::Io)?;
+    fetched.archive = Some(zip::ZipArchive::new(file).map_err(FilesystemError::Zip)?);
+
+    Ok(fetched)
+}
+
+/// Returns the chart hashes of the archive at `url`.
+///
+/// Archives at an http(s) url are only downloaded if their server reports
+/// them changed since they were last hashed, and deleted again right away.
+async fn origin_chart_hashes(
+    url: &str,
+    cache: &mut OriginCache,
+) -> Result<HashMap<String, String>, DownloadError> {
+    if !is_url(url) {
+        let mut archive = fetch_archive(url).await?;
+        return Ok(archive_chart_hashes(&mut archive)?);
+    }
+
+    let path = download_path(url)?;
+    let validators = cache
+        .archives
+        .get(url)
+        .map(|cached| cached.validators.clone())
+        .unwrap_or_default();
+
+    let validators = match download_file_if_modified(url, &path, &validators).await {
+        Ok(Some(validators)) => validators,
+        Ok(None) => {
+            return Ok(cache
+                .archives
+                .get(url)
+                .map(|cached| cached.hashes.clone())
+                .unwrap_or_default())
+        }
+        Err(error) => {
+            if path.exists() {
+                remove_download(&path);
+            }
+
+            return Err(error);
+        }
+    };
+
+    let hashes = File::open(&path)
+        .map_err(FilesystemError::Io)
+        .and_then(|file| Ok(zip::ZipArchive::new(file)?))
+        .and_then(|mut archive| archive_chart_hashes(&mut archive));
+
+    remove_download(&path);
+
+    let hashes = hashes?;
+
+    if validators == Validators::default() {
+        cache.archives.remove(url);
+    } else {
+        cache.archives.insert(
+            url.to_owned(),
+            CachedArchive {
+                validators,
+                hashes: hashes.clone(),
+            },
+        );
+    }
+
+    Ok(hashes)
+}
+
+/// Groups songs with an origin by the url they were installed from.
+fn songs_by_url(songs: Vec<Song>) -> HashMap<String, Vec<Song>> {
+    let mut by_url: HashMap<String, Vec<Song>> = HashMap::new();
+
+    for song in songs {
+        if let Some(origin) = &song.origin {
+            by_url.entry(origin.url.clone()).or_default().push(song);
+        }
+    }
+
+    by_url
+}
+
+/// Fetches the origin of every song and returns the songs whose chart
+/// differs from the one they were installed with.
+///
+/// Every origin is only fetched once, and only downloaded if it changed
+/// since the last check. Origins which can't be fetched are logged and
+/// skipped, so one dead link doesn't stop the check.
+pub async fn check_song_updates(songs: Vec<Song>) -> Result<Vec<SongUpdate>, DownloadError> {
+    let mut updates = vec![];
+
+    let by_url = songs_by_url(songs);
+
+    // Only remote origins use the cache.
+    let has_remote = by_url.keys().any(|url| is_url(url));
+    let mut cache = if has_remote {
+        OriginCache::load_or_default()?
+    } else {
+        OriginCache::default()
+    };
+
+    for (url, songs) in by_url {
+        let hashes = match origin_chart_hashes(&url, &mut cache).await {
+            Ok(hashes) => hashes,
+            Err(error) => {
+                log::error!("failed to check {} for updates: {}", &url, error);
+                continue;
+            }
+        };
+
+        for song in songs {
+            let origin = match &song.origin {
+                Some(origin) if !origin.pinned => origin,
+                _ => continue,
+            };
+
+            if let (Some(hash), Some(remote_hash)) =
+                (&origin.hash, hashes.get(&origin.archive_path))
+            {
+                if hash != remote_hash {
+                    updates.push(SongUpdate {
+                        path: song.path.clone(),
+                        remote_hash: remote_hash.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    if has_remote {
+        cache.save()?;
+    }
+
+    log::debug!("{} songs have updates at their origin", updates.len());
+
+    Ok(updates)
+}
+
+/// Reinstalls songs from their origin, replacing the song folders. The
+/// replaced songs are archived in the versions store first.
+///
+/// The updated songs are read back from disk, with their origin recording
+/// the new chart hash, and their song cache entries replaced. A song which
+/// fails to update is recorded in the result and left as it was.
+pub async fn update_songs(
+    song_cache: Option<Arc<Mutex<SongCache>>>,
+    version_store: Option<Arc<Mutex<VersionStore>>>,
+    songs: Vec<Song>,
+) -> Result<UpdateResult, DownloadError> {
+    let mut result = UpdateResult::default();
+
+    for (url, songs) in songs_by_url(songs) {
+        let fetched = match fetch_archive(&url).await {
+            Ok(mut archive) => archive_chart_hashes(&mut archive)
+                .map(|hashes| (archive, hashes))
+                .map_err(DownloadError::from),
+            Err(error) => Err(error),
+        };
+
+        let (mut archive, hashes) = match fetched {
+            Ok(fetched) => fetched,
+            Err(error) => {
+                log::error!("failed to fetch {}: {}", &url, error);
+
+                let reason = error.to_string();
+                result
+                    .failed
+                    .extend(songs.into_iter().map(|song| (song, reason.clone())));
+                continue;
+            }
+        };
+
+        archive_songs(&version_store, &songs).await;
+
+        for song in songs {
+            let origin = match song.origin.clone() {
+                Some(origin) => origin,
+                None => continue,
+            };
+
+            if !hashes.contains_key(&origin.archive_path) {
+                let error = FilesystemError::MissingFromArchive {
+                    archive_path: origin.archive_path,
+                    url: url.clone(),
+                };
+                result.failed.push((song, error.to_string()));
+                continue;
+            }
+
+            if let Err(error) = update_song_folder(&mut archive, &song.path, origin) {
+                log::error!("failed to update {:?}: {}", &song.path, error);
+                result.failed.push((song, error.to_string()));
+                continue;
+            }
+
+            let mut new_song = read_song_folder(&song.root, &song.path);
+            new_song.scores = song.scores.clone();
+
+            log::debug!("updated {:?} from {}", &song.path, &url);
+
+            result.updated.push(new_song);
+        }
+    }
+
+    if let Some(song_cache) = song_cache {
+        let mut cache = song_cache.lock().await;
+
+        for song in result.updated.iter() {
+            cache.replace_song(song);
+        }
+
+        cache.save()?;
+    }
+
+    Ok(result)
+}
+
+/// Extracts a song from its origin next to its folder, and swaps it in once
+/// that succeeded.
+fn update_song_folder(
+    archive: &mut zip::ZipArchive<File>,
+    path: &Path,
+    mut origin: SongOrigin,
+) -> Result<(), FilesystemError> {
+    let staging = sibling_dir(path, "chmon-staging");
+
+    if staging.exists() {
+        remove_dir_all(&staging)?;
+    }
+
+    let staged =
+        extract_archive(archive, Path::new(&origin.archive_path), &staging).and_then(|_| {
+            origin.hash = chart_hash(&staging);
+            origin.installed = Some(Utc::now());
+            origin.pinned = false;
+            write_song_origin(&staging, &origin)
+        });
+
+    if let Err(error) = staged {
+        if let Err(error) = remove_dir_all(&staging) {
+            log::error!("failed to remove {:?}: {}", &staging, error);
+        }
+
+        return Err(error);
+    }
+
+    replace_dir(&staging, path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use async_std::task;
+    use std::io::Write;
+
+    fn write_archive(path: &Path, files: &[(&str, &str)]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+
+        for (name, contents) in files {
+            zip.start_file(*name, Default::default()).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_check_and_update_songs() {
+        task::block_on(async {
+            let downloads = tempfile::tempdir().unwrap();
+            let root = tempfile::tempdir().unwrap();
+
+            let archive = downloads.path().join("songs.zip");
+            write_archive(
+                &archive,
+                &[
+                    ("Pack/One/notes.chart", "one"),
+                    ("Pack/Two/notes.chart", "two"),
+                ],
+            );
+
+            let mut songs = vec![];
+
+            for id in ["One", "Two"].iter() {
+                let folder = root.path().join(id);
+                extract_archive(
+                    &mut zip::ZipArchive::new(File::open(&archive).unwrap()).unwrap(),
+                    Path::new(id),
+                    &folder,
+                )
+                .unwrap();
+
+                let origin = SongOrigin {
+                    url: archive.to_string_lossy().to_string(),
+                    archive_path: id.to_string(),
+                    hash: chart_hash(&folder),
+                    ..Default::default()
+                };
+                write_song_origin(&folder, &origin).unwrap();
+
+                songs.push(read_song_folder(root.path(), &folder));
+            }
+
+            assert_eq!(read_song_origin(&songs[0].path), songs[0].origin);
+            assert!(check_song_updates(songs.clone()).await.unwrap().is_empty());
+
+            // The second chart changes upstream.
+            write_archive(
+                &archive,
+                &[
+                    ("Pack/One/notes.chart", "one"),
+                    ("Pack/Two/notes.chart", "2"),
+                ],
+            );
+
+            let updates = check_song_updates(songs.clone()).await.unwrap();
+            assert_eq!(
+                updates,
+                vec![SongUpdate {
+                    path: root.path().join("Two"),
+                    remote_hash: format!("{:x}", md5::compute("2")),
+                }]
+            );
+
+            let mut song_updates = SongUpdates::default();
+            song_updates
+                .songs
+                .extend(updates.into_iter().map(|u| (u.path, u.remote_hash)));
+            song_updates.apply(&mut songs);
+
+            assert_eq!(songs[0].state, SongState::Idle);
+            assert_eq!(songs[1].state, SongState::Updatable);
+
+            // A song missing from the archive is left alone.
+            let mut moved = songs[0].clone();
+            if let Some(origin) = moved.origin.as_mut() {
+                origin.archive_path = "Three".to_owned();
+            }
+
+            let result = update_songs(None, None, vec![moved, songs[1].clone()])
+                .await
+                .unwrap();
+
+            assert_eq!(result.failed.len(), 1);
+            assert_eq!(result.failed[0].0.path, songs[0].path);
+            assert!(songs[0].path.join("notes.chart").is_file());
+            assert!(!root.path().join(".One.chmon-staging").exists());
+
+            let updated = result.updated;
+            assert_eq!(updated.len(), 1);
+            assert_eq!(updated[0].hash, Some(format!("{:x}", md5::compute("2"))));
+            assert_eq!(
+                updated[0].origin.as_ref().and_then(|o| o.hash.clone()),
+                updated[0].hash
+            );
+            assert!(check_song_updates(updated).await.unwrap().is_empty());
+        });
+    }
+}
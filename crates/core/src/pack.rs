@@ -0,0 +1,614 @@
+use crate::{
+    cache::SongCache,
+    error::{DownloadError, FilesystemError, ParseError},
+    fs::{replace_dir, sibling_dir, PersistentData},
+    library::{find_song_folders, read_song_folder, CHART_FILES},
+    network::download_file,
+    organizer::sanitize_name,
+    origin::{chart_hash, write_song_origin, SongOrigin},
+    song::Song,
+    versions::{archive_songs, VersionStore},
+};
+
+use async_std::sync::{Arc, Mutex};
+use chrono::{DateTime, Utc};
+use path_slash::PathExt;
+use serde::{Deserialize, Serialize};
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{create_dir_all, remove_dir_all, File};
+use std::path::{Path, PathBuf};
+
+/// File inside a pack folder holding the pack's metadata. Clone Hero
+/// ignores it when scanning for songs.
+pub const PACK_FILE: &str = ".chmon-pack.yml";
+
+/// Metadata written to `PACK_FILE` when a pack is installed.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct PackInfo {
+    pub name: String,
+    /// Url or path of the archive the pack was installed from.
+    pub source: Option<String>,
+    pub version: Option<String>,
+    pub installed: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone)]
+/// Struct which stores information about a song pack, a folder holding
+/// several songs which are installed, updated and deleted together.
+pub struct Pack {
+    /// Id is the path of the pack folder, relative to its library root.
+    pub id: String,
+    /// Absolute path to the pack folder.
+    pub path: PathBuf,
+    /// Library root the pack was found in.
+    pub root: PathBuf,
+    pub info: PackInfo,
+    /// Ids of the songs inside the pack, relative to the library root.
+    pub songs: Vec<String>,
+
+    // States for GUI
+    #[cfg(feature = "gui")]
+    pub open_folder_btn_state: iced_native::button::State,
+    #[cfg(feature = "gui")]
+    pub update_btn_state: iced_native::button::State,
+    #[cfg(feature = "gui")]
+    pub delete_btn_state: iced_native::button::State,
+}
+
+impl Pack {
+    fn new(root: &Path, path: &Path, info: PackInfo) -> Self {
+        let relative_id =
+            |folder: &Path| folder.strip_prefix(root).unwrap_or(folder).to_slash_lossy();
+
+        let mut songs: Vec<String> = find_song_folders(path)
+            .iter()
+            .map(|folder| relative_id(folder))
+            .collect();
+        songs.sort();
+
+        Pack {
+            id: relative_id(path),
+            path: path.to_owned(),
+            root: root.to_owned(),
+            info,
+            songs,
+            #[cfg(feature = "gui")]
+            open_folder_btn_state: Default::default(),
+            #[cfg(feature = "gui")]
+            update_btn_state: Default::default(),
+            #[cfg(feature = "gui")]
+            delete_btn_state: Default::default(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.info.name
+    }
+
+    /// Returns true if the song folder is inside this pack.
+    pub fn contains(&self, song_path: &Path) -> bool {
+        song_path.starts_with(&self.path)
+    }
+}
+
+/// Finds the packs installed in a library root.
+pub async fn read_packs<P: AsRef<Path>>(root_dir: P) -> Result<Vec<Pack>, ParseError> {
+    let root_dir = root_dir.as_ref();
+
+    if !root_dir.is_dir() {
+        return Err(ParseError::MissingSongDirectory {
+            path: root_dir.to_owned(),
+        });
+    }
+
+    let mut packs = vec![];
+
+    for entry in walkdir::WalkDir::new(root_dir)
+        .min_depth(1)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_dir())
+    {
+        let pack_file = entry.path().join(PACK_FILE);
+
+        if !pack_file.is_file() {
+            continue;
+        }
+
+        let info = match read_pack_info(&pack_file) {
+            Ok(info) => info,
+            Err(error) => {
+                log::error!("failed to read {:?}: {}", &pack_file, error);
+                continue;
+            }
+        };
+
+        packs.push(Pack::new(root_dir, entry.path(), info));
+    }
+
+    packs.sort_by_key(|pack| pack.name().to_lowercase());
+
+    log::debug!("{:?} - {} packs found", root_dir, packs.len());
+
+    Ok(packs)
+}
+
+/// Returns true if the song folder is inside an installed pack. Those songs
+/// are installed, updated and deleted with their pack, so they aren't
+/// organized or transferred on their own.
+pub fn is_in_pack(song: &Song) -> bool {
+    song.path
+        .ancestors()
+        .take_while(|dir| dir.starts_with(&song.root) && *dir != song.root)
+        .any(|dir| dir.join(PACK_FILE).is_file())
+}
+
+fn read_pack_info(path: &Path) -> Result<PackInfo, FilesystemError> {
+    let file = File::open(path)?;
+
+    Ok(serde_yaml::from_reader(file)?)
+}
+
+/// Installs a pack from a zip archive, given as an url or a local path, into
+/// a folder named after the pack in `root_dir`.
+///
+/// An existing pack with the same name is replaced, its songs are archived
+/// in the versions store first. A folder with that name which isn't a pack
+/// is left alone and an error returned. If every file of the archive is
+/// inside a single folder, its contents are installed directly.
+pub async fn install_pack(
+    song_cache: Option<Arc<Mutex<SongCache>>>,
+    version_store: Option<Arc<Mutex<VersionStore>>>,
+    source: String,
+    root_dir: PathBuf,
+    name: Option<String>,
+    version: Option<String>,
+) -> Result<Pack, DownloadError> {
+    let is_url = source.starts_with("http://") || source.starts_with("https://");
+
+    let name = name.unwrap_or_else(|| pack_name_from_source(&source));
+
+    let path = root_dir.join(sanitize_name(&name));
+
+    if path.exists() && !path.join(PACK_FILE).is_file() {
+        return Err(FilesystemError::NotAPack { path }.into());
+    }
+
+    // Downloads are kept next to the pack until it's unpacked.
+    let archive = if is_url {
+        create_dir_all(&root_dir).map_err(FilesystemError::Io)?;
+
+        let archive = root_dir.join(format!(".{}.zip", sanitize_name(&name)));
+        download_file(&source, &archive).await?;
+        archive
+    } else {
+        PathBuf::from(&source)
+    };
+
+    let info = PackInfo {
+        name: name.clone(),
+        source: Some(source),
+        version,
+        installed: Some(Utc::now()),
+    };
+
+    // Keep the songs of a pack which is about to be replaced.
+    let replaced: Vec<_> = find_song_folders(&path)
+        .iter()
+        .map(|folder| read_song_folder(&root_dir, folder))
+        .collect();
+    archive_songs(&version_store, &replaced).await;
+
+    let result = unpack_pack(&archive, &path, &info);
+
+    if is_url {
+        let _ = std::fs::remove_file(&archive);
+    }
+
+    result?;
+
+    let pack = Pack::new(&root_dir, &path, info);
+
+    if let Some(song_cache) = song_cache {
+        remove_pack_cache_entries(song_cache, &pack).await?;
+    }
+
+    log::debug!(
+        "installed pack {} with {} songs to {:?}",
+        pack.name(),
+        pack.songs.len(),
+        &pack.path
+    );
+
+    Ok(pack)
+}
+
+/// Reinstalls a pack from the source it was installed from, keeping its
+/// name and location.
+pub async fn update_pack(
+    song_cache: Option<Arc<Mutex<SongCache>>>,
+    version_store: Option<Arc<Mutex<VersionStore>>>,
+    pack: Pack,
+    version: Option<String>,
+) -> Result<Pack, DownloadError> {
+    let source = match pack.info.source.clone() {
+        Some(source) => source,
+        None => {
+            return Err(FilesystemError::PackMissingSource {
+                name: pack.info.name.clone(),
+            }
+            .into())
+        }
+    };
+
+    install_pack(
+        song_cache,
+        version_store,
+        source,
+        pack.root.clone(),
+        Some(pack.info.name.clone()),
+        version.or_else(|| pack.info.version.clone()),
+    )
+    .await
+}
+
+/// Deletes a pack folder with all its songs.
+pub async fn delete_pack(
+    song_cache: Option<Arc<Mutex<SongCache>>>,
+    pack: Pack,
+) -> Result<Pack, FilesystemError> {
+    if pack.path.exists() {
+        remove_dir_all(&pack.path)?;
+    }
+
+    if let Some(song_cache) = song_cache {
+        remove_pack_cache_entries(song_cache, &pack).await?;
+    }
+
+    log::debug!("deleted pack {} from {:?}", pack.name(), &pack.path);
+
+    Ok(pack)
+}
+
+/// Removes the cache entries of every song inside the pack folder, the
+/// next scan parses them from disk again.
+async fn remove_pack_cache_entries(
+    song_cache: Arc<Mutex<SongCache>>,
+    pack: &Pack,
+) -> Result<(), FilesystemError> {
+    let mut cache = song_cache.lock().await;
+
+    cache
+        .get_mut_for_root(&pack.root)
+        .retain(|entry| !pack.contains(&entry.path));
+
+    cache.save()
+}
+
+/// Uses the archive's file name, without extension, as the pack name.
+fn pack_name_from_source(source: &str) -> String {
+    let file_name = source
+        .trim_end_matches('/')
+        .rsplit(&['/', '\\'][..])
+        .next()
+        .unwrap_or(source)
+        .split(&['?', '#'][..])
+        .next()
+        .unwrap_or_default();
+
+    Path::new(file_name)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .filter(|stem| !stem.is_empty())
+        .unwrap_or_else(|| "Pack".to_owned())
+}
+
+/// Extracts `archive` into `path`, replacing the pack there, and writes the
+/// pack file and the origin of every song in it.
+///
+/// The pack is extracted next to `path` first, an existing pack is only
+/// replaced once that succeeded.
+fn unpack_pack(archive: &Path, path: &Path, info: &PackInfo) -> Result<(), FilesystemError> {
+    let mut archive = zip::ZipArchive::new(File::open(archive)?)?;
+
+    let staging = sibling_dir(path, "chmon-staging");
+
+    if staging.exists() {
+        remove_dir_all(&staging)?;
+    }
+
+    if let Err(error) = stage_pack(&mut archive, &staging, info) {
+        if let Err(error) = remove_dir_all(&staging) {
+            log::error!("failed to remove {:?}: {}", &staging, error);
+        }
+
+        return Err(error);
+    }
+
+    replace_dir(&staging, path)
+}
+
+/// Extracts a pack into `path` with its pack file and song origins.
+fn stage_pack(
+    archive: &mut zip::ZipArchive<File>,
+    path: &Path,
+    info: &PackInfo,
+) -> Result<(), FilesystemError> {
+    extract_archive(archive, Path::new(""), path)?;
+
+    let contents = serde_yaml::to_string(info)?;
+    std::fs::write(path.join(PACK_FILE), contents)?;
+
+    if let Some(source) = &info.source {
+        for folder in find_song_folders(path) {
+            let origin = SongOrigin {
+                catalog_id: None,
+                url: source.clone(),
+                archive_path: folder
+                    .strip_prefix(path)
+                    .unwrap_or(&folder)
+                    .to_slash_lossy(),
+                hash: chart_hash(&folder),
+                installed: info.installed,
+                pinned: false,
+            };
+
+            write_song_origin(&folder, &origin)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the top level folder of an archive if every file is inside it,
+/// unless that folder is a song itself. Its contents are treated as the
+/// contents of the archive.
+fn archive_prefix(archive: &mut zip::ZipArchive<File>) -> Result<Option<PathBuf>, FilesystemError> {
+    let mut top_level_folders = HashSet::new();
+    let mut has_top_level_files = false;
+    let mut has_top_level_song = false;
+
+    for idx in 0..archive.len() {
+        let file = archive.by_index(idx)?;
+
+        if let Some(name) = file.enclosed_name() {
+            let mut components = name.components();
+
+            if let Some(first) = components.next() {
+                top_level_folders.insert(PathBuf::from(first.as_os_str()));
+            }
+
+            match components.next() {
+                None if !file.is_dir() => has_top_level_files = true,
+                Some(second) if components.next().is_none() => {
+                    let second = second.as_os_str().to_string_lossy().to_lowercase();
+                    has_top_level_song |= CHART_FILES.contains(&second.as_str());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    if top_level_folders.len() == 1 && !has_top_level_files && !has_top_level_song {
+        Ok(top_level_folders.into_iter().next())
+    } else {
+        Ok(None)
+    }
+}
+
+/// Returns the name of an archive entry relative to the contents of the
+/// archive, `None` for entries which would be extracted outside of it.
+fn archive_entry_name(file: &zip::read::ZipFile, prefix: Option<&Path>) -> Option<PathBuf> {
+    let name = file.enclosed_name()?;
+
+    Some(match prefix {
+        Some(prefix) => name.strip_prefix(prefix).unwrap_or(name).to_owned(),
+        None => name.to_owned(),
+    })
+}
+
+/// Extracts `folder` of an archive, relative to its contents, into `to`.
+/// An empty `folder` extracts everything.
+pub(crate) fn extract_archive(
+    archive: &mut zip::ZipArchive<File>,
+    folder: &Path,
+    to: &Path,
+) -> Result<(), FilesystemError> {
+    let prefix = archive_prefix(archive)?;
+
+    create_dir_all(to)?;
+
+    for idx in 0..archive.len() {
+        let mut file = archive.by_index(idx)?;
+
+        let name = match archive_entry_name(&file, prefix.as_deref()) {
+            Some(name) => name,
+            None => {
+                log::debug!("skipping unsafe path {:?} in archive", file.name());
+                continue;
+            }
+        };
+
+        let name = match name.strip_prefix(folder) {
+            Ok(name) => name,
+            Err(_) => continue,
+        };
+
+        let target = to.join(name);
+
+        if file.is_dir() {
+            create_dir_all(&target)?;
+        } else {
+            if let Some(parent) = target.parent() {
+                create_dir_all(parent)?;
+            }
+
+            let mut outfile = File::create(&target)?;
+            std::io::copy(&mut file, &mut outfile)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the chart hash of every song folder in an archive, keyed by the
+/// folder relative to the contents of the archive.
+pub(crate) fn archive_chart_hashes(
+    archive: &mut zip::ZipArchive<File>,
+) -> Result<HashMap<String, String>, FilesystemError> {
+    let prefix = archive_prefix(archive)?;
+
+    // Chart files per folder, with their preference from `CHART_FILES`.
+    let mut charts: HashMap<String, (usize, String)> = HashMap::new();
+
+    for idx in 0..archive.len() {
+        let mut file = archive.by_index(idx)?;
+
+        let name = match archive_entry_name(&file, prefix.as_deref()) {
+            Some(name) => name,
+            None => continue,
+        };
+
+        let file_name = name
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_lowercase();
+
+        let preference = match CHART_FILES.iter().position(|chart| *chart == file_name) {
+            Some(preference) => preference,
+            None => continue,
+        };
+
+        let folder = name
+            .parent()
+            .map(|folder| folder.to_slash_lossy())
+            .unwrap_or_default();
+
+        if matches!(charts.get(&folder), Some((existing, _)) if *existing < preference) {
+            continue;
+        }
+
+        let mut data = vec![];
+        std::io::Read::read_to_end(&mut file, &mut data)?;
+
+        charts.insert(folder, (preference, format!("{:x}", md5::compute(&data))));
+    }
+
+    Ok(charts
+        .into_iter()
+        .map(|(folder, (_, hash))| (folder, hash))
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::origin::read_song_origin;
+
+    use async_std::task;
+    use std::io::Write;
+
+    fn write_archive(path: &Path, files: &[&str]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+
+        for name in files {
+            zip.start_file(*name, Default::default()).unwrap();
+            zip.write_all(b"[Song]\n{\n}\n").unwrap();
+        }
+
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_pack_name_from_source() {
+        assert_eq!(
+            pack_name_from_source("https://example.com/packs/Monthly%20Pack.zip?dl=1"),
+            "Monthly%20Pack"
+        );
+        assert_eq!(
+            pack_name_from_source("C:\\Downloads\\CSC Pack.zip"),
+            "CSC Pack"
+        );
+        assert_eq!(pack_name_from_source("https://example.com/"), "example");
+    }
+
+    #[test]
+    fn test_install_pack() {
+        task::block_on(async {
+            let downloads = tempfile::tempdir().unwrap();
+            let root = tempfile::tempdir().unwrap();
+
+            let archive = downloads.path().join("Community Pack.zip");
+            write_archive(
+                &archive,
+                &[
+                    "Community Pack/One/notes.chart",
+                    "Community Pack/Two/notes.chart",
+                ],
+            );
+
+            let pack = install_pack(
+                None,
+                None,
+                archive.to_string_lossy().to_string(),
+                root.path().to_owned(),
+                None,
+                Some("1".to_owned()),
+            )
+            .await
+            .unwrap();
+
+            assert_eq!(pack.id, "Community Pack");
+            assert_eq!(pack.name(), "Community Pack");
+            assert_eq!(pack.songs, vec!["Community Pack/One", "Community Pack/Two"]);
+            assert!(root.path().join("Community Pack/One/notes.chart").is_file());
+
+            let origin = read_song_origin(&root.path().join("Community Pack/One")).unwrap();
+            assert_eq!(origin.url, archive.to_string_lossy());
+            assert_eq!(origin.archive_path, "One");
+            assert!(origin.hash.is_some());
+
+            // Updating replaces the contents of the pack folder.
+            write_archive(&archive, &["Three/notes.chart", "Four/notes.chart"]);
+
+            let pack = update_pack(None, None, pack, Some("2".to_owned()))
+                .await
+                .unwrap();
+
+            assert_eq!(
+                pack.songs,
+                vec!["Community Pack/Four", "Community Pack/Three"]
+            );
+            assert!(!root.path().join("Community Pack/One").exists());
+
+            let packs = read_packs(root.path()).await.unwrap();
+            assert_eq!(packs.len(), 1);
+            assert_eq!(packs[0].info.version.as_deref(), Some("2"));
+            assert!(packs[0].contains(&root.path().join("Community Pack/Three")));
+
+            delete_pack(None, pack).await.unwrap();
+            assert!(read_packs(root.path()).await.unwrap().is_empty());
+
+            // A folder which isn't a pack is never replaced.
+            std::fs::create_dir(root.path().join("Community Pack")).unwrap();
+            std::fs::write(root.path().join("Community Pack/song.ini"), "").unwrap();
+
+            let result = install_pack(
+                None,
+                None,
+                archive.to_string_lossy().to_string(),
+                root.path().to_owned(),
+                None,
+                None,
+            )
+            .await;
+
+            assert!(result.is_err());
+            assert!(root.path().join("Community Pack/song.ini").is_file());
+            assert!(!root.path().join("Community Pack/Three").exists());
+        });
+    }
+}
@@ -0,0 +1,533 @@
+use crate::error::ParseError;
+use crate::filter::{parse_length, Bounds};
+use crate::song::{Instrument, Song};
+use crate::stats::parse_year;
+
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+/// Search query for songs, like
+/// `artist:metallica charter:"harmonix" drums>=5 length<4m -genre:pop`.
+///
+/// A query is a list of terms which all have to match. A term is either
+/// free text, or a field followed by `:`, `=`, `<`, `<=`, `>` or `>=` and a
+/// value. Values and free text with spaces are quoted, and a term starting
+/// with `-` excludes the songs it matches. Numbers can also be a range like
+/// `year:1990..1999`. `tag:`, `rating` and `is:favorite` match what the user
+/// noted about a song.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SongQuery {
+    pub terms: Vec<Term>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Term {
+    /// True if the term excludes the songs it matches.
+    pub negated: bool,
+    pub condition: Condition,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    /// Free text, which the artist, title, album or charter has to contain.
+    Text(String),
+    /// Text a field has to contain.
+    Contains(TextField, String),
+    /// Range a number of the song has to be in.
+    Range(NumberField, Bounds<i64>),
+    /// Instrument the song has to be charted for.
+    Charted(Instrument),
+    /// Tag the song has to have, ignoring case.
+    Tag(String),
+    /// The song has to be a favorite.
+    Favorite,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextField {
+    Artist,
+    Title,
+    Album,
+    Genre,
+    Charter,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberField {
+    /// Difficulty of an instrument, or of any charted instrument.
+    Difficulty(Option<Instrument>),
+    /// Length in milliseconds.
+    Length,
+    Year,
+    /// Rating the user gave, songs without a rating never match.
+    Rating,
+}
+
+impl SongQuery {
+    pub fn parse(query: &str) -> Result<Self, ParseError> {
+        let terms = tokenize(query)?
+            .into_iter()
+            .map(|(token, quoted)| parse_term(&token, quoted))
+            .collect::<Result<_, _>>()?;
+
+        Ok(SongQuery { terms })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Returns the free text of the query, which isn't negated. The GUI
+    /// matches it fuzzily instead of with `matches`.
+    pub fn text(&self) -> Option<String> {
+        let text = self
+            .terms
+            .iter()
+            .filter(|term| !term.negated)
+            .filter_map(|term| match &term.condition {
+                Condition::Text(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        if text.is_empty() {
+            None
+        } else {
+            Some(text.join(" "))
+        }
+    }
+
+    /// Returns true if the song matches every term.
+    pub fn matches(&self, song: &Song) -> bool {
+        self.terms.iter().all(|term| term.matches(song))
+    }
+
+    /// Returns true if the song matches every term other than the free text
+    /// returned by `text`.
+    pub fn matches_fields(&self, song: &Song) -> bool {
+        self.terms
+            .iter()
+            .filter(|term| term.negated || !matches!(term.condition, Condition::Text(_)))
+            .all(|term| term.matches(song))
+    }
+}
+
+impl Term {
+    pub fn matches(&self, song: &Song) -> bool {
+        self.condition.matches(song) != self.negated
+    }
+}
+
+impl Condition {
+    pub fn matches(&self, song: &Song) -> bool {
+        let metadata = &song.metadata;
+
+        match self {
+            Condition::Text(text) => [
+                song.artist(),
+                Some(song.title()),
+                metadata.album.as_deref(),
+                song.charter(),
+            ]
+            .iter()
+            .any(|field| contains(*field, text)),
+            Condition::Contains(field, text) => {
+                let value = match field {
+                    TextField::Artist => song.artist(),
+                    TextField::Title => Some(song.title()),
+                    TextField::Album => metadata.album.as_deref(),
+                    TextField::Genre => metadata.genre.as_deref(),
+                    TextField::Charter => song.charter(),
+                };
+
+                contains(value, text)
+            }
+            Condition::Range(NumberField::Difficulty(None), bounds) => metadata
+                .difficulties
+                .values()
+                .any(|d| bounds.contains(*d as i64)),
+            Condition::Range(NumberField::Difficulty(Some(instrument)), bounds) => {
+                matches!(metadata.difficulties.get(instrument), Some(d) if bounds.contains(*d as i64))
+            }
+            Condition::Range(NumberField::Length, bounds) => {
+                matches!(metadata.song_length, Some(length) if bounds.contains(length as i64))
+            }
+            Condition::Range(NumberField::Year, bounds) => {
+                matches!(metadata.year.as_deref().and_then(parse_year), Some(year) if bounds.contains(year as i64))
+            }
+            Condition::Range(NumberField::Rating, bounds) => {
+                matches!(song.user_data.rating, Some(rating) if bounds.contains(rating as i64))
+            }
+            Condition::Charted(instrument) => metadata.difficulties.contains_key(instrument),
+            Condition::Tag(tag) => song.user_data.has_tag(tag),
+            Condition::Favorite => song.user_data.favorite,
+        }
+    }
+}
+
+fn contains(field: Option<&str>, text: &str) -> bool {
+    matches!(field, Some(field) if field.to_lowercase().contains(&text.to_lowercase()))
+}
+
+/// Splits a query on whitespace outside of quotes. Returns each token with
+/// its quotes removed, and whether it started with a quote.
+fn tokenize(query: &str) -> Result<Vec<(String, bool)>, ParseError> {
+    let mut tokens = vec![];
+    let mut token = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+
+    for c in query.chars() {
+        match c {
+            '"' => {
+                if token.is_empty() && !in_quotes {
+                    quoted = true;
+                }
+                in_quotes = !in_quotes;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !token.is_empty() {
+                    tokens.push((std::mem::take(&mut token), quoted));
+                }
+                quoted = false;
+            }
+            c => token.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(ParseError::UnclosedQueryQuote {
+            query: query.to_owned(),
+        });
+    }
+
+    if !token.is_empty() {
+        tokens.push((token, quoted));
+    }
+
+    Ok(tokens)
+}
+
+/// Comparison between a field and its value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    /// `:` or `=`, which also takes ranges.
+    Equals,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+fn parse_term(token: &str, quoted: bool) -> Result<Term, ParseError> {
+    let (negated, token) = match token.strip_prefix('-') {
+        Some(rest) if !quoted && !rest.is_empty() => (true, rest),
+        _ => (false, token),
+    };
+
+    let text = || Term {
+        negated,
+        condition: Condition::Text(token.to_owned()),
+    };
+
+    if quoted {
+        return Ok(text());
+    }
+
+    let idx = match token.find(&[':', '=', '<', '>'][..]) {
+        Some(idx) if idx > 0 => idx,
+        _ => return Ok(text()),
+    };
+
+    let key = token[..idx].to_lowercase();
+    let rest = &token[idx..];
+    let (operator, value) = if let Some(value) = rest.strip_prefix("<=") {
+        (Operator::LessOrEqual, value)
+    } else if let Some(value) = rest.strip_prefix(">=") {
+        (Operator::GreaterOrEqual, value)
+    } else if let Some(value) = rest.strip_prefix('<') {
+        (Operator::Less, value)
+    } else if let Some(value) = rest.strip_prefix('>') {
+        (Operator::Greater, value)
+    } else {
+        (Operator::Equals, &rest[1..])
+    };
+
+    let text_field = match key.as_str() {
+        "artist" => Some(TextField::Artist),
+        "title" | "name" => Some(TextField::Title),
+        "album" => Some(TextField::Album),
+        "genre" => Some(TextField::Genre),
+        "charter" | "frets" => Some(TextField::Charter),
+        _ => None,
+    };
+    let number_field = match key.as_str() {
+        "difficulty" | "diff" => Some(NumberField::Difficulty(None)),
+        "length" | "len" => Some(NumberField::Length),
+        "year" => Some(NumberField::Year),
+        "rating" | "stars" => Some(NumberField::Rating),
+        key => Instrument::from_str(key)
+            .ok()
+            .map(|instrument| NumberField::Difficulty(Some(instrument))),
+    };
+    let is_instrument = matches!(key.as_str(), "instrument" | "inst");
+    let is_tag = key == "tag";
+    let is_flag = key == "is";
+
+    // Anything else, like `AC/DC:`, is searched for as it is.
+    if text_field.is_none() && number_field.is_none() && !is_instrument && !is_tag && !is_flag {
+        return Ok(text());
+    }
+
+    if value.is_empty() {
+        return Err(ParseError::MissingQueryValue { key });
+    }
+
+    let invalid = |reason| ParseError::InvalidQueryValue {
+        key: key.clone(),
+        value: value.to_owned(),
+        reason,
+    };
+
+    let condition = if let Some(field) = text_field {
+        if operator != Operator::Equals {
+            return Err(invalid("text can only be compared with : or ="));
+        }
+
+        Condition::Contains(field, value.to_owned())
+    } else if let Some(field) = number_field {
+        let parse = |s: &str| parse_number(field, s);
+
+        let bounds = match operator {
+            Operator::Equals => Bounds::parse(value, parse),
+            Operator::Less => parse(value)
+                .and_then(|max| max.checked_sub(1))
+                .map(|max| Bounds {
+                    min: None,
+                    max: Some(max),
+                }),
+            Operator::LessOrEqual => parse(value).map(|max| Bounds {
+                min: None,
+                max: Some(max),
+            }),
+            Operator::Greater => {
+                parse(value)
+                    .and_then(|min| min.checked_add(1))
+                    .map(|min| Bounds {
+                        min: Some(min),
+                        max: None,
+                    })
+            }
+            Operator::GreaterOrEqual => parse(value).map(|min| Bounds {
+                min: Some(min),
+                max: None,
+            }),
+        };
+
+        let reason = match field {
+            NumberField::Difficulty(_) => "expected a difficulty like 5 or 3..5",
+            NumberField::Length => "expected a length like 4m30s, 4:30 or 2m..4m",
+            NumberField::Year => "expected a year like 1999 or 1990..1999",
+            NumberField::Rating => "expected a rating like 4 or 3..5",
+        };
+
+        Condition::Range(field, bounds.ok_or_else(|| invalid(reason))?)
+    } else if is_tag {
+        if operator != Operator::Equals {
+            return Err(invalid("a tag can only be compared with : or ="));
+        }
+
+        Condition::Tag(value.to_owned())
+    } else if is_flag {
+        match value.to_lowercase().as_str() {
+            "favorite" | "favourite" | "fav" if operator == Operator::Equals => Condition::Favorite,
+            _ => return Err(invalid("expected is:favorite")),
+        }
+    } else {
+        if operator != Operator::Equals {
+            return Err(invalid("an instrument can only be compared with : or ="));
+        }
+
+        Condition::Charted(Instrument::from_str(value).map_err(invalid)?)
+    };
+
+    Ok(Term { negated, condition })
+}
+
+fn parse_number(field: NumberField, s: &str) -> Option<i64> {
+    match field {
+        NumberField::Difficulty(_) => s.parse().ok().filter(|d| *d >= 0),
+        NumberField::Length => parse_length(s).and_then(|length| i64::try_from(length).ok()),
+        NumberField::Year => s.parse().ok().filter(|year| *year >= 0),
+        NumberField::Rating => s.parse().ok().filter(|rating| *rating >= 0),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    fn song(artist: &str, year: &str, genre: &str, length: u64, drums: i32) -> Song {
+        let mut song = Song::new(artist.to_owned(), PathBuf::from(artist), PathBuf::new());
+        song.metadata.name = Some("Song".to_owned());
+        song.metadata.artist = Some(artist.to_owned());
+        song.metadata.charter = Some("Harmonix".to_owned());
+        song.metadata.year = Some(year.to_owned());
+        song.metadata.genre = Some(genre.to_owned());
+        song.metadata.song_length = Some(length);
+        song.metadata.difficulties = vec![(Instrument::Drums, drums)].into_iter().collect();
+        song
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let query = SongQuery::parse(
+            r#"artist:metallica charter:"harmonix" drums>=5 length<4m year:1990..1999 -genre:pop one"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            query.terms,
+            vec![
+                Term {
+                    negated: false,
+                    condition: Condition::Contains(TextField::Artist, "metallica".to_owned()),
+                },
+                Term {
+                    negated: false,
+                    condition: Condition::Contains(TextField::Charter, "harmonix".to_owned()),
+                },
+                Term {
+                    negated: false,
+                    condition: Condition::Range(
+                        NumberField::Difficulty(Some(Instrument::Drums)),
+                        Bounds {
+                            min: Some(5),
+                            max: None
+                        }
+                    ),
+                },
+                Term {
+                    negated: false,
+                    condition: Condition::Range(
+                        NumberField::Length,
+                        Bounds {
+                            min: None,
+                            max: Some(239_999)
+                        }
+                    ),
+                },
+                Term {
+                    negated: false,
+                    condition: Condition::Range(
+                        NumberField::Year,
+                        Bounds {
+                            min: Some(1990),
+                            max: Some(1999)
+                        }
+                    ),
+                },
+                Term {
+                    negated: true,
+                    condition: Condition::Contains(TextField::Genre, "pop".to_owned()),
+                },
+                Term {
+                    negated: false,
+                    condition: Condition::Text("one".to_owned()),
+                },
+            ]
+        );
+        assert_eq!(query.text().as_deref(), Some("one"));
+
+        let query = SongQuery::parse(r#""one more" AC/DC: -"-live" charter:"Harm onix""#).unwrap();
+        assert_eq!(query.text().as_deref(), Some("one more AC/DC:"));
+        assert_eq!(
+            query.terms[2],
+            Term {
+                negated: true,
+                condition: Condition::Text("-live".to_owned()),
+            }
+        );
+        assert_eq!(
+            query.terms[3].condition,
+            Condition::Contains(TextField::Charter, "Harm onix".to_owned())
+        );
+
+        assert!(SongQuery::parse("").unwrap().is_empty());
+        assert!(matches!(
+            SongQuery::parse("artist:\"metallica"),
+            Err(ParseError::UnclosedQueryQuote { .. })
+        ));
+        assert!(matches!(
+            SongQuery::parse("drums>="),
+            Err(ParseError::MissingQueryValue { .. })
+        ));
+        assert!(matches!(
+            SongQuery::parse("drums>=five"),
+            Err(ParseError::InvalidQueryValue { .. })
+        ));
+        assert!(matches!(
+            SongQuery::parse("artist>a"),
+            Err(ParseError::InvalidQueryValue { .. })
+        ));
+        assert!(matches!(
+            SongQuery::parse("instrument:kazoo"),
+            Err(ParseError::InvalidQueryValue { .. })
+        ));
+
+        // Numbers which don't fit are invalid rather than wrapping around.
+        for query in [
+            "diff>9223372036854775807",
+            "year>99999999999999999999",
+            "length:99999999999999999",
+            "length<5124095576030h",
+        ]
+        .iter()
+        {
+            assert!(
+                matches!(
+                    SongQuery::parse(query),
+                    Err(ParseError::InvalidQueryValue { .. })
+                ),
+                "{}",
+                query
+            );
+        }
+    }
+
+    #[test]
+    fn test_query_matches() {
+        let metallica = song("Metallica", "1991", "Metal", 230_000, 5);
+        let pop = song("Metallica", "1999", "Pop", 200_000, 6);
+        let old = song("Metallica", "1986", "Metal", 500_000, 4);
+
+        let query =
+            SongQuery::parse("artist:metallica drums>=5 length<4m year:1990..1999 -genre:pop")
+                .unwrap();
+        assert!(query.matches(&metallica));
+        assert!(!query.matches(&pop));
+        assert!(!query.matches(&old));
+
+        let query = SongQuery::parse("harmonix -year:1990..").unwrap();
+        assert!(query.matches(&old) && !query.matches(&metallica));
+
+        // Free text is left to the caller.
+        let query = SongQuery::parse("unknown instrument:drums diff:4").unwrap();
+        assert!(!query.matches(&old));
+        assert!(query.matches_fields(&old));
+        assert!(!query.matches_fields(&metallica));
+
+        let mut favorite = metallica.clone();
+        favorite.user_data.favorite = true;
+        favorite.user_data.add_tag("Warmup");
+        favorite.user_data.set_rating(Some(4));
+
+        let query = SongQuery::parse("is:favorite tag:warmup rating>=4").unwrap();
+        assert!(query.matches(&favorite));
+        assert!(!query.matches(&metallica));
+        assert!(!SongQuery::parse("rating<3").unwrap().matches(&favorite));
+        assert!(SongQuery::parse("is:played").is_err());
+    }
+}
@@ -0,0 +1,518 @@
+use crate::{
+    error::FilesystemError,
+    song::{Instrument, Song},
+    userdata::SongUserData,
+};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+
+/// File format of a song library report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum ReportFormat {
+    Csv,
+    Json,
+    Html,
+}
+
+impl ReportFormat {
+    pub const ALL: [ReportFormat; 3] = [ReportFormat::Csv, ReportFormat::Json, ReportFormat::Html];
+
+    /// Returns the file extension used for this format.
+    pub const fn extension(self) -> &'static str {
+        match self {
+            ReportFormat::Csv => "csv",
+            ReportFormat::Json => "json",
+            ReportFormat::Html => "html",
+        }
+    }
+
+    /// Returns the format matching the extension of `path`, if any.
+    pub fn from_path(path: &Path) -> Option<ReportFormat> {
+        path.extension()?.to_str()?.parse().ok()
+    }
+}
+
+impl std::fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ReportFormat::Csv => "CSV",
+                ReportFormat::Json => "JSON",
+                ReportFormat::Html => "HTML",
+            }
+        )
+    }
+}
+
+impl FromStr for ReportFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(ReportFormat::Csv),
+            "json" => Ok(ReportFormat::Json),
+            "html" => Ok(ReportFormat::Html),
+            _ => Err("valid values are: csv, json, html"),
+        }
+    }
+}
+
+/// A song as listed in a report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReportRow {
+    pub artist: Option<String>,
+    pub title: String,
+    pub album: Option<String>,
+    pub charter: Option<String>,
+    /// Length of the song in milliseconds.
+    pub length: Option<u64>,
+    /// Difficulty per charted instrument.
+    pub difficulties: BTreeMap<Instrument, i32>,
+    /// Tags and rating the user gave the song. Notes are private, reports
+    /// are made for publishing.
+    #[serde(skip_serializing_if = "SongUserData::is_empty")]
+    pub user_data: SongUserData,
+}
+
+impl From<&Song> for ReportRow {
+    fn from(song: &Song) -> Self {
+        Self {
+            artist: song.artist().map(str::to_owned),
+            title: song.title().to_owned(),
+            album: song.metadata.album.clone(),
+            charter: song.charter().map(str::to_owned),
+            length: song.metadata.song_length,
+            difficulties: song.metadata.difficulties.clone(),
+            user_data: SongUserData {
+                notes: None,
+                ..song.user_data.clone()
+            },
+        }
+    }
+}
+
+/// Formats a length in milliseconds as `m:ss`.
+fn format_length(length: Option<u64>) -> String {
+    match length {
+        Some(ms) => format!("{}:{:02}", ms / 60_000, ms / 1000 % 60),
+        None => String::new(),
+    }
+}
+
+/// Returns the instruments charted by at least one of the rows, which get a
+/// column in CSV and HTML reports.
+fn charted_instruments(rows: &[ReportRow]) -> Vec<Instrument> {
+    Instrument::ALL
+        .iter()
+        .copied()
+        .filter(|instrument| rows.iter().any(|r| r.difficulties.contains_key(instrument)))
+        .collect()
+}
+
+/// Returns true if any of the rows has user data, which then gets favorite,
+/// rating and tags columns in CSV, HTML and table reports.
+fn has_user_data(rows: &[ReportRow]) -> bool {
+    rows.iter().any(|r| !r.user_data.is_empty())
+}
+
+const USER_DATA_HEADERS: [&str; 3] = ["Favorite", "Rating", "Tags"];
+
+fn user_data_fields(user_data: &SongUserData) -> Vec<String> {
+    vec![
+        if user_data.favorite { "Yes" } else { "" }.to_owned(),
+        user_data.rating.map(|r| r.to_string()).unwrap_or_default(),
+        user_data.tags.join(", "),
+    ]
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Renders the rows as CSV, with a header row.
+pub fn render_csv(rows: &[ReportRow]) -> String {
+    let instruments = charted_instruments(rows);
+    let with_user_data = has_user_data(rows);
+
+    let mut header = vec![
+        "Artist".to_owned(),
+        "Title".to_owned(),
+        "Album".to_owned(),
+        "Charter".to_owned(),
+        "Length".to_owned(),
+    ];
+    header.extend(instruments.iter().map(|i| i.to_string()));
+    if with_user_data {
+        header.extend(USER_DATA_HEADERS.iter().map(|h| h.to_string()));
+    }
+
+    let mut csv = header
+        .iter()
+        .map(|h| csv_field(h))
+        .collect::<Vec<_>>()
+        .join(",");
+    csv.push_str("\r\n");
+
+    for row in rows {
+        let mut fields = vec![
+            row.artist.clone().unwrap_or_default(),
+            row.title.clone(),
+            row.album.clone().unwrap_or_default(),
+            row.charter.clone().unwrap_or_default(),
+            format_length(row.length),
+        ];
+        fields.extend(instruments.iter().map(|i| {
+            row.difficulties
+                .get(i)
+                .map(|d| d.to_string())
+                .unwrap_or_default()
+        }));
+        if with_user_data {
+            fields.extend(user_data_fields(&row.user_data));
+        }
+
+        csv.push_str(
+            &fields
+                .iter()
+                .map(|f| csv_field(f))
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+        csv.push_str("\r\n");
+    }
+
+    csv
+}
+
+/// Renders the rows as a plain text table with aligned columns, for printing
+/// to a terminal.
+pub fn render_table(rows: &[ReportRow]) -> String {
+    let instruments = charted_instruments(rows);
+    let with_user_data = has_user_data(rows);
+
+    let mut lines = vec![];
+
+    let mut header = vec![
+        "Artist".to_owned(),
+        "Title".to_owned(),
+        "Charter".to_owned(),
+        "Length".to_owned(),
+    ];
+    header.extend(instruments.iter().map(|i| i.to_string()));
+    if with_user_data {
+        header.extend(USER_DATA_HEADERS.iter().map(|h| h.to_string()));
+    }
+    lines.push(header);
+
+    for row in rows {
+        let mut fields = vec![
+            row.artist.clone().unwrap_or_default(),
+            row.title.clone(),
+            row.charter.clone().unwrap_or_default(),
+            format_length(row.length),
+        ];
+        fields.extend(instruments.iter().map(|i| {
+            row.difficulties
+                .get(i)
+                .map(|d| d.to_string())
+                .unwrap_or_default()
+        }));
+        if with_user_data {
+            fields.extend(user_data_fields(&row.user_data));
+        }
+        lines.push(fields);
+    }
+
+    let widths = (0..lines[0].len())
+        .map(|idx| {
+            lines
+                .iter()
+                .map(|fields| fields[idx].chars().count())
+                .max()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+
+    let mut table = String::new();
+    for fields in lines.iter() {
+        let line = fields
+            .iter()
+            .zip(widths.iter())
+            .map(|(field, width)| format!("{:width$}", field, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+
+    table
+}
+
+/// Renders the rows as a self-contained HTML page. Clicking a column header
+/// sorts the table by that column.
+pub fn render_html(rows: &[ReportRow]) -> String {
+    let instruments = charted_instruments(rows);
+    let with_user_data = has_user_data(rows);
+
+    let mut header = String::new();
+    for (name, numeric) in ["Artist", "Title", "Album", "Charter"]
+        .iter()
+        .map(|name| (name.to_string(), false))
+        .chain(std::iter::once(("Length".to_owned(), true)))
+        .chain(instruments.iter().map(|i| (i.to_string(), true)))
+        .chain(
+            USER_DATA_HEADERS
+                .iter()
+                .filter(|_| with_user_data)
+                .map(|h| (h.to_string(), *h == "Rating")),
+        )
+    {
+        header.push_str(&format!(
+            "<th data-numeric=\"{}\">{}</th>",
+            numeric,
+            html_escape(&name)
+        ));
+    }
+
+    let mut body = String::new();
+    for row in rows {
+        body.push_str("<tr>");
+
+        for text in [
+            &row.artist,
+            &Some(row.title.clone()),
+            &row.album,
+            &row.charter,
+        ]
+        .iter()
+        {
+            body.push_str(&format!(
+                "<td>{}</td>",
+                html_escape(text.as_deref().unwrap_or_default())
+            ));
+        }
+
+        body.push_str(&format!(
+            "<td data-value=\"{}\">{}</td>",
+            row.length.unwrap_or_default(),
+            format_length(row.length)
+        ));
+
+        for instrument in instruments.iter() {
+            match row.difficulties.get(instrument) {
+                Some(difficulty) => {
+                    body.push_str(&format!("<td data-value=\"{0}\">{0}</td>", difficulty))
+                }
+                None => body.push_str("<td data-value=\"-1\"></td>"),
+            }
+        }
+
+        if with_user_data {
+            let user_data = &row.user_data;
+
+            body.push_str(&format!(
+                "<td>{}</td><td data-value=\"{}\">{}</td><td>{}</td>",
+                if user_data.favorite { "♥" } else { "" },
+                user_data.rating.unwrap_or_default(),
+                user_data.stars().unwrap_or_default(),
+                html_escape(&user_data.tags.join(", "))
+            ));
+        }
+
+        body.push_str("</tr>\n");
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Song list</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; }}
+table {{ border-collapse: collapse; width: 100%; }}
+th, td {{ padding: 4px 8px; text-align: left; border-bottom: 1px solid #ddd; }}
+th {{ cursor: pointer; user-select: none; background: #f4f4f4; }}
+th.asc::after {{ content: " \25B2"; }}
+th.desc::after {{ content: " \25BC"; }}
+tr:hover td {{ background: #fafafa; }}
+</style>
+</head>
+<body>
+<h1>Song list</h1>
+<p>{count} songs, generated {date}</p>
+<table>
+<thead><tr>{header}</tr></thead>
+<tbody>
+{body}</tbody>
+</table>
+<script>
+document.querySelectorAll("th").forEach(function (th, idx) {{
+  th.addEventListener("click", function () {{
+    var numeric = th.dataset.numeric === "true";
+    var asc = !th.classList.contains("asc");
+    document.querySelectorAll("th").forEach(function (h) {{ h.classList.remove("asc", "desc"); }});
+    th.classList.add(asc ? "asc" : "desc");
+    var tbody = document.querySelector("tbody");
+    var rows = Array.prototype.slice.call(tbody.rows);
+    rows.sort(function (a, b) {{
+      var x = a.cells[idx], y = b.cells[idx];
+      var order = numeric
+        ? Number(x.dataset.value) - Number(y.dataset.value)
+        : x.textContent.localeCompare(y.textContent, undefined, {{ sensitivity: "base" }});
+      return asc ? order : -order;
+    }});
+    rows.forEach(function (row) {{ tbody.appendChild(row); }});
+  }});
+}});
+</script>
+</body>
+</html>
+"#,
+        count = rows.len(),
+        date = Local::now().format("%Y-%m-%d %H:%M"),
+        header = header,
+        body = body,
+    )
+}
+
+/// Writes a report of `songs` to `output_file`.
+pub fn export_report(
+    songs: &[Song],
+    format: ReportFormat,
+    output_file: impl AsRef<Path>,
+) -> Result<(), FilesystemError> {
+    let rows = songs.iter().map(ReportRow::from).collect::<Vec<_>>();
+
+    let contents = match format {
+        ReportFormat::Csv => render_csv(&rows),
+        ReportFormat::Json => serde_json::to_string_pretty(&rows)?,
+        ReportFormat::Html => render_html(&rows),
+    };
+
+    std::fs::write(output_file.as_ref(), contents)?;
+
+    log::debug!(
+        "exported {} songs as {} to {:?}",
+        rows.len(),
+        format,
+        output_file.as_ref()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    fn row(title: &str, difficulties: &[(Instrument, i32)]) -> ReportRow {
+        ReportRow {
+            artist: Some("Artist, The".to_owned()),
+            title: title.to_owned(),
+            album: None,
+            charter: Some("<b>".to_owned()),
+            length: Some(245_000),
+            difficulties: difficulties.iter().copied().collect(),
+            user_data: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_render_csv() {
+        let rows = vec![
+            row("One", &[(Instrument::Guitar, 3)]),
+            row("Two \"2\"", &[(Instrument::Drums, 5)]),
+        ];
+
+        assert_eq!(
+            render_csv(&rows),
+            "Artist,Title,Album,Charter,Length,Guitar,Drums\r\n\
+             \"Artist, The\",One,,<b>,4:05,3,\r\n\
+             \"Artist, The\",\"Two \"\"2\"\"\",,<b>,4:05,,5\r\n"
+        );
+    }
+
+    #[test]
+    fn test_render_table() {
+        let rows = vec![
+            row("One", &[(Instrument::Guitar, 3)]),
+            row("Two", &[(Instrument::Drums, 5)]),
+        ];
+
+        assert_eq!(
+            render_table(&rows),
+            "Artist       Title  Charter  Length  Guitar  Drums\n\
+             Artist, The  One    <b>      4:05    3\n\
+             Artist, The  Two    <b>      4:05            5\n"
+        );
+    }
+
+    #[test]
+    fn test_render_html() {
+        let html = render_html(&[row("One", &[(Instrument::Guitar, 3)])]);
+
+        assert!(html.contains("<th data-numeric=\"true\">Guitar</th>"));
+        assert!(html.contains("<td>&lt;b&gt;</td>"));
+        assert!(html.contains("<td data-value=\"245000\">4:05</td>"));
+        assert!(html.contains("1 songs"));
+        assert!(!html.contains("Rating"));
+    }
+
+    #[test]
+    fn test_render_user_data() {
+        let mut rated = row("One", &[]);
+        rated.user_data.favorite = true;
+        rated.user_data.set_rating(Some(4));
+        rated.user_data.add_tag("warmup");
+        rated.user_data.add_tag("ballad");
+
+        let rows = vec![rated, row("Two", &[])];
+
+        assert_eq!(
+            render_csv(&rows),
+            "Artist,Title,Album,Charter,Length,Favorite,Rating,Tags\r\n\
+             \"Artist, The\",One,,<b>,4:05,Yes,4,\"ballad, warmup\"\r\n\
+             \"Artist, The\",Two,,<b>,4:05,,,\r\n"
+        );
+
+        let html = render_html(&rows);
+        assert!(html.contains("<th data-numeric=\"true\">Rating</th>"));
+        assert!(html.contains("<td data-value=\"4\">★★★★☆</td>"));
+
+        // Notes are left out of every format.
+        let mut song = Song::new(
+            "One".to_owned(),
+            PathBuf::from("/songs/One"),
+            PathBuf::from("/songs"),
+        );
+        song.user_data.set_notes("needs practice".to_owned());
+
+        let row = ReportRow::from(&song);
+        assert!(row.user_data.is_empty());
+        assert!(!serde_json::to_string(&row)
+            .unwrap()
+            .contains("needs practice"));
+    }
+}
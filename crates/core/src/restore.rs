@@ -0,0 +1,808 @@
+use crate::{
+    backup::{list_backups, BackupArchive, MANIFEST_NAME},
+    config::{Config, Flavor},
+    error::FilesystemError,
+    fs::{
+        config_dir,
+        encryption::{open_archive, SeekableArchive},
+    },
+    repository::CompressionFormat,
+    snapshot::{SnapshotStore, SNAPSHOT_DIR},
+    userdata::SongUserData,
+};
+
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{create_dir_all, File};
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+/// A backup which can be restored from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackupSource {
+    /// A `.zip` or `.tar.zst` archive.
+    Archive(BackupArchive),
+    /// A snapshot of the incremental song library backups.
+    Snapshot {
+        backup_dir: PathBuf,
+        id: String,
+        as_of: NaiveDateTime,
+    },
+}
+
+impl BackupSource {
+    /// Returns the local time the backup was made.
+    pub fn as_of(&self) -> NaiveDateTime {
+        match self {
+            BackupSource::Archive(archive) => archive.as_of,
+            BackupSource::Snapshot { as_of, .. } => *as_of,
+        }
+    }
+
+    /// Returns the file name of an archive, or the id of a snapshot.
+    pub fn name(&self) -> String {
+        match self {
+            BackupSource::Archive(archive) => archive
+                .path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            BackupSource::Snapshot { id, .. } => id.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for BackupSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BackupSource::Archive(archive) => write!(f, "{}", archive),
+            BackupSource::Snapshot { as_of, .. } => {
+                write!(f, "{} (Songs)", as_of.format("%Y-%m-%d %H:%M:%S"))
+            }
+        }
+    }
+}
+
+/// A file inside a backup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupEntry {
+    /// Path inside the backup, with `/` separators.
+    pub path: String,
+    pub size: u64,
+}
+
+/// A folder of a backup shown when browsing it, such as a song, a pack or
+/// the data directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryGroup {
+    /// Path of the folder inside the backup, usable as a selection.
+    pub path: String,
+    pub files: usize,
+    pub size: u64,
+}
+
+/// What to do with files which already exist where they are restored to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum ConflictPolicy {
+    /// Keep the existing file.
+    Skip,
+    /// Replace the existing file.
+    Overwrite,
+    /// Restore next to the existing file, with a number appended to the
+    /// name.
+    Rename,
+}
+
+impl ConflictPolicy {
+    pub const ALL: [ConflictPolicy; 3] = [
+        ConflictPolicy::Skip,
+        ConflictPolicy::Overwrite,
+        ConflictPolicy::Rename,
+    ];
+}
+
+impl std::fmt::Display for ConflictPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ConflictPolicy::Skip => "Skip",
+                ConflictPolicy::Overwrite => "Overwrite",
+                ConflictPolicy::Rename => "Keep both",
+            }
+        )
+    }
+}
+
+impl std::str::FromStr for ConflictPolicy {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(ConflictPolicy::Skip),
+            "overwrite" => Ok(ConflictPolicy::Overwrite),
+            "rename" => Ok(ConflictPolicy::Rename),
+            _ => Err("valid values are: skip, overwrite, rename"),
+        }
+    }
+}
+
+/// Where a backup is restored to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RestoreTarget {
+    /// Where the files were backed up from. The top level folders of the
+    /// backup are mapped to the folder they are restored into, see
+    /// `restore_origins`.
+    Original(HashMap<String, PathBuf>),
+    /// Into another folder, keeping the paths inside the backup.
+    Directory(PathBuf),
+}
+
+/// A file which will be restored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestoreFile {
+    pub entry: String,
+    pub destination: PathBuf,
+    pub size: u64,
+    /// True if a file already exists at the destination.
+    pub exists: bool,
+}
+
+/// Files `restore` will write, for previewing a restore.
+#[derive(Debug, Clone)]
+pub struct RestorePlan {
+    pub source: BackupSource,
+    pub files: Vec<RestoreFile>,
+    pub conflict: ConflictPolicy,
+}
+
+impl RestorePlan {
+    /// Returns the number of files which already exist.
+    pub fn conflicts(&self) -> usize {
+        self.files.iter().filter(|file| file.exists).count()
+    }
+
+    /// Returns the size of the restored files in bytes.
+    pub fn size(&self) -> u64 {
+        self.files.iter().map(|file| file.size).sum()
+    }
+}
+
+/// What `restore` did.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RestoreSummary {
+    pub restored: usize,
+    /// Existing files which were kept.
+    pub skipped: usize,
+    /// Files restored under another name because the file existed.
+    pub renamed: usize,
+    /// Tags, ratings and notes of songs backed up with a snapshot, which
+    /// the caller merges into its user data.
+    pub user_data: HashMap<String, SongUserData>,
+    /// Top level folders files were written to, e.g. the data directory or
+    /// a song library, which the caller reloads.
+    pub folders: HashSet<PathBuf>,
+}
+
+/// Returns every archive and song library snapshot in the backup folder,
+/// newest first.
+pub async fn list_backup_sources(
+    backup_dir: PathBuf,
+) -> Result<Vec<BackupSource>, FilesystemError> {
+    let mut sources: Vec<_> = list_backups(&backup_dir)
+        .into_iter()
+        .map(BackupSource::Archive)
+        .collect();
+
+    if backup_dir.join(SNAPSHOT_DIR).is_dir() {
+        let store = SnapshotStore::in_backup_directory(&backup_dir)?;
+
+        for snapshot in store.snapshots()? {
+            sources.push(BackupSource::Snapshot {
+                backup_dir: backup_dir.clone(),
+                id: snapshot.id,
+                as_of: snapshot.created.with_timezone(&Local).naive_local(),
+            });
+        }
+    }
+
+    sources.sort_by_key(|source| std::cmp::Reverse(source.as_of()));
+
+    Ok(sources)
+}
+
+/// Returns the top level folders of a backup mapped to the folder they were
+/// backed up from: the data directory, the World of Warcraft flavors and the
+/// song libraries.
+pub fn restore_origins(config: &Config) -> HashMap<String, PathBuf> {
+    let mut origins = HashMap::new();
+
+    let mut insert = |path: &Path| {
+        if let Some(name) = path.file_name() {
+            origins.insert(name.to_string_lossy().to_string(), path.to_owned());
+        }
+    };
+
+    insert(&config_dir());
+
+    for flavor in Flavor::ALL.iter() {
+        if let Some(flavor_dir) = config.wow.directories.get(flavor) {
+            insert(flavor_dir);
+        }
+    }
+
+    for root in config.songs.directories.iter() {
+        insert(root);
+    }
+
+    origins
+}
+
+/// Returns the selection restoring the data directory.
+pub fn config_selection() -> String {
+    config_dir()
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Returns true if an entry path stays inside the folder it's restored to.
+fn is_safe_entry(path: &str) -> bool {
+    Path::new(path)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// Lists the files in a backup. Encrypted archives need the passphrase they
+/// were encrypted with.
+pub async fn backup_entries(
+    source: BackupSource,
+    passphrase: Option<String>,
+) -> Result<Vec<BackupEntry>, FilesystemError> {
+    let mut entries = vec![];
+
+    match &source {
+        BackupSource::Archive(archive) => match archive.format {
+            CompressionFormat::Zip => {
+                let mut zip = zip::ZipArchive::new(SeekableArchive::open(
+                    &archive.path,
+                    passphrase.as_deref(),
+                )?)?;
+
+                for idx in 0..zip.len() {
+                    let file = zip.by_index(idx)?;
+
+                    if file.is_file() {
+                        entries.push(BackupEntry {
+                            path: file.name().trim_end_matches('/').to_owned(),
+                            size: file.size(),
+                        });
+                    }
+                }
+            }
+            CompressionFormat::Zstd => {
+                let decoder = zstd::stream::read::Decoder::new(open_archive(
+                    &archive.path,
+                    passphrase.as_deref(),
+                )?)?;
+                let mut tar = tar::Archive::new(decoder);
+
+                for entry in tar.entries()? {
+                    let entry = entry?;
+
+                    if entry.header().entry_type().is_file() {
+                        entries.push(BackupEntry {
+                            path: entry.path()?.to_string_lossy().replace('\\', "/"),
+                            size: entry.size(),
+                        });
+                    }
+                }
+            }
+        },
+        BackupSource::Snapshot { backup_dir, id, .. } => {
+            let snapshot = SnapshotStore::in_backup_directory(backup_dir)?.snapshot(id)?;
+
+            for root in snapshot.roots.iter() {
+                let name = &root.name;
+
+                for file in root.files.iter() {
+                    entries.push(BackupEntry {
+                        path: format!("{}/{}", name, file.path),
+                        size: file.size,
+                    });
+                }
+            }
+        }
+    }
+
+    // The manifest is only used to verify the archive.
+    entries.retain(|entry| is_safe_entry(&entry.path) && entry.path != MANIFEST_NAME);
+
+    Ok(entries)
+}
+
+/// Groups entries by their first two folders, which is a song or pack for
+/// song libraries and e.g. `ajour/cache` for the data directory. Files at
+/// the top of a folder are grouped with the folder.
+pub fn entry_groups(entries: &[BackupEntry]) -> Vec<EntryGroup> {
+    let mut groups: BTreeMap<String, EntryGroup> = BTreeMap::new();
+
+    for entry in entries {
+        let components: Vec<_> = entry.path.split('/').collect();
+
+        let path = match components.len() {
+            0 | 1 => entry.path.clone(),
+            2 => components[0].to_owned(),
+            _ => components[..2].join("/"),
+        };
+
+        let group = groups.entry(path.clone()).or_insert(EntryGroup {
+            path,
+            files: 0,
+            size: 0,
+        });
+        group.files += 1;
+        group.size += entry.size;
+    }
+
+    groups.into_values().collect()
+}
+
+/// Returns true if `entry` is selected. An empty selection selects
+/// everything.
+fn is_selected(entry: &str, selection: &[String]) -> bool {
+    selection.is_empty()
+        || selection.iter().any(|selected| {
+            let selected = selected.trim_end_matches('/');
+            entry == selected || entry.starts_with(&format!("{}/", selected))
+        })
+}
+
+/// Plans restoring the selected entries of a backup. `selection` holds
+/// paths inside the backup, folders select everything inside them. An empty
+/// selection restores the whole backup.
+pub async fn plan_restore(
+    source: BackupSource,
+    selection: Vec<String>,
+    target: RestoreTarget,
+    conflict: ConflictPolicy,
+    passphrase: Option<String>,
+) -> Result<RestorePlan, FilesystemError> {
+    let mut target = target;
+
+    // Snapshots know exactly where their song libraries were.
+    if let (BackupSource::Snapshot { backup_dir, id, .. }, RestoreTarget::Original(origins)) =
+        (&source, &mut target)
+    {
+        let snapshot = SnapshotStore::in_backup_directory(backup_dir)?.snapshot(id)?;
+
+        for root in snapshot.roots.iter() {
+            origins.insert(root.name.clone(), root.path.clone());
+        }
+    }
+
+    let mut files = vec![];
+
+    for entry in backup_entries(source.clone(), passphrase).await? {
+        if !is_selected(&entry.path, &selection) {
+            continue;
+        }
+
+        let destination = match &target {
+            RestoreTarget::Directory(dir) => dir.join(&entry.path),
+            RestoreTarget::Original(origins) => {
+                let mut components = entry.path.splitn(2, '/');
+                let top = components.next().unwrap_or_default();
+                let rest = components.next().unwrap_or_default();
+
+                match origins.get(top) {
+                    Some(folder) => folder.join(rest),
+                    None => {
+                        return Err(FilesystemError::UnknownRestoreLocation { path: entry.path })
+                    }
+                }
+            }
+        };
+
+        files.push(RestoreFile {
+            exists: destination.exists(),
+            entry: entry.path,
+            destination,
+            size: entry.size,
+        });
+    }
+
+    Ok(RestorePlan {
+        source,
+        files,
+        conflict,
+    })
+}
+
+/// Returns `path` with ` (1)`, ` (2)`, ... appended to the file stem, the
+/// first which doesn't exist.
+fn renamed_destination(path: &Path) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let extension = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+
+    let mut idx = 1;
+    loop {
+        let renamed = path.with_file_name(format!("{} ({}){}", stem, idx, extension));
+        if !renamed.exists() {
+            return renamed;
+        }
+        idx += 1;
+    }
+}
+
+/// Writes one restored file, following the conflict policy.
+fn restore_file(
+    file: &RestoreFile,
+    reader: &mut dyn Read,
+    conflict: ConflictPolicy,
+    summary: &mut RestoreSummary,
+) -> Result<(), FilesystemError> {
+    let mut destination = file.destination.clone();
+
+    if destination.exists() {
+        match conflict {
+            ConflictPolicy::Skip => {
+                summary.skipped += 1;
+                return Ok(());
+            }
+            ConflictPolicy::Overwrite => {}
+            ConflictPolicy::Rename => {
+                destination = renamed_destination(&destination);
+                summary.renamed += 1;
+            }
+        }
+    }
+
+    if let Some(parent) = destination.parent() {
+        create_dir_all(parent)?;
+    }
+
+    let mut output = File::create(&destination)?;
+    std::io::copy(reader, &mut output)?;
+
+    summary.restored += 1;
+
+    let depth = Path::new(&file.entry).components().count();
+    if let Some(folder) = file.destination.ancestors().nth(depth.saturating_sub(1)) {
+        summary.folders.insert(folder.to_owned());
+    }
+
+    Ok(())
+}
+
+/// Restores the files of a plan. Encrypted archives need the passphrase they
+/// were encrypted with.
+pub async fn restore(
+    plan: RestorePlan,
+    passphrase: Option<String>,
+) -> Result<RestoreSummary, FilesystemError> {
+    let mut summary = RestoreSummary::default();
+
+    let files: HashMap<&str, &RestoreFile> = plan
+        .files
+        .iter()
+        .map(|file| (file.entry.as_str(), file))
+        .collect();
+
+    match &plan.source {
+        BackupSource::Archive(archive) => match archive.format {
+            CompressionFormat::Zip => {
+                let mut zip = zip::ZipArchive::new(SeekableArchive::open(
+                    &archive.path,
+                    passphrase.as_deref(),
+                )?)?;
+
+                for file in plan.files.iter() {
+                    let mut reader = zip.by_name(&file.entry)?;
+                    restore_file(file, &mut reader, plan.conflict, &mut summary)?;
+                }
+            }
+            CompressionFormat::Zstd => {
+                let decoder = zstd::stream::read::Decoder::new(open_archive(
+                    &archive.path,
+                    passphrase.as_deref(),
+                )?)?;
+                let mut tar = tar::Archive::new(decoder);
+
+                for entry in tar.entries()? {
+                    let mut entry = entry?;
+                    let path = entry.path()?.to_string_lossy().replace('\\', "/");
+
+                    if let Some(file) = files.get(path.as_str()) {
+                        restore_file(file, &mut entry, plan.conflict, &mut summary)?;
+                    }
+                }
+            }
+        },
+        BackupSource::Snapshot { backup_dir, id, .. } => {
+            let store = SnapshotStore::in_backup_directory(backup_dir)?;
+            let snapshot = store.snapshot(id)?;
+
+            summary.user_data = snapshot.user_data.clone();
+
+            for root in snapshot.roots.iter() {
+                let name = &root.name;
+
+                for snapshot_file in root.files.iter() {
+                    let entry = format!("{}/{}", name, snapshot_file.path);
+
+                    if let Some(file) = files.get(entry.as_str()) {
+                        let mut object = File::open(store.object_path(&snapshot_file.hash))?;
+                        restore_file(file, &mut object, plan.conflict, &mut summary)?;
+                    }
+                }
+            }
+        }
+    }
+
+    log::debug!(
+        "restored {} files from {}, {} skipped, {} renamed",
+        summary.restored,
+        &plan.source,
+        summary.skipped,
+        summary.renamed
+    );
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::backup::{backup_folders, BackupFolder};
+    use crate::userdata::UserData;
+    use async_std::task;
+
+    fn write(path: &Path, contents: &str) {
+        create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_restore_selection() {
+        task::block_on(async {
+            let backup_dir = tempfile::tempdir().unwrap();
+            let library = tempfile::tempdir().unwrap();
+            let root = library.path().join("Songs");
+
+            write(&root.join("One/notes.chart"), "one");
+            write(&root.join("Pack/Two/notes.chart"), "two");
+            write(&root.join("Pack/Three/notes.chart"), "three");
+
+            for format in &[CompressionFormat::Zip, CompressionFormat::Zstd] {
+                std::fs::remove_dir_all(backup_dir.path()).unwrap();
+                create_dir_all(backup_dir.path()).unwrap();
+
+                backup_folders(
+                    vec![BackupFolder::new(&root, library.path())],
+                    backup_dir.path().to_owned(),
+                    *format,
+                    0,
+                    None,
+                )
+                .await
+                .unwrap();
+
+                let sources = list_backup_sources(backup_dir.path().to_owned())
+                    .await
+                    .unwrap();
+                assert_eq!(sources.len(), 1);
+
+                let entries = backup_entries(sources[0].clone(), None).await.unwrap();
+                assert_eq!(entries.len(), 3);
+
+                let groups = entry_groups(&entries);
+                let paths: Vec<_> = groups.iter().map(|g| g.path.as_str()).collect();
+                assert_eq!(paths, vec!["Songs/One", "Songs/Pack"]);
+                assert_eq!(groups[1].files, 2);
+
+                // Restore the pack elsewhere.
+                let other = tempfile::tempdir().unwrap();
+                let plan = plan_restore(
+                    sources[0].clone(),
+                    vec!["Songs/Pack".to_owned()],
+                    RestoreTarget::Directory(other.path().to_owned()),
+                    ConflictPolicy::Skip,
+                    None,
+                )
+                .await
+                .unwrap();
+                assert_eq!(plan.files.len(), 2);
+                assert_eq!(plan.conflicts(), 0);
+
+                let summary = restore(plan, None).await.unwrap();
+                assert_eq!(summary.restored, 2);
+                assert_eq!(
+                    summary.folders.into_iter().collect::<Vec<_>>(),
+                    vec![other.path().join("Songs")]
+                );
+                assert_eq!(
+                    std::fs::read_to_string(other.path().join("Songs/Pack/Two/notes.chart"))
+                        .unwrap(),
+                    "two"
+                );
+                assert!(!other.path().join("Songs/One").exists());
+
+                // Restore a song to where it came from, keeping both.
+                write(&root.join("One/notes.chart"), "changed");
+
+                let mut origins = HashMap::new();
+                origins.insert("Songs".to_owned(), root.clone());
+
+                let plan = plan_restore(
+                    sources[0].clone(),
+                    vec!["Songs/One".to_owned()],
+                    RestoreTarget::Original(origins),
+                    ConflictPolicy::Rename,
+                    None,
+                )
+                .await
+                .unwrap();
+                assert_eq!(plan.conflicts(), 1);
+
+                let summary = restore(plan, None).await.unwrap();
+                assert_eq!(summary.renamed, 1);
+                assert_eq!(
+                    std::fs::read_to_string(root.join("One/notes (1).chart")).unwrap(),
+                    "one"
+                );
+                assert_eq!(
+                    std::fs::read_to_string(root.join("One/notes.chart")).unwrap(),
+                    "changed"
+                );
+                std::fs::remove_file(root.join("One/notes (1).chart")).unwrap();
+                write(&root.join("One/notes.chart"), "one");
+            }
+        });
+    }
+
+    #[test]
+    fn test_restore_snapshot() {
+        task::block_on(async {
+            let backup_dir = tempfile::tempdir().unwrap();
+            let library = tempfile::tempdir().unwrap();
+            let root = library.path().join("Songs");
+            // Another library in a folder with the same name.
+            let other_library = tempfile::tempdir().unwrap();
+            let other = other_library.path().join("Songs");
+
+            write(&root.join("One/notes.chart"), "one");
+            write(&other.join("Two/notes.chart"), "two");
+
+            let mut user_data = UserData::default();
+            user_data.set(
+                "abc",
+                SongUserData {
+                    rating: Some(5),
+                    ..Default::default()
+                },
+            );
+
+            SnapshotStore::in_backup_directory(backup_dir.path())
+                .unwrap()
+                .create(&[root.clone(), other.clone()], &user_data)
+                .unwrap();
+
+            write(&root.join("One/notes.chart"), "broken");
+            std::fs::remove_dir_all(&other).unwrap();
+
+            let sources = list_backup_sources(backup_dir.path().to_owned())
+                .await
+                .unwrap();
+
+            // The original location comes from the snapshot.
+            let plan = plan_restore(
+                sources[0].clone(),
+                vec![],
+                RestoreTarget::Original(HashMap::new()),
+                ConflictPolicy::Overwrite,
+                None,
+            )
+            .await
+            .unwrap();
+            assert_eq!(plan.files[0].entry, "Songs/One/notes.chart");
+            assert_eq!(plan.files[0].destination, root.join("One/notes.chart"));
+            assert_eq!(plan.files[1].entry, "Songs (2)/Two/notes.chart");
+            assert_eq!(plan.files[1].destination, other.join("Two/notes.chart"));
+
+            let summary = restore(plan, None).await.unwrap();
+            assert_eq!(
+                std::fs::read_to_string(root.join("One/notes.chart")).unwrap(),
+                "one"
+            );
+            assert_eq!(
+                std::fs::read_to_string(other.join("Two/notes.chart")).unwrap(),
+                "two"
+            );
+            assert!(!root.join("Two").exists());
+            assert_eq!(summary.user_data, user_data.songs);
+        });
+    }
+
+    #[test]
+    fn test_restore_encrypted() {
+        task::block_on(async {
+            let library = tempfile::tempdir().unwrap();
+            let root = library.path().join("Songs");
+            let passphrase = Some("correct horse".to_owned());
+
+            write(&root.join("One/notes.chart"), "one");
+
+            for format in &[CompressionFormat::Zip, CompressionFormat::Zstd] {
+                let backup_dir = tempfile::tempdir().unwrap();
+
+                backup_folders(
+                    vec![BackupFolder::new(&root, library.path())],
+                    backup_dir.path().to_owned(),
+                    *format,
+                    0,
+                    passphrase.clone(),
+                )
+                .await
+                .unwrap();
+
+                let sources = list_backup_sources(backup_dir.path().to_owned())
+                    .await
+                    .unwrap();
+                let archive = match &sources[0] {
+                    BackupSource::Archive(archive) => archive.clone(),
+                    _ => panic!("expected an archive"),
+                };
+                assert!(archive.is_encrypted());
+                assert_eq!(archive.format, *format);
+                assert!(archive.verification.unwrap().is_ok());
+
+                assert!(matches!(
+                    backup_entries(sources[0].clone(), None).await,
+                    Err(FilesystemError::PassphraseRequired { .. })
+                ));
+                assert!(matches!(
+                    backup_entries(sources[0].clone(), Some("wrong".to_owned())).await,
+                    Err(FilesystemError::WrongPassphrase)
+                ));
+
+                let other = tempfile::tempdir().unwrap();
+                let plan = plan_restore(
+                    sources[0].clone(),
+                    vec![],
+                    RestoreTarget::Directory(other.path().to_owned()),
+                    ConflictPolicy::Skip,
+                    passphrase.clone(),
+                )
+                .await
+                .unwrap();
+
+                let summary = restore(plan, passphrase.clone()).await.unwrap();
+                assert_eq!(summary.restored, 1);
+                assert_eq!(
+                    std::fs::read_to_string(other.path().join("Songs/One/notes.chart")).unwrap(),
+                    "one"
+                );
+
+                // Unencrypted copies of zip archives are removed again.
+                assert!(std::fs::read_dir(backup_dir.path())
+                    .unwrap()
+                    .flatten()
+                    .all(|entry| !entry.file_name().to_string_lossy().ends_with(".tmp")));
+            }
+        });
+    }
+}
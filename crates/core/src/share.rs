@@ -1,8 +1,28 @@
-use std::{collections::HashMap, convert::TryFrom, fs, path::Path};
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    fs,
+    path::{Path, PathBuf},
+};
 
+use async_std::sync::{Arc, Mutex};
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 
-use crate::{addon::Addon, config::Flavor, error, repository::RepositoryKind};
+use crate::{
+    addon::Addon,
+    cache::SongCache,
+    config::Flavor,
+    error,
+    fs::{replace_dir, sibling_dir, unique_destination, PersistentData},
+    library::read_song_folder,
+    organizer::sanitize_name,
+    origin::{chart_hash, fetch_archive, is_url, write_song_origin, SongOrigin},
+    pack::extract_archive,
+    repository::RepositoryKind,
+    song::Song,
+    userdata::SongUserData,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Data {
@@ -103,3 +123,389 @@ pub fn parse_only_needed(
         })
         .collect())
 }
+
+/// A song in a shared song list.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SongData {
+    /// Chart hash, used to find the song in another library.
+    pub hash: Option<String>,
+    pub artist: Option<String>,
+    pub title: String,
+    pub charter: Option<String>,
+    /// Url of the archive the song was installed from, if known. Songs
+    /// installed from a local archive are shared without one.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Folder of the song inside the archive at `url`.
+    #[serde(default)]
+    pub archive_path: Option<String>,
+    /// Tags, rating and notes of the song. They're personal, so they're only
+    /// shared and imported when asked for.
+    #[serde(default, skip_serializing_if = "SongUserData::is_empty")]
+    pub user_data: SongUserData,
+}
+
+impl SongData {
+    /// Returns "artist - title", or the title if the artist is unknown.
+    pub fn name(&self) -> String {
+        match &self.artist {
+            Some(artist) => format!("{} - {}", artist, self.title),
+            None => self.title.clone(),
+        }
+    }
+
+    /// Returns the url the song can be downloaded from, if any.
+    fn download_url(&self) -> Option<&str> {
+        self.url.as_deref().filter(|url| is_url(url))
+    }
+
+    /// Returns true if `song` is the shared song.
+    ///
+    /// Songs are matched on their chart hash. Without a hash, artist, title
+    /// and charter have to match, ignoring case.
+    fn matches(&self, song: &Song) -> bool {
+        fn eq(a: Option<&str>, b: Option<&str>) -> bool {
+            a.map(str::to_lowercase) == b.map(str::to_lowercase)
+        }
+
+        if let Some(hash) = &self.hash {
+            return song.hash.as_ref() == Some(hash);
+        }
+
+        eq(Some(&self.title), Some(song.title()))
+            && eq(self.artist.as_deref(), song.artist())
+            && eq(self.charter.as_deref(), song.charter())
+    }
+}
+
+impl From<&Song> for SongData {
+    fn from(song: &Song) -> Self {
+        // A local path means nothing on another computer.
+        let origin = song.origin.as_ref().filter(|o| is_url(&o.url));
+
+        Self {
+            hash: song.hash.clone(),
+            artist: song.artist().map(str::to_owned),
+            title: song.title().to_owned(),
+            charter: song.charter().map(str::to_owned),
+            url: origin.map(|o| o.url.clone()),
+            archive_path: origin.map(|o| o.archive_path.clone()),
+            user_data: SongUserData::default(),
+        }
+    }
+}
+
+/// Returns true if `path` should be read and written as JSON rather than
+/// YAML.
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.eq_ignore_ascii_case("json"))
+        .unwrap_or_default()
+}
+
+/// Exports a list of songs, with their tags, ratings and notes if
+/// `with_user_data` is set. The file is written as JSON if it has a `.json`
+/// extension, YAML otherwise.
+pub fn export_songs(
+    songs: &[Song],
+    output_file: impl AsRef<Path>,
+    with_user_data: bool,
+) -> Result<(), error::FilesystemError> {
+    let data = songs
+        .iter()
+        .map(|song| SongData {
+            user_data: if with_user_data {
+                song.user_data.clone()
+            } else {
+                SongUserData::default()
+            },
+            ..SongData::from(song)
+        })
+        .collect::<Vec<_>>();
+
+    let contents = if is_json(output_file.as_ref()) {
+        serde_json::to_string_pretty(&data)?
+    } else {
+        serde_yaml::to_string(&data)?
+    };
+
+    fs::write(output_file.as_ref(), contents)?;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ParsedSongs {
+    /// Missing songs which can be downloaded from where they were shared
+    /// from.
+    pub resolvable: Vec<SongData>,
+    /// Missing songs without an http(s) url to download them from.
+    pub unresolvable: Vec<SongData>,
+    /// Number of songs which are already in the library.
+    pub ignored: usize,
+    /// Tags, ratings and notes of the listed songs, by chart hash. Only read
+    /// when asked for.
+    pub user_data: HashMap<String, SongUserData>,
+}
+
+/// Parses a song list and returns the songs which are missing from
+/// `existing_songs`, and the tags, ratings and notes in it if
+/// `with_user_data` is set.
+///
+/// Missing songs are only resolved by the http(s) url they were shared with.
+/// The catalog and its search backends only know addons, there is no song
+/// catalog to look the other songs up in.
+pub fn parse_songs_only_needed(
+    existing_songs: &[Song],
+    path: impl AsRef<Path>,
+    with_user_data: bool,
+) -> Result<ParsedSongs, error::FilesystemError> {
+    let file = fs::File::open(&path)?;
+    let data: Vec<SongData> = if is_json(path.as_ref()) {
+        serde_json::from_reader(file)?
+    } else {
+        serde_yaml::from_reader(file)?
+    };
+
+    let mut parsed = ParsedSongs::default();
+
+    for mut data in data {
+        let user_data = std::mem::take(&mut data.user_data);
+
+        if let (true, Some(hash)) = (with_user_data, &data.hash) {
+            if !user_data.is_empty() {
+                parsed.user_data.insert(hash.clone(), user_data);
+            }
+        }
+
+        if existing_songs.iter().any(|song| data.matches(song)) {
+            parsed.ignored += 1;
+        } else if data.download_url().is_some() {
+            parsed.resolvable.push(data);
+        } else {
+            parsed.unresolvable.push(data);
+        }
+    }
+
+    Ok(parsed)
+}
+
+/// Downloads a shared song into a new folder in `root_dir`, named after its
+/// artist and title, and returns the song read back from disk.
+pub async fn install_shared_song(
+    song_cache: Option<Arc<Mutex<SongCache>>>,
+    data: SongData,
+    root_dir: PathBuf,
+) -> Result<Song, error::DownloadError> {
+    let url = match data.download_url() {
+        Some(url) => url.to_owned(),
+        None => return Err(error::DownloadError::MissingDownloadUrl { name: data.name() }),
+    };
+
+    install_song_from(song_cache, data, url, root_dir).await
+}
+
+/// Installs a shared song from the archive at `url`, which can also be a
+/// local path.
+///
+/// The song is extracted next to its new folder and only moved into place
+/// once its chart is the one that was shared.
+async fn install_song_from(
+    song_cache: Option<Arc<Mutex<SongCache>>>,
+    data: SongData,
+    url: String,
+    root_dir: PathBuf,
+) -> Result<Song, error::DownloadError> {
+    let archive_path = data.archive_path.clone().unwrap_or_default();
+
+    let mut archive = fetch_archive(&url).await?;
+
+    let path = unique_destination(root_dir.join(sanitize_name(&data.name())));
+
+    let staging = sibling_dir(&path, "chmon-staging");
+
+    if staging.exists() {
+        fs::remove_dir_all(&staging).map_err(error::FilesystemError::Io)?;
+    }
+
+    if let Err(error) = stage_shared_song(&mut archive, &data, url, archive_path, &staging) {
+        if let Err(error) = fs::remove_dir_all(&staging) {
+            log::error!("failed to remove {:?}: {}", &staging, error);
+        }
+
+        return Err(error.into());
+    }
+
+    replace_dir(&staging, &path)?;
+
+    let song = read_song_folder(&root_dir, &path);
+
+    if let Some(song_cache) = song_cache {
+        let mut cache = song_cache.lock().await;
+        cache.replace_song(&song);
+        cache.save()?;
+    }
+
+    log::debug!("installed shared song {:?} into {:?}", data.name(), &path);
+
+    Ok(song)
+}
+
+/// Extracts a shared song into `path` and records its origin. Fails if the
+/// archive has no chart at `archive_path`, or another chart than the shared
+/// one.
+fn stage_shared_song(
+    archive: &mut zip::ZipArchive<fs::File>,
+    data: &SongData,
+    url: String,
+    archive_path: String,
+    path: &Path,
+) -> Result<(), error::FilesystemError> {
+    extract_archive(archive, Path::new(&archive_path), path)?;
+
+    let hash = match chart_hash(path) {
+        Some(hash) => hash,
+        None => return Err(error::FilesystemError::MissingFromArchive { archive_path, url }),
+    };
+
+    if matches!(&data.hash, Some(expected) if *expected != hash) {
+        return Err(error::FilesystemError::ChartMismatch { archive_path, url });
+    }
+
+    let origin = SongOrigin {
+        url,
+        archive_path,
+        hash: Some(hash),
+        installed: Some(Utc::now()),
+        ..Default::default()
+    };
+
+    write_song_origin(path, &origin)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use async_std::task;
+    use std::io::Write;
+
+    fn write_song(folder: &Path, chart: &str) -> Song {
+        fs::create_dir_all(folder).unwrap();
+        fs::write(folder.join("notes.chart"), chart).unwrap();
+
+        read_song_folder(folder.parent().unwrap(), folder)
+    }
+
+    #[test]
+    fn test_share_songs() {
+        task::block_on(async {
+            let downloads = tempfile::tempdir().unwrap();
+            let ours = tempfile::tempdir().unwrap();
+            let theirs = tempfile::tempdir().unwrap();
+
+            let archive = downloads.path().join("songs.zip");
+            let mut zip = zip::ZipWriter::new(fs::File::create(&archive).unwrap());
+            zip.start_file("Shared/notes.chart", Default::default())
+                .unwrap();
+            zip.write_all(b"shared").unwrap();
+            zip.finish().unwrap();
+
+            let mut both = write_song(&ours.path().join("Both"), "both");
+            both.user_data.add_tag("Warmup");
+            let mut shared = write_song(&ours.path().join("Shared"), "shared");
+            shared.origin = Some(SongOrigin {
+                url: "https://example.com/songs.zip".to_owned(),
+                archive_path: "Shared".to_owned(),
+                ..Default::default()
+            });
+            let local = write_song(&ours.path().join("Local"), "local");
+            // Songs installed from a local archive are shared without it.
+            let mut local_archive = write_song(&ours.path().join("Local Archive"), "archive");
+            local_archive.origin = Some(SongOrigin {
+                url: archive.to_string_lossy().to_string(),
+                ..Default::default()
+            });
+            assert_eq!(SongData::from(&local_archive).url, None);
+
+            let theirs_both = write_song(&theirs.path().join("Both"), "both");
+
+            for file_name in &["songs.json", "songs.yml"] {
+                let file = downloads.path().join(file_name);
+                let songs = [
+                    both.clone(),
+                    shared.clone(),
+                    local.clone(),
+                    local_archive.clone(),
+                ];
+                let parse = |with_user_data| {
+                    parse_songs_only_needed(
+                        std::slice::from_ref(&theirs_both),
+                        &file,
+                        with_user_data,
+                    )
+                    .unwrap()
+                };
+
+                export_songs(&songs, &file, false).unwrap();
+
+                let parsed = parse(true);
+                assert_eq!(parsed.ignored, 1);
+                assert_eq!(parsed.resolvable, vec![SongData::from(&shared)]);
+                assert_eq!(
+                    parsed.unresolvable,
+                    vec![SongData::from(&local), SongData::from(&local_archive)]
+                );
+                assert!(parsed.user_data.is_empty());
+
+                // Tags, ratings and notes are only shared and imported when
+                // asked for.
+                export_songs(&songs, &file, true).unwrap();
+
+                assert!(parse(false).user_data.is_empty());
+                assert_eq!(
+                    parse(true).user_data.get(both.hash.as_ref().unwrap()),
+                    Some(&both.user_data)
+                );
+            }
+
+            assert!(
+                install_shared_song(None, SongData::from(&local), theirs.path().to_owned())
+                    .await
+                    .is_err()
+            );
+
+            let installed = install_song_from(
+                None,
+                SongData::from(&shared),
+                archive.to_string_lossy().to_string(),
+                theirs.path().to_owned(),
+            )
+            .await
+            .unwrap();
+
+            assert_eq!(installed.hash, shared.hash);
+            assert_eq!(installed.path, theirs.path().join("Shared"));
+            assert_eq!(installed.origin.unwrap().archive_path, "Shared");
+
+            // Nothing is left behind when the archive has another chart, or
+            // none at all.
+            let mut other = SongData::from(&both);
+            other.archive_path = Some("Shared".to_owned());
+            let mut missing = SongData::from(&shared);
+            missing.archive_path = Some("Missing".to_owned());
+
+            for data in [other, missing].iter() {
+                assert!(install_song_from(
+                    None,
+                    data.clone(),
+                    archive.to_string_lossy().to_string(),
+                    theirs.path().to_owned(),
+                )
+                .await
+                .is_err());
+            }
+            assert_eq!(fs::read_dir(theirs.path()).unwrap().count(), 2);
+        });
+    }
+}
@@ -0,0 +1,664 @@
+use crate::{
+    error::FilesystemError,
+    repository::CompressionFormat,
+    userdata::{SongUserData, UserData},
+};
+
+use chrono::{DateTime, Local, NaiveDateTime, Utc};
+use path_slash::PathExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{create_dir_all, rename, File};
+use std::io::{BufWriter, Read};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+/// Folder inside the backup directory holding incremental song backups.
+pub const SNAPSHOT_DIR: &str = "chmon_songs";
+
+/// Folder holding the backed up files, named after their content hash.
+const OBJECTS_DIR: &str = "objects";
+
+/// Folder holding one manifest per snapshot.
+const MANIFESTS_DIR: &str = "snapshots";
+
+/// File in exported snapshots holding the user data of the songs.
+const USER_DATA_FILE: &str = "userdata.yml";
+
+/// A file in a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SnapshotFile {
+    /// Path relative to the library root, with `/` separators.
+    pub path: String,
+    /// Lowercase hex encoded SHA-256 checksum of the contents.
+    pub hash: String,
+    pub size: u64,
+    pub modified: Option<DateTime<Utc>>,
+}
+
+/// A library root in a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SnapshotRoot {
+    /// Name the root is exported and restored under, unique within the
+    /// snapshot.
+    #[serde(default)]
+    pub name: String,
+    pub path: PathBuf,
+    pub files: Vec<SnapshotFile>,
+}
+
+/// Names every root without a name after its folder. Roots in folders with
+/// the same name get ` (2)`, ` (3)`, ... appended, compared case
+/// insensitively as on Windows.
+fn name_roots(roots: &mut [SnapshotRoot]) {
+    let mut taken: HashSet<String> = roots
+        .iter()
+        .filter(|root| !root.name.is_empty())
+        .map(|root| root.name.to_lowercase())
+        .collect();
+
+    for root in roots.iter_mut().filter(|root| root.name.is_empty()) {
+        let base = root
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Songs".to_owned());
+
+        let mut name = base.clone();
+        let mut idx = 2;
+
+        while taken.contains(&name.to_lowercase()) {
+            name = format!("{} ({})", base, idx);
+            idx += 1;
+        }
+
+        taken.insert(name.to_lowercase());
+        root.name = name;
+    }
+}
+
+/// Reads a snapshot manifest. Roots of manifests written before roots were
+/// named are named after their folder.
+fn read_manifest(path: &Path) -> Result<Snapshot, FilesystemError> {
+    let mut snapshot: Snapshot = serde_yaml::from_reader(File::open(path)?)?;
+    name_roots(&mut snapshot.roots);
+
+    Ok(snapshot)
+}
+
+/// Manifest of an incremental backup, listing every file of the backed up
+/// song libraries at the time.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Snapshot {
+    pub id: String,
+    pub created: DateTime<Utc>,
+    pub roots: Vec<SnapshotRoot>,
+    /// Tags, ratings and notes of songs at the time, by chart hash.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub user_data: HashMap<String, SongUserData>,
+}
+
+impl Snapshot {
+    /// Returns the number of files in the snapshot.
+    pub fn file_count(&self) -> usize {
+        self.roots.iter().map(|root| root.files.len()).sum()
+    }
+
+    /// Returns the size of the backed up libraries in bytes.
+    pub fn size(&self) -> u64 {
+        self.roots
+            .iter()
+            .flat_map(|root| root.files.iter())
+            .map(|file| file.size)
+            .sum()
+    }
+}
+
+/// What `SnapshotStore::create` backed up.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SnapshotSummary {
+    pub id: String,
+    /// Number of files in the snapshot.
+    pub files: usize,
+    /// Number of files which were new or changed and had to be copied.
+    pub copied: usize,
+    /// Size of the copied files in bytes.
+    pub copied_size: u64,
+    /// Number of song folders with new or changed files.
+    pub songs_changed: usize,
+}
+
+/// Incremental backups of song libraries.
+///
+/// Files are stored once by their content hash, every backup only writes a
+/// manifest and the files which aren't stored yet.
+#[derive(Debug, Clone)]
+pub struct SnapshotStore {
+    dir: PathBuf,
+}
+
+impl SnapshotStore {
+    /// Opens the store in `dir`, creating it if needed.
+    pub fn open(dir: PathBuf) -> Result<Self, FilesystemError> {
+        create_dir_all(dir.join(OBJECTS_DIR))?;
+        create_dir_all(dir.join(MANIFESTS_DIR))?;
+
+        Ok(SnapshotStore { dir })
+    }
+
+    /// Returns the store inside a backup directory.
+    pub fn in_backup_directory(backup_dir: &Path) -> Result<Self, FilesystemError> {
+        Self::open(backup_dir.join(SNAPSHOT_DIR))
+    }
+
+    fn manifest_path(&self, id: &str) -> PathBuf {
+        self.dir.join(MANIFESTS_DIR).join(format!("{}.yml", id))
+    }
+
+    /// Returns the path a file with `hash` is stored at.
+    pub fn object_path(&self, hash: &str) -> PathBuf {
+        self.dir
+            .join(OBJECTS_DIR)
+            .join(&hash[..2.min(hash.len())])
+            .join(hash)
+    }
+
+    /// Returns every snapshot, oldest first.
+    pub fn snapshots(&self) -> Result<Vec<Snapshot>, FilesystemError> {
+        let mut snapshots = vec![];
+
+        for entry in std::fs::read_dir(self.dir.join(MANIFESTS_DIR))? {
+            let path = entry?.path();
+
+            if path.extension().and_then(|ext| ext.to_str()) != Some("yml") {
+                continue;
+            }
+
+            match read_manifest(&path) {
+                Ok(snapshot) => snapshots.push(snapshot),
+                Err(error) => log::error!("failed to read snapshot {:?}: {}", &path, error),
+            }
+        }
+
+        snapshots.sort_by_key(|snapshot| snapshot.created);
+
+        Ok(snapshots)
+    }
+
+    /// Returns the snapshot with `id`.
+    pub fn snapshot(&self, id: &str) -> Result<Snapshot, FilesystemError> {
+        let path = self.manifest_path(id);
+
+        if !path.is_file() {
+            return Err(FilesystemError::SnapshotNotFound { id: id.to_owned() });
+        }
+
+        read_manifest(&path)
+    }
+
+    /// Returns the most recent snapshot, if any.
+    pub fn latest(&self) -> Result<Option<Snapshot>, FilesystemError> {
+        Ok(self.snapshots()?.pop())
+    }
+
+    /// Backs up `roots` and the user data of their songs into a new
+    /// snapshot.
+    ///
+    /// Files with the same size and modification time as in the previous
+    /// snapshot are assumed unchanged and aren't read again. Other files
+    /// are hashed and only copied if their contents aren't stored yet.
+    pub fn create(
+        &self,
+        roots: &[PathBuf],
+        user_data: &UserData,
+    ) -> Result<SnapshotSummary, FilesystemError> {
+        let previous = self.latest()?;
+
+        let mut known: HashMap<(&Path, &str), &SnapshotFile> = HashMap::new();
+        if let Some(previous) = &previous {
+            for root in previous.roots.iter() {
+                for file in root.files.iter() {
+                    known.insert((root.path.as_path(), file.path.as_str()), file);
+                }
+            }
+        }
+
+        let mut summary = SnapshotSummary::default();
+        let mut snapshot_roots = vec![];
+
+        for root in roots {
+            let mut files = vec![];
+            let mut changed_folders = HashSet::new();
+
+            for entry in WalkDir::new(root)
+                .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+                .into_iter()
+                .filter_map(Result::ok)
+                .filter(|entry| entry.file_type().is_file())
+            {
+                let path = entry.path();
+                let relative = path.strip_prefix(root).unwrap_or(path);
+                let relative =
+                    relative
+                        .to_slash()
+                        .ok_or(FilesystemError::NormalizingPathSlash {
+                            path: relative.to_owned(),
+                        })?;
+
+                let metadata = entry.metadata()?;
+                let size = metadata.len();
+                let modified = metadata.modified().ok().map(DateTime::<Utc>::from);
+
+                let unchanged = known
+                    .get(&(root.as_path(), relative.as_str()))
+                    .filter(|file| file.size == size && file.modified == modified)
+                    .filter(|file| self.object_path(&file.hash).is_file());
+
+                let hash = match unchanged {
+                    Some(file) => file.hash.clone(),
+                    None => {
+                        let hash = file_hash(path)?;
+
+                        if self.store_object(path, &hash)? {
+                            summary.copied += 1;
+                            summary.copied_size += size;
+                        }
+
+                        if let Some(parent) = path.parent() {
+                            changed_folders.insert(parent.to_owned());
+                        }
+
+                        hash
+                    }
+                };
+
+                files.push(SnapshotFile {
+                    path: relative,
+                    hash,
+                    size,
+                    modified,
+                });
+            }
+
+            summary.files += files.len();
+            summary.songs_changed += changed_folders.len();
+
+            snapshot_roots.push(SnapshotRoot {
+                name: String::new(),
+                path: root.clone(),
+                files,
+            });
+        }
+
+        name_roots(&mut snapshot_roots);
+
+        let snapshot = Snapshot {
+            id: self.new_id(),
+            created: Utc::now(),
+            roots: snapshot_roots,
+            user_data: user_data.songs.clone(),
+        };
+
+        let contents = serde_yaml::to_string(&snapshot)?;
+        std::fs::write(self.manifest_path(&snapshot.id), contents)?;
+
+        summary.id = snapshot.id;
+
+        log::debug!(
+            "created snapshot {}, {} of {} files copied",
+            &summary.id,
+            summary.copied,
+            summary.files
+        );
+
+        Ok(summary)
+    }
+
+    /// Returns an id for a new snapshot, based on the current time.
+    fn new_id(&self) -> String {
+        let base = Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
+
+        let mut id = base.clone();
+        let mut idx = 1;
+
+        while self.manifest_path(&id).exists() {
+            id = format!("{}_{}", base, idx);
+            idx += 1;
+        }
+
+        id
+    }
+
+    /// Copies a file into the store unless its contents are already stored.
+    /// Returns true if the file was copied.
+    fn store_object(&self, path: &Path, hash: &str) -> Result<bool, FilesystemError> {
+        let object = self.object_path(hash);
+
+        if object.is_file() {
+            return Ok(false);
+        }
+
+        if let Some(parent) = object.parent() {
+            create_dir_all(parent)?;
+        }
+
+        // Copy under a temporary name first, so an interrupted backup never
+        // leaves a truncated object behind.
+        let partial = object.with_extension("partial");
+        std::fs::copy(path, &partial)?;
+        rename(&partial, &object)?;
+
+        Ok(true)
+    }
+
+    /// Returns the hashes of the files any snapshot references. Fails if a
+    /// manifest can't be read, so its files are never removed.
+    fn referenced_objects(&self) -> Result<HashSet<String>, FilesystemError> {
+        let mut referenced = HashSet::new();
+
+        for entry in std::fs::read_dir(self.dir.join(MANIFESTS_DIR))? {
+            let path = entry?.path();
+
+            if path.extension().and_then(|ext| ext.to_str()) != Some("yml") {
+                continue;
+            }
+
+            let snapshot: Snapshot = serde_yaml::from_reader(File::open(&path)?)?;
+
+            for root in snapshot.roots {
+                referenced.extend(root.files.into_iter().map(|file| file.hash));
+            }
+        }
+
+        Ok(referenced)
+    }
+
+    /// Removes snapshots and every stored file the remaining snapshots don't
+    /// reference. Returns the size of the removed files in bytes.
+    pub fn remove(&self, ids: &[String]) -> Result<u64, FilesystemError> {
+        for id in ids {
+            let path = self.manifest_path(id);
+
+            if path.is_file() {
+                std::fs::remove_file(path)?;
+            }
+        }
+
+        let referenced = self.referenced_objects()?;
+        let mut size = 0;
+
+        // This also removes what an interrupted backup left behind.
+        for entry in WalkDir::new(self.dir.join(OBJECTS_DIR))
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+        {
+            if !referenced.contains(entry.file_name().to_string_lossy().as_ref()) {
+                size += entry.metadata()?.len();
+                std::fs::remove_file(entry.path())?;
+            }
+        }
+
+        log::debug!(
+            "removed {} snapshots, {} bytes no longer referenced",
+            ids.len(),
+            size
+        );
+
+        Ok(size)
+    }
+
+    /// Writes a snapshot as a regular archive, with a folder per library
+    /// root.
+    pub fn export(
+        &self,
+        id: &str,
+        output: &Path,
+        format: CompressionFormat,
+        zstd_level: i32,
+    ) -> Result<(), FilesystemError> {
+        let snapshot = self.snapshot(id)?;
+
+        match format {
+            CompressionFormat::Zip => {
+                let mut zip = ZipWriter::new(BufWriter::new(File::create(output)?));
+                let options = FileOptions::default()
+                    .compression_method(CompressionMethod::Deflated)
+                    .unix_permissions(0o755);
+
+                for root in snapshot.roots.iter() {
+                    let name = &root.name;
+
+                    for file in root.files.iter() {
+                        zip.start_file(format!("{}/{}", name, file.path), options)?;
+
+                        let mut object = File::open(self.object_path(&file.hash))?;
+                        std::io::copy(&mut object, &mut zip)?;
+                    }
+                }
+
+                if !snapshot.user_data.is_empty() {
+                    zip.start_file(USER_DATA_FILE, options)?;
+                    serde_yaml::to_writer(&mut zip, &snapshot.user_data)?;
+                }
+
+                zip.finish()?;
+            }
+            CompressionFormat::Zstd => {
+                use zstd::stream::write::Encoder as ZstdEncoder;
+
+                let mut enc = ZstdEncoder::new(File::create(output)?, zstd_level)?;
+                enc.multithread(num_cpus::get() as u32)?;
+                let mut tar = tar::Builder::new(enc.auto_finish());
+
+                for root in snapshot.roots.iter() {
+                    let name = &root.name;
+
+                    for file in root.files.iter() {
+                        let mut header = tar::Header::new_gnu();
+                        header.set_size(file.size);
+                        header.set_mode(0o644);
+                        header.set_mtime(
+                            file.modified
+                                .map(|modified| modified.timestamp().max(0) as u64)
+                                .unwrap_or_default(),
+                        );
+
+                        let object = File::open(self.object_path(&file.hash))?;
+                        tar.append_data(&mut header, format!("{}/{}", name, file.path), object)?;
+                    }
+                }
+
+                if !snapshot.user_data.is_empty() {
+                    let contents = serde_yaml::to_string(&snapshot.user_data)?;
+
+                    let mut header = tar::Header::new_gnu();
+                    header.set_size(contents.len() as u64);
+                    header.set_mode(0o644);
+                    header.set_mtime(snapshot.created.timestamp().max(0) as u64);
+
+                    tar.append_data(&mut header, USER_DATA_FILE, contents.as_bytes())?;
+                }
+
+                tar.finish()?;
+            }
+        }
+
+        log::debug!("exported snapshot {} to {:?}", id, output);
+
+        Ok(())
+    }
+}
+
+/// Returns the lowercase hex encoded SHA-256 checksum of a file.
+pub(crate) fn file_hash(path: &Path) -> Result<String, FilesystemError> {
+    Ok(read_hash(File::open(path)?)?.0)
+}
+
+/// Returns the lowercase hex encoded SHA-256 checksum of everything read
+/// from `reader`, and the number of bytes read.
+pub(crate) fn read_hash(mut reader: impl Read) -> std::io::Result<(String, u64)> {
+    let mut hasher = Sha256::new();
+    let mut buffer = [0; 64 * 1024];
+    let mut size = 0;
+
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+    }
+
+    Ok((format!("{:x}", hasher.finalize()), size))
+}
+
+/// Returns when the latest snapshot in the backup folder was made, without
+/// reading the manifests. Snapshot ids start with the local time they were
+/// made at.
+pub fn latest_snapshot_time(backup_dir: &Path) -> Option<NaiveDateTime> {
+    let manifests_dir = backup_dir.join(SNAPSHOT_DIR).join(MANIFESTS_DIR);
+
+    std::fs::read_dir(manifests_dir)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let id = entry.file_name().to_string_lossy().to_string();
+            let timestamp = id.get(..19)?;
+
+            NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d_%H-%M-%S").ok()
+        })
+        .max()
+}
+
+/// Backs up song libraries and the user data of their songs incrementally
+/// into the backup directory.
+pub async fn backup_songs(
+    backup_dir: PathBuf,
+    roots: Vec<PathBuf>,
+    user_data: UserData,
+) -> Result<SnapshotSummary, FilesystemError> {
+    SnapshotStore::in_backup_directory(&backup_dir)?.create(&roots, &user_data)
+}
+
+/// Exports a snapshot, or the latest one if `id` is `None`, as a regular
+/// archive. Returns the id of the exported snapshot.
+pub async fn export_snapshot(
+    backup_dir: PathBuf,
+    id: Option<String>,
+    output: PathBuf,
+    format: CompressionFormat,
+    zstd_level: i32,
+) -> Result<String, FilesystemError> {
+    let store = SnapshotStore::in_backup_directory(&backup_dir)?;
+
+    let id = match id {
+        Some(id) => id,
+        None => match store.latest()? {
+            Some(snapshot) => snapshot.id,
+            None => {
+                return Err(FilesystemError::SnapshotNotFound {
+                    id: "latest".to_owned(),
+                })
+            }
+        },
+    };
+
+    store.export(&id, &output, format, zstd_level)?;
+
+    Ok(id)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::io::Write;
+
+    fn write(path: &Path, contents: &str) {
+        create_dir_all(path.parent().unwrap()).unwrap();
+        let mut file = File::create(path).unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn test_incremental_snapshots() {
+        let backup_dir = tempfile::tempdir().unwrap();
+        let library = tempfile::tempdir().unwrap();
+        let root = library.path().join("Songs");
+
+        write(&root.join("One/notes.chart"), "one");
+        write(&root.join("One/song.ini"), "[song]");
+        write(&root.join("Two/notes.chart"), "two");
+        // Same contents as another file, only stored once.
+        write(&root.join("Two/song.ini"), "[song]");
+
+        let store = SnapshotStore::in_backup_directory(backup_dir.path()).unwrap();
+        let roots = vec![root.clone()];
+
+        let first = store.create(&roots, &UserData::default()).unwrap();
+        assert_eq!(first.files, 4);
+        assert_eq!(first.copied, 3);
+        assert_eq!(first.songs_changed, 2);
+
+        // Nothing changed, nothing is copied.
+        let second = store.create(&roots, &UserData::default()).unwrap();
+        assert_eq!(second.copied, 0);
+        assert_eq!(second.songs_changed, 0);
+        assert_ne!(first.id, second.id);
+
+        write(&root.join("Two/notes.chart"), "two, fixed");
+        write(&root.join("Three/notes.chart"), "three");
+
+        let mut user_data = UserData::default();
+        user_data.set(
+            "abc",
+            SongUserData {
+                favorite: true,
+                ..Default::default()
+            },
+        );
+
+        let third = store.create(&roots, &user_data).unwrap();
+        assert_eq!(third.files, 5);
+        assert_eq!(third.copied, 2);
+        assert_eq!(third.songs_changed, 2);
+
+        assert_eq!(store.snapshots().unwrap().len(), 3);
+        assert_eq!(
+            store.snapshot(&third.id).unwrap().user_data,
+            user_data.songs
+        );
+
+        // The first snapshot still has the original chart.
+        let output = backup_dir.path().join("first.zip");
+        store
+            .export(&first.id, &output, CompressionFormat::Zip, 0)
+            .unwrap();
+
+        let mut archive = zip::ZipArchive::new(File::open(&output).unwrap()).unwrap();
+        assert_eq!(archive.len(), 4);
+
+        let mut contents = String::new();
+        archive
+            .by_name("Songs/Two/notes.chart")
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "two");
+
+        let output = backup_dir.path().join("third.tar.zst");
+        store
+            .export(&third.id, &output, CompressionFormat::Zstd, 0)
+            .unwrap();
+
+        let decoder = zstd::stream::read::Decoder::new(File::open(&output).unwrap()).unwrap();
+        let mut tar = tar::Archive::new(decoder);
+        // The songs and the user data.
+        assert_eq!(tar.entries().unwrap().count(), 6);
+    }
+}
@@ -0,0 +1,331 @@
+use crate::clone_hero::scoredata::SongScores;
+use crate::origin::SongOrigin;
+use crate::userdata::SongUserData;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Eq, PartialEq, PartialOrd, Ord)]
+pub enum SongState {
+    Completed,
+    Downloading,
+    Error(String),
+    Idle,
+    Unpacking,
+    Updatable,
+}
+
+/// Instruments known by Clone Hero.
+///
+/// The order of the variants matches the instrument ids Clone Hero uses
+/// in `scoredata.bin` and `songcache.bin`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Hash, PartialOrd, Ord)]
+pub enum Instrument {
+    Guitar,
+    Bass,
+    Rhythm,
+    GuitarCoop,
+    GhlGuitar,
+    GhlBass,
+    Drums,
+    Keys,
+    Band,
+    Vocals,
+}
+
+impl Instrument {
+    pub const ALL: [Instrument; 10] = [
+        Instrument::Guitar,
+        Instrument::Bass,
+        Instrument::Rhythm,
+        Instrument::GuitarCoop,
+        Instrument::GhlGuitar,
+        Instrument::GhlBass,
+        Instrument::Drums,
+        Instrument::Keys,
+        Instrument::Band,
+        Instrument::Vocals,
+    ];
+
+    /// Returns the instrument for an id used in the Clone Hero binary files.
+    pub fn from_id(id: u16) -> Option<Instrument> {
+        match id {
+            0 => Some(Instrument::Guitar),
+            1 => Some(Instrument::Bass),
+            2 => Some(Instrument::Rhythm),
+            3 => Some(Instrument::GuitarCoop),
+            4 => Some(Instrument::GhlGuitar),
+            5 => Some(Instrument::GhlBass),
+            6 => Some(Instrument::Drums),
+            7 => Some(Instrument::Keys),
+            8 => Some(Instrument::Band),
+            9 => Some(Instrument::Vocals),
+            _ => None,
+        }
+    }
+
+    /// Returns the `song.ini` key holding the difficulty for this instrument.
+    pub const fn ini_key(self) -> &'static str {
+        match self {
+            Instrument::Guitar => "diff_guitar",
+            Instrument::Bass => "diff_bass",
+            Instrument::Rhythm => "diff_rhythm",
+            Instrument::GuitarCoop => "diff_guitar_coop",
+            Instrument::GhlGuitar => "diff_guitarghl",
+            Instrument::GhlBass => "diff_bassghl",
+            Instrument::Drums => "diff_drums",
+            Instrument::Keys => "diff_keys",
+            Instrument::Band => "diff_band",
+            Instrument::Vocals => "diff_vocals",
+        }
+    }
+}
+
+impl std::fmt::Display for Instrument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Instrument::Guitar => "Guitar",
+                Instrument::Bass => "Bass",
+                Instrument::Rhythm => "Rhythm",
+                Instrument::GuitarCoop => "Guitar Co-op",
+                Instrument::GhlGuitar => "GHL Guitar",
+                Instrument::GhlBass => "GHL Bass",
+                Instrument::Drums => "Drums",
+                Instrument::Keys => "Keys",
+                Instrument::Band => "Band",
+                Instrument::Vocals => "Vocals",
+            }
+        )
+    }
+}
+
+impl std::str::FromStr for Instrument {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "guitar" => Ok(Instrument::Guitar),
+            "bass" => Ok(Instrument::Bass),
+            "rhythm" => Ok(Instrument::Rhythm),
+            "guitar_coop" => Ok(Instrument::GuitarCoop),
+            "ghl_guitar" => Ok(Instrument::GhlGuitar),
+            "ghl_bass" => Ok(Instrument::GhlBass),
+            "drums" => Ok(Instrument::Drums),
+            "keys" => Ok(Instrument::Keys),
+            "band" => Ok(Instrument::Band),
+            "vocals" => Ok(Instrument::Vocals),
+            _ => Err("valid values are: guitar, bass, rhythm, guitar_coop, ghl_guitar, ghl_bass, drums, keys, band, vocals"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Hash, PartialOrd, Ord)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+    Expert,
+}
+
+impl Difficulty {
+    /// Returns the difficulty for an id used in the Clone Hero binary files.
+    pub fn from_id(id: u8) -> Option<Difficulty> {
+        match id {
+            0 => Some(Difficulty::Easy),
+            1 => Some(Difficulty::Medium),
+            2 => Some(Difficulty::Hard),
+            3 => Some(Difficulty::Expert),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Difficulty::Easy => "Easy",
+                Difficulty::Medium => "Medium",
+                Difficulty::Hard => "Hard",
+                Difficulty::Expert => "Expert",
+            }
+        )
+    }
+}
+
+/// Metadata parsed from a song folder's `song.ini`, or from the `[Song]`
+/// section of `notes.chart` if no `song.ini` is present.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct SongMetadata {
+    pub name: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub genre: Option<String>,
+    pub year: Option<String>,
+    pub charter: Option<String>,
+    /// Length of the song in milliseconds.
+    pub song_length: Option<u64>,
+    /// Difficulty per instrument. Instruments without a chart are omitted.
+    #[serde(default)]
+    pub difficulties: BTreeMap<Instrument, i32>,
+}
+
+#[derive(Debug, Clone)]
+/// Struct which stores information about a single song folder.
+pub struct Song {
+    /// Id is the path of the song folder, relative to its library root.
+    pub id: String,
+    /// Absolute path to the song folder.
+    pub path: PathBuf,
+    /// Library root the song folder was found in.
+    pub root: PathBuf,
+    /// Lowercase hex encoded MD5 checksum of the chart file. This is what
+    /// Clone Hero uses to identify a song in its binary files.
+    pub hash: Option<String>,
+    pub metadata: SongMetadata,
+    pub state: SongState,
+    /// Play history from `scoredata.bin`, if the song has ever been played.
+    pub scores: Option<SongScores>,
+    /// Where the song was installed from, if it was installed by us.
+    pub origin: Option<SongOrigin>,
+    /// Tags, rating and notes the user gave the song.
+    pub user_data: SongUserData,
+
+    // States for GUI
+    #[cfg(feature = "gui")]
+    pub details_btn_state: iced_native::button::State,
+    #[cfg(feature = "gui")]
+    pub open_folder_btn_state: iced_native::button::State,
+    #[cfg(feature = "gui")]
+    pub validate_btn_state: iced_native::button::State,
+    #[cfg(feature = "gui")]
+    pub quarantine_btn_state: iced_native::button::State,
+    #[cfg(feature = "gui")]
+    pub fuzzy_score: Option<i64>,
+}
+
+impl Song {
+    pub fn new(id: String, path: PathBuf, root: PathBuf) -> Self {
+        Song {
+            id,
+            path,
+            root,
+            hash: None,
+            metadata: Default::default(),
+            state: SongState::Idle,
+            scores: None,
+            origin: None,
+            user_data: Default::default(),
+            #[cfg(feature = "gui")]
+            details_btn_state: Default::default(),
+            #[cfg(feature = "gui")]
+            open_folder_btn_state: Default::default(),
+            #[cfg(feature = "gui")]
+            validate_btn_state: Default::default(),
+            #[cfg(feature = "gui")]
+            quarantine_btn_state: Default::default(),
+            #[cfg(feature = "gui")]
+            fuzzy_score: None,
+        }
+    }
+
+    /// Returns the name of the song, falling back to the folder name.
+    pub fn title(&self) -> &str {
+        self.metadata.name.as_deref().unwrap_or_else(|| {
+            self.path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or(&self.id)
+        })
+    }
+
+    pub fn artist(&self) -> Option<&str> {
+        self.metadata.artist.as_deref()
+    }
+
+    pub fn charter(&self) -> Option<&str> {
+        self.metadata.charter.as_deref()
+    }
+
+    /// Returns the number of times the song has been played.
+    pub fn play_count(&self) -> u32 {
+        self.scores
+            .as_ref()
+            .map(|s| s.play_count)
+            .unwrap_or_default()
+    }
+
+    /// Returns the best score across all instruments and difficulties.
+    pub fn best_score(&self) -> Option<u32> {
+        self.scores.as_ref().and_then(|s| s.best()).map(|s| s.score)
+    }
+
+    /// Returns the stars of the best score.
+    pub fn stars(&self) -> Option<u8> {
+        self.scores.as_ref().and_then(|s| s.best()).map(|s| s.stars)
+    }
+
+    /// Returns the accuracy, between 0 and 1, of the best score.
+    pub fn accuracy(&self) -> Option<f32> {
+        self.scores
+            .as_ref()
+            .and_then(|s| s.best())
+            .and_then(|s| s.accuracy())
+    }
+}
+
+/// Filter on play history and favorites used in My Songs.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, Hash, PartialOrd, Ord,
+)]
+pub enum PlayFilter {
+    #[default]
+    All,
+    NeverPlayed,
+    MostPlayed,
+    Favorites,
+}
+
+impl PlayFilter {
+    pub const ALL: [PlayFilter; 4] = [
+        PlayFilter::All,
+        PlayFilter::NeverPlayed,
+        PlayFilter::MostPlayed,
+        PlayFilter::Favorites,
+    ];
+
+    /// Returns true if the song should be shown with this filter.
+    ///
+    /// `MostPlayed` only shows songs which have been played at least once,
+    /// they are expected to be sorted by play count by the caller.
+    pub fn matches(self, song: &Song) -> bool {
+        match self {
+            PlayFilter::All => true,
+            PlayFilter::NeverPlayed => song.play_count() == 0,
+            PlayFilter::MostPlayed => song.play_count() > 0,
+            PlayFilter::Favorites => song.user_data.favorite,
+        }
+    }
+}
+
+impl std::fmt::Display for PlayFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                PlayFilter::All => "All",
+                PlayFilter::NeverPlayed => "Never played",
+                PlayFilter::MostPlayed => "Most played",
+                PlayFilter::Favorites => "Favorites",
+            }
+        )
+    }
+}
@@ -0,0 +1,168 @@
+use crate::fs::folder_size;
+use crate::song::{Instrument, Song};
+
+use serde::Serialize;
+
+use std::collections::BTreeMap;
+
+/// Totals and breakdowns over the songs of a library.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LibraryStats {
+    pub songs: usize,
+    /// Length in milliseconds of all songs with a known length.
+    pub playtime: u64,
+    /// Number of songs per genre.
+    pub genres: BTreeMap<String, usize>,
+    /// Number of songs per decade, keyed by its first year.
+    pub decades: BTreeMap<u32, usize>,
+    /// Number of songs per charter.
+    pub charters: BTreeMap<String, usize>,
+    /// Number of songs charted for each instrument.
+    pub instruments: BTreeMap<Instrument, usize>,
+    /// Number of songs per difficulty, for each instrument.
+    pub difficulties: BTreeMap<Instrument, BTreeMap<i32, usize>>,
+    /// Size in bytes of all song folders.
+    pub disk_usage: u64,
+}
+
+impl LibraryStats {
+    /// Counts the songs without looking at the song folders, so `disk_usage`
+    /// is left at 0.
+    pub fn from_songs(songs: &[Song]) -> Self {
+        let mut stats = LibraryStats {
+            songs: songs.len(),
+            ..Default::default()
+        };
+
+        for song in songs {
+            let metadata = &song.metadata;
+
+            stats.playtime += metadata.song_length.unwrap_or_default();
+
+            if let Some(genre) = non_empty(metadata.genre.as_deref()) {
+                *stats.genres.entry(genre.to_owned()).or_default() += 1;
+            }
+
+            if let Some(year) = metadata.year.as_deref().and_then(parse_year) {
+                *stats.decades.entry(year / 10 * 10).or_default() += 1;
+            }
+
+            if let Some(charter) = non_empty(song.charter()) {
+                *stats.charters.entry(charter.to_owned()).or_default() += 1;
+            }
+
+            for (instrument, difficulty) in metadata.difficulties.iter() {
+                *stats.instruments.entry(*instrument).or_default() += 1;
+                *stats
+                    .difficulties
+                    .entry(*instrument)
+                    .or_default()
+                    .entry(*difficulty)
+                    .or_default() += 1;
+            }
+        }
+
+        stats
+    }
+}
+
+/// Computes the statistics of `songs`, including the disk usage of their
+/// folders.
+pub async fn library_stats(songs: Vec<Song>) -> LibraryStats {
+    let mut stats = LibraryStats::from_songs(&songs);
+
+    stats.disk_usage = songs.iter().map(|song| folder_size(&song.path)).sum();
+
+    stats
+}
+
+/// Returns the entries of a breakdown, with the highest count first.
+pub fn by_count<K: Ord>(counts: &BTreeMap<K, usize>) -> Vec<(&K, usize)> {
+    let mut entries = counts
+        .iter()
+        .map(|(key, count)| (key, *count))
+        .collect::<Vec<_>>();
+
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+    entries
+}
+
+/// Formats a playtime in milliseconds as hours, minutes and seconds.
+pub fn format_playtime(ms: u64) -> String {
+    let seconds = ms / 1000;
+
+    format!(
+        "{}h {:02}m {:02}s",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+fn non_empty(value: Option<&str>) -> Option<&str> {
+    value.map(str::trim).filter(|value| !value.is_empty())
+}
+
+/// Parses the year of a song, which is often written with extra text like
+/// `, 1999`.
+pub(crate) fn parse_year(year: &str) -> Option<u32> {
+    let digits = year
+        .chars()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<String>();
+
+    digits.parse().ok().filter(|year| *year >= 1000)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    fn song(genre: &str, year: &str, difficulties: &[(Instrument, i32)]) -> Song {
+        let mut song = Song::new(String::new(), PathBuf::new(), PathBuf::new());
+        song.metadata.genre = Some(genre.to_owned());
+        song.metadata.year = Some(year.to_owned());
+        song.metadata.charter = Some("Harmonix".to_owned());
+        song.metadata.song_length = Some(200_000);
+        song.metadata.difficulties = difficulties.iter().copied().collect();
+        song
+    }
+
+    #[test]
+    fn test_library_stats() {
+        let songs = vec![
+            song(
+                "Rock",
+                "1991",
+                &[(Instrument::Guitar, 5), (Instrument::Drums, 5)],
+            ),
+            song("Rock", ", 1999", &[(Instrument::Guitar, 3)]),
+            song(" ", "unknown", &[]),
+        ];
+
+        let stats = LibraryStats::from_songs(&songs);
+
+        assert_eq!(stats.songs, 3);
+        assert_eq!(stats.playtime, 600_000);
+        assert_eq!(stats.genres.get("Rock"), Some(&2));
+        assert_eq!(stats.genres.len(), 1);
+        assert_eq!(stats.decades.get(&1990), Some(&2));
+        assert_eq!(stats.decades.len(), 1);
+        assert_eq!(stats.charters.get("Harmonix"), Some(&3));
+        assert_eq!(stats.instruments.get(&Instrument::Guitar), Some(&2));
+        assert_eq!(
+            stats.difficulties[&Instrument::Guitar],
+            vec![(3, 1), (5, 1)].into_iter().collect()
+        );
+
+        assert_eq!(
+            by_count(&stats.instruments),
+            vec![(&Instrument::Guitar, 2), (&Instrument::Drums, 1)]
+        );
+        assert_eq!(format_playtime(stats.playtime), "0h 10m 00s");
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{
+    cache::{SongCache, SongCacheEntry},
+    error::FilesystemError,
+    fs::{copy_dir, move_dir, unique_destination, PersistentData},
+    library::song_folder_modified,
+    organizer::remove_empty_parents,
+    pack::is_in_pack,
+    song::Song,
+};
+
+use async_std::sync::{Arc, Mutex};
+
+use std::path::{Path, PathBuf};
+
+/// Whether songs are copied or moved to another library root.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransferMode {
+    Copy,
+    Move,
+}
+
+impl std::fmt::Display for TransferMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                TransferMode::Copy => "Copy",
+                TransferMode::Move => "Move",
+            }
+        )
+    }
+}
+
+/// Outcome of `transfer_songs`.
+#[derive(Debug, Clone, Default)]
+pub struct TransferResult {
+    /// Songs which were transferred, as `(source, destination)`.
+    pub transferred: Vec<(Song, Song)>,
+    /// Songs which couldn't be transferred, with the reason.
+    pub failed: Vec<(Song, String)>,
+}
+
+/// Copies or moves songs into `target_root`, keeping their path relative to
+/// the library root they came from.
+///
+/// A number is appended to the folder name if the destination already
+/// exists. Songs inside a pack aren't transferred, they belong to the pack.
+/// The song cache of the target root gains an entry for every transferred
+/// song, moved songs are removed from the cache of their source root.
+/// `progress` is called before each song with its index and the total.
+pub async fn transfer_songs<F>(
+    song_cache: Option<Arc<Mutex<SongCache>>>,
+    songs: Vec<Song>,
+    target_root: PathBuf,
+    mode: TransferMode,
+    mut progress: F,
+) -> Result<TransferResult, FilesystemError>
+where
+    F: FnMut(usize, usize, &Song),
+{
+    let mut result = TransferResult::default();
+    let total = songs.len();
+
+    for (idx, song) in songs.into_iter().enumerate() {
+        progress(idx, total, &song);
+
+        if song.root == target_root {
+            result
+                .failed
+                .push((song, "Song is already in this library".to_owned()));
+            continue;
+        }
+
+        if is_in_pack(&song) {
+            result
+                .failed
+                .push((song, "Song is part of a pack".to_owned()));
+            continue;
+        }
+
+        let to = unique_destination(target_root.join(&song.id));
+
+        match transfer_song_folder(&song, &to, mode) {
+            Ok(_) => {
+                let mut transferred = song.clone();
+                transferred.id = to
+                    .strip_prefix(&target_root)
+                    .map(|id| id.to_string_lossy().replace('\\', "/"))
+                    .unwrap_or_else(|_| song.id.clone());
+                transferred.path = to;
+                transferred.root = target_root.clone();
+
+                result.transferred.push((song, transferred));
+            }
+            Err(error) => {
+                log::error!("failed to {} {:?}: {}", mode, &song.path, error);
+                result.failed.push((song, error.to_string()));
+            }
+        }
+    }
+
+    if let Some(song_cache) = song_cache {
+        let mut cache = song_cache.lock().await;
+
+        for (from, to) in result.transferred.iter() {
+            if mode == TransferMode::Move {
+                cache
+                    .get_mut_for_root(&from.root)
+                    .retain(|entry| entry.path != from.path);
+            }
+
+            if let Some(modified) = song_folder_modified(&to.path) {
+                let entries = cache.get_mut_for_root(&to.root);
+
+                entries.retain(|entry| entry.path != to.path);
+                entries.push(SongCacheEntry {
+                    path: to.path.clone(),
+                    hash: to.hash.clone(),
+                    metadata: to.metadata.clone(),
+                    modified,
+                    origin: to.origin.clone(),
+                });
+            }
+        }
+
+        cache.save()?;
+    }
+
+    log::debug!(
+        "{} songs to {:?}, {} done and {} failed",
+        mode,
+        &target_root,
+        result.transferred.len(),
+        result.failed.len()
+    );
+
+    Ok(result)
+}
+
+fn transfer_song_folder(song: &Song, to: &Path, mode: TransferMode) -> Result<(), FilesystemError> {
+    match mode {
+        TransferMode::Copy => copy_dir(&song.path, to),
+        TransferMode::Move => {
+            move_dir(&song.path, to)?;
+            remove_empty_parents(&song.path, &song.root);
+
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{library::read_song_directory, pack::PACK_FILE};
+
+    use async_std::task;
+
+    #[test]
+    fn test_transfer_songs() {
+        task::block_on(async {
+            let source = tempfile::tempdir().unwrap();
+            let target = tempfile::tempdir().unwrap();
+
+            for id in ["Artist/One", "Artist/Two"].iter() {
+                let folder = source.path().join(id);
+                std::fs::create_dir_all(&folder).unwrap();
+                std::fs::write(folder.join("notes.chart"), "[Song]\n{\n}\n").unwrap();
+            }
+
+            // Already taken in the target library
+            std::fs::create_dir_all(target.path().join("Artist/Two")).unwrap();
+
+            // Songs of a pack stay with it
+            let pack = source.path().join("Pack/Three");
+            std::fs::create_dir_all(&pack).unwrap();
+            std::fs::write(pack.join("notes.chart"), "[Song]\n{\n}\n").unwrap();
+            std::fs::write(source.path().join("Pack").join(PACK_FILE), "name: Pack").unwrap();
+
+            let songs = read_song_directory(None, source.path()).await.unwrap();
+            let mut seen = vec![];
+
+            let result = transfer_songs(
+                None,
+                songs.clone(),
+                target.path().to_owned(),
+                TransferMode::Copy,
+                |idx, total, song| seen.push((idx, total, song.id.clone())),
+            )
+            .await
+            .unwrap();
+
+            assert_eq!(seen.len(), 3);
+            assert_eq!(result.transferred.len(), 2);
+            assert_eq!(result.failed.len(), 1);
+            assert_eq!(result.failed[0].0.path, pack);
+            assert!(!target.path().join("Pack").exists());
+            assert!(source.path().join("Artist/One/notes.chart").is_file());
+            assert!(target.path().join("Artist/One/notes.chart").is_file());
+            assert!(target.path().join("Artist/Two (1)/notes.chart").is_file());
+
+            let result = transfer_songs(
+                None,
+                songs,
+                target.path().to_owned(),
+                TransferMode::Move,
+                |_, _, _| {},
+            )
+            .await
+            .unwrap();
+
+            let (_, moved) = &result.transferred[0];
+            assert_eq!(moved.root, target.path());
+            assert!(moved.path.join("notes.chart").is_file());
+            assert!(!source.path().join("Artist").exists());
+            assert!(pack.join("notes.chart").is_file());
+        });
+    }
+}
@@ -0,0 +1,222 @@
+use crate::{error::FilesystemError, fs::PersistentData, song::Song};
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Highest rating a song can be given.
+pub const MAX_RATING: u8 = 5;
+
+/// Returns a rating as stars, like `★★★☆☆`.
+pub fn stars(rating: u8) -> String {
+    (1..=MAX_RATING)
+        .map(|r| if r <= rating { '★' } else { '☆' })
+        .collect()
+}
+
+/// What the user noted about a song.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SongUserData {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub favorite: bool,
+    /// Rating from 1 to `MAX_RATING`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+impl SongUserData {
+    /// Returns true if nothing was noted about the song.
+    pub fn is_empty(&self) -> bool {
+        self == &SongUserData::default()
+    }
+
+    /// Returns true if the song has the tag, ignoring case.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag.trim()))
+    }
+
+    /// Adds a tag, unless the song already has it. Returns true if it was
+    /// added.
+    pub fn add_tag(&mut self, tag: &str) -> bool {
+        let tag = tag.trim();
+
+        if tag.is_empty() || self.has_tag(tag) {
+            return false;
+        }
+
+        self.tags.push(tag.to_owned());
+        self.tags.sort_by_key(|t| t.to_lowercase());
+
+        true
+    }
+
+    pub fn remove_tag(&mut self, tag: &str) {
+        self.tags.retain(|t| !t.eq_ignore_ascii_case(tag.trim()));
+    }
+
+    /// Sets the rating, clamped to 1 to `MAX_RATING`. `None` or 0 removes it.
+    pub fn set_rating(&mut self, rating: Option<u8>) {
+        self.rating = rating.filter(|r| *r > 0).map(|r| r.min(MAX_RATING));
+    }
+
+    /// Sets the notes, removing them if they are empty.
+    pub fn set_notes(&mut self, notes: String) {
+        self.notes = if notes.trim().is_empty() {
+            None
+        } else {
+            Some(notes)
+        };
+    }
+
+    /// Returns the rating as stars, like `★★★☆☆`.
+    pub fn stars(&self) -> Option<String> {
+        self.rating.map(stars)
+    }
+}
+
+/// Tags, favorites, ratings and notes of songs, saved as `userdata.yml` in
+/// the config directory.
+///
+/// Songs are keyed by their chart hash rather than their path, so the data
+/// stays with a song when it's renamed or moved to another library root.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct UserData {
+    #[serde(default)]
+    pub songs: HashMap<String, SongUserData>,
+}
+
+impl UserData {
+    pub fn get(&self, hash: &str) -> Option<&SongUserData> {
+        self.songs.get(hash)
+    }
+
+    /// Stores the data of a song, removing the entry if it's empty.
+    pub fn set(&mut self, hash: &str, data: SongUserData) {
+        if data.is_empty() {
+            self.songs.remove(hash);
+        } else {
+            self.songs.insert(hash.to_owned(), data);
+        }
+    }
+
+    /// Adds the entries of songs which have no data yet, keeping the data of
+    /// the others. Returns the number of songs added.
+    pub fn merge(&mut self, songs: HashMap<String, SongUserData>) -> usize {
+        let mut added = 0;
+
+        for (hash, data) in songs {
+            if !data.is_empty() && !self.songs.contains_key(&hash) {
+                self.songs.insert(hash, data);
+                added += 1;
+            }
+        }
+
+        added
+    }
+
+    /// Copies the data of a song whose chart changed from hash `from` to
+    /// `to`, like after an update, unless there is data for `to` already.
+    /// Returns true if anything was copied.
+    ///
+    /// The data is kept for `from` too, for other copies of the song and for
+    /// rolling it back.
+    pub fn carry_over(&mut self, from: &str, to: &str) -> bool {
+        if from == to || self.songs.contains_key(to) {
+            return false;
+        }
+
+        match self.songs.get(from).cloned() {
+            Some(data) => {
+                self.songs.insert(to.to_owned(), data);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Joins the user data onto the songs, matching them on chart hash.
+    pub fn apply(&self, songs: &mut [Song]) {
+        for song in songs.iter_mut() {
+            song.user_data = song
+                .hash
+                .as_ref()
+                .and_then(|hash| self.songs.get(hash))
+                .cloned()
+                .unwrap_or_default();
+        }
+    }
+}
+
+impl PersistentData for UserData {
+    fn relative_path() -> PathBuf {
+        PathBuf::from("userdata.yml")
+    }
+}
+
+pub async fn load_user_data() -> Result<UserData, FilesystemError> {
+    UserData::load_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_user_data() {
+        let mut data = SongUserData::default();
+        assert!(data.is_empty());
+
+        assert!(data.add_tag(" warmup "));
+        assert!(data.add_tag("Ballad"));
+        assert!(!data.add_tag("WARMUP"));
+        assert!(!data.add_tag(" "));
+        assert_eq!(data.tags, vec!["Ballad".to_owned(), "warmup".to_owned()]);
+
+        data.set_rating(Some(9));
+        assert_eq!(data.rating, Some(MAX_RATING));
+        data.set_rating(Some(3));
+        assert_eq!(data.stars().as_deref(), Some("★★★☆☆"));
+
+        data.set_notes(" ".to_owned());
+        assert_eq!(data.notes, None);
+
+        let mut user_data = UserData::default();
+        user_data.set("abc", data.clone());
+
+        let mut one = Song::new("One".to_owned(), PathBuf::from("One"), PathBuf::new());
+        one.hash = Some("abc".to_owned());
+        let two = Song::new("Two".to_owned(), PathBuf::from("Two"), PathBuf::new());
+        let mut songs = vec![one, two];
+
+        user_data.apply(&mut songs);
+        assert_eq!(songs[0].user_data, data);
+        assert!(songs[1].user_data.is_empty());
+
+        // Existing data is kept when merging.
+        let other = SongUserData {
+            favorite: true,
+            ..Default::default()
+        };
+        let merged = user_data.merge(
+            vec![("abc".to_owned(), other.clone()), ("def".to_owned(), other)]
+                .into_iter()
+                .collect(),
+        );
+        assert_eq!(merged, 1);
+        assert_eq!(user_data.get("abc"), Some(&data));
+
+        assert!(user_data.carry_over("abc", "ghi"));
+        assert!(!user_data.carry_over("abc", "def"));
+        assert_eq!(user_data.get("ghi"), Some(&data));
+
+        data.tags.clear();
+        data.set_rating(None);
+        user_data.set("abc", data);
+        assert_eq!(user_data.get("abc"), None);
+    }
+}
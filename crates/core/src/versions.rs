@@ -0,0 +1,413 @@
+use crate::{
+    cache::SongCache,
+    error::FilesystemError,
+    fs::{config_dir, copy_dir, folder_size, replace_dir, sibling_dir, PersistentData},
+    library::read_song_folder,
+    origin::{read_song_origin, write_song_origin, ORIGIN_FILE},
+    snapshot::file_hash,
+    song::Song,
+    utility::rename,
+};
+
+use async_std::sync::{Arc, Mutex};
+use chrono::{DateTime, Utc};
+use path_slash::PathExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs::{create_dir_all, remove_dir_all, File};
+use std::path::{Path, PathBuf};
+
+/// Index of the versions store, inside the store folder.
+const INDEX_FILE: &str = "index.yml";
+
+/// Default size cap of the versions store, in MB.
+pub const DEFAULT_MAX_SIZE_MB: u64 = 1024;
+
+/// A previous version of a song, archived before it was replaced.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SongVersion {
+    /// Hash of the contents of the song folder. Versions with the same
+    /// contents are stored once.
+    pub id: String,
+    /// The song the version belongs to, see `song_key`.
+    pub key: String,
+    /// Chart hash of the version, which its scores are kept under.
+    pub hash: Option<String>,
+    pub name: String,
+    pub archived: DateTime<Utc>,
+    /// Size of the stored song folder in bytes.
+    pub size: u64,
+}
+
+/// Previous versions of songs, stored in a folder per content hash.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct VersionStore {
+    #[serde(skip)]
+    dir: PathBuf,
+    /// Maximum size of all stored versions in bytes.
+    #[serde(skip)]
+    max_size: u64,
+    versions: Vec<SongVersion>,
+}
+
+impl VersionStore {
+    /// Opens the versions store in `dir`, creating it if needed.
+    pub fn open(dir: PathBuf, max_size: u64) -> Result<Self, FilesystemError> {
+        create_dir_all(&dir)?;
+
+        let index = dir.join(INDEX_FILE);
+
+        let mut store: VersionStore = if index.is_file() {
+            serde_yaml::from_reader(File::open(&index)?)?
+        } else {
+            VersionStore::default()
+        };
+
+        store.dir = dir;
+        store.max_size = max_size;
+
+        Ok(store)
+    }
+
+    /// Returns the default location of the versions store.
+    pub fn default_dir() -> PathBuf {
+        config_dir().join("versions")
+    }
+
+    /// Returns the archived versions of a song, newest first. Versions follow
+    /// the song when its folder is moved.
+    pub fn versions(&self, song: &Song) -> Vec<SongVersion> {
+        let key = match song_key(song) {
+            Some(key) => key,
+            None => return vec![],
+        };
+
+        let mut versions: Vec<_> = self
+            .versions
+            .iter()
+            .filter(|version| version.key == key)
+            .cloned()
+            .collect();
+        versions.sort_by_key(|version| Reverse(version.archived));
+
+        versions
+    }
+
+    /// Returns the total size of the stored versions in bytes.
+    pub fn size(&self) -> u64 {
+        let mut sizes = HashMap::new();
+
+        for version in self.versions.iter() {
+            sizes.insert(&version.id, version.size);
+        }
+
+        sizes.values().sum()
+    }
+
+    /// Archives the current contents of a song folder.
+    ///
+    /// The song folder is only copied if no version with the same contents
+    /// is stored yet. The oldest versions are removed afterwards if the
+    /// store grew larger than its size cap.
+    pub fn archive(&mut self, song: &Song) -> Result<(), FilesystemError> {
+        self.store(song)?;
+        self.prune()?;
+        self.save()
+    }
+
+    /// Copies a song folder into the store, unless its contents are already
+    /// stored, and records the version.
+    fn store(&mut self, song: &Song) -> Result<(), FilesystemError> {
+        let key = match song_key(song) {
+            Some(key) => key,
+            None => return Ok(()),
+        };
+
+        if !song.path.is_dir() {
+            return Ok(());
+        }
+
+        let id = folder_hash(&song.path)?;
+
+        let blob = self.dir.join(&id);
+        if !blob.exists() {
+            // A copy which was cut short is never taken for a stored version.
+            let partial = self.dir.join(format!("{}.partial", &id));
+            if partial.exists() {
+                remove_dir_all(&partial)?;
+            }
+
+            copy_dir(&song.path, &partial)?;
+            rename(&partial, &blob)?;
+        }
+
+        self.versions
+            .retain(|version| !(version.key == key && version.id == id));
+        self.versions.push(SongVersion {
+            id,
+            key,
+            hash: song.hash.clone(),
+            name: song.title().to_owned(),
+            archived: Utc::now(),
+            size: folder_size(&blob),
+        });
+
+        Ok(())
+    }
+
+    /// Replaces a song folder with an archived version.
+    ///
+    /// The current contents are always archived first, so the rollback can
+    /// be undone. The restored song is pinned, so it isn't updated again.
+    pub fn restore(&mut self, song: &Song, id: &str) -> Result<(), FilesystemError> {
+        let blob = self.dir.join(id);
+
+        if !blob.is_dir() {
+            return Err(FilesystemError::VersionNotFound {
+                hash: id.to_owned(),
+            });
+        }
+
+        self.store(song)?;
+
+        // The version is copied next to the song folder, which is only
+        // replaced once the copy is complete.
+        let staging = sibling_dir(&song.path, "chmon-staging");
+        if staging.exists() {
+            remove_dir_all(&staging)?;
+        }
+
+        copy_dir(&blob, &staging)?;
+
+        if let Some(mut origin) = read_song_origin(&staging) {
+            origin.pinned = true;
+            write_song_origin(&staging, &origin)?;
+        }
+
+        replace_dir(&staging, &song.path)?;
+
+        self.prune()?;
+        self.save()
+    }
+
+    /// Removes the oldest versions until the store fits its size cap. The
+    /// newest version is always kept, even if it's larger than the cap.
+    fn prune(&mut self) -> Result<(), FilesystemError> {
+        self.versions.sort_by_key(|version| version.archived);
+
+        while self.size() > self.max_size && self.versions.len() > 1 {
+            let version = self.versions.remove(0);
+
+            // Other versions can share the stored folder.
+            if !self.versions.iter().any(|other| other.id == version.id) {
+                let blob = self.dir.join(&version.id);
+                if blob.exists() {
+                    remove_dir_all(&blob)?;
+                }
+            }
+
+            log::debug!("pruned version {} from the versions store", &version.id);
+        }
+
+        Ok(())
+    }
+
+    /// Writes the index of the store.
+    pub fn save(&self) -> Result<(), FilesystemError> {
+        let contents = serde_yaml::to_string(self)?;
+        std::fs::write(self.dir.join(INDEX_FILE), contents)?;
+
+        Ok(())
+    }
+}
+
+/// Returns what the versions of a song are stored under: the archive it was
+/// installed from, or its chart hash for songs without an origin. Unlike
+/// the folder, neither changes when the song is moved.
+fn song_key(song: &Song) -> Option<String> {
+    match &song.origin {
+        Some(origin) => Some(format!("{}#{}", origin.url, origin.archive_path)),
+        None => song.hash.clone(),
+    }
+}
+
+/// Returns a hash of the files in a song folder, by their path and contents.
+/// The origin file is left out, it records when the song was installed.
+fn folder_hash(path: &Path) -> Result<String, FilesystemError> {
+    let mut hasher = Sha256::new();
+
+    for entry in WalkDir::new(path).sort_by_file_name() {
+        let entry = entry?;
+
+        if !entry.file_type().is_file() || entry.file_name() == ORIGIN_FILE {
+            continue;
+        }
+
+        let name = entry
+            .path()
+            .strip_prefix(path)
+            .unwrap_or_else(|_| entry.path())
+            .to_slash_lossy();
+
+        hasher.update(name.as_bytes());
+        hasher.update(b"\0");
+        hasher.update(file_hash(entry.path())?.as_bytes());
+        hasher.update(b"\n");
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Opens the versions store in the config directory.
+pub async fn load_version_store(max_size: u64) -> Result<VersionStore, FilesystemError> {
+    VersionStore::open(VersionStore::default_dir(), max_size)
+}
+
+/// Archives the current contents of song folders which are about to be
+/// replaced. Failures are logged, as they shouldn't stop an update.
+pub(crate) async fn archive_songs(
+    version_store: &Option<Arc<Mutex<VersionStore>>>,
+    songs: &[Song],
+) {
+    if let Some(version_store) = version_store {
+        let mut store = version_store.lock().await;
+
+        for song in songs {
+            if let Err(error) = store.archive(song) {
+                log::error!("failed to archive {:?}: {}", &song.path, error);
+            }
+        }
+    }
+}
+
+/// Returns the archived versions of a song, newest first, except one with
+/// the same contents as the song folder.
+pub async fn song_versions(
+    version_store: Arc<Mutex<VersionStore>>,
+    song: Song,
+) -> (PathBuf, Vec<SongVersion>) {
+    let mut versions = version_store.lock().await.versions(&song);
+
+    if let Ok(current) = folder_hash(&song.path) {
+        versions.retain(|version| version.id != current);
+    }
+
+    (song.path, versions)
+}
+
+/// Rolls a song back to an archived version and returns the song read back
+/// from disk.
+pub async fn rollback_song(
+    song_cache: Option<Arc<Mutex<SongCache>>>,
+    version_store: Arc<Mutex<VersionStore>>,
+    song: Song,
+    id: String,
+) -> Result<Song, FilesystemError> {
+    version_store.lock().await.restore(&song, &id)?;
+
+    let mut restored = read_song_folder(&song.root, &song.path);
+    restored.scores = None;
+
+    if let Some(song_cache) = song_cache {
+        let mut cache = song_cache.lock().await;
+        cache.replace_song(&restored);
+        cache.save()?;
+    }
+
+    log::debug!("rolled {:?} back to {}", &song.path, &id);
+
+    Ok(restored)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::origin::SongOrigin;
+
+    fn write_song(folder: &Path, chart: &str) -> Song {
+        create_dir_all(folder).unwrap();
+        std::fs::write(folder.join("notes.chart"), chart).unwrap();
+
+        let origin = SongOrigin {
+            url: "https://example.com/songs.zip".to_owned(),
+            archive_path: folder.file_name().unwrap().to_string_lossy().to_string(),
+            ..Default::default()
+        };
+        write_song_origin(folder, &origin).unwrap();
+
+        read_song_folder(folder.parent().unwrap(), folder)
+    }
+
+    #[test]
+    fn test_archive_and_restore() {
+        let store_dir = tempfile::tempdir().unwrap();
+        let root = tempfile::tempdir().unwrap();
+        let folder = root.path().join("Song");
+
+        let mut store = VersionStore::open(store_dir.path().to_owned(), u64::MAX).unwrap();
+
+        let first = write_song(&folder, "first");
+        store.archive(&first).unwrap();
+        // Archiving the same contents again is deduplicated.
+        store.archive(&first).unwrap();
+        assert_eq!(store.versions(&first).len(), 1);
+
+        remove_dir_all(&folder).unwrap();
+        let second = write_song(&folder, "second");
+
+        let first_id = store.versions(&first)[0].id.clone();
+        store.restore(&second, &first_id).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(folder.join("notes.chart")).unwrap(),
+            "first"
+        );
+        assert_eq!(store.versions(&second).len(), 2);
+
+        // A folder with the same chart but other files is archived as well.
+        std::fs::write(folder.join("song.ogg"), "audio").unwrap();
+        let restored = read_song_folder(root.path(), &folder);
+        assert_eq!(restored.hash, first.hash);
+
+        store.restore(&restored, &first_id).unwrap();
+        assert_eq!(store.versions(&restored).len(), 3);
+        assert!(!folder.join("song.ogg").exists());
+
+        // Versions follow a song which is moved.
+        let moved = root.path().join("Artist/Song");
+        create_dir_all(moved.parent().unwrap()).unwrap();
+        rename(&folder, &moved).unwrap();
+        let moved = read_song_folder(root.path(), &moved);
+        assert_eq!(store.versions(&moved).len(), 3);
+
+        // The index survives reopening the store.
+        let store = VersionStore::open(store_dir.path().to_owned(), u64::MAX).unwrap();
+        assert_eq!(store.versions(&moved).len(), 3);
+    }
+
+    #[test]
+    fn test_prune() {
+        let store_dir = tempfile::tempdir().unwrap();
+        let root = tempfile::tempdir().unwrap();
+
+        let mut store = VersionStore::open(store_dir.path().to_owned(), 10).unwrap();
+
+        let old = write_song(&root.path().join("Old"), "123456");
+        store.archive(&old).unwrap();
+        let old_id = store.versions(&old)[0].id.clone();
+
+        let new = write_song(&root.path().join("New"), "abcdef");
+        store.archive(&new).unwrap();
+
+        assert!(store.versions(&old).is_empty());
+        assert!(!store_dir.path().join(old_id).exists());
+        // The newest version is kept, even though it's over the cap.
+        assert_eq!(store.versions(&new).len(), 1);
+        assert!(store.size() > 10);
+    }
+}
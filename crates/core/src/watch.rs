@@ -0,0 +1,126 @@
+use crate::error::FilesystemError;
+
+use futures::channel::mpsc;
+use futures::Stream;
+use notify::{watcher, DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+/// Delay notify waits for more events on the same path before reporting it.
+const EVENT_DELAY: Duration = Duration::from_millis(500);
+
+/// Watches a folder for changes. Changes are reported in batches, once no
+/// more changes were seen for the debounce delay, so a song which is being
+/// copied or extracted is reported once it's complete.
+pub struct FolderWatcher {
+    // Stops watching when dropped.
+    _watcher: RecommendedWatcher,
+    events: Receiver<DebouncedEvent>,
+    debounce: Duration,
+}
+
+impl FolderWatcher {
+    /// Starts watching `path`, and everything below it if `recursive`.
+    pub fn new(path: &Path, recursive: bool, debounce: Duration) -> Result<Self, FilesystemError> {
+        let (sender, events) = channel();
+
+        let mut watcher = watcher(sender, EVENT_DELAY)?;
+
+        let mode = if recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        watcher.watch(path, mode)?;
+
+        log::debug!("watching {:?} for changes", path);
+
+        Ok(FolderWatcher {
+            _watcher: watcher,
+            events,
+            debounce,
+        })
+    }
+
+    /// Blocks until something changes and returns the changed paths,
+    /// including both paths of a rename. Returns `None` once the watcher
+    /// stopped.
+    pub fn next_changes(&self) -> Option<Vec<PathBuf>> {
+        loop {
+            let event = self.events.recv().ok()?;
+            let changes = self.collect_changes(event)?;
+
+            if !changes.is_empty() {
+                return Some(changes);
+            }
+        }
+    }
+
+    /// Returns a stream of the batches of changed paths. The watcher runs on
+    /// its own thread until the stream is dropped.
+    pub fn into_stream(self) -> impl Stream<Item = Vec<PathBuf>> {
+        let (sender, receiver) = mpsc::unbounded();
+
+        std::thread::spawn(move || loop {
+            // Wakes up regularly to notice when the stream was dropped.
+            let event = match self.events.recv_timeout(Duration::from_secs(1)) {
+                Ok(event) => event,
+                Err(RecvTimeoutError::Timeout) if !sender.is_closed() => continue,
+                Err(_) => break,
+            };
+
+            match self.collect_changes(event) {
+                Some(changes) if changes.is_empty() => {}
+                Some(changes) => {
+                    if sender.unbounded_send(changes).is_err() {
+                        break;
+                    }
+                }
+                None => break,
+            }
+        });
+
+        receiver
+    }
+
+    /// Collects the changes of `event` and of every event following it
+    /// until none are seen for the debounce delay.
+    fn collect_changes(&self, event: DebouncedEvent) -> Option<Vec<PathBuf>> {
+        let mut changes = BTreeSet::new();
+
+        add_changes(&mut changes, event);
+
+        loop {
+            match self.events.recv_timeout(self.debounce) {
+                Ok(event) => add_changes(&mut changes, event),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return None,
+            }
+        }
+
+        Some(changes.into_iter().collect())
+    }
+}
+
+fn add_changes(changes: &mut BTreeSet<PathBuf>, event: DebouncedEvent) {
+    match event {
+        DebouncedEvent::Create(path)
+        | DebouncedEvent::Write(path)
+        | DebouncedEvent::Chmod(path)
+        | DebouncedEvent::Remove(path) => {
+            changes.insert(path);
+        }
+        DebouncedEvent::Rename(from, to) => {
+            changes.insert(from);
+            changes.insert(to);
+        }
+        DebouncedEvent::Error(error, path) => {
+            log::error!("watching {:?} failed: {}", path, error);
+        }
+        DebouncedEvent::NoticeWrite(_) | DebouncedEvent::NoticeRemove(_) => {}
+        DebouncedEvent::Rescan => {}
+    }
+}
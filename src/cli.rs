@@ -1,6 +1,16 @@
 use crate::VERSION;
 
-use ajour_core::{config::Flavor, repository::CompressionFormat};
+use ajour_core::{
+    config::Flavor,
+    filter::{
+        parse_difficulty_bounds, parse_length_bounds, parse_rating_bounds, Bounds, SongFilter,
+    },
+    query::SongQuery,
+    report::ReportFormat,
+    repository::CompressionFormat,
+    restore::ConflictPolicy,
+    song::Instrument,
+};
 
 use isahc::http::Uri;
 use structopt::{
@@ -82,8 +92,12 @@ pub struct Opts {
 
 #[derive(Debug, StructOpt)]
 pub enum Command {
-    /// Update all addons 
-    Update,
+    /// Update all addons, and the songs and packs of song libraries with automatic updates enabled
+    Update {
+        #[structopt(long)]
+        /// print the planned song and pack updates as JSON without installing anything
+        dry_run: bool,
+    },
     /// Update all addons
     UpdateAddons,
     /// Install an addon
@@ -95,17 +109,89 @@ pub enum Command {
         /// source url [Github & Gitlab currently supported]
         url: Uri,
     },
-    /// Backup your WTF and/or AddOns folders
+    /// Backup your WTF and/or AddOns folders. Archives are also uploaded to the S3 destination set in CHmon, with the credentials in AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY.
     Backup {
-        #[structopt(short, long, default_value = "all", parse(try_from_str = str_to_backup_folder), possible_values = &["all","wtf","addons","config", "screenshots", "fonts"])]
-        /// folder to backup
+        #[structopt(short, long, default_value = "all", parse(try_from_str = str_to_backup_folder), possible_values = &["all","wtf","addons","config", "screenshots", "fonts", "songs"])]
+        /// folder to backup. "songs" backs up the song libraries incrementally, only copying new and changed files
         backup_folder: BackupFolder,
         #[structopt(short, long, parse(try_from_str = str_to_flavor), possible_values = &["retail","ptr","beta","classic_tbc","classic_era","classic_era_ptr","classic_ptr","classic_beta"])]
         /// space separated list of flavors to include in backup. If ommited, all flavors will be included.
         flavors: Vec<Flavor>,
         #[structopt()]
-        /// folder to save backups to
-        destination: PathBuf,
+        /// folder to save backups to. If ommited, the backup directory set in CHmon is used.
+        destination: Option<PathBuf>,
+        #[structopt(short, long, default_value = "zip", possible_values = &["zip", "zstd"])]
+        compression_format: CompressionFormat,
+        #[structopt(short, long, default_value = "0")]
+        level: i32,
+        #[structopt(long)]
+        /// only back up if the backup interval set in CHmon passed since the latest archive, or the latest snapshot with `songs`, for running from cron or a systemd timer
+        if_due: bool,
+        #[structopt(long)]
+        /// encrypt the archive with the passphrase in the CHMON_BACKUP_PASSPHRASE environment variable. Always done when encryption is turned on in CHmon.
+        encrypt: bool,
+        #[structopt(subcommand)]
+        action: Option<BackupAction>,
+    },
+    /// List backups, browse the contents of a backup or restore it. Encrypted backups are decrypted with the passphrase in the CHMON_BACKUP_PASSPHRASE environment variable.
+    Restore {
+        #[structopt()]
+        /// folder the backups were saved to
+        backup_directory: PathBuf,
+        #[structopt()]
+        /// backup to browse or restore, as listed when ommited
+        backup: Option<String>,
+        #[structopt(long)]
+        /// list the songs, packs and folders in the backup instead of restoring it
+        browse: bool,
+        #[structopt(long)]
+        /// folder in the backup to restore, as listed by --browse. Can be repeated. If ommited, everything is restored.
+        only: Vec<String>,
+        #[structopt(long = "config")]
+        /// restore the data directory
+        config_only: bool,
+        #[structopt(long)]
+        /// folder to restore to. If ommited, files are restored to where they were backed up from.
+        to: Option<PathBuf>,
+        #[structopt(long, default_value = "skip", possible_values = &["skip", "overwrite", "rename"])]
+        /// what to do with files which already exist
+        conflict: ConflictPolicy,
+        #[structopt(long)]
+        /// print the files which would be restored without restoring them
+        dry_run: bool,
+    },
+    /// Remove old backups according to the retention rules
+    PruneBackups {
+        #[structopt()]
+        /// folder the backups were saved to
+        backup_directory: PathBuf,
+        #[structopt(long)]
+        /// keep the latest N backups. If no rule is given, the rules set in CHmon are used.
+        keep_last: Option<usize>,
+        #[structopt(long)]
+        /// keep the latest backup of each of the last N days
+        keep_daily: Option<u32>,
+        #[structopt(long)]
+        /// keep the latest backup of each of the last N weeks
+        keep_weekly: Option<u32>,
+        #[structopt(long)]
+        /// remove the oldest archives until they are at most this many MB together. Song snapshots only follow the other rules.
+        max_size: Option<u64>,
+        #[structopt(long)]
+        /// print the backups which would be removed without removing them
+        dry_run: bool,
+    },
+    /// Export an incremental song library backup as a regular archive
+    ExportSnapshot {
+        #[structopt()]
+        /// folder the song libraries were backed up to
+        backup_directory: PathBuf,
+        #[structopt(short, long)]
+        /// id of the snapshot to export. If ommited, the latest snapshot is exported.
+        snapshot: Option<String>,
+        #[structopt()]
+        /// archive to write
+        output: PathBuf,
         #[structopt(short, long, default_value = "zip", possible_values = &["zip", "zstd"])]
         compression_format: CompressionFormat,
         #[structopt(short, long, default_value = "0")]
@@ -119,6 +205,144 @@ pub enum Command {
         /// flavor to use from the path. If none, we use all we find
         flavor: Option<Flavor>,
     },
+    /// Move songs to another song library
+    Move {
+        #[structopt(long)]
+        /// song library to move songs from
+        from: PathBuf,
+        #[structopt(long)]
+        /// song library to move songs to
+        to: PathBuf,
+        #[structopt()]
+        /// space separated list of song folders, relative to the library. If ommited, all songs are moved.
+        songs: Vec<String>,
+        #[structopt(long)]
+        /// add the destination to your song libraries
+        add_library: bool,
+    },
+    /// Copy songs to another song library
+    Copy {
+        #[structopt(long)]
+        /// song library to copy songs from
+        from: PathBuf,
+        #[structopt(long)]
+        /// song library to copy songs to
+        to: PathBuf,
+        #[structopt()]
+        /// space separated list of song folders, relative to the library. If ommited, all songs are copied.
+        songs: Vec<String>,
+        #[structopt(long)]
+        /// add the destination to your song libraries
+        add_library: bool,
+    },
+    /// Export a list of your songs as CSV, JSON or a sortable HTML page
+    Export {
+        #[structopt(short, long, default_value = "html", possible_values = &["csv", "json", "html"])]
+        format: ReportFormat,
+        #[structopt(short, long)]
+        /// song library to export. Can be repeated. If ommited, all song libraries are exported.
+        directory: Vec<PathBuf>,
+        #[structopt(short, long, parse(try_from_str = SongQuery::parse))]
+        /// only export songs matching this query, with the same syntax as `search`
+        query: Option<SongQuery>,
+        #[structopt()]
+        /// file to write the list to
+        output: PathBuf,
+    },
+    /// List the songs in your song libraries
+    List {
+        #[structopt(flatten)]
+        filter: SongFilterOpts,
+    },
+    /// Search the songs in your song libraries with a query like 'artist:metallica charter:"harmonix" drums>=5 length<4m year:1990..1999 -genre:pop'. Free text has to be contained in the artist, title, album or charter.
+    Search {
+        #[structopt(parse(try_from_str = SongQuery::parse))]
+        /// query to search with, quoted if it has spaces. Fields are artist, title, album, genre, charter, year, length, difficulty, instrument and the instruments themselves, like drums.
+        query: SongQuery,
+        #[structopt(flatten)]
+        filter: SongFilterOpts,
+    },
+    /// Print statistics about your song libraries, like playtime, genres, charters and difficulties
+    Stats {
+        #[structopt(short, long)]
+        /// song library to count. Can be repeated. If ommited, all song libraries are counted.
+        directory: Vec<PathBuf>,
+        #[structopt(long)]
+        /// print the statistics as JSON
+        json: bool,
+    },
+    /// Watch the inbox folder and import every archive and song folder dropped into it. Imported items are moved to processed/, items which couldn't be imported to failed/.
+    Watch {
+        #[structopt(long)]
+        /// folder to watch. If ommited, the inbox folder set in CHmon is used.
+        inbox: Option<PathBuf>,
+        #[structopt(long)]
+        /// song library to import into. If ommited, the library set in CHmon is used.
+        library: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+pub struct SongFilterOpts {
+    #[structopt(short, long)]
+    /// song library to list. Can be repeated. If ommited, all song libraries are listed.
+    pub directory: Vec<PathBuf>,
+    #[structopt(long)]
+    /// only list songs whose artist contains this text
+    pub artist: Option<String>,
+    #[structopt(long)]
+    /// only list songs whose charter contains this text
+    pub charter: Option<String>,
+    #[structopt(long, possible_values = &["guitar", "bass", "rhythm", "guitar_coop", "ghl_guitar", "ghl_bass", "drums", "keys", "band", "vocals"])]
+    /// only list songs charted for this instrument
+    pub instrument: Option<Instrument>,
+    #[structopt(long, parse(try_from_str = parse_difficulty_bounds))]
+    /// only list songs with a difficulty in this range, like 3..5, 4.. or ..2. Applies to --instrument if given, otherwise to any charted instrument.
+    pub difficulty: Option<Bounds<i32>>,
+    #[structopt(long, parse(try_from_str = parse_length_bounds))]
+    /// only list songs with a length in this range, like 2m..4m30s, ..3:00 or 180.. in seconds
+    pub length: Option<Bounds<u64>>,
+    #[structopt(long)]
+    /// only list songs with this tag
+    pub tag: Option<String>,
+    #[structopt(long)]
+    /// only list favorite songs
+    pub favorite: bool,
+    #[structopt(long, parse(try_from_str = parse_rating_bounds))]
+    /// only list songs with a rating in this range, like 4.., 2..3 or 5
+    pub rating: Option<Bounds<u8>>,
+    #[structopt(long)]
+    /// print the songs as JSON instead of a table
+    pub json: bool,
+}
+
+impl SongFilterOpts {
+    /// Returns the filter for these options.
+    pub fn to_filter(&self) -> SongFilter {
+        SongFilter {
+            text: None,
+            artist: self.artist.clone(),
+            charter: self.charter.clone(),
+            instrument: self.instrument,
+            difficulty: self.difficulty.unwrap_or_default(),
+            length: self.length.unwrap_or_default(),
+            tag: self.tag.clone(),
+            favorite: self.favorite,
+            rating: self.rating.unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub enum BackupAction {
+    /// Verify backups against the manifest in the archive, exiting with an error if any is corrupt. Encrypted backups are decrypted with the passphrase in the CHMON_BACKUP_PASSPHRASE environment variable.
+    Verify {
+        #[structopt()]
+        /// archive to verify. If ommited, every backup in the backup directory set in CHmon is verified.
+        archive: Option<PathBuf>,
+    },
+    /// List the backups uploaded to the S3 destination set in CHmon. Credentials are read from AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY.
+    ListRemote,
 }
 
 fn str_to_flavor(s: &str) -> Result<Flavor, &'static str> {
@@ -143,6 +367,7 @@ pub enum BackupFolder {
     Config,
     Screenshots,
     Fonts,
+    Songs,
 }
 
 fn str_to_backup_folder(s: &str) -> Result<BackupFolder, &'static str> {
@@ -153,6 +378,7 @@ fn str_to_backup_folder(s: &str) -> Result<BackupFolder, &'static str> {
         "config" => Ok(BackupFolder::Config),
         "screenshots" => Ok(BackupFolder::Screenshots),
         "fonts" => Ok(BackupFolder::Fonts),
-        _ => Err("valid values are ['all','wtf','addons','config','screenshots','fonts']"),
+        "songs" => Ok(BackupFolder::Songs),
+        _ => Err("valid values are ['all','wtf','addons','config','screenshots','fonts','songs']"),
     }
 }
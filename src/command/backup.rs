@@ -1,25 +1,88 @@
 use crate::cli::BackupFolder;
 use crate::Result;
 
-use ajour_core::backup::{self, backup_folders};
+use ajour_core::backup::{
+    self, backup_folders, latest_backups, list_backups, preview_prune, verify_backup,
+    BackupRetention,
+};
 use ajour_core::config::{load_config, Flavor};
+use ajour_core::fs::encryption::is_encrypted;
 use ajour_core::repository::CompressionFormat;
+use ajour_core::s3::{list_remote_backups, upload_backup};
+use ajour_core::snapshot::{backup_songs, export_snapshot};
+use ajour_core::userdata::load_user_data;
 use anyhow::format_err;
+use chrono::Local;
 
 use async_std::task;
 use std::fs::create_dir;
 use std::path::PathBuf;
 
+/// Environment variable holding the passphrase for encrypted backups.
+const PASSPHRASE_VAR: &str = "CHMON_BACKUP_PASSPHRASE";
+
+/// Returns the passphrase for encrypted backups, if one is set.
+pub(crate) fn backup_passphrase() -> Option<String> {
+    std::env::var(PASSPHRASE_VAR)
+        .ok()
+        .filter(|passphrase| !passphrase.is_empty())
+}
+
+/// Returns the passphrase for encrypted backups, failing if none is set.
+pub(crate) fn require_backup_passphrase() -> Result<String> {
+    backup_passphrase().ok_or_else(|| {
+        format_err!(
+            "Backups are encrypted, set {} to the passphrase.",
+            PASSPHRASE_VAR
+        )
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn backup(
     backup_folder: BackupFolder,
-    destination: PathBuf,
+    destination: Option<PathBuf>,
     flavors: Vec<Flavor>,
     compression_format: CompressionFormat,
     zstd_level: i32,
+    if_due: bool,
+    encrypt: bool,
 ) -> Result<()> {
     task::block_on(async {
         let config = load_config().await?;
 
+        let destination = destination
+            .or_else(|| config.backup_directory.clone())
+            .ok_or_else(|| {
+                format_err!("No destination given and no backup directory set in CHmon.")
+            })?;
+
+        if if_due {
+            let schedule = config.backup_schedule;
+
+            if schedule.interval_days.is_none() {
+                return Err(format_err!("No backup interval set. Launch CHmon and choose how often to back up before using --if-due."));
+            }
+
+            // Song snapshots and archives are due on their own.
+            let latest = latest_backups(destination.clone(), config.backup_s3.clone()).await;
+            let last_backup = match backup_folder {
+                BackupFolder::Songs => latest.snapshot,
+                _ => latest.archive,
+            };
+
+            if !schedule.is_due(last_backup, Local::now().naive_local()) {
+                log::info!(
+                    "No backup due, the latest backup is from {}",
+                    last_backup
+                        .map(|as_of| as_of.format("%Y-%m-%d %H:%M:%S").to_string())
+                        .unwrap_or_default()
+                );
+
+                return Ok(());
+            }
+        }
+
         let flavors = if flavors.is_empty() {
             Flavor::ALL.to_vec()
         } else {
@@ -34,6 +97,39 @@ pub fn backup(
             return Err(format_err!("destination must be a folder, not a file"));
         }
 
+        if let BackupFolder::Songs = backup_folder {
+            if config.songs.directories.is_empty() {
+                return Err(format_err!("No song directories set. Launch CHmon and add a song directory before using the command line."));
+            }
+
+            log::info!(
+                "Backing up song libraries {:?} to {:?}",
+                &config.songs.directories,
+                &destination
+            );
+
+            let user_data = load_user_data().await?;
+            let summary =
+                backup_songs(destination, config.songs.directories.clone(), user_data).await?;
+
+            log::info!(
+                "Snapshot {} complete! {} songs changed, {} of {} files ({} MB) copied",
+                &summary.id,
+                summary.songs_changed,
+                summary.copied,
+                summary.files,
+                summary.copied_size / 1_000_000
+            );
+
+            return Ok(());
+        }
+
+        let passphrase = if encrypt || config.backup_encrypt {
+            Some(require_backup_passphrase()?)
+        } else {
+            None
+        };
+
         if config.wow.directories.keys().next().is_none() {
             return Err(format_err!("No WoW directories set. Launch Ajour and make sure a WoW directory is set before using the command line."));
         }
@@ -109,10 +205,206 @@ pub fn backup(
             _ => {}
         }
 
-        backup_folders(src_folders, destination, compression_format, zstd_level).await?;
+        let as_of = backup_folders(
+            src_folders,
+            destination.clone(),
+            compression_format,
+            zstd_level,
+            passphrase,
+        )
+        .await?;
 
         log::info!("Backup complete!");
 
+        if config.backup_s3.is_configured() {
+            let archive = list_backups(&destination)
+                .into_iter()
+                .find(|archive| archive.as_of == as_of)
+                .ok_or_else(|| format_err!("backup archive from {} not found", as_of))?;
+
+            log::info!("Uploading {:?} to S3", &archive.path);
+
+            let remote = upload_backup(config.backup_s3.clone(), archive.path).await?;
+
+            log::info!(
+                "Uploaded to {}/{}",
+                config.backup_s3.bucket.trim(),
+                remote.key
+            );
+        }
+
+        let pruned = backup::prune_backups(destination, config.backup_retention).await?;
+
+        for archive in pruned.archives.iter() {
+            log::info!("Removed backup {:?}", &archive.path);
+        }
+        for snapshot in pruned.snapshots.iter() {
+            log::info!("Removed song snapshot {}", &snapshot.id);
+        }
+
+        Ok(())
+    })
+}
+
+/// Exports an incremental song library backup as a regular archive.
+pub fn snapshot_export(
+    backup_directory: PathBuf,
+    snapshot: Option<String>,
+    output: PathBuf,
+    compression_format: CompressionFormat,
+    zstd_level: i32,
+) -> Result<()> {
+    task::block_on(async {
+        let id = export_snapshot(
+            backup_directory,
+            snapshot,
+            output.clone(),
+            compression_format,
+            zstd_level,
+        )
+        .await?;
+
+        log::info!("Exported snapshot {} to {:?}", id, &output);
+
+        Ok(())
+    })
+}
+
+/// Removes the backups `retention` doesn't keep. The retention rules set in
+/// the config are used if `retention` has none.
+pub fn prune_backups(
+    backup_directory: PathBuf,
+    retention: BackupRetention,
+    dry_run: bool,
+) -> Result<()> {
+    task::block_on(async {
+        let retention = if retention.is_enabled() {
+            retention
+        } else {
+            load_config().await?.backup_retention
+        };
+
+        if !retention.is_enabled() {
+            return Err(format_err!(
+                "no retention rules given. Pass at least one rule or set them in CHmon."
+            ));
+        }
+
+        let pruned = if dry_run {
+            preview_prune(backup_directory, retention).await?
+        } else {
+            backup::prune_backups(backup_directory, retention).await?
+        };
+
+        for archive in pruned.archives.iter() {
+            if dry_run {
+                println!("{}\t{}", archive.path.display(), archive);
+            } else {
+                log::info!("Removed backup {:?}", &archive.path);
+            }
+        }
+        for snapshot in pruned.snapshots.iter() {
+            if dry_run {
+                println!("{}\t{}", snapshot.id, snapshot);
+            } else {
+                log::info!("Removed song snapshot {}", &snapshot.id);
+            }
+        }
+
+        log::info!(
+            "{} {} backups ({} MB)",
+            if dry_run { "Would remove" } else { "Removed" },
+            pruned.len(),
+            pruned.size() / 1_000_000
+        );
+
+        Ok(())
+    })
+}
+
+/// Lists the backups uploaded to the S3 destination set in the config.
+pub fn list_remote() -> Result<()> {
+    task::block_on(async {
+        let config = load_config().await?;
+
+        if !config.backup_s3.is_configured() {
+            return Err(format_err!(
+                "No S3 destination set. Launch CHmon and set an endpoint and bucket under Backup."
+            ));
+        }
+
+        let backups = list_remote_backups(config.backup_s3).await?;
+
+        if backups.is_empty() {
+            log::info!("No remote backups found");
+        }
+
+        for backup in backups.iter() {
+            println!(
+                "{}\t{} ({})\t{:.1} MB",
+                backup.key,
+                backup.as_of.format("%Y-%m-%d %H:%M:%S"),
+                backup.format,
+                backup.size as f64 / 1_000_000.0
+            );
+        }
+
+        Ok(())
+    })
+}
+
+/// Verifies backup archives, failing if any is corrupt. Without `archive`
+/// every backup in the backup directory is verified.
+pub fn verify_backups(archive: Option<PathBuf>) -> Result<()> {
+    task::block_on(async {
+        let archives = match archive {
+            Some(archive) => vec![archive],
+            None => {
+                let config = load_config().await?;
+                let backup_directory = config.backup_directory.ok_or_else(|| {
+                    format_err!("No backup directory set. Launch CHmon and choose a backup directory or pass an archive.")
+                })?;
+
+                list_backups(&backup_directory)
+                    .into_iter()
+                    .map(|archive| archive.path)
+                    .collect()
+            }
+        };
+
+        let mut corrupt = 0;
+
+        for archive in archives {
+            let passphrase = if is_encrypted(&archive) {
+                Some(require_backup_passphrase()?)
+            } else {
+                None
+            };
+
+            let verification = verify_backup(archive.clone(), passphrase).await?;
+
+            if verification.is_ok() && verification.has_manifest {
+                log::info!("{:?}: OK, {} files", &archive, verification.files);
+            } else if verification.is_ok() {
+                log::info!(
+                    "{:?}: readable, {} files. Made before backups had a manifest, so checksums weren't checked",
+                    &archive,
+                    verification.files
+                );
+            } else {
+                corrupt += 1;
+
+                log::error!("{:?}: corrupt", &archive);
+                for issue in verification.issues.iter() {
+                    log::error!("\t{}", issue);
+                }
+            }
+        }
+
+        if corrupt > 0 {
+            return Err(format_err!("{} corrupt backups found", corrupt));
+        }
+
         Ok(())
     })
 }
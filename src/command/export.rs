@@ -0,0 +1,32 @@
+use crate::Result;
+
+use super::list::read_songs;
+
+use ajour_core::query::SongQuery;
+use ajour_core::report::{export_report, ReportFormat};
+
+use async_std::task;
+use std::path::PathBuf;
+
+/// Exports a report of the songs in `directories`, or in every song library
+/// if none are given. With `query`, only songs matching it are exported.
+pub fn export_songs(
+    format: ReportFormat,
+    directories: Vec<PathBuf>,
+    query: Option<SongQuery>,
+    output: PathBuf,
+) -> Result<()> {
+    task::block_on(async {
+        let mut songs = read_songs(directories).await?;
+
+        if let Some(query) = query {
+            songs.retain(|song| query.matches(song));
+        }
+
+        export_report(&songs, format, &output)?;
+
+        log::info!("Exported {} songs to {:?}", songs.len(), &output);
+
+        Ok(())
+    })
+}
@@ -0,0 +1,77 @@
+use crate::Result;
+
+use ajour_core::cache::load_song_cache;
+use ajour_core::config::load_config;
+use ajour_core::filter::SongFilter;
+use ajour_core::library::read_song_directory;
+use ajour_core::query::SongQuery;
+use ajour_core::report::{render_table, ReportRow};
+use ajour_core::song::Song;
+use ajour_core::userdata::load_user_data;
+use anyhow::format_err;
+
+use async_std::sync::{Arc, Mutex};
+use async_std::task;
+use std::path::PathBuf;
+
+/// Prints the songs in `directories`, or in every song library if none are
+/// given, which match `filter` and `query`. Printed as a table, or as JSON
+/// with `json`.
+pub fn list_songs(
+    filter: SongFilter,
+    query: SongQuery,
+    directories: Vec<PathBuf>,
+    json: bool,
+) -> Result<()> {
+    task::block_on(async {
+        let mut songs = read_songs(directories).await?;
+
+        songs.retain(|song| filter.matches(song) && query.matches(song));
+
+        let rows = songs.iter().map(ReportRow::from).collect::<Vec<_>>();
+
+        if json {
+            println!("{}", serde_json::to_string_pretty(&rows)?);
+        } else if rows.is_empty() {
+            log::info!("No songs found");
+        } else {
+            print!("{}", render_table(&rows));
+        }
+
+        Ok(())
+    })
+}
+
+/// Reads the songs in `directories`, or in every song library if none are
+/// given, with their user data and sorted by artist and title.
+pub(crate) async fn read_songs(directories: Vec<PathBuf>) -> Result<Vec<Song>> {
+    let config = load_config().await?;
+
+    let roots = if directories.is_empty() {
+        config.songs.directories.clone()
+    } else {
+        directories
+    };
+
+    if roots.is_empty() {
+        return Err(format_err!("no song libraries configured"));
+    }
+
+    let song_cache = Arc::new(Mutex::new(load_song_cache().await?));
+
+    let mut songs = vec![];
+    for root in roots.iter() {
+        songs.extend(read_song_directory(Some(song_cache.clone()), root).await?);
+    }
+
+    load_user_data().await?.apply(&mut songs);
+
+    songs.sort_by_cached_key(|song| {
+        (
+            song.artist().unwrap_or_default().to_lowercase(),
+            song.title().to_lowercase(),
+        )
+    });
+
+    Ok(songs)
+}
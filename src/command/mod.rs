@@ -1,7 +1,13 @@
 use super::Result;
 
 mod backup;
-pub use backup::backup;
+pub use backup::{backup, list_remote, prune_backups, snapshot_export, verify_backups};
+
+mod export;
+pub use export::export_songs;
+
+mod list;
+pub use list::list_songs;
 
 mod install;
 pub use install::install_from_source;
@@ -12,8 +18,28 @@ pub use update_addons::update_all_addons;
 mod paths;
 pub use paths::path_add;
 
-pub fn update_both() -> Result<()> {
+mod restore;
+pub use restore::restore;
+
+mod stats;
+pub use stats::print_stats;
+
+mod transfer;
+pub use transfer::transfer_songs;
+
+mod update_songs;
+pub use update_songs::update_all_songs;
+
+mod watch;
+pub use watch::watch_inbox;
+
+pub fn update_both(dry_run: bool) -> Result<()> {
+    if dry_run {
+        return update_all_songs(true);
+    }
+
     update_all_addons()?;
+    update_all_songs(false)?;
 
     Ok(())
 }
@@ -0,0 +1,139 @@
+use super::backup::require_backup_passphrase;
+use crate::Result;
+
+use ajour_core::config::load_config;
+use ajour_core::fs::PersistentData;
+use ajour_core::restore::{
+    backup_entries, config_selection, entry_groups, list_backup_sources, plan_restore,
+    restore_origins, BackupSource, ConflictPolicy, RestoreTarget,
+};
+use ajour_core::userdata::load_user_data;
+use anyhow::format_err;
+
+use async_std::task;
+use std::path::PathBuf;
+
+/// Lists the backups in `backup_directory`, browses the contents of one or
+/// restores it.
+///
+/// Without `backup` the backups are listed. With `browse` the folders in the
+/// backup are listed. Otherwise the backup is restored, limited to the
+/// `only` folders and the data directory if any are given.
+#[allow(clippy::too_many_arguments)]
+pub fn restore(
+    backup_directory: PathBuf,
+    backup: Option<String>,
+    browse: bool,
+    only: Vec<String>,
+    config_only: bool,
+    to: Option<PathBuf>,
+    conflict: ConflictPolicy,
+    dry_run: bool,
+) -> Result<()> {
+    task::block_on(async {
+        let sources = list_backup_sources(backup_directory.clone()).await?;
+
+        let backup = match backup {
+            Some(backup) => backup,
+            None => {
+                if sources.is_empty() {
+                    log::info!("No backups found in {:?}", &backup_directory);
+                }
+
+                for source in sources.iter() {
+                    println!("{}\t{}", source.name(), source);
+                }
+
+                return Ok(());
+            }
+        };
+
+        let source = sources
+            .into_iter()
+            .find(|source| source.name() == backup)
+            .ok_or_else(|| {
+                format_err!("no backup {:?} found in {:?}", backup, &backup_directory)
+            })?;
+
+        let passphrase = match &source {
+            BackupSource::Archive(archive) if archive.is_encrypted() => {
+                Some(require_backup_passphrase()?)
+            }
+            _ => None,
+        };
+
+        if browse {
+            let entries = backup_entries(source, passphrase).await?;
+
+            for group in entry_groups(&entries) {
+                println!(
+                    "{}\t{} files\t{:.1} MB",
+                    group.path,
+                    group.files,
+                    group.size as f64 / 1_000_000.0
+                );
+            }
+
+            return Ok(());
+        }
+
+        let mut selection = only;
+        if config_only {
+            selection.push(config_selection());
+        }
+
+        let target = match to {
+            Some(dir) => RestoreTarget::Directory(dir),
+            None => {
+                let config = load_config().await?;
+                RestoreTarget::Original(restore_origins(&config))
+            }
+        };
+
+        let plan = plan_restore(source, selection, target, conflict, passphrase.clone()).await?;
+
+        if plan.files.is_empty() {
+            return Err(format_err!("nothing selected to restore"));
+        }
+
+        log::info!(
+            "Restoring {} files ({} MB) from {}, {} already exist",
+            plan.files.len(),
+            plan.size() / 1_000_000,
+            &plan.source,
+            plan.conflicts()
+        );
+
+        if dry_run {
+            for file in plan.files.iter() {
+                println!(
+                    "{}{}",
+                    file.destination.display(),
+                    if file.exists { "\t(exists)" } else { "" }
+                );
+            }
+
+            return Ok(());
+        }
+
+        let summary = ajour_core::restore::restore(plan, passphrase).await?;
+
+        log::info!(
+            "Restore complete! {} files restored, {} skipped, {} renamed",
+            summary.restored,
+            summary.skipped,
+            summary.renamed
+        );
+
+        // Songs keep the user data they have now.
+        if !summary.user_data.is_empty() {
+            let mut user_data = load_user_data().await?;
+            let added = user_data.merge(summary.user_data);
+            user_data.save()?;
+
+            log::info!("Restored the tags and ratings of {} songs", added);
+        }
+
+        Ok(())
+    })
+}
@@ -0,0 +1,79 @@
+use crate::Result;
+
+use super::list::read_songs;
+
+use ajour_core::stats::{by_count, format_playtime, library_stats, LibraryStats};
+
+use async_std::task;
+use std::fmt::Display;
+use std::path::PathBuf;
+
+/// Prints the statistics of the songs in `directories`, or of every song
+/// library if none are given. Printed as text, or as JSON with `json`.
+pub fn print_stats(directories: Vec<PathBuf>, json: bool) -> Result<()> {
+    task::block_on(async {
+        let songs = read_songs(directories).await?;
+
+        let stats = library_stats(songs).await;
+
+        if json {
+            println!("{}", serde_json::to_string_pretty(&stats)?);
+        } else {
+            print!("{}", render_stats(&stats));
+        }
+
+        Ok(())
+    })
+}
+
+fn render_stats(stats: &LibraryStats) -> String {
+    let mut text = format!(
+        "Songs:       {}\nPlaytime:    {}\nDisk usage:  {:.1} MB\n",
+        stats.songs,
+        format_playtime(stats.playtime),
+        stats.disk_usage as f64 / 1_000_000.0
+    );
+
+    render_counts(&mut text, "Instruments", by_count(&stats.instruments));
+    // Decades read best in order, rather than by count.
+    let decades = stats
+        .decades
+        .iter()
+        .map(|(decade, count)| (format!("{}s", decade), *count))
+        .collect();
+    render_counts(&mut text, "Decades", decades);
+    render_counts(&mut text, "Genres", by_count(&stats.genres));
+    render_counts(&mut text, "Charters", by_count(&stats.charters));
+
+    for (instrument, histogram) in stats.difficulties.iter() {
+        render_counts(
+            &mut text,
+            &format!("{} difficulty", instrument),
+            histogram.iter().map(|(d, count)| (d, *count)).collect(),
+        );
+    }
+
+    text
+}
+
+fn render_counts<K: Display>(text: &mut String, title: &str, counts: Vec<(K, usize)>) {
+    if counts.is_empty() {
+        return;
+    }
+
+    let names = counts
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .collect::<Vec<_>>();
+    let width = names
+        .iter()
+        .map(|n| n.chars().count())
+        .max()
+        .unwrap_or_default();
+
+    text.push_str(&format!("\n{}\n", title));
+
+    for (name, (_, count)) in names.iter().zip(counts.iter()) {
+        text.push_str(&format!("  {:width$}  {}\n", name, count, width = width));
+    }
+}
@@ -0,0 +1,111 @@
+use crate::Result;
+
+use ajour_core::cache::load_song_cache;
+use ajour_core::config::load_config;
+use ajour_core::fs::{check_available_space, PersistentData};
+use ajour_core::library::read_song_directory;
+use ajour_core::transfer::{self, TransferMode};
+use anyhow::format_err;
+
+use async_std::sync::{Arc, Mutex};
+use async_std::task;
+use std::fs::create_dir_all;
+use std::path::PathBuf;
+
+pub fn transfer_songs(
+    from: PathBuf,
+    to: PathBuf,
+    song_ids: Vec<String>,
+    mode: TransferMode,
+    add_library: bool,
+) -> Result<()> {
+    task::block_on(async {
+        let mut config = load_config().await?;
+
+        if from == to {
+            return Err(format_err!(
+                "source and destination must be different libraries"
+            ));
+        }
+
+        if !to.exists() {
+            create_dir_all(&to)?;
+        }
+
+        if !to.is_dir() {
+            return Err(format_err!("destination must be a folder, not a file"));
+        }
+
+        let song_cache = Arc::new(Mutex::new(load_song_cache().await?));
+
+        let mut songs = read_song_directory(Some(song_cache.clone()), &from).await?;
+
+        if !song_ids.is_empty() {
+            let ids: Vec<String> = song_ids
+                .iter()
+                .map(|id| id.replace('\\', "/").trim_matches('/').to_owned())
+                .collect();
+
+            if let Some(missing) = ids.iter().find(|id| !songs.iter().any(|s| s.id == **id)) {
+                return Err(format_err!("no song {:?} found in {:?}", missing, &from));
+            }
+
+            songs.retain(|song| ids.contains(&song.id));
+        }
+
+        if songs.is_empty() {
+            log::info!(
+                "No songs to {} in {:?}",
+                mode.to_string().to_lowercase(),
+                &from
+            );
+            return Ok(());
+        }
+
+        let required = check_available_space(
+            songs.iter().map(|song| song.path.clone()).collect(),
+            to.clone(),
+        )
+        .await?;
+
+        log::info!(
+            "{} {} songs ({} MB) from {:?} to {:?}",
+            mode,
+            songs.len(),
+            required / 1_000_000,
+            &from,
+            &to
+        );
+
+        let result = transfer::transfer_songs(
+            Some(song_cache),
+            songs,
+            to.clone(),
+            mode,
+            |idx, total, song| log::info!("[{}/{}] {}", idx + 1, total, &song.id),
+        )
+        .await?;
+
+        for (song, error) in result.failed.iter() {
+            log::error!("{}: {}", &song.id, error);
+        }
+
+        // Make the destination show up in the GUI, if asked to.
+        if add_library {
+            config.songs.add_directory(to);
+            config.save()?;
+        }
+
+        log::info!(
+            "{} songs done, {} failed",
+            result.transferred.len(),
+            result.failed.len()
+        );
+
+        if result.failed.is_empty() {
+            Ok(())
+        } else {
+            Err(format_err!("{} songs failed", result.failed.len()))
+        }
+    })
+}
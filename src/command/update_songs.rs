@@ -0,0 +1,71 @@
+use crate::Result;
+
+use ajour_core::autoupdate::{apply_updates, plan_updates};
+use ajour_core::cache::load_song_cache;
+use ajour_core::config::load_config;
+use ajour_core::fs::PersistentData;
+use ajour_core::versions::load_version_store;
+use anyhow::format_err;
+
+use async_std::sync::{Arc, Mutex};
+use async_std::task;
+use chrono::Utc;
+
+/// Updates the songs and packs of every song library with automatic updates
+/// enabled. With `dry_run` the planned updates are printed as JSON instead.
+pub fn update_all_songs(dry_run: bool) -> Result<()> {
+    task::block_on(async {
+        let mut config = load_config().await?;
+
+        let roots = config.songs.auto_update.directories.clone();
+
+        if roots.is_empty() && !dry_run {
+            log::info!("No song libraries have automatic updates enabled");
+            return Ok(());
+        }
+
+        let song_cache = Arc::new(Mutex::new(load_song_cache().await?));
+
+        log::info!("Checking {} song libraries for updates...", roots.len());
+
+        let plan = plan_updates(Some(song_cache.clone()), roots).await?;
+
+        if dry_run {
+            println!("{}", serde_json::to_string_pretty(&plan)?);
+            return Ok(());
+        }
+
+        for update in plan.updates.iter() {
+            log::info!("Updating {} from {}", &update.name, &update.url);
+        }
+
+        let version_store = load_version_store(config.songs.versions_max_size()).await?;
+
+        let summary = apply_updates(
+            Some(song_cache),
+            Some(Arc::new(Mutex::new(version_store))),
+            plan,
+        )
+        .await;
+
+        config.songs.auto_update.last_run = Some(Utc::now());
+        config.save()?;
+
+        for (name, error) in summary.failed.iter() {
+            log::error!("{}: {}", name, error);
+        }
+
+        log::info!(
+            "{} songs and {} packs updated, {} failed",
+            summary.songs.len(),
+            summary.packs.len(),
+            summary.failed.len()
+        );
+
+        if summary.failed.is_empty() {
+            Ok(())
+        } else {
+            Err(format_err!("{} updates failed", summary.failed.len()))
+        }
+    })
+}
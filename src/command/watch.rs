@@ -0,0 +1,74 @@
+use crate::Result;
+
+use ajour_core::cache::{load_song_cache, SongCache};
+use ajour_core::config::load_config;
+use ajour_core::inbox::{changed_inbox_items, import_inbox_item, inbox_items, INBOX_DEBOUNCE};
+use ajour_core::watch::FolderWatcher;
+use anyhow::format_err;
+
+use async_std::sync::{Arc, Mutex};
+use async_std::task;
+use std::path::{Path, PathBuf};
+
+/// Imports everything dropped into the inbox into a song library, until
+/// interrupted. `inbox` and `library` default to the ones set in CHmon.
+pub fn watch_inbox(inbox: Option<PathBuf>, library: Option<PathBuf>) -> Result<()> {
+    task::block_on(async {
+        let config = load_config().await?;
+
+        let inbox = inbox
+            .or_else(|| config.songs.inbox.directory.clone())
+            .ok_or_else(|| format_err!("no inbox folder configured"))?;
+        let library = library
+            .or_else(|| config.songs.inbox_library())
+            .ok_or_else(|| format_err!("no song library configured"))?;
+
+        if !inbox.is_dir() {
+            return Err(format_err!("inbox folder {:?} doesn't exist", inbox));
+        }
+
+        let song_cache = Arc::new(Mutex::new(load_song_cache().await?));
+
+        log::info!("Importing songs from {:?} into {:?}", inbox, library);
+
+        // Items which were dropped in while nothing was watching.
+        import_items(&song_cache, &inbox, &library, inbox_items(&inbox)).await;
+
+        let watcher = FolderWatcher::new(&inbox, true, INBOX_DEBOUNCE)?;
+
+        while let Some(changes) = watcher.next_changes() {
+            let items = changed_inbox_items(&inbox, &changes);
+
+            import_items(&song_cache, &inbox, &library, items).await;
+        }
+
+        Ok(())
+    })
+}
+
+/// Imports the items one by one. Failures are logged and don't stop the
+/// watcher.
+async fn import_items(
+    song_cache: &Arc<Mutex<SongCache>>,
+    inbox: &Path,
+    library: &Path,
+    items: Vec<PathBuf>,
+) {
+    for item in items {
+        let result = import_inbox_item(
+            Some(song_cache.clone()),
+            inbox.to_owned(),
+            item.clone(),
+            library.to_owned(),
+        )
+        .await;
+
+        match result {
+            Ok(import) => match import.error {
+                Some(error) => log::error!("Failed to import {}: {}", import.name, error),
+                None => log::info!("Imported {} songs from {}", import.songs.len(), import.name),
+            },
+            Err(error) => log::error!("Failed to import {:?}: {}", item, error),
+        }
+    }
+}
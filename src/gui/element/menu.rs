@@ -27,6 +27,8 @@ pub fn data_container<'a>(
     about_button_state: &'a mut button::State,
     catalog_mode_btn_state: &'a mut button::State,
     install_mode_btn_state: &'a mut button::State,
+    my_songs_btn_state: &'a mut button::State,
+    statistics_btn_state: &'a mut button::State,
     flavor_picklist_state: &'a mut pick_list::State<Flavor>,
 ) -> Container<'a, Message> {
     let flavor = config.wow.flavor;
@@ -90,6 +92,22 @@ pub fn data_container<'a>(
         })
     };
 
+    let mut my_songs_mode_button = Button::new(
+        my_songs_btn_state,
+        Text::new(localized_string("my-songs"))
+            .horizontal_alignment(HorizontalAlignment::Center)
+            .size(DEFAULT_FONT_SIZE),
+    )
+    .on_press(Interaction::ModeSelected(Mode::MySongs));
+
+    let mut statistics_mode_button = Button::new(
+        statistics_btn_state,
+        Text::new(localized_string("statistics"))
+            .horizontal_alignment(HorizontalAlignment::Center)
+            .size(DEFAULT_FONT_SIZE),
+    )
+    .on_press(Interaction::ModeSelected(Mode::Statistics));
+
     let mut catalog_mode_button = Button::new(
         catalog_mode_btn_state,
         Text::new(localized_string("catalog")).size(DEFAULT_FONT_SIZE),
@@ -120,6 +138,9 @@ pub fn data_container<'a>(
 
     match mode {
         Mode::MyAddons(_) => {
+            my_songs_mode_button = my_songs_mode_button.style(style::DefaultButton(color_palette));
+            statistics_mode_button =
+                statistics_mode_button.style(style::DefaultButton(color_palette));
             catalog_mode_button = catalog_mode_button.style(style::DefaultButton(color_palette));
             install_mode_button = install_mode_button.style(style::DefaultButton(color_palette));
             about_mode_button = about_mode_button.style(style::DefaultButton(color_palette));
@@ -128,7 +149,33 @@ pub fn data_container<'a>(
             my_addons_table_row =
                 my_addons_table_row.style(style::SelectedSegmentTableRow(color_palette));
         }
+        Mode::MySongs => {
+            my_songs_mode_button =
+                my_songs_mode_button.style(style::SelectedDefaultButton(color_palette));
+            statistics_mode_button =
+                statistics_mode_button.style(style::DefaultButton(color_palette));
+            catalog_mode_button = catalog_mode_button.style(style::DefaultButton(color_palette));
+            install_mode_button = install_mode_button.style(style::DefaultButton(color_palette));
+            about_mode_button = about_mode_button.style(style::DefaultButton(color_palette));
+            settings_mode_button = settings_mode_button.style(style::DefaultButton(color_palette));
+
+            my_addons_table_row = my_addons_table_row.style(style::SegmentTableRow(color_palette));
+        }
+        Mode::Statistics => {
+            my_songs_mode_button = my_songs_mode_button.style(style::DefaultButton(color_palette));
+            statistics_mode_button =
+                statistics_mode_button.style(style::SelectedDefaultButton(color_palette));
+            catalog_mode_button = catalog_mode_button.style(style::DefaultButton(color_palette));
+            install_mode_button = install_mode_button.style(style::DefaultButton(color_palette));
+            about_mode_button = about_mode_button.style(style::DefaultButton(color_palette));
+            settings_mode_button = settings_mode_button.style(style::DefaultButton(color_palette));
+
+            my_addons_table_row = my_addons_table_row.style(style::SegmentTableRow(color_palette));
+        }
         Mode::Install => {
+            my_songs_mode_button = my_songs_mode_button.style(style::DefaultButton(color_palette));
+            statistics_mode_button =
+                statistics_mode_button.style(style::DefaultButton(color_palette));
             catalog_mode_button = catalog_mode_button.style(style::DefaultButton(color_palette));
             install_mode_button =
                 install_mode_button.style(style::SelectedDefaultButton(color_palette));
@@ -138,6 +185,9 @@ pub fn data_container<'a>(
             my_addons_table_row = my_addons_table_row.style(style::SegmentTableRow(color_palette));
         }
         Mode::Catalog => {
+            my_songs_mode_button = my_songs_mode_button.style(style::DefaultButton(color_palette));
+            statistics_mode_button =
+                statistics_mode_button.style(style::DefaultButton(color_palette));
             catalog_mode_button =
                 catalog_mode_button.style(style::SelectedDefaultButton(color_palette));
             install_mode_button = install_mode_button.style(style::DefaultButton(color_palette));
@@ -147,6 +197,9 @@ pub fn data_container<'a>(
             my_addons_table_row = my_addons_table_row.style(style::SegmentTableRow(color_palette));
         }
         Mode::Settings => {
+            my_songs_mode_button = my_songs_mode_button.style(style::DefaultButton(color_palette));
+            statistics_mode_button =
+                statistics_mode_button.style(style::DefaultButton(color_palette));
             catalog_mode_button = catalog_mode_button.style(style::DefaultButton(color_palette));
             install_mode_button = install_mode_button.style(style::DefaultButton(color_palette));
             about_mode_button = about_mode_button.style(style::DefaultButton(color_palette));
@@ -156,6 +209,9 @@ pub fn data_container<'a>(
             my_addons_table_row = my_addons_table_row.style(style::SegmentTableRow(color_palette));
         }
         Mode::About => {
+            my_songs_mode_button = my_songs_mode_button.style(style::DefaultButton(color_palette));
+            statistics_mode_button =
+                statistics_mode_button.style(style::DefaultButton(color_palette));
             catalog_mode_button = catalog_mode_button.style(style::DefaultButton(color_palette));
             install_mode_button = install_mode_button.style(style::DefaultButton(color_palette));
             about_mode_button =
@@ -178,6 +234,8 @@ pub fn data_container<'a>(
             install_mode_button.on_press(Interaction::ModeSelected(Mode::Install));
     }
 
+    let my_songs_mode_button: Element<Interaction> = my_songs_mode_button.into();
+    let statistics_mode_button: Element<Interaction> = statistics_mode_button.into();
     let catalog_mode_button: Element<Interaction> = catalog_mode_button.into();
     let install_mode_button: Element<Interaction> = install_mode_button.into();
     let settings_mode_button: Element<Interaction> = settings_mode_button.into();
@@ -188,7 +246,11 @@ pub fn data_container<'a>(
         .push(install_mode_button.map(Message::Interaction))
         .spacing(1);
 
-    let segmented_mode_row = Row::new().push(my_addons_table_row).spacing(1);
+    let segmented_mode_row = Row::new()
+        .push(my_addons_table_row)
+        .push(my_songs_mode_button.map(Message::Interaction))
+        .push(statistics_mode_button.map(Message::Interaction))
+        .spacing(1);
 
     let segmented_mode_container = Container::new(segmented_mode_row)
         .padding(2)
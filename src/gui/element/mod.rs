@@ -2,7 +2,9 @@ pub mod about;
 pub mod catalog;
 pub mod menu;
 pub mod my_addons;
+pub mod my_songs;
 pub mod settings;
+pub mod statistics;
 pub mod status;
 
 // Default values used on multiple elements.
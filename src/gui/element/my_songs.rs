@@ -0,0 +1,1104 @@
+use {
+    super::{DEFAULT_FONT_SIZE, DEFAULT_PADDING},
+    crate::gui::{
+        style, Confirm, Interaction, Message, Mode, SmartFiltersState, SongColumnKey,
+        SongColumnState, SongDirectory, SongRating, SongTransferState, SongUserDataState,
+        SongVersionState, SongsSearchState, SortDirection, State,
+    },
+    crate::localization::localized_string,
+    ajour_core::{
+        config::SmartFilter,
+        pack::Pack,
+        song::{PlayFilter, Song, SongState},
+        theme::ColorPalette,
+        transfer::TransferMode,
+    },
+    ajour_widgets::{header, Header, TableRow},
+    iced::{
+        button, Align, Button, Checkbox, Column, Container, Element, Length, PickList, Row, Space,
+        Text, TextInput,
+    },
+    std::collections::HashMap,
+    std::path::PathBuf,
+    strfmt::strfmt,
+};
+
+fn row_title<T: PartialEq>(
+    column_key: T,
+    previous_column_key: Option<T>,
+    previous_sort_direction: Option<SortDirection>,
+    title: &str,
+) -> String {
+    if Some(column_key) == previous_column_key {
+        match previous_sort_direction {
+            Some(SortDirection::Asc) => format!("{} ▲", title),
+            Some(SortDirection::Desc) => format!("{} ▼", title),
+            _ => title.to_string(),
+        }
+    } else {
+        title.to_string()
+    }
+}
+
+/// Formats a song length in milliseconds as `m:ss`.
+pub fn format_length(length: Option<u64>) -> String {
+    match length {
+        Some(length) => {
+            let seconds = length / 1000;
+            format!("{}:{:02}", seconds / 60, seconds % 60)
+        }
+        None => "-".to_owned(),
+    }
+}
+
+pub fn titles_row_header<'a>(
+    color_palette: ColorPalette,
+    songs: &[Song],
+    header_state: &'a mut header::State,
+    column_state: &'a mut [SongColumnState],
+    previous_column_key: Option<SongColumnKey>,
+    previous_sort_direction: Option<SortDirection>,
+) -> Header<'a, Message> {
+    // A row containing titles above the song rows.
+    let mut row_titles = vec![];
+
+    for column in column_state.iter_mut().filter(|c| !c.hidden) {
+        let column_key = column.key;
+
+        let row_title = row_title(
+            column_key,
+            previous_column_key,
+            previous_sort_direction,
+            &column.key.title(),
+        );
+
+        let mut row_header = Button::new(
+            &mut column.btn_state,
+            Text::new(row_title)
+                .size(DEFAULT_FONT_SIZE)
+                .width(Length::Fill),
+        )
+        .width(Length::Fill)
+        .on_press(Interaction::SortSongColumn(column_key));
+
+        if previous_column_key == Some(column_key) {
+            row_header = row_header.style(style::SelectedColumnHeaderButton(color_palette));
+        } else {
+            row_header = row_header.style(style::ColumnHeaderButton(color_palette));
+        }
+
+        let row_header: Element<Interaction> = row_header.into();
+
+        let row_container = Container::new(row_header.map(Message::Interaction))
+            .width(column.width)
+            .style(style::NormalBackgroundContainer(color_palette));
+
+        // Only shows row titles if we have any songs.
+        if !songs.is_empty() {
+            row_titles.push((column.key.as_string(), row_container));
+        }
+    }
+
+    Header::new(
+        header_state,
+        row_titles,
+        Some(Length::Units(DEFAULT_PADDING)),
+        Some(Length::Units(DEFAULT_PADDING + 5)),
+    )
+    .spacing(1)
+    .height(Length::Units(25))
+    .on_resize(3, |event| {
+        Message::Interaction(Interaction::ResizeColumn(Mode::MySongs, event))
+    })
+}
+
+pub fn data_row_container<'a, 'b>(
+    color_palette: ColorPalette,
+    song: &'a mut Song,
+    is_song_expanded: bool,
+    is_selected: Option<bool>,
+    versions: &'a mut [SongVersionState],
+    user_data_state: Option<&'a mut SongUserDataState>,
+    column_config: &'b [(SongColumnKey, Length, bool)],
+    is_odd: Option<bool>,
+    pending_confirmation: &Option<Confirm>,
+) -> TableRow<'a, Message> {
+    let default_height = Length::Units(26);
+    let default_row_height = 26;
+
+    // Songs Clone Hero can't load are highlighted.
+    let is_error = matches!(song.state, SongState::Error(_));
+
+    let mut row_containers = vec![];
+
+    for (idx, (key, width, _)) in column_config
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, _, hidden))| !hidden)
+    {
+        let text = match key {
+            SongColumnKey::Title => match song.state {
+                SongState::Updatable => {
+                    format!("{} ({})", song.title(), localized_string("updatable"))
+                }
+                SongState::Downloading => {
+                    format!("{} ({})", song.title(), localized_string("downloading"))
+                }
+                _ => song.title().to_owned(),
+            },
+            SongColumnKey::Artist => song.artist().unwrap_or("-").to_owned(),
+            SongColumnKey::Album => song.metadata.album.as_deref().unwrap_or("-").to_owned(),
+            SongColumnKey::Genre => song.metadata.genre.as_deref().unwrap_or("-").to_owned(),
+            SongColumnKey::Year => song.metadata.year.as_deref().unwrap_or("-").to_owned(),
+            SongColumnKey::Charter => song.charter().unwrap_or("-").to_owned(),
+            SongColumnKey::Length => format_length(song.metadata.song_length),
+            SongColumnKey::PlayCount => song.play_count().to_string(),
+            SongColumnKey::BestScore => song
+                .best_score()
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| "-".to_owned()),
+            SongColumnKey::Stars => song
+                .stars()
+                .map(|s| format!("{} ★", s))
+                .unwrap_or_else(|| "-".to_owned()),
+            SongColumnKey::Accuracy => song
+                .accuracy()
+                .map(|a| format!("{:.1}%", a * 100.0))
+                .unwrap_or_else(|| "-".to_owned()),
+            SongColumnKey::Tags => {
+                if song.user_data.tags.is_empty() {
+                    "-".to_owned()
+                } else {
+                    song.user_data.tags.join(", ")
+                }
+            }
+            SongColumnKey::Rating => {
+                let favorite = if song.user_data.favorite { "♥ " } else { "" };
+
+                match song.user_data.stars() {
+                    Some(stars) => format!("{}{}", favorite, stars),
+                    None if song.user_data.favorite => "♥".to_owned(),
+                    None => "-".to_owned(),
+                }
+            }
+            SongColumnKey::FuzzyScore => continue,
+        };
+
+        let text = Text::new(text).size(DEFAULT_FONT_SIZE);
+
+        // Songs get a checkbox to select them for a transfer to another
+        // library root.
+        let content: Element<Message> = match is_selected {
+            Some(is_selected) if *key == SongColumnKey::Title => {
+                let path = song.path.clone();
+                let checkbox = Checkbox::new(is_selected, "", move |is_checked| {
+                    Message::Interaction(Interaction::ToggleSongSelected(path.clone(), is_checked))
+                })
+                .style(style::DefaultCheckbox(color_palette))
+                .size(14)
+                .spacing(0);
+
+                Row::new()
+                    .align_items(Align::Center)
+                    .spacing(5)
+                    .push(checkbox)
+                    .push(text)
+                    .into()
+            }
+            _ => text.into(),
+        };
+
+        let mut container = Container::new(content)
+            .padding(5)
+            .height(default_height)
+            .width(*width)
+            .center_y();
+
+        container = if *key == SongColumnKey::Title {
+            if is_error {
+                container.style(style::NormalErrorForegroundContainer(color_palette))
+            } else if is_song_expanded {
+                container.style(style::SelectedBrightForegroundContainer(color_palette))
+            } else {
+                container.style(style::HoverableBrightForegroundContainer(color_palette))
+            }
+        } else {
+            container.style(style::HoverableForegroundContainer(color_palette))
+        };
+
+        row_containers.push((idx, container));
+    }
+
+    let left_spacer = Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0));
+    let right_spacer = Space::new(Length::Units(DEFAULT_PADDING + 5), Length::Units(0));
+
+    let mut row = Row::new().push(left_spacer).spacing(1);
+
+    // Sort columns and push them into row
+    row_containers.sort_by(|a, b| a.0.cmp(&b.0));
+    for (_, elem) in row_containers.into_iter() {
+        row = row.push(elem);
+    }
+
+    row = row.push(right_spacer);
+
+    let mut song_column = Column::new().push(row);
+
+    if is_song_expanded {
+        let left_spacer = Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0));
+        let bottom_space = Space::new(Length::Units(0), Length::Units(4));
+
+        let mut details_column = Column::new();
+
+        if let SongState::Error(error) = &song.state {
+            details_column = details_column
+                .push(
+                    Container::new(Text::new(error.clone()).size(DEFAULT_FONT_SIZE))
+                        .style(style::NormalErrorForegroundContainer(color_palette)),
+                )
+                .push(Space::new(Length::Units(0), Length::Units(15)));
+        }
+
+        let folder_title_container =
+            Container::new(Text::new(localized_string("folder")).size(DEFAULT_FONT_SIZE))
+                .style(style::HoverableBrightForegroundContainer(color_palette));
+        let folder_text = Text::new(song.path.display().to_string()).size(DEFAULT_FONT_SIZE);
+
+        let origin_title_container =
+            Container::new(Text::new(localized_string("origin")).size(DEFAULT_FONT_SIZE))
+                .style(style::HoverableBrightForegroundContainer(color_palette));
+        let origin_text = match &song.origin {
+            Some(origin) => {
+                let installed = origin
+                    .installed
+                    .map(|installed| installed.format("%Y-%m-%d").to_string())
+                    .unwrap_or_else(|| "-".to_owned());
+
+                format!("{} ({})", origin.url, installed)
+            }
+            None => localized_string("origin-unknown"),
+        };
+        let origin_text = Text::new(origin_text).size(DEFAULT_FONT_SIZE);
+
+        let scores_title_container =
+            Container::new(Text::new(localized_string("scores")).size(DEFAULT_FONT_SIZE))
+                .style(style::HoverableBrightForegroundContainer(color_palette));
+
+        let mut scores_column = Column::new();
+        match &song.scores {
+            Some(scores) if !scores.scores.is_empty() => {
+                for score in scores.scores.iter() {
+                    let accuracy = score
+                        .accuracy()
+                        .map(|a| format!("{:.1}%", a * 100.0))
+                        .unwrap_or_else(|| "-".to_owned());
+
+                    scores_column = scores_column.push(
+                        Text::new(format!(
+                            "{} ({}): {} - {} ★ - {}",
+                            score.instrument, score.difficulty, score.score, score.stars, accuracy
+                        ))
+                        .size(DEFAULT_FONT_SIZE),
+                    );
+                }
+            }
+            _ => {
+                scores_column = scores_column
+                    .push(Text::new(localized_string("never-played")).size(DEFAULT_FONT_SIZE));
+            }
+        }
+
+        let versions_title_container =
+            Container::new(Text::new(localized_string("versions")).size(DEFAULT_FONT_SIZE))
+                .style(style::HoverableBrightForegroundContainer(color_palette));
+
+        let mut versions_column = Column::new().spacing(2);
+        if versions.is_empty() {
+            versions_column = versions_column
+                .push(Text::new(localized_string("no-versions")).size(DEFAULT_FONT_SIZE));
+        }
+
+        for state in versions.iter_mut() {
+            let archived = state
+                .version
+                .archived
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string();
+            let play_count = state.play_count.to_string();
+            let mut vars = HashMap::new();
+            vars.insert("date".to_string(), &archived);
+            vars.insert("number".to_string(), &play_count);
+            let fmt = localized_string("song-version");
+
+            let rollback_button: Element<Interaction> = Button::new(
+                &mut state.rollback_btn_state,
+                Text::new(localized_string("rollback")).size(DEFAULT_FONT_SIZE),
+            )
+            .style(style::DefaultButton(color_palette))
+            .on_press(Interaction::RollbackSong(
+                song.path.clone(),
+                state.version.id.clone(),
+            ))
+            .into();
+
+            let version_row = Row::new()
+                .push(Text::new(strfmt(&fmt, &vars).unwrap()).size(DEFAULT_FONT_SIZE))
+                .push(Space::new(Length::Units(10), Length::Units(0)))
+                .push(rollback_button.map(Message::Interaction))
+                .align_items(Align::Center);
+
+            versions_column = versions_column.push(version_row);
+        }
+
+        let user_data_title_container =
+            Container::new(Text::new(localized_string("your-notes")).size(DEFAULT_FONT_SIZE))
+                .style(style::HoverableBrightForegroundContainer(color_palette));
+
+        let mut user_data_column = Column::new().spacing(5);
+
+        if let Some(state) = user_data_state {
+            let path = song.path.clone();
+
+            // Clicking a tag removes it.
+            let mut tags_row = Row::new().spacing(5).align_items(Align::Center);
+            state
+                .tag_btn_states
+                .resize_with(song.user_data.tags.len(), Default::default);
+
+            for (tag, btn_state) in song
+                .user_data
+                .tags
+                .iter()
+                .zip(state.tag_btn_states.iter_mut())
+            {
+                let tag_button: Element<Interaction> = Button::new(
+                    btn_state,
+                    Text::new(format!("{} ✕", tag)).size(DEFAULT_FONT_SIZE),
+                )
+                .style(style::DefaultButton(color_palette))
+                .on_press(Interaction::RemoveSongTag(path.clone(), tag.clone()))
+                .into();
+
+                tags_row = tags_row.push(tag_button.map(Message::Interaction));
+            }
+
+            let tag_input: Element<Interaction> = TextInput::new(
+                &mut state.tag_input_state,
+                &localized_string("tag")[..],
+                &state.tag,
+                Interaction::SongTagInputChanged,
+            )
+            .on_submit(Interaction::AddSongTag(path.clone()))
+            .size(DEFAULT_FONT_SIZE)
+            .padding(5)
+            .width(Length::Units(150))
+            .style(style::AddonsQueryInput(color_palette))
+            .into();
+
+            let mut add_tag_button = Button::new(
+                &mut state.add_tag_btn_state,
+                Text::new(localized_string("add-tag")).size(DEFAULT_FONT_SIZE),
+            )
+            .style(style::DefaultButton(color_palette));
+
+            if !state.tag.trim().is_empty() {
+                add_tag_button = add_tag_button.on_press(Interaction::AddSongTag(path.clone()));
+            }
+
+            let add_tag_button: Element<Interaction> = add_tag_button.into();
+
+            tags_row = tags_row
+                .push(tag_input.map(Message::Interaction))
+                .push(add_tag_button.map(Message::Interaction));
+
+            let favorite_path = path.clone();
+            let favorite_checkbox = Checkbox::new(
+                song.user_data.favorite,
+                localized_string("favorite"),
+                move |is_checked| {
+                    Message::Interaction(Interaction::ToggleSongFavorite(
+                        favorite_path.clone(),
+                        is_checked,
+                    ))
+                },
+            )
+            .style(style::DefaultCheckbox(color_palette))
+            .text_size(DEFAULT_FONT_SIZE)
+            .spacing(5);
+
+            let rating_path = path.clone();
+            let rating_picklist: Element<Interaction> = PickList::new(
+                &mut state.rating_state,
+                SongRating::all(),
+                Some(SongRating(song.user_data.rating)),
+                move |rating| Interaction::SongRatingSelected(rating_path.clone(), rating),
+            )
+            .text_size(DEFAULT_FONT_SIZE)
+            .width(Length::Units(100))
+            .style(style::PickList(color_palette))
+            .into();
+
+            let rating_row = Row::new()
+                .push(favorite_checkbox)
+                .push(Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0)))
+                .push(Text::new(localized_string("rating")).size(DEFAULT_FONT_SIZE))
+                .push(Space::new(Length::Units(5), Length::Units(0)))
+                .push(rating_picklist.map(Message::Interaction))
+                .align_items(Align::Center);
+
+            let notes_input: Element<Interaction> = TextInput::new(
+                &mut state.notes_input_state,
+                &localized_string("notes")[..],
+                &state.notes,
+                Interaction::SongNotesChanged,
+            )
+            .on_submit(Interaction::SaveSongNotes(path))
+            .size(DEFAULT_FONT_SIZE)
+            .padding(5)
+            .width(Length::Fill)
+            .style(style::AddonsQueryInput(color_palette))
+            .into();
+
+            user_data_column = user_data_column
+                .push(tags_row)
+                .push(rating_row)
+                .push(notes_input.map(Message::Interaction));
+        }
+
+        let open_folder_button: Element<Interaction> = Button::new(
+            &mut song.open_folder_btn_state,
+            Text::new(localized_string("open-folder")).size(DEFAULT_FONT_SIZE),
+        )
+        .style(style::DefaultButton(color_palette))
+        .on_press(Interaction::OpenDirectory(song.path.clone()))
+        .into();
+
+        let validate_button: Element<Interaction> = Button::new(
+            &mut song.validate_btn_state,
+            Text::new(localized_string("validate")).size(DEFAULT_FONT_SIZE),
+        )
+        .style(style::DefaultButton(color_palette))
+        .on_press(Interaction::ValidateSong(song.path.clone()))
+        .into();
+
+        let (title, interaction) = if Some(Confirm::QuarantineSong) == *pending_confirmation {
+            (
+                localized_string("confirm-quarantine"),
+                Interaction::ConfirmQuarantineSong(song.path.clone()),
+            )
+        } else {
+            (localized_string("quarantine"), Interaction::QuarantineSong)
+        };
+
+        let quarantine_button: Element<Interaction> = Button::new(
+            &mut song.quarantine_btn_state,
+            Text::new(title).size(DEFAULT_FONT_SIZE),
+        )
+        .style(style::DefaultDeleteButton(color_palette))
+        .on_press(interaction)
+        .into();
+
+        let button_row = Row::new()
+            .push(Space::new(Length::Fill, Length::Units(0)))
+            .push(open_folder_button.map(Message::Interaction))
+            .push(Space::new(Length::Units(5), Length::Units(0)))
+            .push(validate_button.map(Message::Interaction))
+            .push(Space::new(Length::Units(5), Length::Units(0)))
+            .push(quarantine_button.map(Message::Interaction))
+            .width(Length::Fill);
+
+        details_column = details_column
+            .push(folder_title_container)
+            .push(Space::new(Length::Units(0), Length::Units(3)))
+            .push(folder_text)
+            .push(Space::new(Length::Units(0), Length::Units(15)))
+            .push(origin_title_container)
+            .push(Space::new(Length::Units(0), Length::Units(3)))
+            .push(origin_text)
+            .push(Space::new(Length::Units(0), Length::Units(15)))
+            .push(scores_title_container)
+            .push(Space::new(Length::Units(0), Length::Units(3)))
+            .push(scores_column)
+            .push(Space::new(Length::Units(0), Length::Units(15)))
+            .push(versions_title_container)
+            .push(Space::new(Length::Units(0), Length::Units(3)))
+            .push(versions_column)
+            .push(Space::new(Length::Units(0), Length::Units(15)))
+            .push(user_data_title_container)
+            .push(Space::new(Length::Units(0), Length::Units(3)))
+            .push(user_data_column)
+            .push(Space::new(
+                Length::Units(0),
+                Length::Units(DEFAULT_PADDING * 2),
+            ))
+            .push(button_row)
+            .push(bottom_space);
+
+        let details_container = Container::new(details_column)
+            .width(Length::Fill)
+            .padding(20)
+            .style(style::FadedNormalForegroundContainer(color_palette));
+
+        let row = Row::new()
+            .push(left_spacer)
+            .push(details_container)
+            .push(Space::new(
+                Length::Units(DEFAULT_PADDING + 5),
+                Length::Units(0),
+            ))
+            .spacing(1);
+
+        song_column = song_column
+            .push(Space::new(Length::FillPortion(1), Length::Units(1)))
+            .push(row);
+    }
+
+    let path = song.path.clone();
+
+    let mut table_row = TableRow::new(song_column)
+        .width(Length::Fill)
+        .inner_row_height(default_row_height)
+        .on_press(move |_| Message::Interaction(Interaction::ExpandSong(path.clone())));
+
+    if is_odd == Some(true) {
+        table_row = table_row.style(style::TableRowAlternate(color_palette))
+    } else {
+        table_row = table_row.style(style::TableRow(color_palette))
+    }
+
+    table_row
+}
+
+pub fn pack_row_container<'a>(
+    color_palette: ColorPalette,
+    pack: &'a mut Pack,
+    song_count: usize,
+    is_pack_expanded: bool,
+    title_width: Length,
+    is_odd: Option<bool>,
+    pending_confirmation: &Option<Confirm>,
+) -> TableRow<'a, Message> {
+    let default_height = Length::Units(26);
+    let default_row_height = 26;
+
+    let arrow = if is_pack_expanded { "▼" } else { "▶" };
+
+    let title_container =
+        Container::new(Text::new(format!("{} {}", arrow, pack.name())).size(DEFAULT_FONT_SIZE))
+            .padding(5)
+            .height(default_height)
+            .width(title_width)
+            .center_y();
+
+    let title_container = if is_pack_expanded {
+        title_container.style(style::SelectedBrightForegroundContainer(color_palette))
+    } else {
+        title_container.style(style::HoverableBrightForegroundContainer(color_palette))
+    };
+
+    let song_count = song_count.to_string();
+    let mut vars = HashMap::new();
+    vars.insert("number".to_string(), &song_count);
+    let fmt = localized_string("pack-songs");
+
+    let songs_container =
+        Container::new(Text::new(strfmt(&fmt, &vars).unwrap()).size(DEFAULT_FONT_SIZE))
+            .padding(5)
+            .height(default_height)
+            .width(Length::Fill)
+            .center_y()
+            .style(style::HoverableForegroundContainer(color_palette));
+
+    let row = Row::new()
+        .push(Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0)))
+        .push(title_container)
+        .push(songs_container)
+        .push(Space::new(
+            Length::Units(DEFAULT_PADDING + 5),
+            Length::Units(0),
+        ))
+        .spacing(1);
+
+    let mut pack_column = Column::new().push(row);
+
+    if is_pack_expanded {
+        let detail_row = |title: String, value: String| -> Row<Message> {
+            Row::new()
+                .push(
+                    Container::new(Text::new(title).size(DEFAULT_FONT_SIZE))
+                        .width(Length::Units(100))
+                        .style(style::HoverableBrightForegroundContainer(color_palette)),
+                )
+                .push(Text::new(value).size(DEFAULT_FONT_SIZE))
+        };
+
+        let source = pack.info.source.clone().unwrap_or_else(|| "-".to_owned());
+        let version = pack.info.version.clone().unwrap_or_else(|| "-".to_owned());
+        let installed = pack
+            .info
+            .installed
+            .map(|installed| installed.format("%Y-%m-%d").to_string())
+            .unwrap_or_else(|| "-".to_owned());
+
+        let open_folder_button: Element<Interaction> = Button::new(
+            &mut pack.open_folder_btn_state,
+            Text::new(localized_string("open-folder")).size(DEFAULT_FONT_SIZE),
+        )
+        .style(style::DefaultButton(color_palette))
+        .on_press(Interaction::OpenDirectory(pack.path.clone()))
+        .into();
+
+        let mut update_button = Button::new(
+            &mut pack.update_btn_state,
+            Text::new(localized_string("update")).size(DEFAULT_FONT_SIZE),
+        )
+        .style(style::DefaultButton(color_palette));
+
+        // Packs can only be updated from where they were installed from.
+        if pack.info.source.is_some() {
+            update_button = update_button.on_press(Interaction::UpdatePack(pack.path.clone()));
+        }
+
+        let update_button: Element<Interaction> = update_button.into();
+
+        let (title, interaction) = if Some(Confirm::DeletePack) == *pending_confirmation {
+            (
+                localized_string("confirm-deletion"),
+                Interaction::ConfirmDeletePack(pack.path.clone()),
+            )
+        } else {
+            (localized_string("delete"), Interaction::DeletePack())
+        };
+
+        let delete_button: Element<Interaction> = Button::new(
+            &mut pack.delete_btn_state,
+            Text::new(title).size(DEFAULT_FONT_SIZE),
+        )
+        .style(style::DefaultDeleteButton(color_palette))
+        .on_press(interaction)
+        .into();
+
+        let button_row = Row::new()
+            .push(Space::new(Length::Fill, Length::Units(0)))
+            .push(open_folder_button.map(Message::Interaction))
+            .push(Space::new(Length::Units(5), Length::Units(0)))
+            .push(update_button.map(Message::Interaction))
+            .push(Space::new(Length::Units(5), Length::Units(0)))
+            .push(delete_button.map(Message::Interaction))
+            .width(Length::Fill);
+
+        let details_column = Column::new()
+            .push(detail_row(localized_string("source"), source))
+            .push(Space::new(Length::Units(0), Length::Units(3)))
+            .push(detail_row(localized_string("version"), version))
+            .push(Space::new(Length::Units(0), Length::Units(3)))
+            .push(detail_row(localized_string("installed"), installed))
+            .push(Space::new(
+                Length::Units(0),
+                Length::Units(DEFAULT_PADDING * 2),
+            ))
+            .push(button_row)
+            .push(Space::new(Length::Units(0), Length::Units(4)));
+
+        let details_container = Container::new(details_column)
+            .width(Length::Fill)
+            .padding(20)
+            .style(style::FadedNormalForegroundContainer(color_palette));
+
+        let row = Row::new()
+            .push(Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0)))
+            .push(details_container)
+            .push(Space::new(
+                Length::Units(DEFAULT_PADDING + 5),
+                Length::Units(0),
+            ))
+            .spacing(1);
+
+        pack_column = pack_column
+            .push(Space::new(Length::FillPortion(1), Length::Units(1)))
+            .push(row);
+    }
+
+    let path = pack.path.clone();
+
+    let mut table_row = TableRow::new(pack_column)
+        .width(Length::Fill)
+        .inner_row_height(default_row_height)
+        .on_press(move |_| Message::Interaction(Interaction::ExpandPack(path.clone())));
+
+    if is_odd == Some(true) {
+        table_row = table_row.style(style::TableRowAlternate(color_palette))
+    } else {
+        table_row = table_row.style(style::TableRow(color_palette))
+    }
+
+    table_row
+}
+
+pub fn menu_container<'a>(
+    color_palette: ColorPalette,
+    update_all_button_state: &'a mut button::State,
+    refresh_button_state: &'a mut button::State,
+    songs_search_state: &'a mut SongsSearchState,
+    song_transfer_state: &'a mut SongTransferState,
+    selected_count: usize,
+    install_pack_button_state: &'a mut button::State,
+    directories: &[PathBuf],
+    pending_confirmation: &Option<Confirm>,
+    state: &HashMap<Mode, State>,
+    songs: &[Song],
+) -> Container<'a, Message> {
+    // MySongs state.
+    let state = state.get(&Mode::MySongs);
+
+    // A row contain general settings.
+    let mut settings_row = Row::new().align_items(Align::Center);
+
+    let mut update_all_button = Button::new(
+        update_all_button_state,
+        Text::new(localized_string("update-all")).size(DEFAULT_FONT_SIZE),
+    )
+    .style(style::DefaultButton(color_palette));
+
+    let mut refresh_button = Button::new(
+        refresh_button_state,
+        Text::new(localized_string("refresh")).size(DEFAULT_FONT_SIZE),
+    )
+    .style(style::DefaultButton(color_palette));
+
+    // Is any song being reinstalled from its origin.
+    let songs_performing_actions = songs
+        .iter()
+        .any(|s| matches!(s.state, SongState::Downloading | SongState::Unpacking));
+
+    // Enable update_all_button if no song is being updated and any song
+    // changed at its origin.
+    if !songs_performing_actions && songs.iter().any(|s| s.state == SongState::Updatable) {
+        update_all_button = update_all_button.on_press(Interaction::UpdateAll(Mode::MySongs));
+    }
+
+    // Enable refresh_button if no song is being updated and mode state
+    // isn't start or loading
+    if !songs_performing_actions && !matches!(state, None | Some(State::Loading)) {
+        refresh_button = refresh_button.on_press(Interaction::Refresh(Mode::MySongs));
+    }
+
+    let update_all_button: Element<Interaction> = update_all_button.into();
+    let refresh_button: Element<Interaction> = refresh_button.into();
+
+    let mut install_pack_button = Button::new(
+        install_pack_button_state,
+        Text::new(localized_string("install-pack")).size(DEFAULT_FONT_SIZE),
+    )
+    .style(style::DefaultButton(color_palette));
+
+    if !directories.is_empty() {
+        install_pack_button = install_pack_button.on_press(Interaction::InstallPack);
+    }
+
+    let install_pack_button: Element<Interaction> = install_pack_button.into();
+
+    let status_text = match (state, song_transfer_state.progress) {
+        (Some(State::Ready), Some((done, total))) => {
+            let done = done.to_string();
+            let total = total.to_string();
+            let mut vars = HashMap::new();
+            vars.insert("done".to_string(), &done);
+            vars.insert("total".to_string(), &total);
+            let fmt = localized_string("transferring-songs");
+
+            Text::new(strfmt(&fmt, &vars).unwrap()).size(DEFAULT_FONT_SIZE)
+        }
+        (Some(State::Ready), None) => {
+            let songs_count = songs.len().to_string();
+            let mut vars = HashMap::new();
+            vars.insert("number".to_string(), &songs_count);
+            let fmt = localized_string("songs-loaded");
+
+            Text::new(strfmt(&fmt, &vars).unwrap()).size(DEFAULT_FONT_SIZE)
+        }
+        _ => Text::new(""),
+    };
+
+    let status_container = Container::new(status_text)
+        .center_y()
+        .padding(5)
+        .style(style::NormalBackgroundContainer(color_palette));
+
+    let play_filter_picklist: Element<Interaction> = PickList::new(
+        &mut songs_search_state.play_filter_state,
+        &PlayFilter::ALL[..],
+        Some(songs_search_state.play_filter),
+        Interaction::PlayFilterSelected,
+    )
+    .text_size(DEFAULT_FONT_SIZE)
+    .width(Length::Units(120))
+    .style(style::PickList(color_palette))
+    .into();
+
+    let query = songs_search_state.query.as_deref().unwrap_or_default();
+    let songs_query = TextInput::new(
+        &mut songs_search_state.query_state,
+        &localized_string("search-for-song")[..],
+        query,
+        Interaction::SongsQuery,
+    )
+    .size(DEFAULT_FONT_SIZE)
+    .padding(7)
+    .width(Length::Units(225))
+    .style(style::AddonsQueryInput(color_palette));
+
+    let songs_query: Element<Interaction> = songs_query.into();
+
+    // Copy and move songs to another library root, only shown with more
+    // than one root.
+    let mut transfer_row = Row::new().align_items(Align::Center);
+
+    if directories.len() > 1 {
+        let is_transferring = song_transfer_state.progress.is_some();
+        let target = song_transfer_state.target.clone();
+
+        let target_picklist: Element<Interaction> = PickList::new(
+            &mut song_transfer_state.target_state,
+            directories
+                .iter()
+                .cloned()
+                .map(SongDirectory)
+                .collect::<Vec<_>>(),
+            target.clone(),
+            Interaction::SongTransferTargetSelected,
+        )
+        .text_size(DEFAULT_FONT_SIZE)
+        .width(Length::Units(180))
+        .style(style::PickList(color_palette))
+        .into();
+
+        let mut copy_button = Button::new(
+            &mut song_transfer_state.copy_btn_state,
+            Text::new(localized_string("copy-songs")).size(DEFAULT_FONT_SIZE),
+        )
+        .style(style::DefaultButton(color_palette));
+
+        let move_title = if Some(Confirm::MoveSongs) == *pending_confirmation {
+            localized_string("confirm-move-songs")
+        } else {
+            localized_string("move-songs")
+        };
+
+        let mut move_button = Button::new(
+            &mut song_transfer_state.move_btn_state,
+            Text::new(move_title).size(DEFAULT_FONT_SIZE),
+        )
+        .style(style::DefaultDeleteButton(color_palette));
+
+        let select_title = if selected_count == 0 {
+            localized_string("select-shown-songs")
+        } else {
+            let count = selected_count.to_string();
+            let mut vars = HashMap::new();
+            vars.insert("number".to_string(), &count);
+            let fmt = localized_string("clear-song-selection");
+
+            strfmt(&fmt, &vars).unwrap()
+        };
+
+        let mut select_button = Button::new(
+            &mut song_transfer_state.select_btn_state,
+            Text::new(select_title).size(DEFAULT_FONT_SIZE),
+        )
+        .style(style::DefaultButton(color_palette));
+
+        if !is_transferring {
+            select_button = select_button.on_press(if selected_count == 0 {
+                Interaction::SelectShownSongs
+            } else {
+                Interaction::ClearSongSelection
+            });
+        }
+
+        // Only the selected songs are transferred.
+        if target.is_some() && selected_count > 0 && !is_transferring {
+            copy_button = copy_button.on_press(Interaction::TransferSongs(TransferMode::Copy));
+            move_button = move_button.on_press(Interaction::TransferSongs(TransferMode::Move));
+        }
+
+        let select_button: Element<Interaction> = select_button.into();
+        let copy_button: Element<Interaction> = copy_button.into();
+        let move_button: Element<Interaction> = move_button.into();
+
+        let target_text = Text::new(localized_string("transfer-songs-to")).size(DEFAULT_FONT_SIZE);
+
+        transfer_row = transfer_row
+            .push(target_text)
+            .push(Space::new(Length::Units(7), Length::Units(0)))
+            .push(target_picklist.map(Message::Interaction))
+            .push(Space::new(Length::Units(7), Length::Units(0)))
+            .push(select_button.map(Message::Interaction))
+            .push(Space::new(Length::Units(7), Length::Units(0)))
+            .push(copy_button.map(Message::Interaction))
+            .push(Space::new(Length::Units(7), Length::Units(0)))
+            .push(move_button.map(Message::Interaction))
+            .push(Space::new(Length::Units(7), Length::Units(0)));
+    }
+
+    // Surrounds the elements with spacers, in order to make the GUI look good.
+    settings_row = settings_row
+        .push(Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0)))
+        .push(update_all_button.map(Message::Interaction))
+        .push(Space::new(Length::Units(7), Length::Units(0)))
+        .push(refresh_button.map(Message::Interaction))
+        .push(Space::new(Length::Units(7), Length::Units(0)))
+        .push(install_pack_button.map(Message::Interaction))
+        .push(Space::new(Length::Units(7), Length::Units(0)))
+        .push(status_container)
+        .push(Space::new(Length::Fill, Length::Units(0)))
+        .push(transfer_row)
+        .push(play_filter_picklist.map(Message::Interaction))
+        .push(Space::new(Length::Units(7), Length::Units(0)))
+        .push(songs_query.map(Message::Interaction))
+        .push(Space::new(
+            Length::Units(DEFAULT_PADDING + 5),
+            Length::Units(0),
+        ));
+
+    // Add space above settings_row.
+    let settings_column = Column::new()
+        .push(Space::new(Length::Units(0), Length::Units(5)))
+        .push(settings_row)
+        .push(Space::new(Length::Units(0), Length::Units(8)));
+
+    // Wraps it in a container.
+    Container::new(settings_column)
+}
+
+pub fn smart_filters_container<'a>(
+    color_palette: ColorPalette,
+    smart_filters_state: &'a mut SmartFiltersState,
+    smart_filters: &[SmartFilter],
+    songs_count: usize,
+    has_query: bool,
+) -> Container<'a, Message> {
+    let selected = smart_filters_state.selected;
+
+    let mut tabs_row = Row::new()
+        .align_items(Align::Center)
+        .push(Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0)));
+
+    let all_title = format!("{} ({})", localized_string("smart-filter-all"), songs_count);
+    let mut all_button = Button::new(
+        &mut smart_filters_state.all_btn_state,
+        Text::new(all_title).size(DEFAULT_FONT_SIZE),
+    )
+    .on_press(Interaction::SelectSmartFilter(None));
+
+    all_button = if selected.is_none() {
+        all_button.style(style::SelectedDefaultButton(color_palette))
+    } else {
+        all_button.style(style::DefaultButton(color_palette))
+    };
+
+    let all_button: Element<Interaction> = all_button.into();
+    tabs_row = tabs_row.push(all_button.map(Message::Interaction));
+
+    for (idx, (filter, state)) in smart_filters
+        .iter()
+        .zip(smart_filters_state.filters.iter_mut())
+        .enumerate()
+    {
+        let title = format!("{} ({})", filter.name, state.count);
+        let mut button = Button::new(
+            &mut state.btn_state,
+            Text::new(title).size(DEFAULT_FONT_SIZE),
+        )
+        .on_press(Interaction::SelectSmartFilter(Some(idx)));
+
+        button = if selected == Some(idx) {
+            button.style(style::SelectedDefaultButton(color_palette))
+        } else {
+            button.style(style::DefaultButton(color_palette))
+        };
+
+        let button: Element<Interaction> = button.into();
+        tabs_row = tabs_row
+            .push(Space::new(Length::Units(3), Length::Units(0)))
+            .push(button.map(Message::Interaction));
+    }
+
+    tabs_row = tabs_row.push(Space::new(Length::Fill, Length::Units(0)));
+
+    // Export and delete the selected filter.
+    if selected.is_some() {
+        if let Some(path) = &smart_filters_state.exported {
+            let mut vars = HashMap::new();
+            vars.insert("path".to_string(), path.display().to_string());
+            let fmt = localized_string("setlist-exported");
+
+            tabs_row = tabs_row
+                .push(Text::new(strfmt(&fmt, &vars).unwrap()).size(DEFAULT_FONT_SIZE))
+                .push(Space::new(Length::Units(7), Length::Units(0)));
+        }
+
+        let export_button: Element<Interaction> = Button::new(
+            &mut smart_filters_state.export_btn_state,
+            Text::new(localized_string("export-setlist")).size(DEFAULT_FONT_SIZE),
+        )
+        .style(style::DefaultButton(color_palette))
+        .on_press(Interaction::ExportSmartFilter)
+        .into();
+
+        let delete_button: Element<Interaction> = Button::new(
+            &mut smart_filters_state.delete_btn_state,
+            Text::new(localized_string("delete-smart-filter")).size(DEFAULT_FONT_SIZE),
+        )
+        .style(style::DefaultDeleteButton(color_palette))
+        .on_press(Interaction::DeleteSmartFilter)
+        .into();
+
+        tabs_row = tabs_row
+            .push(export_button.map(Message::Interaction))
+            .push(Space::new(Length::Units(7), Length::Units(0)))
+            .push(delete_button.map(Message::Interaction))
+            .push(Space::new(Length::Units(7), Length::Units(0)));
+    }
+
+    // Saves the current search query as a filter.
+    let name_input: Element<Interaction> = TextInput::new(
+        &mut smart_filters_state.name_state,
+        &localized_string("smart-filter-name")[..],
+        &smart_filters_state.name,
+        Interaction::SmartFilterNameChanged,
+    )
+    .size(DEFAULT_FONT_SIZE)
+    .padding(7)
+    .width(Length::Units(150))
+    .style(style::AddonsQueryInput(color_palette))
+    .into();
+
+    let mut save_button = Button::new(
+        &mut smart_filters_state.save_btn_state,
+        Text::new(localized_string("save-smart-filter")).size(DEFAULT_FONT_SIZE),
+    )
+    .style(style::DefaultButton(color_palette));
+
+    if has_query && !smart_filters_state.name.trim().is_empty() {
+        save_button = save_button.on_press(Interaction::SaveSmartFilter);
+    }
+
+    let save_button: Element<Interaction> = save_button.into();
+
+    tabs_row = tabs_row
+        .push(name_input.map(Message::Interaction))
+        .push(Space::new(Length::Units(7), Length::Units(0)))
+        .push(save_button.map(Message::Interaction))
+        .push(Space::new(
+            Length::Units(DEFAULT_PADDING + 5),
+            Length::Units(0),
+        ));
+
+    let column = Column::new()
+        .push(tabs_row)
+        .push(Space::new(Length::Units(0), Length::Units(8)));
+
+    Container::new(column)
+}
@@ -1,25 +1,37 @@
 #![allow(clippy::too_many_arguments)]
 
-use ajour_core::{config::Flavor, repository::CompressionFormat};
+use ajour_core::{
+    config::Flavor,
+    repository::CompressionFormat,
+    restore::{BackupSource, ConflictPolicy},
+};
 
 use {
     super::{DEFAULT_FONT_SIZE, DEFAULT_HEADER_FONT_SIZE, DEFAULT_PADDING},
     crate::gui::{
-        style, BackupFolderKind, BackupState, CatalogColumnKey, CatalogColumnSettings, ColumnKey,
-        ColumnSettings, GlobalReleaseChannel, Interaction, Language, Message, ScaleState,
-        ShareState, ThemeState, WowDirectoryState,
+        style, BackupFolderKind, BackupInterval, BackupState, CatalogColumnKey,
+        CatalogColumnSettings, ColumnKey, ColumnSettings, GlobalReleaseChannel, Hour, Interaction,
+        Language, Message, RestoreState, RetentionRule, S3Setting, ScaleState, ShareState,
+        SongSettingsState, ThemeState, UpdateInterval, WowDirectoryState,
     },
     crate::localization::localized_string,
-    ajour_core::{config::Config, theme::ColorPalette},
+    ajour_core::{
+        config::{Config, SongAutoUpdate},
+        organizer,
+        theme::ColorPalette,
+    },
     iced::{
-        button, pick_list, scrollable, slider, Align, Button, Checkbox, Column, Container, Element,
-        HorizontalAlignment, Length, PickList, Row, Scrollable, Slider, Space, Text, TextInput,
-        VerticalAlignment,
+        button, pick_list, scrollable, slider, text_input, Align, Button, Checkbox, Column,
+        Container, Element, HorizontalAlignment, Length, PickList, Row, Scrollable, Slider, Space,
+        Text, TextInput, VerticalAlignment,
     },
     std::collections::HashMap,
     strfmt::strfmt,
 };
 
+/// Number of planned moves listed in the organizer preview.
+const MAX_ORGANIZE_PREVIEW: usize = 50;
+
 pub fn data_container<'a, 'b>(
     color_palette: ColorPalette,
     scrollable_state: &'a mut scrollable::State,
@@ -40,6 +52,8 @@ pub fn data_container<'a, 'b>(
     localization_picklist_state: &'a mut pick_list::State<Language>,
     wow_directories: &'a mut Vec<WowDirectoryState>,
     share_state: &'a mut ShareState,
+    song_settings_state: &'a mut SongSettingsState,
+    restore_state: &'a mut RestoreState,
 ) -> Container<'a, Message> {
     let mut scrollable = Scrollable::new(scrollable_state)
         .spacing(1)
@@ -86,6 +100,412 @@ pub fn data_container<'a, 'b>(
         wow_dir_column
     };
 
+    let song_directory_column = {
+        let mut song_dir_column = Column::new();
+
+        for dir_state in song_settings_state.directories.iter_mut() {
+            let remove_button: Element<Interaction> = Button::new(
+                &mut dir_state.remove_btn_state,
+                Text::new(localized_string("remove")).size(DEFAULT_FONT_SIZE),
+            )
+            .style(style::DefaultDeleteButton(color_palette))
+            .on_press(Interaction::RemoveSongDirectory(dir_state.path.clone()))
+            .into();
+            let path_text = Text::new(dir_state.path.display().to_string())
+                .size(14)
+                .vertical_alignment(VerticalAlignment::Center);
+            let path_text_container = Container::new(path_text)
+                .height(Length::Units(25))
+                .center_y()
+                .style(style::NormalBackgroundContainer(color_palette));
+            let path = dir_state.path.clone();
+            let auto_update_checkbox = Checkbox::new(
+                config.songs.auto_update.is_enabled(&dir_state.path),
+                localized_string("auto-update-songs"),
+                move |is_checked| {
+                    Message::Interaction(Interaction::ToggleSongAutoUpdate(
+                        path.clone(),
+                        is_checked,
+                    ))
+                },
+            )
+            .style(style::DefaultCheckbox(color_palette))
+            .text_size(DEFAULT_FONT_SIZE)
+            .spacing(5);
+            let auto_update_container = Container::new(auto_update_checkbox)
+                .style(style::NormalBackgroundContainer(color_palette));
+            let dir_row = Row::new()
+                .push(remove_button.map(Message::Interaction))
+                .push(Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0)))
+                .push(path_text_container)
+                .push(Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0)))
+                .push(auto_update_container)
+                .align_items(Align::Center);
+
+            song_dir_column = song_dir_column.push(dir_row);
+        }
+
+        let add_button: Element<Interaction> = Button::new(
+            &mut song_settings_state.add_directory_btn_state,
+            Text::new(localized_string("add-song-directory")).size(DEFAULT_FONT_SIZE),
+        )
+        .style(style::DefaultBoxedButton(color_palette))
+        .on_press(Interaction::SelectSongDirectory)
+        .into();
+
+        let data_dir_str = config
+            .songs
+            .data_directory()
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|| "-".to_string());
+        let data_dir_title_container = Container::new(
+            Text::new(localized_string("clone-hero-data-directory")).size(DEFAULT_FONT_SIZE),
+        )
+        .center_x()
+        .align_x(Align::Center);
+        let data_dir_button: Element<Interaction> = Button::new(
+            &mut song_settings_state.data_directory_btn_state,
+            data_dir_title_container,
+        )
+        .style(style::DefaultBoxedButton(color_palette))
+        .on_press(Interaction::SelectCloneHeroDataDirectory)
+        .into();
+        let data_dir_text = Text::new(data_dir_str)
+            .size(14)
+            .vertical_alignment(VerticalAlignment::Center);
+        let data_dir_text_container = Container::new(data_dir_text)
+            .height(Length::Units(25))
+            .center_y()
+            .style(style::NormalBackgroundContainer(color_palette));
+        let data_dir_row = Row::new()
+            .push(data_dir_button.map(Message::Interaction))
+            .push(Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0)))
+            .push(data_dir_text_container)
+            .align_items(Align::Center);
+
+        song_dir_column
+            .push(Space::new(Length::Units(0), Length::Units(5)))
+            .push(add_button.map(Message::Interaction))
+            .push(Space::new(Length::Units(0), Length::Units(10)))
+            .push(data_dir_row)
+    };
+
+    let song_auto_update_column = {
+        let auto_update = &config.songs.auto_update;
+
+        let title_container = Container::new(
+            Text::new(localized_string("auto-update-songs-title")).size(DEFAULT_FONT_SIZE),
+        )
+        .style(style::NormalBackgroundContainer(color_palette));
+
+        let interval_picklist: Element<Interaction> = PickList::new(
+            &mut song_settings_state.auto_update_interval_state,
+            &UpdateInterval::ALL[..],
+            Some(UpdateInterval(
+                auto_update
+                    .interval_hours
+                    .unwrap_or(SongAutoUpdate::DEFAULT_INTERVAL_HOURS),
+            )),
+            Interaction::SongAutoUpdateIntervalSelected,
+        )
+        .text_size(DEFAULT_FONT_SIZE)
+        .width(Length::Units(120))
+        .style(style::PickList(color_palette))
+        .into();
+
+        let quiet_hours_checkbox = Checkbox::new(
+            auto_update.quiet_hours.is_some(),
+            localized_string("quiet-hours"),
+            move |is_checked| Message::Interaction(Interaction::ToggleSongQuietHours(is_checked)),
+        )
+        .style(style::DefaultCheckbox(color_palette))
+        .text_size(DEFAULT_FONT_SIZE)
+        .spacing(5);
+        let quiet_hours_container = Container::new(quiet_hours_checkbox)
+            .style(style::NormalBackgroundContainer(color_palette));
+
+        let mut settings_row = Row::new()
+            .push(interval_picklist.map(Message::Interaction))
+            .push(Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0)))
+            .push(quiet_hours_container)
+            .align_items(Align::Center);
+
+        if let Some((start, end)) = auto_update.quiet_hours {
+            let start_picklist: Element<Interaction> = PickList::new(
+                &mut song_settings_state.quiet_hours_start_state,
+                Hour::all(),
+                Some(Hour(start)),
+                Interaction::SongQuietHoursStartSelected,
+            )
+            .text_size(DEFAULT_FONT_SIZE)
+            .width(Length::Units(75))
+            .style(style::PickList(color_palette))
+            .into();
+
+            let end_picklist: Element<Interaction> = PickList::new(
+                &mut song_settings_state.quiet_hours_end_state,
+                Hour::all(),
+                Some(Hour(end)),
+                Interaction::SongQuietHoursEndSelected,
+            )
+            .text_size(DEFAULT_FONT_SIZE)
+            .width(Length::Units(75))
+            .style(style::PickList(color_palette))
+            .into();
+
+            let until_container = Container::new(Text::new("–").size(DEFAULT_FONT_SIZE))
+                .style(style::NormalBackgroundContainer(color_palette));
+
+            settings_row = settings_row
+                .push(Space::new(Length::Units(5), Length::Units(0)))
+                .push(start_picklist.map(Message::Interaction))
+                .push(Space::new(Length::Units(5), Length::Units(0)))
+                .push(until_container)
+                .push(Space::new(Length::Units(5), Length::Units(0)))
+                .push(end_picklist.map(Message::Interaction));
+        }
+
+        let description =
+            Text::new(localized_string("auto-update-songs-description")).size(DEFAULT_FONT_SIZE);
+        let description_container =
+            Container::new(description).style(style::NormalBackgroundContainer(color_palette));
+
+        let mut column = Column::new()
+            .push(title_container)
+            .push(Space::new(Length::Units(0), Length::Units(5)))
+            .push(description_container)
+            .push(Space::new(Length::Units(0), Length::Units(5)))
+            .push(settings_row);
+
+        // Summary of the last automatic update.
+        let last_run = if song_settings_state.auto_update_running {
+            Some(localized_string("auto-update-songs-running"))
+        } else {
+            auto_update.last_run.map(|last_run| {
+                let last_run = last_run
+                    .with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string();
+                let mut vars = HashMap::new();
+                vars.insert("date".to_string(), &last_run);
+                let fmt = localized_string("auto-update-songs-last-run");
+
+                strfmt(&fmt, &vars).unwrap()
+            })
+        };
+
+        if let Some(last_run) = last_run {
+            let mut summary_column =
+                Column::new().push(Text::new(last_run).size(DEFAULT_FONT_SIZE));
+
+            if let Some(summary) = &song_settings_state.auto_update_summary {
+                if summary.is_empty() {
+                    summary_column = summary_column.push(
+                        Text::new(localized_string("auto-update-songs-nothing"))
+                            .size(DEFAULT_FONT_SIZE),
+                    );
+                }
+
+                for name in summary.packs.iter().chain(summary.songs.iter()) {
+                    summary_column = summary_column
+                        .push(Text::new(format!("✓ {}", name)).size(DEFAULT_FONT_SIZE));
+                }
+
+                for (name, error) in summary.failed.iter() {
+                    summary_column = summary_column
+                        .push(Text::new(format!("✗ {}: {}", name, error)).size(DEFAULT_FONT_SIZE));
+                }
+            }
+
+            let summary_container = Container::new(summary_column.spacing(2))
+                .padding(10)
+                .style(style::NormalForegroundContainer(color_palette));
+
+            column = column
+                .push(Space::new(Length::Units(0), Length::Units(5)))
+                .push(summary_container);
+        }
+
+        column
+    };
+
+    let song_inbox_column = {
+        let title_container =
+            Container::new(Text::new(localized_string("inbox-title")).size(DEFAULT_FONT_SIZE))
+                .style(style::NormalBackgroundContainer(color_palette));
+
+        let description = Text::new(localized_string("inbox-description")).size(DEFAULT_FONT_SIZE);
+        let description_container =
+            Container::new(description).style(style::NormalBackgroundContainer(color_palette));
+
+        let inbox_str = config
+            .songs
+            .inbox
+            .directory
+            .as_ref()
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|| "-".to_string());
+        let inbox_row = directory_row(
+            color_palette,
+            &mut song_settings_state.inbox_directory_btn_state,
+            "inbox-directory",
+            Interaction::SelectInboxDirectory,
+            inbox_str,
+        );
+
+        let library_str = config
+            .songs
+            .inbox_library()
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|| "-".to_string());
+        let library_row = directory_row(
+            color_palette,
+            &mut song_settings_state.inbox_library_btn_state,
+            "inbox-library",
+            Interaction::SelectInboxLibrary,
+            library_str,
+        );
+
+        let mut column = Column::new()
+            .push(title_container)
+            .push(Space::new(Length::Units(0), Length::Units(5)))
+            .push(description_container)
+            .push(Space::new(Length::Units(0), Length::Units(5)))
+            .push(inbox_row)
+            .push(Space::new(Length::Units(0), Length::Units(5)))
+            .push(library_row);
+
+        // Latest imports, newest first.
+        if !song_settings_state.inbox_imports.is_empty() {
+            let mut imports_column = Column::new();
+
+            for import in song_settings_state.inbox_imports.iter().rev() {
+                let text = match &import.error {
+                    Some(error) => format!("✗ {}: {}", import.name, error),
+                    None => {
+                        let mut vars = HashMap::new();
+                        vars.insert("name".to_string(), import.name.clone());
+                        vars.insert("count".to_string(), import.songs.len().to_string());
+                        let fmt = localized_string("inbox-imported");
+
+                        format!("✓ {}", strfmt(&fmt, &vars).unwrap())
+                    }
+                };
+
+                imports_column = imports_column.push(Text::new(text).size(DEFAULT_FONT_SIZE));
+            }
+
+            let imports_container = Container::new(imports_column.spacing(2))
+                .padding(10)
+                .style(style::NormalForegroundContainer(color_palette));
+
+            column = column
+                .push(Space::new(Length::Units(0), Length::Units(5)))
+                .push(imports_container);
+        }
+
+        column
+    };
+
+    let organize_column = {
+        let title_container =
+            Container::new(Text::new(localized_string("organize-songs")).size(DEFAULT_FONT_SIZE))
+                .style(style::NormalBackgroundContainer(color_palette));
+
+        let template_input = TextInput::new(
+            &mut song_settings_state.organize_template_input_state,
+            organizer::DEFAULT_TEMPLATE,
+            &song_settings_state.organize_template,
+            Interaction::OrganizeTemplateChanged,
+        )
+        .size(DEFAULT_FONT_SIZE)
+        .padding(6)
+        .width(Length::Units(350))
+        .style(style::AddonsQueryInput(color_palette));
+
+        let template_input: Element<Interaction> = template_input.into();
+
+        let preview_button: Element<Interaction> = Button::new(
+            &mut song_settings_state.organize_preview_btn_state,
+            Text::new(localized_string("preview")).size(DEFAULT_FONT_SIZE),
+        )
+        .style(style::DefaultBoxedButton(color_palette))
+        .on_press(Interaction::PreviewOrganize)
+        .into();
+
+        let mut apply_button = Button::new(
+            &mut song_settings_state.organize_apply_btn_state,
+            Text::new(localized_string("apply")).size(DEFAULT_FONT_SIZE),
+        )
+        .style(style::DefaultBoxedButton(color_palette));
+
+        if matches!(&song_settings_state.organize_plans, Some(plans) if !plans.is_empty()) {
+            apply_button = apply_button.on_press(Interaction::ApplyOrganize);
+        }
+
+        let apply_button: Element<Interaction> = apply_button.into();
+
+        let input_row = Row::new()
+            .push(template_input.map(Message::Interaction))
+            .push(Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0)))
+            .push(preview_button.map(Message::Interaction))
+            .push(Space::new(Length::Units(5), Length::Units(0)))
+            .push(apply_button.map(Message::Interaction))
+            .align_items(Align::Center);
+
+        let description =
+            Text::new(localized_string("organize-songs-description")).size(DEFAULT_FONT_SIZE);
+        let description_container =
+            Container::new(description).style(style::NormalBackgroundContainer(color_palette));
+
+        let mut column = Column::new()
+            .push(title_container)
+            .push(Space::new(Length::Units(0), Length::Units(5)))
+            .push(description_container)
+            .push(Space::new(Length::Units(0), Length::Units(5)))
+            .push(input_row);
+
+        // Preview of the planned moves.
+        if let Some(plans) = &song_settings_state.organize_plans {
+            let mut vars = HashMap::new();
+            let count = plans.len().to_string();
+            vars.insert("number".to_string(), &count);
+            let fmt = localized_string("organize-songs-preview");
+
+            let mut preview_column =
+                Column::new().push(Text::new(strfmt(&fmt, &vars).unwrap()).size(DEFAULT_FONT_SIZE));
+
+            for plan in plans.iter().take(MAX_ORGANIZE_PREVIEW) {
+                let from = plan
+                    .from
+                    .strip_prefix(&plan.root)
+                    .unwrap_or(&plan.from)
+                    .display()
+                    .to_string();
+
+                preview_column = preview_column
+                    .push(Text::new(format!("{}  →  {}", from, plan.id())).size(DEFAULT_FONT_SIZE));
+            }
+
+            if plans.len() > MAX_ORGANIZE_PREVIEW {
+                preview_column = preview_column.push(
+                    Text::new(format!("… {}", plans.len() - MAX_ORGANIZE_PREVIEW))
+                        .size(DEFAULT_FONT_SIZE),
+                );
+            }
+
+            let preview_container = Container::new(preview_column.spacing(2))
+                .padding(10)
+                .style(style::NormalForegroundContainer(color_palette));
+
+            column = column
+                .push(Space::new(Length::Units(0), Length::Units(5)))
+                .push(preview_container);
+        }
+
+        column
+    };
+
     let theme_column = {
         let title_container =
             Container::new(Text::new(localized_string("theme")).size(DEFAULT_FONT_SIZE))
@@ -302,6 +722,18 @@ pub fn data_container<'a, 'b>(
         .style(style::BrightBackgroundContainer(color_palette))
         .into();
 
+        let checkbox_title = &localized_string("backup-songs")[..];
+        let songs_folder_checkbox: Element<_> = Container::new(
+            Checkbox::new(config.backup_songs, checkbox_title, move |is_checked| {
+                Interaction::ToggleBackupFolder(is_checked, BackupFolderKind::Songs)
+            })
+            .text_size(DEFAULT_FONT_SIZE)
+            .spacing(5)
+            .style(style::DefaultCheckbox(color_palette)),
+        )
+        .style(style::BrightBackgroundContainer(color_palette))
+        .into();
+
         let backup_compr_fmt_pick_list: Element<_> = PickList::new(
             default_backup_compression_format,
             &CompressionFormat::ALL[..],
@@ -366,7 +798,9 @@ pub fn data_container<'a, 'b>(
 
         let backup_directory_row = backup_directory_row
             .push(Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0)))
-            .push(config_folder_checkbox.map(Message::Interaction));
+            .push(config_folder_checkbox.map(Message::Interaction))
+            .push(Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0)))
+            .push(songs_folder_checkbox.map(Message::Interaction));
 
         // Data row for the Backup action selection.
         let backup_action_row = Row::new()
@@ -411,25 +845,41 @@ pub fn data_container<'a, 'b>(
 
             // Only show button as clickable if it's not currently backing up and
             // the wow folder is chosen and at least one of the folders is selected
-            // for backup
+            // for backup. Song libraries can be backed up without a wow folder.
+            let backup_songs = config.backup_songs && !config.songs.directories.is_empty();
             if !backup_state.backing_up
-                && config.wow.directories.keys().next().is_some()
-                && (config.backup_addons
-                    || config.backup_wtf
-                    || config.backup_screenshots
-                    || config.backup_config
-                    || config.backup_fonts)
+                && !backup_state.backing_up_songs
+                && ((config.wow.directories.keys().next().is_some()
+                    && (config.backup_addons
+                        || config.backup_wtf
+                        || config.backup_screenshots
+                        || config.backup_config
+                        || config.backup_fonts))
+                    || backup_songs)
             {
                 backup_button = backup_button.on_press(Interaction::Backup);
             }
 
-            let backup_status_text = if backup_state.backing_up {
+            let backup_status_text = if backup_state.backing_up || backup_state.backing_up_songs {
                 Text::new(localized_string("backup-progress"))
                     .size(DEFAULT_FONT_SIZE)
                     .vertical_alignment(VerticalAlignment::Center)
+            } else if let Some(summary) = &backup_state.last_song_backup {
+                let mut vars = HashMap::new();
+                vars.insert("songs".to_string(), summary.songs_changed.to_string());
+                vars.insert(
+                    "size".to_string(),
+                    format!("{:.1}", summary.copied_size as f64 / 1_000_000.0),
+                );
+                let fmt = localized_string("backup-songs-latest");
+
+                Text::new(strfmt(&fmt, &vars).unwrap())
+                    .size(DEFAULT_FONT_SIZE)
+                    .vertical_alignment(VerticalAlignment::Center)
             } else {
                 let as_of = backup_state
                     .last_backup
+                    .max(backup_state.last_snapshot)
                     .map(|d| d.format("%Y-%m-%d %H:%M:%S").to_string())
                     .unwrap_or_else(|| localized_string("backup-never"));
 
@@ -528,6 +978,555 @@ pub fn data_container<'a, 'b>(
         )
     };
 
+    let schedule_column = {
+        let schedule = config.backup_schedule;
+
+        let title_container =
+            Container::new(Text::new(localized_string("backup-schedule")).size(DEFAULT_FONT_SIZE))
+                .style(style::NormalBackgroundContainer(color_palette));
+
+        let interval_picklist: Element<Interaction> = PickList::new(
+            &mut backup_state.interval_state,
+            &BackupInterval::ALL[..],
+            Some(BackupInterval(schedule.interval_days)),
+            Interaction::BackupIntervalSelected,
+        )
+        .text_size(DEFAULT_FONT_SIZE)
+        .width(Length::Units(120))
+        .style(style::PickList(color_palette))
+        .into();
+
+        let on_startup_checkbox: Element<Interaction> = Checkbox::new(
+            schedule.on_startup,
+            localized_string("backup-on-startup"),
+            Interaction::ToggleBackupOnStartup,
+        )
+        .style(style::DefaultCheckbox(color_palette))
+        .text_size(DEFAULT_FONT_SIZE)
+        .spacing(5)
+        .into();
+        let on_startup_container = Container::new(on_startup_checkbox.map(Message::Interaction))
+            .style(style::NormalBackgroundContainer(color_palette));
+
+        let before_bulk_checkbox: Element<Interaction> = Checkbox::new(
+            schedule.before_bulk_changes,
+            localized_string("backup-before-bulk-changes"),
+            Interaction::ToggleBackupBeforeBulkChanges,
+        )
+        .style(style::DefaultCheckbox(color_palette))
+        .text_size(DEFAULT_FONT_SIZE)
+        .spacing(5)
+        .into();
+        let before_bulk_container = Container::new(before_bulk_checkbox.map(Message::Interaction))
+            .style(style::NormalBackgroundContainer(color_palette));
+
+        let settings_row = Row::new()
+            .push(interval_picklist.map(Message::Interaction))
+            .push(Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0)))
+            .push(on_startup_container)
+            .push(Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0)))
+            .push(before_bulk_container)
+            .align_items(Align::Center);
+
+        Column::new()
+            .push(title_container)
+            .push(Space::new(Length::Units(0), Length::Units(5)))
+            .push(settings_row)
+    };
+
+    let encryption_column = {
+        let title_container = Container::new(
+            Text::new(localized_string("backup-encryption")).size(DEFAULT_FONT_SIZE),
+        )
+        .style(style::NormalBackgroundContainer(color_palette));
+
+        let description =
+            Text::new(localized_string("backup-encryption-description")).size(DEFAULT_FONT_SIZE);
+        let description_container =
+            Container::new(description).style(style::NormalBackgroundContainer(color_palette));
+
+        let encrypt_checkbox: Element<Interaction> = Checkbox::new(
+            config.backup_encrypt,
+            localized_string("backup-encrypt"),
+            Interaction::ToggleBackupEncryption,
+        )
+        .style(style::DefaultCheckbox(color_palette))
+        .text_size(DEFAULT_FONT_SIZE)
+        .spacing(5)
+        .into();
+        let encrypt_container = Container::new(encrypt_checkbox.map(Message::Interaction))
+            .style(style::NormalBackgroundContainer(color_palette));
+
+        let passphrase_input: Element<Interaction> = TextInput::new(
+            &mut backup_state.passphrase_input_state,
+            &localized_string("backup-passphrase"),
+            &backup_state.passphrase,
+            Interaction::BackupPassphraseChanged,
+        )
+        .password()
+        .size(DEFAULT_FONT_SIZE)
+        .padding(6)
+        .width(Length::Units(200))
+        .style(style::AddonsQueryInput(color_palette))
+        .into();
+
+        let settings_row = Row::new()
+            .push(encrypt_container)
+            .push(Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0)))
+            .push(passphrase_input.map(Message::Interaction))
+            .align_items(Align::Center);
+
+        Column::new()
+            .push(title_container)
+            .push(Space::new(Length::Units(0), Length::Units(5)))
+            .push(description_container)
+            .push(Space::new(Length::Units(0), Length::Units(5)))
+            .push(settings_row)
+    };
+
+    let remote_column = {
+        let title_container =
+            Container::new(Text::new(localized_string("backup-remote")).size(DEFAULT_FONT_SIZE))
+                .style(style::NormalBackgroundContainer(color_palette));
+
+        let description =
+            Text::new(localized_string("backup-remote-description")).size(DEFAULT_FONT_SIZE);
+        let description_container =
+            Container::new(description).style(style::NormalBackgroundContainer(color_palette));
+
+        let destination = &config.backup_s3;
+        let settings_row = Row::new()
+            .push(s3_setting_row(
+                color_palette,
+                &mut backup_state.s3_endpoint_input_state,
+                S3Setting::Endpoint,
+                "backup-s3-endpoint",
+                &destination.endpoint,
+                200,
+            ))
+            .push(Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0)))
+            .push(s3_setting_row(
+                color_palette,
+                &mut backup_state.s3_bucket_input_state,
+                S3Setting::Bucket,
+                "backup-s3-bucket",
+                &destination.bucket,
+                120,
+            ))
+            .push(Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0)))
+            .push(s3_setting_row(
+                color_palette,
+                &mut backup_state.s3_prefix_input_state,
+                S3Setting::Prefix,
+                "backup-s3-prefix",
+                &destination.prefix,
+                120,
+            ))
+            .push(Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0)))
+            .push(s3_setting_row(
+                color_palette,
+                &mut backup_state.s3_region_input_state,
+                S3Setting::Region,
+                "backup-s3-region",
+                destination.region.as_deref().unwrap_or_default(),
+                100,
+            ))
+            .align_items(Align::Center);
+
+        let mut column = Column::new()
+            .push(title_container)
+            .push(Space::new(Length::Units(0), Length::Units(5)))
+            .push(description_container)
+            .push(Space::new(Length::Units(0), Length::Units(5)))
+            .push(settings_row);
+
+        let status = if backup_state.uploading {
+            Some(localized_string("backup-uploading"))
+        } else if let Some(remote) = &backup_state.last_upload {
+            let mut vars = HashMap::new();
+            vars.insert("key".to_string(), remote.key.clone());
+            vars.insert(
+                "size".to_string(),
+                format!("{:.1}", remote.size as f64 / 1_000_000.0),
+            );
+            let fmt = localized_string("backup-uploaded");
+
+            Some(strfmt(&fmt, &vars).unwrap())
+        } else {
+            None
+        };
+
+        if let Some(status) = status {
+            let status_container = Container::new(Text::new(status).size(DEFAULT_FONT_SIZE))
+                .style(style::NormalBackgroundContainer(color_palette));
+
+            column = column
+                .push(Space::new(Length::Units(0), Length::Units(5)))
+                .push(status_container);
+        }
+
+        column
+    };
+
+    let retention_column = {
+        let title_container =
+            Container::new(Text::new(localized_string("backup-retention")).size(DEFAULT_FONT_SIZE))
+                .style(style::NormalBackgroundContainer(color_palette));
+
+        let description =
+            Text::new(localized_string("backup-retention-description")).size(DEFAULT_FONT_SIZE);
+        let description_container =
+            Container::new(description).style(style::NormalBackgroundContainer(color_palette));
+
+        let retention = config.backup_retention;
+        let rules_row = Row::new()
+            .push(retention_rule_row(
+                color_palette,
+                &mut backup_state.keep_last_input_state,
+                RetentionRule::KeepLast,
+                "backup-keep-last",
+                retention.keep_last.map(|n| n.to_string()),
+            ))
+            .push(Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0)))
+            .push(retention_rule_row(
+                color_palette,
+                &mut backup_state.keep_daily_input_state,
+                RetentionRule::KeepDaily,
+                "backup-keep-daily",
+                retention.keep_daily.map(|n| n.to_string()),
+            ))
+            .push(Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0)))
+            .push(retention_rule_row(
+                color_palette,
+                &mut backup_state.keep_weekly_input_state,
+                RetentionRule::KeepWeekly,
+                "backup-keep-weekly",
+                retention.keep_weekly.map(|n| n.to_string()),
+            ))
+            .push(Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0)))
+            .push(retention_rule_row(
+                color_palette,
+                &mut backup_state.max_size_input_state,
+                RetentionRule::MaxSize,
+                "backup-max-size",
+                retention.max_size_mb.map(|n| n.to_string()),
+            ))
+            .align_items(Align::Center);
+
+        let mut preview_button = Button::new(
+            &mut backup_state.preview_prune_btn_state,
+            Text::new(localized_string("preview")).size(DEFAULT_FONT_SIZE),
+        )
+        .style(style::DefaultBoxedButton(color_palette));
+
+        let mut prune_button = Button::new(
+            &mut backup_state.prune_btn_state,
+            Text::new(localized_string("backup-prune")).size(DEFAULT_FONT_SIZE),
+        )
+        .style(style::DefaultBoxedButton(color_palette));
+
+        if retention.is_enabled() {
+            preview_button = preview_button.on_press(Interaction::PreviewPrune);
+        }
+
+        // Only prune after the user has seen what will be removed.
+        if matches!(&backup_state.prune_preview, Some(pruned) if !pruned.is_empty()) {
+            prune_button = prune_button.on_press(Interaction::PruneBackups);
+        }
+
+        let preview_button: Element<Interaction> = preview_button.into();
+        let prune_button: Element<Interaction> = prune_button.into();
+
+        let button_row = Row::new()
+            .push(preview_button.map(Message::Interaction))
+            .push(Space::new(Length::Units(5), Length::Units(0)))
+            .push(prune_button.map(Message::Interaction))
+            .align_items(Align::Center);
+
+        let mut column = Column::new()
+            .push(title_container)
+            .push(Space::new(Length::Units(0), Length::Units(5)))
+            .push(description_container)
+            .push(Space::new(Length::Units(0), Length::Units(5)))
+            .push(rules_row)
+            .push(Space::new(Length::Units(0), Length::Units(5)))
+            .push(button_row);
+
+        let status = match (&backup_state.prune_preview, &backup_state.pruned) {
+            (Some(pruned), _) => Some((pruned, "backup-prune-preview")),
+            (None, Some(pruned)) => Some((pruned, "backup-pruned")),
+            (None, None) => None,
+        };
+
+        if let Some((pruned, key)) = status {
+            let mut vars = HashMap::new();
+            vars.insert("number".to_string(), pruned.len().to_string());
+            vars.insert(
+                "size".to_string(),
+                format!("{:.1}", pruned.size() as f64 / 1_000_000.0),
+            );
+            let fmt = localized_string(key);
+
+            let mut status_column =
+                Column::new().push(Text::new(strfmt(&fmt, &vars).unwrap()).size(DEFAULT_FONT_SIZE));
+
+            if backup_state.prune_preview.is_some() {
+                for archive in pruned.archives.iter() {
+                    status_column =
+                        status_column.push(Text::new(archive.to_string()).size(DEFAULT_FONT_SIZE));
+                }
+                for snapshot in pruned.snapshots.iter() {
+                    status_column =
+                        status_column.push(Text::new(snapshot.to_string()).size(DEFAULT_FONT_SIZE));
+                }
+            }
+
+            let status_container = Container::new(status_column.spacing(2))
+                .padding(10)
+                .style(style::NormalForegroundContainer(color_palette));
+
+            column = column
+                .push(Space::new(Length::Units(0), Length::Units(5)))
+                .push(status_container);
+        }
+
+        column
+    };
+
+    let restore_column = {
+        let conflict = restore_state.conflict();
+
+        let title_container =
+            Container::new(Text::new(localized_string("restore")).size(DEFAULT_FONT_SIZE))
+                .style(style::NormalBackgroundContainer(color_palette));
+
+        let refresh_button: Element<Interaction> = Button::new(
+            &mut restore_state.refresh_btn_state,
+            Text::new(localized_string("restore-refresh")).size(DEFAULT_FONT_SIZE),
+        )
+        .style(style::DefaultBoxedButton(color_palette))
+        .on_press(Interaction::RefreshBackups)
+        .into();
+
+        let source_pick_list: Element<_> = PickList::new(
+            &mut restore_state.source_state,
+            restore_state.sources.clone(),
+            restore_state.source.clone(),
+            Interaction::BackupSourceSelected,
+        )
+        .text_size(DEFAULT_FONT_SIZE)
+        .width(Length::Units(350))
+        .style(style::PickList(color_palette))
+        .into();
+
+        let source_row = Row::new()
+            .push(refresh_button.map(Message::Interaction))
+            .push(Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0)))
+            .push(source_pick_list.map(Message::Interaction))
+            .align_items(Align::Center);
+
+        let mut column = Column::new()
+            .push(title_container)
+            .push(Space::new(Length::Units(0), Length::Units(5)))
+            .push(source_row);
+
+        if restore_state.source.is_some() {
+            // Folders of the selected backup. Nothing selected restores the
+            // whole backup.
+            let mut groups_scrollable = Scrollable::new(&mut restore_state.groups_scrollable_state)
+                .spacing(2)
+                .height(Length::Units(150))
+                .style(style::Scrollable(color_palette));
+
+            for group in restore_state.groups.iter() {
+                let mut vars = HashMap::new();
+                vars.insert("path".to_string(), group.path.clone());
+                vars.insert("files".to_string(), group.files.to_string());
+                vars.insert(
+                    "size".to_string(),
+                    format!("{:.1}", group.size as f64 / 1_000_000.0),
+                );
+                let fmt = localized_string("restore-group");
+
+                let path = group.path.clone();
+                let checkbox: Element<_> = Checkbox::new(
+                    restore_state.selection.contains(&group.path),
+                    strfmt(&fmt, &vars).unwrap(),
+                    move |is_checked| Interaction::ToggleRestoreGroup(path.clone(), is_checked),
+                )
+                .text_size(DEFAULT_FONT_SIZE)
+                .spacing(5)
+                .style(style::DefaultCheckbox(color_palette))
+                .into();
+
+                groups_scrollable = groups_scrollable.push(checkbox.map(Message::Interaction));
+            }
+
+            let groups_container = Container::new(groups_scrollable)
+                .padding(10)
+                .width(Length::Units(600))
+                .style(style::NormalForegroundContainer(color_palette));
+
+            let description =
+                Text::new(localized_string("restore-description")).size(DEFAULT_FONT_SIZE);
+            let description_container =
+                Container::new(description).style(style::NormalBackgroundContainer(color_palette));
+
+            // Where to restore to: where the files came from or another folder.
+            let original_button: Element<Interaction> = Button::new(
+                &mut restore_state.original_btn_state,
+                Text::new(localized_string("restore-original")).size(DEFAULT_FONT_SIZE),
+            )
+            .style(style::DefaultBoxedButton(color_palette))
+            .on_press(Interaction::RestoreToOriginal)
+            .into();
+
+            let directory_button: Element<Interaction> = Button::new(
+                &mut restore_state.directory_btn_state,
+                Text::new(localized_string("select-directory")).size(DEFAULT_FONT_SIZE),
+            )
+            .style(style::DefaultBoxedButton(color_palette))
+            .on_press(Interaction::SelectRestoreDirectory)
+            .into();
+
+            let target_str = restore_state
+                .directory
+                .as_ref()
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|| localized_string("restore-original-description"));
+            let target_text_container = Container::new(
+                Text::new(target_str)
+                    .size(DEFAULT_FONT_SIZE)
+                    .vertical_alignment(VerticalAlignment::Center),
+            )
+            .center_y()
+            .style(style::NormalBackgroundContainer(color_palette));
+
+            let target_row = Row::new()
+                .push(original_button.map(Message::Interaction))
+                .push(Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0)))
+                .push(directory_button.map(Message::Interaction))
+                .push(Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0)))
+                .push(target_text_container)
+                .align_items(Align::Center);
+
+            let conflict_text = Text::new(localized_string("restore-conflict"))
+                .size(DEFAULT_FONT_SIZE)
+                .vertical_alignment(VerticalAlignment::Center);
+            let conflict_text_container = Container::new(conflict_text)
+                .center_y()
+                .style(style::NormalBackgroundContainer(color_palette));
+
+            let conflict_pick_list: Element<_> = PickList::new(
+                &mut restore_state.conflict_state,
+                &ConflictPolicy::ALL[..],
+                Some(conflict),
+                Interaction::RestoreConflictSelected,
+            )
+            .text_size(DEFAULT_FONT_SIZE)
+            .width(Length::Units(120))
+            .style(style::PickList(color_palette))
+            .into();
+
+            let preview_button: Element<Interaction> = Button::new(
+                &mut restore_state.preview_btn_state,
+                Text::new(localized_string("preview")).size(DEFAULT_FONT_SIZE),
+            )
+            .style(style::DefaultBoxedButton(color_palette))
+            .on_press(Interaction::PreviewRestore)
+            .into();
+
+            let mut restore_button = Button::new(
+                &mut restore_state.restore_btn_state,
+                Text::new(localized_string("restore-now")).size(DEFAULT_FONT_SIZE),
+            )
+            .style(style::DefaultBoxedButton(color_palette));
+
+            // Only restore after the user has seen what will be written.
+            if !restore_state.restoring
+                && matches!(&restore_state.plan, Some(plan) if !plan.files.is_empty())
+            {
+                restore_button = restore_button.on_press(Interaction::Restore);
+            }
+
+            let restore_button: Element<Interaction> = restore_button.into();
+
+            let action_row = Row::new()
+                .push(conflict_text_container)
+                .push(Space::new(Length::Units(5), Length::Units(0)))
+                .push(conflict_pick_list.map(Message::Interaction))
+                .push(Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0)))
+                .push(preview_button.map(Message::Interaction))
+                .push(Space::new(Length::Units(5), Length::Units(0)))
+                .push(restore_button.map(Message::Interaction))
+                .align_items(Align::Center);
+
+            // Archives which failed verification may not restore completely.
+            if let Some(BackupSource::Archive(archive)) = &restore_state.source {
+                if let Some(verification) = archive.verification.as_ref().filter(|v| !v.is_ok()) {
+                    let mut vars = HashMap::new();
+                    vars.insert("issue".to_string(), verification.issues[0].to_string());
+                    let fmt = localized_string("restore-corrupt");
+
+                    let warning_container = Container::new(
+                        Text::new(strfmt(&fmt, &vars).unwrap()).size(DEFAULT_FONT_SIZE),
+                    )
+                    .style(style::NormalErrorForegroundContainer(color_palette));
+
+                    column = column
+                        .push(Space::new(Length::Units(0), Length::Units(5)))
+                        .push(warning_container);
+                }
+            }
+
+            column = column
+                .push(Space::new(Length::Units(0), Length::Units(5)))
+                .push(description_container)
+                .push(Space::new(Length::Units(0), Length::Units(5)))
+                .push(groups_container)
+                .push(Space::new(Length::Units(0), Length::Units(5)))
+                .push(target_row)
+                .push(Space::new(Length::Units(0), Length::Units(5)))
+                .push(action_row);
+
+            let status = if restore_state.restoring {
+                Some(localized_string("restore-progress"))
+            } else if let Some(plan) = &restore_state.plan {
+                let mut vars = HashMap::new();
+                vars.insert("files".to_string(), plan.files.len().to_string());
+                vars.insert(
+                    "size".to_string(),
+                    format!("{:.1}", plan.size() as f64 / 1_000_000.0),
+                );
+                vars.insert("conflicts".to_string(), plan.conflicts().to_string());
+                let fmt = localized_string("restore-plan");
+
+                Some(strfmt(&fmt, &vars).unwrap())
+            } else if let Some(summary) = &restore_state.summary {
+                let mut vars = HashMap::new();
+                vars.insert("restored".to_string(), summary.restored.to_string());
+                vars.insert("skipped".to_string(), summary.skipped.to_string());
+                vars.insert("renamed".to_string(), summary.renamed.to_string());
+                let fmt = localized_string("restore-summary");
+
+                Some(strfmt(&fmt, &vars).unwrap())
+            } else {
+                None
+            };
+
+            if let Some(status) = status {
+                let status_container = Container::new(Text::new(status).size(DEFAULT_FONT_SIZE))
+                    .style(style::NormalBackgroundContainer(color_palette));
+
+                column = column
+                    .push(Space::new(Length::Units(0), Length::Units(5)))
+                    .push(status_container);
+            }
+        }
+
+        column
+    };
+
     let auto_update_column = {
         let auto_update = config.auto_update;
         let checkbox = Checkbox::new(
@@ -636,10 +1635,99 @@ pub fn data_container<'a, 'b>(
             .push(Space::new(Length::Units(5), Length::Units(0)))
             .push(export_button.map(Message::Interaction));
 
-        Column::new()
+        let songs_description =
+            Text::new(localized_string("share-songs-title")).size(DEFAULT_FONT_SIZE);
+        let import_songs_button_title_container = Container::new(
+            Text::new(localized_string("share-addons-import")).size(DEFAULT_FONT_SIZE),
+        )
+        .center_x()
+        .align_x(Align::Center);
+        let import_songs_button: Element<Interaction> = Button::new(
+            &mut share_state.import_songs_btn_state,
+            import_songs_button_title_container,
+        )
+        .style(style::DefaultBoxedButton(color_palette))
+        .on_press(Interaction::ImportSongs)
+        .into();
+
+        let export_songs_button_title_container = Container::new(
+            Text::new(localized_string("share-addons-export")).size(DEFAULT_FONT_SIZE),
+        )
+        .center_x()
+        .align_x(Align::Center);
+        let export_songs_button: Element<Interaction> = Button::new(
+            &mut share_state.export_songs_btn_state,
+            export_songs_button_title_container,
+        )
+        .style(style::DefaultBoxedButton(color_palette))
+        .on_press(Interaction::ExportSongs)
+        .into();
+
+        let export_report_button_title_container = Container::new(
+            Text::new(localized_string("export-song-report")).size(DEFAULT_FONT_SIZE),
+        )
+        .center_x()
+        .align_x(Align::Center);
+        let export_report_button: Element<Interaction> = Button::new(
+            &mut share_state.export_report_btn_state,
+            export_report_button_title_container,
+        )
+        .style(style::DefaultBoxedButton(color_palette))
+        .on_press(Interaction::ExportSongReport)
+        .into();
+
+        let user_data_checkbox: Element<Interaction> = Checkbox::new(
+            share_state.with_user_data,
+            localized_string("share-songs-user-data"),
+            Interaction::ToggleShareUserData,
+        )
+        .style(style::DefaultCheckbox(color_palette))
+        .text_size(DEFAULT_FONT_SIZE)
+        .spacing(5)
+        .into();
+
+        let songs_row = Row::new()
+            .push(import_songs_button.map(Message::Interaction))
+            .push(Space::new(Length::Units(5), Length::Units(0)))
+            .push(export_songs_button.map(Message::Interaction))
+            .push(Space::new(Length::Units(5), Length::Units(0)))
+            .push(export_report_button.map(Message::Interaction))
+            .push(Space::new(Length::Units(10), Length::Units(0)))
+            .push(user_data_checkbox.map(Message::Interaction))
+            .align_items(Align::Center);
+
+        let mut column = Column::new()
             .push(description)
             .push(Space::new(Length::Units(0), Length::Units(5)))
             .push(row)
+            .push(Space::new(Length::Units(0), Length::Units(10)))
+            .push(songs_description)
+            .push(Space::new(Length::Units(0), Length::Units(5)))
+            .push(songs_row);
+
+        if share_state.songs_pending > 0 {
+            let mut vars = HashMap::new();
+            vars.insert("count".to_string(), share_state.songs_pending.to_string());
+            let fmt = localized_string("share-songs-pending");
+
+            column = column
+                .push(Space::new(Length::Units(0), Length::Units(5)))
+                .push(Text::new(strfmt(&fmt, &vars).unwrap()).size(DEFAULT_FONT_SIZE));
+        }
+
+        if !share_state.unresolved_songs.is_empty() {
+            column = column
+                .push(Space::new(Length::Units(0), Length::Units(5)))
+                .push(
+                    Text::new(localized_string("share-songs-unresolved")).size(DEFAULT_FONT_SIZE),
+                );
+
+            for name in share_state.unresolved_songs.iter() {
+                column = column.push(Text::new(name.clone()).size(DEFAULT_FONT_SIZE));
+            }
+        }
+
+        column
     };
 
     let config_column = {
@@ -704,6 +1792,11 @@ pub fn data_container<'a, 'b>(
     let general_settings_title_container = Container::new(general_settings_title)
         .style(style::BrightBackgroundContainer(color_palette));
 
+    let songs_settings_title =
+        Text::new(localized_string("song-directories")).size(DEFAULT_HEADER_FONT_SIZE);
+    let songs_settings_title_container =
+        Container::new(songs_settings_title).style(style::BrightBackgroundContainer(color_palette));
+
     let directories_settings_title =
         Text::new(localized_string("wow-directories")).size(DEFAULT_HEADER_FONT_SIZE);
     let directories_settings_title_container = Container::new(directories_settings_title)
@@ -865,6 +1958,19 @@ pub fn data_container<'a, 'b>(
         .push(wow_directory_column)
         .push(Space::new(Length::Units(0), Length::Units(20)));
 
+    // Songs
+    scrollable = scrollable
+        .push(songs_settings_title_container)
+        .push(Space::new(Length::Units(0), Length::Units(5)))
+        .push(song_directory_column)
+        .push(Space::new(Length::Units(0), Length::Units(10)))
+        .push(song_auto_update_column)
+        .push(Space::new(Length::Units(0), Length::Units(10)))
+        .push(song_inbox_column)
+        .push(Space::new(Length::Units(0), Length::Units(10)))
+        .push(organize_column)
+        .push(Space::new(Length::Units(0), Length::Units(20)));
+
     // Backup
     scrollable = scrollable
         .push(backup_title_row)
@@ -881,6 +1987,20 @@ pub fn data_container<'a, 'b>(
             .push(compression_level_column);
     }
 
+    if config.backup_directory.is_some() {
+        scrollable = scrollable
+            .push(Space::new(Length::Units(0), Length::Units(10)))
+            .push(schedule_column)
+            .push(Space::new(Length::Units(0), Length::Units(10)))
+            .push(encryption_column)
+            .push(Space::new(Length::Units(0), Length::Units(10)))
+            .push(remote_column)
+            .push(Space::new(Length::Units(0), Length::Units(10)))
+            .push(retention_column)
+            .push(Space::new(Length::Units(0), Length::Units(10)))
+            .push(restore_column);
+    }
+
     scrollable = scrollable.push(Space::new(Length::Units(0), Length::Units(30)));
 
     // Addons
@@ -1126,3 +2246,103 @@ pub fn data_container<'a, 'b>(
         .height(Length::Shrink)
         .style(style::NormalBackgroundContainer(color_palette))
 }
+
+/// Label and text input for a backup retention rule.
+fn retention_rule_row<'a>(
+    color_palette: ColorPalette,
+    input_state: &'a mut text_input::State,
+    rule: RetentionRule,
+    label: &str,
+    value: Option<String>,
+) -> Row<'a, Message> {
+    let label_container = Container::new(
+        Text::new(localized_string(label))
+            .size(DEFAULT_FONT_SIZE)
+            .vertical_alignment(VerticalAlignment::Center),
+    )
+    .center_y()
+    .style(style::NormalBackgroundContainer(color_palette));
+
+    let input: Element<Interaction> = TextInput::new(
+        input_state,
+        "-",
+        value.as_deref().unwrap_or_default(),
+        move |value| Interaction::RetentionChanged(rule, value),
+    )
+    .size(DEFAULT_FONT_SIZE)
+    .padding(6)
+    .width(Length::Units(60))
+    .style(style::AddonsQueryInput(color_palette))
+    .into();
+
+    Row::new()
+        .push(label_container)
+        .push(Space::new(Length::Units(5), Length::Units(0)))
+        .push(input.map(Message::Interaction))
+        .align_items(Align::Center)
+}
+
+/// Label and text input for a remote backup destination setting.
+fn s3_setting_row<'a>(
+    color_palette: ColorPalette,
+    input_state: &'a mut text_input::State,
+    setting: S3Setting,
+    label: &str,
+    value: &str,
+    width: u16,
+) -> Row<'a, Message> {
+    let label_container = Container::new(
+        Text::new(localized_string(label))
+            .size(DEFAULT_FONT_SIZE)
+            .vertical_alignment(VerticalAlignment::Center),
+    )
+    .center_y()
+    .style(style::NormalBackgroundContainer(color_palette));
+
+    let input: Element<Interaction> = TextInput::new(input_state, "-", value, move |value| {
+        Interaction::S3SettingChanged(setting, value)
+    })
+    .size(DEFAULT_FONT_SIZE)
+    .padding(6)
+    .width(Length::Units(width))
+    .style(style::AddonsQueryInput(color_palette))
+    .into();
+
+    Row::new()
+        .push(label_container)
+        .push(Space::new(Length::Units(5), Length::Units(0)))
+        .push(input.map(Message::Interaction))
+        .align_items(Align::Center)
+}
+
+/// Button picking a folder, next to the folder currently set.
+fn directory_row<'a>(
+    color_palette: ColorPalette,
+    btn_state: &'a mut button::State,
+    label: &str,
+    interaction: Interaction,
+    path: String,
+) -> Row<'a, Message> {
+    let title_container =
+        Container::new(Text::new(localized_string(label)).size(DEFAULT_FONT_SIZE))
+            .center_x()
+            .align_x(Align::Center);
+    let button: Element<Interaction> = Button::new(btn_state, title_container)
+        .style(style::DefaultBoxedButton(color_palette))
+        .on_press(interaction)
+        .into();
+
+    let path_text = Text::new(path)
+        .size(14)
+        .vertical_alignment(VerticalAlignment::Center);
+    let path_text_container = Container::new(path_text)
+        .height(Length::Units(25))
+        .center_y()
+        .style(style::NormalBackgroundContainer(color_palette));
+
+    Row::new()
+        .push(button.map(Message::Interaction))
+        .push(Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0)))
+        .push(path_text_container)
+        .align_items(Align::Center)
+}
@@ -0,0 +1,181 @@
+use {
+    super::{DEFAULT_FONT_SIZE, DEFAULT_HEADER_FONT_SIZE, DEFAULT_PADDING},
+    crate::gui::{style, Message},
+    crate::localization::localized_string,
+    ajour_core::{
+        stats::{by_count, format_playtime, LibraryStats},
+        theme::ColorPalette,
+    },
+    iced::{scrollable, Align, Column, Container, Length, Row, Scrollable, Space, Text},
+    std::collections::HashMap,
+    strfmt::strfmt,
+};
+
+/// Number of genres and charters shown, the rest are left out.
+const TOP_ENTRIES: usize = 15;
+
+/// Width of the longest bar.
+const BAR_WIDTH: usize = 300;
+
+pub fn data_container<'a>(
+    color_palette: ColorPalette,
+    scrollable_state: &'a mut scrollable::State,
+    stats: &LibraryStats,
+) -> Container<'a, Message> {
+    let title = Text::new(localized_string("statistics")).size(DEFAULT_HEADER_FONT_SIZE);
+    let title_container =
+        Container::new(title).style(style::BrightBackgroundContainer(color_palette));
+
+    let totals_text = {
+        let mut vars = HashMap::new();
+        vars.insert("songs".to_string(), stats.songs.to_string());
+        vars.insert("playtime".to_string(), format_playtime(stats.playtime));
+        vars.insert(
+            "size".to_string(),
+            format!("{:.1}", stats.disk_usage as f64 / 1_000_000.0),
+        );
+        let fmt = localized_string("statistics-totals");
+
+        Text::new(strfmt(&fmt, &vars).unwrap()).size(DEFAULT_FONT_SIZE)
+    };
+    let totals_container =
+        Container::new(totals_text).style(style::NormalBackgroundContainer(color_palette));
+
+    let mut scrollable = Scrollable::new(scrollable_state)
+        .spacing(1)
+        .height(Length::FillPortion(1))
+        .style(style::Scrollable(color_palette));
+
+    scrollable = scrollable
+        .push(title_container)
+        .push(Space::new(Length::Units(0), Length::Units(5)))
+        .push(totals_container);
+
+    let instruments = by_count(&stats.instruments)
+        .into_iter()
+        .map(|(instrument, count)| (instrument.to_string(), count))
+        .collect();
+    scrollable = push_counts(
+        scrollable,
+        color_palette,
+        localized_string("statistics-instruments"),
+        instruments,
+    );
+
+    let decades = stats
+        .decades
+        .iter()
+        .map(|(decade, count)| (format!("{}s", decade), *count))
+        .collect();
+    scrollable = push_counts(
+        scrollable,
+        color_palette,
+        localized_string("statistics-decades"),
+        decades,
+    );
+
+    let genres = by_count(&stats.genres)
+        .into_iter()
+        .take(TOP_ENTRIES)
+        .map(|(genre, count)| (genre.clone(), count))
+        .collect();
+    scrollable = push_counts(
+        scrollable,
+        color_palette,
+        localized_string("statistics-genres"),
+        genres,
+    );
+
+    let charters = by_count(&stats.charters)
+        .into_iter()
+        .take(TOP_ENTRIES)
+        .map(|(charter, count)| (charter.clone(), count))
+        .collect();
+    scrollable = push_counts(
+        scrollable,
+        color_palette,
+        localized_string("statistics-charters"),
+        charters,
+    );
+
+    for (instrument, histogram) in stats.difficulties.iter() {
+        let mut vars = HashMap::new();
+        vars.insert("instrument".to_string(), instrument.to_string());
+        let fmt = localized_string("statistics-difficulty");
+
+        let difficulties = histogram
+            .iter()
+            .map(|(difficulty, count)| (difficulty.to_string(), *count))
+            .collect();
+        scrollable = push_counts(
+            scrollable,
+            color_palette,
+            strfmt(&fmt, &vars).unwrap(),
+            difficulties,
+        );
+    }
+
+    scrollable = scrollable.push(Space::new(Length::Units(0), Length::Units(20)));
+
+    let col = Column::new().push(scrollable);
+    let row = Row::new()
+        .push(Space::new(Length::Units(DEFAULT_PADDING), Length::Units(0)))
+        .push(col);
+
+    // Returns the final container.
+    Container::new(row)
+        .center_x()
+        .width(Length::Fill)
+        .height(Length::Shrink)
+        .style(style::NormalBackgroundContainer(color_palette))
+        .padding(20)
+}
+
+/// Adds a titled breakdown to `scrollable`, with a bar for each count.
+fn push_counts<'a>(
+    scrollable: Scrollable<'a, Message>,
+    color_palette: ColorPalette,
+    title: String,
+    counts: Vec<(String, usize)>,
+) -> Scrollable<'a, Message> {
+    if counts.is_empty() {
+        return scrollable;
+    }
+
+    let title_container = Container::new(Text::new(title).size(DEFAULT_FONT_SIZE))
+        .style(style::BrightBackgroundContainer(color_palette));
+
+    let mut column = Column::new()
+        .push(title_container)
+        .push(Space::new(Length::Units(0), Length::Units(5)));
+
+    let max = counts.iter().map(|(_, count)| *count).max().unwrap_or(1);
+
+    for (name, count) in counts {
+        let bar = Container::new(Space::new(Length::Fill, Length::Fill))
+            .width(Length::Units(((count * BAR_WIDTH + max - 1) / max) as u16))
+            .height(Length::Units(10))
+            .style(style::BarContainer(color_palette));
+
+        let row = Row::new()
+            .push(
+                Text::new(name)
+                    .size(DEFAULT_FONT_SIZE)
+                    .width(Length::Units(200)),
+            )
+            .push(
+                Text::new(count.to_string())
+                    .size(DEFAULT_FONT_SIZE)
+                    .width(Length::Units(60)),
+            )
+            .push(bar)
+            .align_items(Align::Center);
+
+        column =
+            column.push(Container::new(row).style(style::NormalForegroundContainer(color_palette)));
+    }
+
+    scrollable
+        .push(Space::new(Length::Units(0), Length::Units(15)))
+        .push(column)
+}
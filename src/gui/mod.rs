@@ -9,18 +9,36 @@ use ajour_core::repository::RepositoryKind;
 use ajour_core::share;
 use ajour_core::{
     addon::{Addon, AddonFolder, AddonState},
+    autoupdate::{UpdatePlan, UpdateSummary},
+    backup::{BackupSchedule, LatestBackups, PrunedBackups},
     cache::catalog_download_latest_or_use_cache,
     cache::{
-        load_addon_cache, load_fingerprint_cache, AddonCache, AddonCacheEntry, FingerprintCache,
+        load_addon_cache, load_fingerprint_cache, load_song_cache, AddonCache, AddonCacheEntry,
+        FingerprintCache, SongCache, SongCacheEntry,
     },
     catalog::{self, Catalog, CatalogAddon},
+    clone_hero::{BadSongs, ScoreData},
     config::{ColumnConfig, ColumnConfigV2, Config, Flavor, Language},
     error::*,
     fs::PersistentData,
+    inbox::{InboxImport, INBOX_DEBOUNCE},
+    library::LIBRARY_DEBOUNCE,
+    organizer::{OrganizeResult, RenamePlan},
+    origin::{SongUpdates, UpdateResult},
+    pack::Pack,
     repository::{
         Changelog, CompressionFormat, GlobalReleaseChannel, ReleaseChannel, RepositoryPackage,
     },
+    restore::{BackupEntry, BackupSource, ConflictPolicy, EntryGroup, RestorePlan, RestoreSummary},
+    s3::RemoteBackup,
+    snapshot::SnapshotSummary,
+    song::{PlayFilter, Song},
+    stats::LibraryStats,
     theme::{load_user_themes, Theme},
+    transfer::{TransferMode, TransferResult},
+    userdata::{load_user_data, stars, UserData, MAX_RATING},
+    versions::{load_version_store, SongVersion, VersionStore},
+    watch::FolderWatcher,
 };
 use ajour_widgets::header;
 use async_std::sync::{Arc, Mutex};
@@ -32,7 +50,7 @@ use iced::{
 };
 use image::ImageFormat;
 use isahc::http::Uri;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::RwLock;
 use std::time::{Duration, Instant};
@@ -57,6 +75,8 @@ impl Default for State {
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Mode {
     MyAddons(Flavor),
+    MySongs,
+    Statistics,
     Catalog,
     Install,
     Settings,
@@ -68,7 +88,9 @@ impl Mode {
     fn next(&self, flavor: Flavor) -> Self {
         use Mode::*;
         match *self {
-            MyAddons(_) => Catalog,
+            MyAddons(_) => MySongs,
+            MySongs => Statistics,
+            Statistics => Catalog,
             Catalog => Install,
             Install => About,
             About => Settings,
@@ -81,7 +103,9 @@ impl Mode {
         use Mode::*;
         match *self {
             MyAddons(_) => Settings,
-            Catalog => MyAddons(flavor),
+            MySongs => MyAddons(flavor),
+            Catalog => Statistics,
+            Statistics => MySongs,
             Install => Catalog,
             About => Install,
             Settings => About,
@@ -93,6 +117,9 @@ impl Mode {
 pub enum Confirm {
     DeleteAddon,
     DeleteSavedVariables,
+    QuarantineSong,
+    MoveSongs,
+    DeletePack,
 }
 
 impl std::fmt::Display for Mode {
@@ -102,6 +129,8 @@ impl std::fmt::Display for Mode {
             "{}",
             match self {
                 Mode::MyAddons(_) => localized_string("my-addons"),
+                Mode::MySongs => localized_string("my-songs"),
+                Mode::Statistics => localized_string("statistics"),
                 Mode::Install => localized_string("install"),
                 Mode::Catalog => localized_string("catalog"),
                 Mode::Settings => localized_string("settings"),
@@ -171,12 +200,76 @@ pub enum Interaction {
     CompressionLevelChanged(i32),
     ExportAddons,
     ImportAddons,
+    ExportSongs,
+    ImportSongs,
+    ToggleShareUserData(bool),
+    ExportSongReport,
+    BackupIntervalSelected(BackupInterval),
+    ToggleBackupOnStartup(bool),
+    ToggleBackupBeforeBulkChanges(bool),
+    ToggleBackupEncryption(bool),
+    BackupPassphraseChanged(String),
+    S3SettingChanged(S3Setting, String),
+    RetentionChanged(RetentionRule, String),
+    PreviewPrune,
+    PruneBackups,
+    RefreshBackups,
+    BackupSourceSelected(BackupSource),
+    ToggleRestoreGroup(String, bool),
+    SelectRestoreDirectory,
+    RestoreToOriginal,
+    RestoreConflictSelected(ConflictPolicy),
+    PreviewRestore,
+    Restore,
+    SortSongColumn(SongColumnKey),
+    SongsQuery(String),
+    PlayFilterSelected(PlayFilter),
+    SelectSmartFilter(Option<usize>),
+    SmartFilterNameChanged(String),
+    SaveSmartFilter,
+    DeleteSmartFilter,
+    ExportSmartFilter,
+    ExpandSong(PathBuf),
+    SongTagInputChanged(String),
+    AddSongTag(PathBuf),
+    RemoveSongTag(PathBuf, String),
+    ToggleSongFavorite(PathBuf, bool),
+    SongRatingSelected(PathBuf, SongRating),
+    SongNotesChanged(String),
+    SaveSongNotes(PathBuf),
+    SelectSongDirectory,
+    RemoveSongDirectory(PathBuf),
+    SelectCloneHeroDataDirectory,
+    SelectInboxDirectory,
+    SelectInboxLibrary,
+    ValidateSong(PathBuf),
+    QuarantineSong,
+    ConfirmQuarantineSong(PathBuf),
+    OrganizeTemplateChanged(String),
+    PreviewOrganize,
+    ApplyOrganize,
+    SongTransferTargetSelected(SongDirectory),
+    ToggleSongSelected(PathBuf, bool),
+    SelectShownSongs,
+    ClearSongSelection,
+    TransferSongs(TransferMode),
+    ExpandPack(PathBuf),
+    InstallPack,
+    UpdatePack(PathBuf),
+    DeletePack(),
+    ConfirmDeletePack(PathBuf),
+    ToggleSongAutoUpdate(PathBuf, bool),
+    SongAutoUpdateIntervalSelected(UpdateInterval),
+    ToggleSongQuietHours(bool),
+    RollbackSong(PathBuf, String),
+    SongQuietHoursStartSelected(Hour),
+    SongQuietHoursEndSelected(Hour),
 }
 
 #[derive(Debug)]
 #[allow(clippy::large_enum_variant)]
 pub enum Message {
-    CachesLoaded(Result<(FingerprintCache, AddonCache)>),
+    CachesLoaded(Result<(FingerprintCache, AddonCache, SongCache)>),
     DownloadedAddon((DownloadReason, Flavor, String, Result<(), DownloadError>)),
     Error(anyhow::Error),
     Interaction(Interaction),
@@ -199,8 +292,19 @@ pub enum Message {
     UpdateWowDirectory((Option<PathBuf>, Option<Flavor>)),
     UpdateBackupDirectory(Option<PathBuf>),
     RuntimeEvent(iced_native::Event),
-    LatestBackup(Option<NaiveDateTime>),
+    LatestBackup(LatestBackups),
     BackupFinished(Result<NaiveDateTime, FilesystemError>),
+    BackupUploaded(Result<RemoteBackup, RemoteBackupError>),
+    SongsBackedUp(Result<SnapshotSummary, FilesystemError>),
+    LibraryStatsComputed(LibraryStats),
+    PrunePreviewed(Result<PrunedBackups, FilesystemError>),
+    BackupsPruned(Result<PrunedBackups, FilesystemError>),
+    BackupSourcesLoaded(Result<Vec<BackupSource>, FilesystemError>),
+    BackupEntriesLoaded(Result<Vec<BackupEntry>, FilesystemError>),
+    UpdateRestoreDirectory(Option<PathBuf>),
+    RestorePlanned(Result<RestorePlan, FilesystemError>),
+    Restored(Result<RestoreSummary, FilesystemError>),
+    RestoredConfigLoaded(Result<Config, FilesystemError>),
     CatalogDownloaded(Result<Catalog, DownloadError>),
     InstallAddonFetched((Flavor, String, Result<Addon, RepositoryError>)),
     AddonCacheUpdated(Result<AddonCacheEntry, CacheError>),
@@ -214,6 +318,44 @@ pub enum Message {
     AddonsExported(Result<(), FilesystemError>),
     ImportAddons(Option<PathBuf>),
     ImportParsed(Result<HashMap<Flavor, share::Parsed>, FilesystemError>),
+    ExportSongs(Option<PathBuf>),
+    SongsExported(Result<(), FilesystemError>),
+    ImportSongs(Option<PathBuf>),
+    ImportSongsParsed(Result<share::ParsedSongs, FilesystemError>),
+    SharedSongInstalled(Result<Song, DownloadError>),
+    ExportSongReport(Option<PathBuf>),
+    SongReportExported(Result<(), FilesystemError>),
+    ParsedSongs((PathBuf, Result<Vec<Song>, ParseError>)),
+    ScoreDataLoaded(Result<ScoreData, ParseError>),
+    UpdateSongDirectory(Option<PathBuf>),
+    UpdateCloneHeroDataDirectory(Option<PathBuf>),
+    UpdateInboxDirectory(Option<PathBuf>),
+    UpdateInboxLibrary(Option<PathBuf>),
+    InboxChanged(Vec<PathBuf>),
+    LibraryChanged((PathBuf, Vec<PathBuf>)),
+    SongFoldersRead((PathBuf, Vec<PathBuf>, Result<Vec<Song>, ParseError>)),
+    InboxImported((PathBuf, Result<InboxImport, FilesystemError>)),
+    SmartFilterExported(Result<PathBuf, FilesystemError>),
+    BadSongsLoaded(Result<BadSongs, ParseError>),
+    SongValidated(Song),
+    SongQuarantined((Song, Result<PathBuf, FilesystemError>)),
+    SongCacheEntryRemoved(Result<Option<SongCacheEntry>, CacheError>),
+    SongsOrganized(Result<OrganizeResult, FilesystemError>),
+    SongTransferSpaceChecked((TransferMode, Vec<Song>, Result<u64, FilesystemError>)),
+    SongsTransferred((TransferMode, Result<TransferResult, FilesystemError>)),
+    ParsedPacks((PathBuf, Result<Vec<Pack>, ParseError>)),
+    InstallPack(Option<PathBuf>),
+    PackInstalled(Result<Pack, DownloadError>),
+    PackDeleted(Result<Pack, FilesystemError>),
+    SongsUpdated(Result<UpdateResult, DownloadError>),
+    AutoUpdateSongs(Instant),
+    CheckScheduledBackup(Instant),
+    SongUpdatesPlanned(Result<UpdatePlan, ParseError>),
+    SongsAutoUpdated(UpdateSummary),
+    VersionStoreLoaded(Result<VersionStore, FilesystemError>),
+    UserDataLoaded(Result<UserData, FilesystemError>),
+    SongVersionsLoaded((PathBuf, Vec<SongVersion>)),
+    SongRolledBack(Result<Song, FilesystemError>),
 }
 
 pub struct Ajour {
@@ -262,6 +404,32 @@ pub struct Ajour {
     zstd_compression_level_slider_state: slider::State,
     share_state: ShareState,
     status_button_state: button::State,
+    songs: Vec<Song>,
+    packs: Vec<Pack>,
+    expanded_packs: HashSet<PathBuf>,
+    install_pack_btn_state: button::State,
+    song_cache: Option<Arc<Mutex<SongCache>>>,
+    version_store: Option<Arc<Mutex<VersionStore>>>,
+    score_data: ScoreData,
+    user_data: UserData,
+    bad_songs: BadSongs,
+    song_updates: SongUpdates,
+    songs_scrollable_state: scrollable::State,
+    song_header_state: SongHeaderState,
+    songs_search_state: SongsSearchState,
+    smart_filters_state: SmartFiltersState,
+    expanded_song: Option<PathBuf>,
+    expanded_song_versions: Vec<SongVersionState>,
+    song_user_data_state: SongUserDataState,
+    song_settings_state: SongSettingsState,
+    restore_state: RestoreState,
+    song_transfer_state: SongTransferState,
+    /// Songs selected to be copied or moved to another library root.
+    transfer_selection: HashSet<PathBuf>,
+    my_songs_btn_state: button::State,
+    library_stats: Option<LibraryStats>,
+    statistics_scrollable_state: scrollable::State,
+    statistics_btn_state: button::State,
 }
 
 impl Default for Ajour {
@@ -321,6 +489,31 @@ impl Default for Ajour {
             zstd_compression_level_slider_state: Default::default(),
             share_state: Default::default(),
             status_button_state: Default::default(),
+            songs: Default::default(),
+            packs: Default::default(),
+            expanded_packs: Default::default(),
+            install_pack_btn_state: Default::default(),
+            song_cache: None,
+            version_store: None,
+            score_data: Default::default(),
+            user_data: Default::default(),
+            bad_songs: Default::default(),
+            song_updates: Default::default(),
+            songs_scrollable_state: Default::default(),
+            song_header_state: Default::default(),
+            songs_search_state: Default::default(),
+            smart_filters_state: Default::default(),
+            expanded_song: None,
+            expanded_song_versions: Default::default(),
+            song_user_data_state: Default::default(),
+            song_settings_state: Default::default(),
+            restore_state: Default::default(),
+            song_transfer_state: Default::default(),
+            transfer_selection: Default::default(),
+            my_songs_btn_state: Default::default(),
+            library_stats: None,
+            statistics_scrollable_state: Default::default(),
+            statistics_btn_state: Default::default(),
         }
     }
 }
@@ -333,6 +526,11 @@ impl Application for Ajour {
     fn new(config: Config) -> (Self, Command<Message>) {
         let init_commands = vec![
             Command::perform(load_caches(), Message::CachesLoaded),
+            Command::perform(
+                load_version_store(config.songs.versions_max_size()),
+                Message::VersionStoreLoaded,
+            ),
+            Command::perform(load_user_data(), Message::UserDataLoaded),
             Command::perform(load_user_themes(), Message::ThemesLoaded),
             Command::perform(
                 catalog_download_latest_or_use_cache(),
@@ -382,12 +580,43 @@ impl Application for Ajour {
             iced_futures::time::every(Duration::from_secs(60 * 5)).map(Message::RefreshCatalog);
         let check_updates_subscription = iced_futures::time::every(Duration::from_secs(60 * 30))
             .map(Message::CheckRepositoryUpdates);
+        let auto_update_songs_subscription =
+            iced_futures::time::every(Duration::from_secs(60 * 10)).map(Message::AutoUpdateSongs);
+        let scheduled_backup_subscription = iced_futures::time::every(Duration::from_secs(60 * 10))
+            .map(Message::CheckScheduledBackup);
 
-        iced::Subscription::batch(vec![
+        let mut subscriptions = vec![
             runtime_subscription,
             catalog_subscription,
             check_updates_subscription,
-        ])
+            auto_update_songs_subscription,
+            scheduled_backup_subscription,
+        ];
+
+        // Songs changed outside of CHmon are read again as soon as they change.
+        for root in self.config.songs.directories.iter() {
+            let watch = FolderWatch {
+                id: "library",
+                path: root.clone(),
+                debounce: LIBRARY_DEBOUNCE,
+            };
+
+            subscriptions.push(Subscription::from_recipe(watch).map(Message::LibraryChanged));
+        }
+
+        if let Some(inbox) = self.config.songs.inbox.directory.clone() {
+            let watch = FolderWatch {
+                id: "inbox",
+                path: inbox,
+                debounce: INBOX_DEBOUNCE,
+            };
+
+            subscriptions.push(
+                Subscription::from_recipe(watch).map(|(_, changes)| Message::InboxChanged(changes)),
+            );
+        }
+
+        iced::Subscription::batch(subscriptions)
     }
 
     fn update(&mut self, message: Message, _clipboard: &mut Clipboard) -> Command<Message> {
@@ -437,11 +666,14 @@ impl Application for Ajour {
             &mut self.about_btn_state,
             &mut self.catalog_mode_btn_state,
             &mut self.install_mode_btn_state,
+            &mut self.my_songs_btn_state,
+            &mut self.statistics_btn_state,
             &mut self.flavor_picklist_state,
         );
 
         let column_config = self.header_state.column_config();
         let catalog_column_config = self.catalog_header_state.column_config();
+        let song_column_config = self.song_header_state.column_config();
 
         // This column gathers all the other elements together.
         let mut content = Column::new().push(menu_container);
@@ -547,6 +779,209 @@ impl Application for Ajour {
                         .push(bottom_space)
                 }
             }
+            Mode::MySongs => {
+                let has_songs = !self.songs.is_empty() || !self.packs.is_empty();
+
+                let query = self.songs_search_state.query.clone();
+                let play_filter = self.songs_search_state.play_filter;
+
+                // Menu for songs.
+                let menu_songs_container = element::my_songs::menu_container(
+                    color_palette,
+                    &mut self.update_all_btn_state,
+                    &mut self.refresh_btn_state,
+                    &mut self.songs_search_state,
+                    &mut self.song_transfer_state,
+                    self.transfer_selection.len(),
+                    &mut self.install_pack_btn_state,
+                    &self.config.songs.directories,
+                    &self.pending_confirmation,
+                    &self.state,
+                    &self.songs,
+                );
+                content = content.push(menu_songs_container);
+
+                let songs_count = self.songs.len();
+
+                let song_row_titles = element::my_songs::titles_row_header(
+                    color_palette,
+                    &self.songs,
+                    &mut self.song_header_state.state,
+                    &mut self.song_header_state.columns,
+                    self.song_header_state.previous_column_key,
+                    self.song_header_state.previous_sort_direction,
+                );
+
+                let mut songs_scrollable = Scrollable::new(&mut self.songs_scrollable_state)
+                    .spacing(1)
+                    .height(Length::FillPortion(1))
+                    .style(style::Scrollable(color_palette));
+
+                // Songs inside a pack are listed below the pack, the rest
+                // after all packs.
+                let mut pack_songs: Vec<Vec<&mut Song>> =
+                    self.packs.iter().map(|_| vec![]).collect();
+                let mut loose_songs = vec![];
+
+                let smart_filters_state = &self.smart_filters_state;
+
+                for song in self
+                    .songs
+                    .iter_mut()
+                    .filter(|s| query.is_none() || s.fuzzy_score.is_some())
+                    .filter(|s| play_filter.matches(s))
+                    .filter(|s| smart_filters_state.matches(s))
+                {
+                    match self.packs.iter().position(|p| p.contains(&song.path)) {
+                        Some(pack_idx) => pack_songs[pack_idx].push(song),
+                        None => loose_songs.push(song),
+                    }
+                }
+
+                // Packs without matching songs are hidden while filtering.
+                let is_filtered = query.is_some()
+                    || play_filter != PlayFilter::All
+                    || self.smart_filters_state.selected.is_some();
+
+                // Tabs for the saved smart filters.
+                let smart_filters_container = element::my_songs::smart_filters_container(
+                    color_palette,
+                    &mut self.smart_filters_state,
+                    &self.config.songs.smart_filters,
+                    songs_count,
+                    query.is_some(),
+                );
+
+                let title_width = song_column_config
+                    .iter()
+                    .find(|(key, _, _)| *key == SongColumnKey::Title)
+                    .map(|(_, width, _)| *width)
+                    .unwrap_or(Length::Fill);
+
+                let mut idx = 0;
+
+                // Versions and user data are only shown for the expanded song.
+                let mut song_versions = Some(&mut self.expanded_song_versions[..]);
+                let mut song_user_data_state = Some(&mut self.song_user_data_state);
+                // Songs can only be selected for a transfer to another library.
+                let selected_songs = if self.config.songs.directories.len() > 1 {
+                    Some(&self.transfer_selection)
+                } else {
+                    None
+                };
+
+                for (pack, songs) in self.packs.iter_mut().zip(pack_songs) {
+                    if is_filtered && songs.is_empty() {
+                        continue;
+                    }
+
+                    let is_pack_expanded = self.expanded_packs.contains(&pack.path);
+
+                    let is_odd = if self.config.alternating_row_colors {
+                        Some(idx % 2 != 0)
+                    } else {
+                        None
+                    };
+                    idx += 1;
+
+                    let pack_data_cell = element::my_songs::pack_row_container(
+                        color_palette,
+                        pack,
+                        songs.len(),
+                        is_pack_expanded,
+                        title_width,
+                        is_odd,
+                        &self.pending_confirmation,
+                    );
+
+                    songs_scrollable = songs_scrollable.push(pack_data_cell);
+
+                    if !is_pack_expanded {
+                        continue;
+                    }
+
+                    for song in songs {
+                        let is_song_expanded = self.expanded_song.as_ref() == Some(&song.path);
+                        let (versions, user_data_state) = if is_song_expanded {
+                            (
+                                song_versions.take().unwrap_or_default(),
+                                song_user_data_state.take(),
+                            )
+                        } else {
+                            Default::default()
+                        };
+
+                        let is_odd = if self.config.alternating_row_colors {
+                            Some(idx % 2 != 0)
+                        } else {
+                            None
+                        };
+                        idx += 1;
+
+                        let is_selected =
+                            selected_songs.map(|selected| selected.contains(&song.path));
+                        let song_data_cell = element::my_songs::data_row_container(
+                            color_palette,
+                            song,
+                            is_song_expanded,
+                            is_selected,
+                            versions,
+                            user_data_state,
+                            &song_column_config,
+                            is_odd,
+                            &self.pending_confirmation,
+                        );
+
+                        songs_scrollable = songs_scrollable.push(song_data_cell);
+                    }
+                }
+
+                for song in loose_songs {
+                    let is_song_expanded = self.expanded_song.as_ref() == Some(&song.path);
+                    let (versions, user_data_state) = if is_song_expanded {
+                        (
+                            song_versions.take().unwrap_or_default(),
+                            song_user_data_state.take(),
+                        )
+                    } else {
+                        Default::default()
+                    };
+
+                    let is_odd = if self.config.alternating_row_colors {
+                        Some(idx % 2 != 0)
+                    } else {
+                        None
+                    };
+                    idx += 1;
+
+                    let is_selected = selected_songs.map(|selected| selected.contains(&song.path));
+                    let song_data_cell = element::my_songs::data_row_container(
+                        color_palette,
+                        song,
+                        is_song_expanded,
+                        is_selected,
+                        versions,
+                        user_data_state,
+                        &song_column_config,
+                        is_odd,
+                        &self.pending_confirmation,
+                    );
+
+                    songs_scrollable = songs_scrollable.push(song_data_cell);
+                }
+
+                // Bottom space below the scrollable.
+                let bottom_space =
+                    Space::new(Length::FillPortion(1), Length::Units(DEFAULT_PADDING));
+
+                if has_songs {
+                    content = content
+                        .push(smart_filters_container)
+                        .push(song_row_titles)
+                        .push(songs_scrollable)
+                        .push(bottom_space)
+                }
+            }
             Mode::Install => {
                 let query = self
                     .install_from_scm_state
@@ -884,10 +1319,27 @@ impl Application for Ajour {
                     &mut self.localization_picklist_state,
                     &mut self.wow_directories,
                     &mut self.share_state,
+                    &mut self.song_settings_state,
+                    &mut self.restore_state,
                 );
 
                 content = content.push(settings_container)
             }
+            Mode::Statistics => {
+                let is_ready = matches!(self.state.get(&Mode::Statistics), Some(State::Ready));
+
+                if let (true, Some(stats)) = (is_ready, &self.library_stats) {
+                    if stats.songs > 0 {
+                        let statistics_container = element::statistics::data_container(
+                            color_palette,
+                            &mut self.statistics_scrollable_state,
+                            stats,
+                        );
+
+                        content = content.push(statistics_container)
+                    }
+                }
+            }
             Mode::About => {
                 let about_container = element::about::data_container(
                     color_palette,
@@ -966,6 +1418,68 @@ impl Application for Ajour {
                     )),
                 }
             }
+            Mode::MySongs => match self.state.get(&Mode::MySongs) {
+                Some(State::Loading) => Some(element::status::data_container(
+                    color_palette,
+                    &localized_string("loading")[..],
+                    &localized_string("parsing-songs")[..],
+                    None,
+                )),
+                Some(State::Ready) if self.songs.is_empty() => {
+                    Some(element::status::data_container(
+                        color_palette,
+                        &localized_string("woops")[..],
+                        &localized_string("no-songs")[..],
+                        None,
+                    ))
+                }
+                Some(State::Ready) => None,
+                Some(State::Error(error)) => {
+                    let error_title = format!("{}", error);
+                    let mut error_description = String::new();
+                    let cause = error.chain().last();
+
+                    if let Some(cause) = cause {
+                        error_description.push_str(&format!("Caused by {}", cause)[..]);
+                    }
+
+                    Some(element::status::data_container(
+                        color_palette,
+                        &error_title,
+                        &error_description,
+                        Some((
+                            &mut self.status_button_state,
+                            localized_string("retry"),
+                            Interaction::Refresh(Mode::MySongs),
+                        )),
+                    ))
+                }
+                None => Some(element::status::data_container(
+                    color_palette,
+                    &localized_string("setup-title")[..],
+                    &localized_string("setup-songs-description")[..],
+                    Some((
+                        &mut self.status_button_state,
+                        localized_string("select-directory"),
+                        Interaction::SelectSongDirectory,
+                    )),
+                )),
+            },
+            Mode::Statistics => match self.state.get(&Mode::Statistics) {
+                Some(State::Loading) => Some(element::status::data_container(
+                    color_palette,
+                    &localized_string("loading")[..],
+                    &localized_string("counting-songs")[..],
+                    None,
+                )),
+                _ if self.songs.is_empty() => Some(element::status::data_container(
+                    color_palette,
+                    &localized_string("woops")[..],
+                    &localized_string("no-songs")[..],
+                    None,
+                )),
+                _ => None,
+            },
             Mode::Settings => None,
             Mode::About => None,
             Mode::Install => None,
@@ -1016,6 +1530,54 @@ impl Application for Ajour {
     }
 }
 
+/// Watches a folder and everything below it, reporting the folder with the
+/// paths which changed in it.
+struct FolderWatch {
+    /// Tells apart watches of the same folder for different purposes.
+    id: &'static str,
+    path: PathBuf,
+    debounce: Duration,
+}
+
+impl<H, I> iced_native::subscription::Recipe<H, I> for FolderWatch
+where
+    H: std::hash::Hasher,
+{
+    type Output = (PathBuf, Vec<PathBuf>);
+
+    fn hash(&self, state: &mut H) {
+        use std::hash::Hash;
+
+        std::any::TypeId::of::<Self>().hash(state);
+        self.id.hash(state);
+        self.path.hash(state);
+    }
+
+    fn stream(
+        self: Box<Self>,
+        _input: iced_futures::BoxStream<I>,
+    ) -> iced_futures::BoxStream<Self::Output> {
+        use futures::StreamExt;
+
+        match FolderWatcher::new(&self.path, true, self.debounce) {
+            Ok(watcher) => {
+                let path = self.path;
+
+                Box::pin(
+                    watcher
+                        .into_stream()
+                        .map(move |changes| (path.clone(), changes)),
+                )
+            }
+            Err(error) => {
+                log::error!("failed to watch {:?}: {}", &self.path, error);
+
+                Box::pin(futures::stream::empty())
+            }
+        }
+    }
+}
+
 /// Starts the GUI.
 /// This function does not return.
 pub fn run(opts: Opts, config: Config) {
@@ -1878,6 +2440,15 @@ impl Default for ScaleState {
 pub struct ShareState {
     import_btn_state: button::State,
     export_btn_state: button::State,
+    import_songs_btn_state: button::State,
+    export_songs_btn_state: button::State,
+    export_report_btn_state: button::State,
+    /// Imported songs which are still being downloaded.
+    songs_pending: usize,
+    /// Imported songs which couldn't be downloaded.
+    unresolved_songs: Vec<String>,
+    /// Export and import the tags, ratings and notes of the listed songs.
+    with_user_data: bool,
 }
 
 #[allow(clippy::upper_case_acronyms)]
@@ -1888,15 +2459,487 @@ pub enum BackupFolderKind {
     Config,
     Screenshots,
     Fonts,
+    Songs,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum RetentionRule {
+    KeepLast,
+    KeepDaily,
+    KeepWeekly,
+    MaxSize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum S3Setting {
+    Endpoint,
+    Bucket,
+    Prefix,
+    Region,
 }
 
 #[derive(Default)]
 pub struct BackupState {
     backing_up: bool,
+    /// When the latest archive was saved.
     last_backup: Option<NaiveDateTime>,
+    backing_up_songs: bool,
+    last_song_backup: Option<SnapshotSummary>,
+    /// When the latest song library snapshot was made.
+    last_snapshot: Option<NaiveDateTime>,
     directory_btn_state: button::State,
     backup_now_btn_state: button::State,
     open_directory_btn_state: button::State,
+    /// When an archive was last started, successful or not.
+    last_attempt: Option<NaiveDateTime>,
+    /// When a song library snapshot was last started, successful or not.
+    last_snapshot_attempt: Option<NaiveDateTime>,
+    /// Set once the schedule was checked on startup.
+    schedule_checked: bool,
+    /// Bulk changes waiting for a backup to finish.
+    deferred: Vec<Message>,
+    running_deferred: bool,
+    interval_state: pick_list::State<BackupInterval>,
+    keep_last_input_state: text_input::State,
+    keep_daily_input_state: text_input::State,
+    keep_weekly_input_state: text_input::State,
+    max_size_input_state: text_input::State,
+    preview_prune_btn_state: button::State,
+    prune_btn_state: button::State,
+    /// Archives and snapshots the retention rules would remove.
+    prune_preview: Option<PrunedBackups>,
+    /// Archives and snapshots removed by the last prune.
+    pruned: Option<PrunedBackups>,
+    /// Passphrase for encrypted backups. Only kept in memory.
+    passphrase: String,
+    passphrase_input_state: text_input::State,
+    uploading: bool,
+    /// Archive uploaded by the last backup.
+    last_upload: Option<RemoteBackup>,
+    s3_endpoint_input_state: text_input::State,
+    s3_bucket_input_state: text_input::State,
+    s3_prefix_input_state: text_input::State,
+    s3_region_input_state: text_input::State,
+}
+
+impl BackupState {
+    /// Returns the passphrase for encrypted backups, if one was entered.
+    pub fn passphrase(&self) -> Option<String> {
+        if self.passphrase.is_empty() {
+            None
+        } else {
+            Some(self.passphrase.clone())
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct RestoreState {
+    refresh_btn_state: button::State,
+    /// Backups in the backup directory, newest first.
+    sources: Vec<BackupSource>,
+    source: Option<BackupSource>,
+    source_state: pick_list::State<BackupSource>,
+    /// Folders of the selected backup.
+    groups: Vec<EntryGroup>,
+    groups_scrollable_state: scrollable::State,
+    /// Folders selected for restoring. Nothing selected restores everything.
+    selection: HashSet<String>,
+    /// Folder to restore to, `None` restores to where the files came from.
+    directory: Option<PathBuf>,
+    directory_btn_state: button::State,
+    original_btn_state: button::State,
+    conflict: Option<ConflictPolicy>,
+    conflict_state: pick_list::State<ConflictPolicy>,
+    preview_btn_state: button::State,
+    restore_btn_state: button::State,
+    plan: Option<RestorePlan>,
+    restoring: bool,
+    summary: Option<RestoreSummary>,
+}
+
+impl RestoreState {
+    /// Returns the selected conflict policy, skipping existing files by
+    /// default.
+    pub fn conflict(&self) -> ConflictPolicy {
+        self.conflict.unwrap_or(ConflictPolicy::Skip)
+    }
+}
+
+pub struct SongsSearchState {
+    pub query: Option<String>,
+    pub query_state: text_input::State,
+    pub play_filter: PlayFilter,
+    pub play_filter_state: pick_list::State<PlayFilter>,
+}
+
+impl Default for SongsSearchState {
+    fn default() -> Self {
+        SongsSearchState {
+            query: Default::default(),
+            query_state: Default::default(),
+            play_filter: Default::default(),
+            play_filter_state: Default::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Hash, Eq)]
+pub enum SongColumnKey {
+    Title,
+    Artist,
+    Album,
+    Genre,
+    Year,
+    Charter,
+    Length,
+    PlayCount,
+    BestScore,
+    Stars,
+    Accuracy,
+    Tags,
+    Rating,
+    // Only used for sorting, not an actual visible column that can be shown
+    FuzzyScore,
+}
+
+impl SongColumnKey {
+    fn title(self) -> String {
+        use SongColumnKey::*;
+
+        match self {
+            Title => localized_string("song"),
+            Artist => localized_string("artist"),
+            Album => localized_string("album"),
+            Genre => localized_string("genre"),
+            Year => localized_string("year"),
+            Charter => localized_string("charter"),
+            Length => localized_string("length"),
+            PlayCount => localized_string("play-count"),
+            BestScore => localized_string("best-score"),
+            Stars => localized_string("stars"),
+            Accuracy => localized_string("accuracy"),
+            Tags => localized_string("tags"),
+            Rating => localized_string("rating"),
+            FuzzyScore => unreachable!("fuzzy score not used as an actual column"),
+        }
+    }
+
+    fn as_string(self) -> String {
+        use SongColumnKey::*;
+
+        let s = match self {
+            Title => "title",
+            Artist => "artist",
+            Album => "album",
+            Genre => "genre",
+            Year => "year",
+            Charter => "charter",
+            Length => "length",
+            PlayCount => "play_count",
+            BestScore => "best_score",
+            Stars => "stars",
+            Accuracy => "accuracy",
+            Tags => "tags",
+            Rating => "rating",
+            FuzzyScore => unreachable!("fuzzy score not used as an actual column"),
+        };
+
+        s.to_string()
+    }
+}
+
+impl From<&str> for SongColumnKey {
+    fn from(s: &str) -> Self {
+        match s {
+            "title" => SongColumnKey::Title,
+            "artist" => SongColumnKey::Artist,
+            "album" => SongColumnKey::Album,
+            "genre" => SongColumnKey::Genre,
+            "year" => SongColumnKey::Year,
+            "charter" => SongColumnKey::Charter,
+            "length" => SongColumnKey::Length,
+            "play_count" => SongColumnKey::PlayCount,
+            "best_score" => SongColumnKey::BestScore,
+            "stars" => SongColumnKey::Stars,
+            "accuracy" => SongColumnKey::Accuracy,
+            "tags" => SongColumnKey::Tags,
+            "rating" => SongColumnKey::Rating,
+            _ => panic!("Unknown SongColumnKey for {}", s),
+        }
+    }
+}
+
+pub struct SongHeaderState {
+    state: header::State,
+    previous_column_key: Option<SongColumnKey>,
+    previous_sort_direction: Option<SortDirection>,
+    columns: Vec<SongColumnState>,
+}
+
+impl SongHeaderState {
+    fn column_config(&self) -> Vec<(SongColumnKey, Length, bool)> {
+        self.columns
+            .iter()
+            .map(|c| (c.key, c.width, c.hidden))
+            .collect()
+    }
+}
+
+impl Default for SongHeaderState {
+    fn default() -> Self {
+        let column = |key, width, hidden| SongColumnState {
+            key,
+            btn_state: Default::default(),
+            width,
+            hidden,
+        };
+
+        Self {
+            state: Default::default(),
+            previous_column_key: None,
+            previous_sort_direction: None,
+            columns: vec![
+                column(SongColumnKey::Title, Length::Fill, false),
+                column(SongColumnKey::Artist, Length::Units(150), false),
+                column(SongColumnKey::Album, Length::Units(150), true),
+                column(SongColumnKey::Genre, Length::Units(100), true),
+                column(SongColumnKey::Year, Length::Units(60), true),
+                column(SongColumnKey::Charter, Length::Units(110), false),
+                column(SongColumnKey::Length, Length::Units(70), false),
+                column(SongColumnKey::PlayCount, Length::Units(85), false),
+                column(SongColumnKey::BestScore, Length::Units(100), false),
+                column(SongColumnKey::Stars, Length::Units(60), false),
+                column(SongColumnKey::Accuracy, Length::Units(85), false),
+                column(SongColumnKey::Tags, Length::Units(120), false),
+                column(SongColumnKey::Rating, Length::Units(90), false),
+            ],
+        }
+    }
+}
+
+pub struct SongColumnState {
+    key: SongColumnKey,
+    btn_state: button::State,
+    width: Length,
+    hidden: bool,
+}
+
+#[derive(Default)]
+pub struct SongSettingsState {
+    add_directory_btn_state: button::State,
+    data_directory_btn_state: button::State,
+    directories: Vec<SongDirectoryState>,
+    organize_template: String,
+    organize_template_input_state: text_input::State,
+    organize_preview_btn_state: button::State,
+    organize_apply_btn_state: button::State,
+    organize_plans: Option<Vec<RenamePlan>>,
+    auto_update_interval_state: pick_list::State<UpdateInterval>,
+    quiet_hours_start_state: pick_list::State<Hour>,
+    quiet_hours_end_state: pick_list::State<Hour>,
+    /// True while songs are being updated automatically.
+    auto_update_running: bool,
+    /// What the last automatic update changed.
+    auto_update_summary: Option<UpdateSummary>,
+    inbox_directory_btn_state: button::State,
+    inbox_library_btn_state: button::State,
+    /// Inbox items which are being imported.
+    inbox_pending: HashSet<PathBuf>,
+    /// Latest imports from the inbox, oldest first.
+    inbox_imports: Vec<InboxImport>,
+}
+
+pub struct SongDirectoryState {
+    path: PathBuf,
+    remove_btn_state: button::State,
+}
+
+impl SongDirectoryState {
+    fn new(path: PathBuf) -> Self {
+        SongDirectoryState {
+            path,
+            remove_btn_state: Default::default(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct SongTransferState {
+    target: Option<SongDirectory>,
+    target_state: pick_list::State<SongDirectory>,
+    copy_btn_state: button::State,
+    move_btn_state: button::State,
+    select_btn_state: button::State,
+    /// Mode and library root of the running transfer.
+    running: Option<(TransferMode, PathBuf)>,
+    /// Songs waiting for the running batch to finish.
+    queue: Vec<Song>,
+    /// Number of songs done and total number of songs of the running transfer.
+    progress: Option<(usize, usize)>,
+}
+
+/// Saved smart filters shown as tabs in My Songs.
+#[derive(Default)]
+pub struct SmartFiltersState {
+    all_btn_state: button::State,
+    filters: Vec<SmartFilterState>,
+    /// Index of the selected filter, `None` shows all songs.
+    selected: Option<usize>,
+    /// Paths of the songs the selected filter matches.
+    selected_songs: HashSet<PathBuf>,
+    name: String,
+    name_state: text_input::State,
+    save_btn_state: button::State,
+    export_btn_state: button::State,
+    delete_btn_state: button::State,
+    /// Setlist the selected filter was last exported to.
+    exported: Option<PathBuf>,
+}
+
+impl SmartFiltersState {
+    /// Returns true if the song is shown with the selected filter.
+    pub fn matches(&self, song: &Song) -> bool {
+        self.selected.is_none() || self.selected_songs.contains(&song.path)
+    }
+}
+
+#[derive(Default)]
+pub struct SmartFilterState {
+    btn_state: button::State,
+    /// Number of songs the filter matches.
+    count: usize,
+}
+
+/// Archived version of the expanded song.
+pub struct SongVersionState {
+    version: SongVersion,
+    /// Times the version has been played. Scores are kept per chart hash.
+    play_count: u32,
+    rollback_btn_state: button::State,
+}
+
+/// Tags, rating and notes editing of the expanded song.
+#[derive(Default)]
+pub struct SongUserDataState {
+    tag: String,
+    tag_input_state: text_input::State,
+    add_tag_btn_state: button::State,
+    tag_btn_states: Vec<button::State>,
+    rating_state: pick_list::State<SongRating>,
+    /// Notes being edited, saved on enter or when the song is collapsed.
+    notes: String,
+    notes_input_state: text_input::State,
+}
+
+/// Rating picked for a song, `None` removes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SongRating(pub Option<u8>);
+
+impl SongRating {
+    fn all() -> Vec<SongRating> {
+        std::iter::once(SongRating(None))
+            .chain((1..=MAX_RATING).map(|r| SongRating(Some(r))))
+            .collect()
+    }
+}
+
+impl std::fmt::Display for SongRating {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(rating) => write!(f, "{}", stars(rating)),
+            None => write!(f, "-"),
+        }
+    }
+}
+
+/// Hours between two automatic song updates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UpdateInterval(pub u32);
+
+impl UpdateInterval {
+    const ALL: [UpdateInterval; 5] = [
+        UpdateInterval(3),
+        UpdateInterval(6),
+        UpdateInterval(12),
+        UpdateInterval(24),
+        UpdateInterval(24 * 7),
+    ];
+}
+
+impl std::fmt::Display for UpdateInterval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self.0 {
+            24 => localized_string("daily"),
+            168 => localized_string("weekly"),
+            hours => {
+                let hours = hours.to_string();
+                let mut vars = HashMap::new();
+                vars.insert("number".to_string(), &hours);
+                let fmt = localized_string("every-hours");
+
+                strfmt(&fmt, &vars).unwrap()
+            }
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Days between two scheduled backups, `None` turns them off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackupInterval(pub Option<u32>);
+
+impl BackupInterval {
+    const ALL: [BackupInterval; 3] = [
+        BackupInterval(None),
+        BackupInterval(Some(BackupSchedule::DAILY)),
+        BackupInterval(Some(BackupSchedule::WEEKLY)),
+    ];
+}
+
+impl std::fmt::Display for BackupInterval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self.0 {
+            None => localized_string("never"),
+            Some(BackupSchedule::DAILY) => localized_string("daily"),
+            Some(BackupSchedule::WEEKLY) => localized_string("weekly"),
+            Some(days) => {
+                let days = days.to_string();
+                let mut vars = HashMap::new();
+                vars.insert("number".to_string(), &days);
+                let fmt = localized_string("every-days");
+
+                strfmt(&fmt, &vars).unwrap()
+            }
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Hour of the day shown in a `PickList`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hour(pub u32);
+
+impl Hour {
+    fn all() -> Vec<Hour> {
+        (0..24).map(Hour).collect()
+    }
+}
+
+impl std::fmt::Display for Hour {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:02}:00", self.0)
+    }
+}
+
+/// Library root shown in a `PickList`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SongDirectory(pub PathBuf);
+
+impl std::fmt::Display for SongDirectory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.display())
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -1905,12 +2948,12 @@ pub enum DownloadReason {
     Install,
 }
 
-
-async fn load_caches() -> Result<(FingerprintCache, AddonCache)> {
+async fn load_caches() -> Result<(FingerprintCache, AddonCache, SongCache)> {
     let fingerprint_cache = load_fingerprint_cache().await?;
     let addon_cache = load_addon_cache().await?;
+    let song_cache = load_song_cache().await?;
 
-    Ok((fingerprint_cache, addon_cache))
+    Ok((fingerprint_cache, addon_cache, song_cache))
 }
 
 fn apply_config(ajour: &mut Ajour, mut config: Config) {
@@ -2122,6 +3165,16 @@ fn apply_config(ajour: &mut Ajour, mut config: Config) {
     // Set the inital mode flavor
     ajour.mode = Mode::MyAddons(config.wow.flavor);
 
+    // Button states for the song library roots
+    ajour.song_settings_state.directories = config
+        .songs
+        .directories
+        .iter()
+        .cloned()
+        .map(SongDirectoryState::new)
+        .collect();
+    ajour.song_settings_state.organize_template = config.songs.organize_template();
+
     ajour.config = config;
 
     // @see (casperstorm): Migration from single World of Warcraft directory to multiple directories.
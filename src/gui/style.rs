@@ -75,6 +75,17 @@ impl container::StyleSheet for HoverableSegmentAlternateContainer {
     }
 }
 
+pub struct BarContainer(pub ColorPalette);
+impl container::StyleSheet for BarContainer {
+    fn style(&self) -> container::Style {
+        container::Style {
+            background: Some(Background::Color(self.0.normal.primary)),
+            border_radius: 2.0,
+            ..container::Style::default()
+        }
+    }
+}
+
 pub struct FadedNormalForegroundContainer(pub ColorPalette);
 impl container::StyleSheet for FadedNormalForegroundContainer {
     fn style(&self) -> container::Style {
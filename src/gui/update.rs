@@ -1,60 +1,122 @@
 use {
+    super::apply_config,
     super::{
-        Ajour, BackupFolderKind, CatalogCategory, CatalogColumnKey, CatalogRow,
-        CatalogSource, ColumnKey, DownloadReason, ExpandType, GlobalReleaseChannel, InstallAddon,
-        InstallKind, InstallStatus, Interaction, Message, Mode, ReleaseChannel, SortDirection, State,
+        Ajour, BackupFolderKind, CatalogCategory, CatalogColumnKey, CatalogRow, CatalogSource,
+        ColumnKey, DownloadReason, ExpandType, GlobalReleaseChannel, InstallAddon, InstallKind,
+        InstallStatus, Interaction, Message, Mode, ReleaseChannel, RetentionRule, S3Setting,
+        SongColumnKey, SongDirectory, SongDirectoryState, SongRating, SongVersionState,
+        SortDirection, State, UpdateInterval,
     },
     crate::localization::{localized_string, LANG},
     crate::{log_error, Result},
     ajour_core::{
         addon::{Addon, AddonFolder, AddonState},
-        backup::{backup_folders, latest_backup, BackupFolder},
+        autoupdate::{apply_updates, plan_updates},
+        backup::{
+            backup_folders, latest_backups, list_backups, preview_prune, prune_backups,
+            BackupFolder,
+        },
         cache::{
-            catalog_download_latest_or_use_cache, remove_addon_cache_entry, update_addon_cache,
-            AddonCache, AddonCacheEntry, FingerprintCache,
+            catalog_download_latest_or_use_cache, remove_addon_cache_entry,
+            remove_song_cache_entry, update_addon_cache, AddonCache, AddonCacheEntry,
+            FingerprintCache, SongCache,
         },
         catalog,
-        config::{ColumnConfigV2, Flavor},
+        clone_hero::{export_setlist, load_bad_songs, load_score_data},
+        config::{load_config, ColumnConfigV2, Flavor, SmartFilter},
         error::{DownloadError, FilesystemError, ParseError, RepositoryError, ThemeError},
-        fs::{delete_addons, delete_saved_variables, import_theme, install_addon, PersistentData},
+        fs::{
+            check_available_space, config_dir, delete_addons, delete_saved_variables, import_theme,
+            install_addon, quarantine_song, PersistentData,
+        },
+        inbox::{changed_inbox_items, import_inbox_item, inbox_items, InboxImport},
+        library::{
+            changed_song_folders, read_song_directory, reread_song_folders, seed_song_cache,
+            validate_song_folder,
+        },
         network::download_addon,
+        organizer::{apply_rename_plans, plan_organize},
+        origin::update_songs,
+        pack::{delete_pack, install_pack, read_packs, update_pack, Pack},
         parse::{read_addon_directory, update_addon_fingerprint},
+        query::SongQuery,
+        report::{export_report, ReportFormat},
         repository::{
             batch_refresh_repository_packages, Changelog, RepositoryKind, RepositoryPackage,
         },
-        share,
-        utility::{wow_path_resolution},
+        restore::{
+            backup_entries, entry_groups, list_backup_sources, plan_restore, restore,
+            restore_origins, RestoreTarget,
+        },
+        s3::upload_backup,
+        share::{self, install_shared_song},
+        snapshot::backup_songs,
+        song::{PlayFilter, Song, SongState},
+        stats::library_stats,
+        transfer::{transfer_songs, TransferMode, TransferResult},
+        userdata::{load_user_data, SongUserData},
+        utility::wow_path_resolution,
+        versions::{rollback_song, song_versions},
     },
     ajour_widgets::header::ResizeEvent,
     anyhow::Context,
     async_std::sync::{Arc, Mutex},
-    chrono::{NaiveTime, Utc},
+    chrono::{Local, NaiveTime, Utc},
     fuzzy_matcher::{
         skim::{SkimMatcherV2, SkimScoreConfig},
         FuzzyMatcher,
     },
     iced::{Command, Length},
     isahc::http::Uri,
-    std::collections::{hash_map::DefaultHasher, HashMap},
+    std::cmp::Ordering,
+    std::collections::{hash_map::DefaultHasher, HashMap, HashSet},
     std::convert::TryFrom,
     std::hash::Hasher,
     std::path::{Path, PathBuf},
 };
 
 use crate::gui::Confirm;
+
+/// Number of songs transferred between two progress updates.
+const SONG_TRANSFER_BATCH_SIZE: usize = 25;
+
+/// Number of inbox imports listed in the settings.
+const MAX_INBOX_IMPORTS: usize = 10;
 #[cfg(target_os = "windows")]
 use crate::tray::{TrayMessage, SHOULD_EXIT, TRAY_SENDER};
 #[cfg(target_os = "windows")]
 use std::sync::atomic::Ordering;
 
 pub fn handle_message(ajour: &mut Ajour, message: Message) -> Result<Command<Message>> {
+    // Changes to several songs or addons at once wait for a backup if the
+    // backup schedule asks for one.
+    if ajour.config.backup_schedule.before_bulk_changes
+        && !ajour.backup_state.running_deferred
+        && is_bulk_change(ajour, &message)
+    {
+        if ajour.backup_state.backing_up || ajour.backup_state.backing_up_songs {
+            log::debug!("deferring {:?} until the backup finished", &message);
+
+            ajour.backup_state.deferred.push(message);
+            return Ok(Command::none());
+        }
+
+        if let Some(command) = start_backup(ajour) {
+            log::debug!("backing up before {:?}", &message);
+
+            ajour.backup_state.deferred.push(message);
+            return Ok(command);
+        }
+    }
+
     match message {
         Message::CachesLoaded(result) => {
             log::debug!("Message::CachesLoaded(error: {})", result.is_err());
 
-            if let Ok((fingerprint_cache, addon_cache)) = result {
+            if let Ok((fingerprint_cache, addon_cache, song_cache)) = result {
                 ajour.fingerprint_cache = Some(Arc::new(Mutex::new(fingerprint_cache)));
                 ajour.addon_cache = Some(Arc::new(Mutex::new(addon_cache)));
+                ajour.song_cache = Some(Arc::new(Mutex::new(song_cache)));
             }
 
             return Ok(Command::perform(async {}, Message::Parse));
@@ -68,11 +130,19 @@ pub fn handle_message(ajour: &mut Ajour, message: Message) -> Result<Command<Mes
             // If a backup directory is selected, find the latest backup
             if let Some(dir) = &ajour.config.backup_directory {
                 commands.push(Command::perform(
-                    latest_backup(dir.to_owned()),
+                    latest_backups(dir.to_owned(), ajour.config.backup_s3.clone()),
                     Message::LatestBackup,
                 ));
             }
 
+            // Begin to parse song library roots.
+            commands.extend(parse_song_directories(ajour));
+
+            // Import what was dropped into the inbox while CHmon wasn't running.
+            if let Some(inbox) = ajour.config.songs.inbox.directory.clone() {
+                commands.extend(import_inbox_items(ajour, inbox_items(&inbox)));
+            }
+
             // Check if any new flavor has been added since last time.
             // Get missing flavors.
             let mut missing_added = 0;
@@ -193,6 +263,19 @@ pub fn handle_message(ajour: &mut Ajour, message: Message) -> Result<Command<Mes
 
                     return Ok(Command::perform(async {}, Message::Parse));
                 }
+                Mode::MySongs => {
+                    // Clear query
+                    ajour.songs_search_state.query = None;
+
+                    // Close details if shown.
+                    ajour.expanded_song = None;
+
+                    // Cleans the songs.
+                    ajour.songs = vec![];
+                    apply_smart_filters(ajour);
+
+                    return Ok(Command::batch(parse_song_directories(ajour)));
+                }
                 Mode::Catalog => {
                     ajour.catalog = None;
                     ajour.state.insert(Mode::Catalog, State::Loading);
@@ -353,6 +436,17 @@ pub fn handle_message(ajour: &mut Ajour, message: Message) -> Result<Command<Mes
             else {
                 ajour.mode = mode;
             }
+
+            // Statistics are counted again each time they're shown, since the
+            // songs may have changed.
+            if ajour.mode == Mode::Statistics && !ajour.songs.is_empty() {
+                ajour.state.insert(Mode::Statistics, State::Loading);
+
+                return Ok(Command::perform(
+                    library_stats(ajour.songs.clone()),
+                    Message::LibraryStatsComputed,
+                ));
+            }
         }
 
         Message::Interaction(Interaction::Expand(expand_type)) => {
@@ -579,6 +673,30 @@ pub fn handle_message(ajour: &mut Ajour, message: Message) -> Result<Command<Mes
                     }
                     return Ok(Command::batch(commands));
                 }
+                Mode::MySongs => {
+                    // Close details if shown.
+                    ajour.expanded_song = None;
+
+                    // Reinstall every song which changed at its origin.
+                    let mut songs = vec![];
+                    for song in ajour.songs.iter_mut() {
+                        if song.state == SongState::Updatable {
+                            song.state = SongState::Downloading;
+                            songs.push(song.clone());
+                        }
+                    }
+
+                    if !songs.is_empty() {
+                        return Ok(Command::perform(
+                            update_songs(
+                                ajour.song_cache.clone(),
+                                ajour.version_store.clone(),
+                                songs,
+                            ),
+                            Message::SongsUpdated,
+                        ));
+                    }
+                }
                 _ => {}
             }
         }
@@ -1196,7 +1314,30 @@ pub fn handle_message(ajour: &mut Ajour, message: Message) -> Result<Command<Mes
                         column.width = Length::Units(right_width);
                     }
                 }
+                Mode::MySongs => {
+                    let left_key = SongColumnKey::from(left_name.as_str());
+                    let right_key = SongColumnKey::from(right_name.as_str());
+
+                    if let Some(column) = ajour
+                        .song_header_state
+                        .columns
+                        .iter_mut()
+                        .find(|c| c.key == left_key && left_key != SongColumnKey::Title)
+                    {
+                        column.width = Length::Units(left_width);
+                    }
+
+                    if let Some(column) = ajour
+                        .song_header_state
+                        .columns
+                        .iter_mut()
+                        .find(|c| c.key == right_key && right_key != SongColumnKey::Title)
+                    {
+                        column.width = Length::Units(right_width);
+                    }
+                }
                 Mode::Install => {}
+                Mode::Statistics => {}
                 Mode::Settings => {}
                 Mode::About => {}
                 Mode::Catalog => {
@@ -1264,84 +1405,26 @@ pub fn handle_message(ajour: &mut Ajour, message: Message) -> Result<Command<Mes
                 // Persist the newly updated config.
                 let _ = &ajour.config.save();
 
+                // Backups in the previous directory can't be restored anymore.
+                ajour.restore_state = Default::default();
+
+                // Only back up on startup when the app starts.
+                ajour.backup_state.schedule_checked = true;
+
                 // Check if a latest backup exists in path
-                return Ok(Command::perform(latest_backup(path), Message::LatestBackup));
+                return Ok(Command::perform(
+                    latest_backups(path, ajour.config.backup_s3.clone()),
+                    Message::LatestBackup,
+                ));
             }
         }
 
         Message::Interaction(Interaction::Backup) => {
             log::debug!("Interaction::Backup");
 
-            // This will disable our backup button and show a message that the
-            // app is processing the backup. We will unflag this on completion.
-            ajour.backup_state.backing_up = true;
-
-            let mut src_folders = vec![];
-
-            // Shouldn't panic since button is only shown if backup directory is chosen
-            let dest = ajour.config.backup_directory.as_ref().unwrap();
-
-            // Backup WTF & AddOn directories for flavor if it exist
-            for flavor in Flavor::ALL.iter() {
-                if let Some(wow_dir) = ajour.config.get_root_directory_for_flavor(flavor) {
-                    if ajour.config.backup_addons {
-                        let addon_dir =
-                            ajour.config.get_addon_directory_for_flavor(flavor).unwrap();
-
-                        // Backup starting with `Interface` folder as some users save
-                        // custom data here that they would like retained
-                        if let Some(interface_dir) = addon_dir.parent() {
-                            if interface_dir.exists() {
-                                src_folders.push(BackupFolder::new(interface_dir, &wow_dir));
-                            }
-                        }
-                    }
-
-                    if ajour.config.backup_wtf {
-                        let wtf_dir = ajour.config.get_wtf_directory_for_flavor(flavor).unwrap();
-
-                        if wtf_dir.exists() {
-                            src_folders.push(BackupFolder::new(&wtf_dir, &wow_dir));
-                        }
-                    }
-
-                    if ajour.config.backup_screenshots {
-                        let screenshot_dir = ajour
-                            .config
-                            .get_screenshots_directory_for_flavor(flavor)
-                            .unwrap();
-                        if screenshot_dir.exists() {
-                            src_folders.push(BackupFolder::new(&screenshot_dir, &wow_dir));
-                        }
-                    }
-
-                    if ajour.config.backup_fonts {
-                        let fonts_dir =
-                            ajour.config.get_fonts_directory_for_flavor(flavor).unwrap();
-                        if fonts_dir.exists() {
-                            src_folders.push(BackupFolder::new(&fonts_dir, &wow_dir));
-                        }
-                    }
-                }
-            }
-
-            // Backup Ajour config.
-            if ajour.config.backup_config {
-                let config_path = ajour_core::fs::config_dir();
-                if let Some(config_prefix) = config_path.parent() {
-                    src_folders.push(BackupFolder::new(&config_path, config_prefix));
-                }
+            if let Some(command) = start_backup(ajour) {
+                return Ok(command);
             }
-
-            return Ok(Command::perform(
-                backup_folders(
-                    src_folders,
-                    dest.to_owned(),
-                    ajour.config.compression_format,
-                    ajour.config.zstd_compression_level,
-                ),
-                Message::BackupFinished,
-            ));
         }
         Message::Interaction(Interaction::ToggleBackupFolder(is_checked, folder)) => {
             log::debug!(
@@ -1366,20 +1449,77 @@ pub fn handle_message(ajour: &mut Ajour, message: Message) -> Result<Command<Mes
                 BackupFolderKind::Fonts => {
                     ajour.config.backup_fonts = is_checked;
                 }
+                BackupFolderKind::Songs => {
+                    ajour.config.backup_songs = is_checked;
+                }
             }
 
             let _ = ajour.config.save();
         }
-        Message::LatestBackup(as_of) => {
-            log::debug!("Message::LatestBackup({:?})", &as_of);
+        Message::LatestBackup(latest) => {
+            log::debug!("Message::LatestBackup({:?})", &latest);
+
+            ajour.backup_state.last_backup = latest.archive;
+            ajour.backup_state.last_snapshot = latest.snapshot;
+
+            // The latest backup is first looked up on startup.
+            if !ajour.backup_state.schedule_checked {
+                ajour.backup_state.schedule_checked = true;
+
+                let schedule = ajour.config.backup_schedule;
+                let now = Local::now().naive_local();
+
+                let command = if schedule.on_startup {
+                    start_backup(ajour)
+                } else {
+                    start_backup_of(
+                        ajour,
+                        schedule.is_due(latest.archive, now),
+                        schedule.is_due(latest.snapshot, now),
+                    )
+                };
 
-            ajour.backup_state.last_backup = as_of;
+                if let Some(command) = command {
+                    return Ok(command);
+                }
+            }
         }
         Message::BackupFinished(Ok(as_of)) => {
             log::debug!("Message::BackupFinished({})", as_of.format("%H:%M:%S"));
 
             ajour.backup_state.backing_up = false;
             ajour.backup_state.last_backup = Some(as_of);
+
+            let mut commands = vec![run_deferred(ajour)?];
+
+            // Remove the archives the retention rules don't keep anymore.
+            let retention = ajour.config.backup_retention;
+            if let (true, Some(backup_dir)) = (
+                retention.is_enabled(),
+                ajour.config.backup_directory.clone(),
+            ) {
+                commands.push(Command::perform(
+                    prune_backups(backup_dir, retention),
+                    Message::BackupsPruned,
+                ));
+            }
+
+            // Upload the new archive off-machine. Pruning always keeps it.
+            let archive = ajour
+                .config
+                .backup_directory
+                .as_ref()
+                .and_then(|dir| list_backups(dir).into_iter().find(|a| a.as_of == as_of));
+            if let (true, Some(archive)) = (ajour.config.backup_s3.is_configured(), archive) {
+                ajour.backup_state.uploading = true;
+
+                commands.push(Command::perform(
+                    upload_backup(ajour.config.backup_s3.clone(), archive.path),
+                    Message::BackupUploaded,
+                ));
+            }
+
+            return Ok(Command::batch(commands));
         }
         Message::BackupFinished(error @ Err(_)) => {
             let error = error
@@ -1390,164 +1530,461 @@ pub fn handle_message(ajour: &mut Ajour, message: Message) -> Result<Command<Mes
             ajour.error = Some(error);
 
             ajour.backup_state.backing_up = false;
+            cancel_deferred(ajour);
         }
-        Message::Interaction(Interaction::ToggleColumn(is_checked, key)) => {
-            // We can't untoggle the addon title column
-            if key == ColumnKey::Title {
-                return Ok(Command::none());
-            }
+        Message::BackupUploaded(result) => {
+            ajour.backup_state.uploading = false;
 
-            log::debug!("Interaction::ToggleColumn({}, {:?})", is_checked, key);
+            match result.context(localized_string("error-upload-backup")) {
+                Ok(remote) => {
+                    log::debug!("Message::BackupUploaded({})", &remote.key);
 
-            if is_checked {
-                if let Some(column) = ajour.header_state.columns.iter_mut().find(|c| c.key == key) {
-                    column.hidden = false;
+                    ajour.backup_state.last_upload = Some(remote);
+                }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.error = Some(error);
                 }
-            } else if let Some(column) =
-                ajour.header_state.columns.iter_mut().find(|c| c.key == key)
-            {
-                column.hidden = true;
             }
+        }
+        Message::LibraryStatsComputed(stats) => {
+            log::debug!("Message::LibraryStatsComputed({} songs)", stats.songs);
 
-            // Persist changes to config
-            save_column_configs(ajour);
+            ajour.library_stats = Some(stats);
+            ajour.state.insert(Mode::Statistics, State::Ready);
         }
-        Message::Interaction(Interaction::MoveColumnLeft(key)) => {
-            log::debug!("Interaction::MoveColumnLeft({:?})", key);
+        Message::SongsBackedUp(result) => {
+            ajour.backup_state.backing_up_songs = false;
 
-            // Update header state ordering and save to config
-            if let Some(idx) = ajour.header_state.columns.iter().position(|c| c.key == key) {
-                ajour.header_state.columns.swap(idx, idx - 1);
+            match result.context(localized_string("error-backup-songs")) {
+                Ok(summary) => {
+                    log::debug!(
+                        "Message::SongsBackedUp({}, {} of {} files copied)",
+                        &summary.id,
+                        summary.copied,
+                        summary.files
+                    );
 
-                ajour
-                    .header_state
-                    .columns
-                    .iter_mut()
-                    .enumerate()
-                    .for_each(|(idx, column)| column.order = idx);
+                    ajour.backup_state.last_song_backup = Some(summary);
+                    ajour.backup_state.last_snapshot = Some(Local::now().naive_local());
 
-                // Persist changes to config
-                save_column_configs(ajour);
-            }
+                    return run_deferred(ajour);
+                }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.error = Some(error);
 
-            // Update column ordering in settings
-            if let Some(idx) = ajour
-                .column_settings
-                .columns
-                .iter()
-                .position(|c| c.key == key)
-            {
-                ajour.column_settings.columns.swap(idx, idx - 1);
+                    cancel_deferred(ajour);
+                }
             }
         }
-        Message::Interaction(Interaction::MoveColumnRight(key)) => {
-            log::debug!("Interaction::MoveColumnRight({:?})", key);
-
-            // Update header state ordering and save to config
-            if let Some(idx) = ajour.header_state.columns.iter().position(|c| c.key == key) {
-                ajour.header_state.columns.swap(idx, idx + 1);
+        Message::Interaction(Interaction::BackupIntervalSelected(interval)) => {
+            log::debug!("Interaction::BackupIntervalSelected({:?})", interval);
 
-                ajour
-                    .header_state
-                    .columns
-                    .iter_mut()
-                    .enumerate()
-                    .for_each(|(idx, column)| column.order = idx);
+            ajour.config.backup_schedule.interval_days = interval.0;
+            let _ = ajour.config.save();
+        }
+        Message::Interaction(Interaction::ToggleBackupOnStartup(is_checked)) => {
+            log::debug!("Interaction::ToggleBackupOnStartup({})", is_checked);
 
-                // Persist changes to config
-                save_column_configs(ajour);
-            }
+            ajour.config.backup_schedule.on_startup = is_checked;
+            let _ = ajour.config.save();
+        }
+        Message::Interaction(Interaction::ToggleBackupBeforeBulkChanges(is_checked)) => {
+            log::debug!("Interaction::ToggleBackupBeforeBulkChanges({})", is_checked);
 
-            // Update column ordering in settings
-            if let Some(idx) = ajour
-                .column_settings
-                .columns
-                .iter()
-                .position(|c| c.key == key)
-            {
-                ajour.column_settings.columns.swap(idx, idx + 1);
-            }
+            ajour.config.backup_schedule.before_bulk_changes = is_checked;
+            let _ = ajour.config.save();
         }
-        Message::Interaction(Interaction::ToggleCatalogColumn(is_checked, key)) => {
-            // We can't untoggle the addon title column
-            if key == CatalogColumnKey::Title {
-                return Ok(Command::none());
-            }
+        Message::Interaction(Interaction::ToggleBackupEncryption(is_checked)) => {
+            log::debug!("Interaction::ToggleBackupEncryption({})", is_checked);
 
-            log::debug!(
-                "Interaction::ToggleCatalogColumn({}, {:?})",
-                is_checked,
-                key
-            );
+            ajour.config.backup_encrypt = is_checked;
+            let _ = ajour.config.save();
+        }
+        Message::Interaction(Interaction::BackupPassphraseChanged(passphrase)) => {
+            log::debug!("Interaction::BackupPassphraseChanged");
 
-            if is_checked {
-                if let Some(column) = ajour
-                    .catalog_header_state
-                    .columns
-                    .iter_mut()
-                    .find(|c| c.key == key)
-                {
-                    column.hidden = false;
+            ajour.backup_state.passphrase = passphrase;
+        }
+        Message::Interaction(Interaction::S3SettingChanged(setting, value)) => {
+            log::debug!("Interaction::S3SettingChanged({:?}, {})", setting, &value);
+
+            let destination = &mut ajour.config.backup_s3;
+            match setting {
+                S3Setting::Endpoint => destination.endpoint = value,
+                S3Setting::Bucket => destination.bucket = value,
+                S3Setting::Prefix => destination.prefix = value,
+                S3Setting::Region => {
+                    destination.region = Some(value).filter(|region| !region.trim().is_empty())
                 }
-            } else if let Some(column) = ajour
-                .catalog_header_state
-                .columns
-                .iter_mut()
-                .find(|c| c.key == key)
-            {
-                column.hidden = true;
             }
 
-            // Persist changes to config
-            save_column_configs(ajour);
+            ajour.backup_state.last_upload = None;
+            let _ = ajour.config.save();
         }
-        Message::Interaction(Interaction::MoveCatalogColumnLeft(key)) => {
-            log::debug!("Interaction::MoveCatalogColumnLeft({:?})", key);
+        Message::CheckScheduledBackup(_) => {
+            let backup_state = &ajour.backup_state;
+            let schedule = ajour.config.backup_schedule;
+            let now = Local::now().naive_local();
+
+            // Failed backups are retried after the next interval.
+            let archive_due =
+                schedule.is_due(backup_state.last_backup.max(backup_state.last_attempt), now);
+            let snapshot_due = schedule.is_due(
+                backup_state
+                    .last_snapshot
+                    .max(backup_state.last_snapshot_attempt),
+                now,
+            );
 
-            // Update header state ordering and save to config
-            if let Some(idx) = ajour
-                .catalog_header_state
-                .columns
-                .iter()
-                .position(|c| c.key == key)
+            if backup_state.schedule_checked
+                && !backup_state.backing_up
+                && !backup_state.backing_up_songs
+                && (archive_due || snapshot_due)
             {
-                ajour.catalog_header_state.columns.swap(idx, idx - 1);
+                log::debug!("Message::CheckScheduledBackup");
 
-                ajour
-                    .catalog_header_state
-                    .columns
-                    .iter_mut()
-                    .enumerate()
-                    .for_each(|(idx, column)| column.order = idx);
+                if let Some(command) = start_backup_of(ajour, archive_due, snapshot_due) {
+                    return Ok(command);
+                }
+            }
+        }
+        Message::Interaction(Interaction::RetentionChanged(rule, value)) => {
+            log::debug!("Interaction::RetentionChanged({:?}, {})", rule, &value);
+
+            let value = value.trim();
+            let retention = &mut ajour.config.backup_retention;
+
+            // Empty input turns the rule off, anything but a number is ignored.
+            let valid = match rule {
+                RetentionRule::KeepLast => parse_retention_rule(value, &mut retention.keep_last),
+                RetentionRule::KeepDaily => parse_retention_rule(value, &mut retention.keep_daily),
+                RetentionRule::KeepWeekly => {
+                    parse_retention_rule(value, &mut retention.keep_weekly)
+                }
+                RetentionRule::MaxSize => parse_retention_rule(value, &mut retention.max_size_mb),
+            };
 
-                // Persist changes to config
-                save_column_configs(ajour);
+            if valid {
+                ajour.backup_state.prune_preview = None;
+                let _ = ajour.config.save();
             }
+        }
+        Message::Interaction(Interaction::PreviewPrune) => {
+            log::debug!("Interaction::PreviewPrune");
 
-            // Update column ordering in settings
-            if let Some(idx) = ajour
-                .catalog_column_settings
-                .columns
-                .iter()
-                .position(|c| c.key == key)
-            {
-                ajour.catalog_column_settings.columns.swap(idx, idx - 1);
+            if let Some(backup_dir) = ajour.config.backup_directory.clone() {
+                return Ok(Command::perform(
+                    preview_prune(backup_dir, ajour.config.backup_retention),
+                    Message::PrunePreviewed,
+                ));
             }
         }
-        Message::Interaction(Interaction::MoveCatalogColumnRight(key)) => {
-            log::debug!("Interaction::MoveCatalogColumnRight({:?})", key);
+        Message::PrunePreviewed(result) => {
+            match result.context(localized_string("error-prune-backups")) {
+                Ok(pruned) => {
+                    log::debug!("Message::PrunePreviewed({} backups)", pruned.len());
 
-            // Update header state ordering and save to config
-            if let Some(idx) = ajour
-                .catalog_header_state
-                .columns
-                .iter()
-                .position(|c| c.key == key)
+                    ajour.backup_state.pruned = None;
+                    ajour.backup_state.prune_preview = Some(pruned);
+                }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.error = Some(error);
+                }
+            }
+        }
+        Message::Interaction(Interaction::PruneBackups) => {
+            log::debug!("Interaction::PruneBackups");
+
+            if let Some(backup_dir) = ajour.config.backup_directory.clone() {
+                return Ok(Command::perform(
+                    prune_backups(backup_dir, ajour.config.backup_retention),
+                    Message::BackupsPruned,
+                ));
+            }
+        }
+        Message::BackupsPruned(result) => {
+            match result.context(localized_string("error-prune-backups")) {
+                Ok(pruned) => {
+                    log::debug!("Message::BackupsPruned({} backups)", pruned.len());
+
+                    ajour.backup_state.prune_preview = None;
+                    ajour.backup_state.pruned = Some(pruned);
+                }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.error = Some(error);
+                }
+            }
+        }
+        Message::Interaction(Interaction::RefreshBackups) => {
+            log::debug!("Interaction::RefreshBackups");
+
+            if let Some(backup_dir) = ajour.config.backup_directory.clone() {
+                return Ok(Command::perform(
+                    list_backup_sources(backup_dir),
+                    Message::BackupSourcesLoaded,
+                ));
+            }
+        }
+        Message::BackupSourcesLoaded(result) => {
+            match result.context(localized_string("error-load-backups")) {
+                Ok(sources) => {
+                    log::debug!("Message::BackupSourcesLoaded({} backups)", sources.len());
+
+                    let restore_state = &mut ajour.restore_state;
+
+                    // Keep the selected backup if it still exists.
+                    if !restore_state
+                        .source
+                        .as_ref()
+                        .map(|source| sources.contains(source))
+                        .unwrap_or_default()
+                    {
+                        restore_state.source = None;
+                        restore_state.groups.clear();
+                        restore_state.selection.clear();
+                        restore_state.plan = None;
+                    }
+
+                    restore_state.sources = sources;
+                }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.error = Some(error);
+                }
+            }
+        }
+        Message::Interaction(Interaction::BackupSourceSelected(source)) => {
+            log::debug!("Interaction::BackupSourceSelected({})", &source);
+
+            let restore_state = &mut ajour.restore_state;
+            restore_state.source = Some(source.clone());
+            restore_state.groups.clear();
+            restore_state.selection.clear();
+            restore_state.plan = None;
+            restore_state.summary = None;
+
+            return Ok(Command::perform(
+                backup_entries(source, ajour.backup_state.passphrase()),
+                Message::BackupEntriesLoaded,
+            ));
+        }
+        Message::BackupEntriesLoaded(result) => {
+            match result.context(localized_string("error-load-backups")) {
+                Ok(entries) => {
+                    log::debug!("Message::BackupEntriesLoaded({} files)", entries.len());
+
+                    ajour.restore_state.groups = entry_groups(&entries);
+                }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.error = Some(error);
+                }
+            }
+        }
+        Message::Interaction(Interaction::ToggleRestoreGroup(path, is_checked)) => {
+            log::debug!(
+                "Interaction::ToggleRestoreGroup({}, checked: {})",
+                &path,
+                is_checked
+            );
+
+            if is_checked {
+                ajour.restore_state.selection.insert(path);
+            } else {
+                ajour.restore_state.selection.remove(&path);
+            }
+
+            ajour.restore_state.plan = None;
+        }
+        Message::Interaction(Interaction::SelectRestoreDirectory) => {
+            log::debug!("Interaction::SelectRestoreDirectory");
+
+            return Ok(Command::perform(
+                select_directory(),
+                Message::UpdateRestoreDirectory,
+            ));
+        }
+        Message::UpdateRestoreDirectory(path) => {
+            log::debug!("Message::UpdateRestoreDirectory({:?})", &path);
+
+            if let Some(path) = path {
+                ajour.restore_state.directory = Some(path);
+                ajour.restore_state.plan = None;
+            }
+        }
+        Message::Interaction(Interaction::RestoreToOriginal) => {
+            log::debug!("Interaction::RestoreToOriginal");
+
+            ajour.restore_state.directory = None;
+            ajour.restore_state.plan = None;
+        }
+        Message::Interaction(Interaction::RestoreConflictSelected(conflict)) => {
+            log::debug!("Interaction::RestoreConflictSelected({:?})", conflict);
+
+            ajour.restore_state.conflict = Some(conflict);
+            ajour.restore_state.plan = None;
+        }
+        Message::Interaction(Interaction::PreviewRestore) => {
+            log::debug!("Interaction::PreviewRestore");
+
+            let restore_state = &mut ajour.restore_state;
+
+            if let Some(source) = restore_state.source.clone() {
+                let selection = restore_state.selection.iter().cloned().collect();
+                let target = match &restore_state.directory {
+                    Some(directory) => RestoreTarget::Directory(directory.clone()),
+                    None => RestoreTarget::Original(restore_origins(&ajour.config)),
+                };
+
+                restore_state.summary = None;
+
+                return Ok(Command::perform(
+                    plan_restore(
+                        source,
+                        selection,
+                        target,
+                        restore_state.conflict(),
+                        ajour.backup_state.passphrase(),
+                    ),
+                    Message::RestorePlanned,
+                ));
+            }
+        }
+        Message::RestorePlanned(result) => {
+            match result.context(localized_string("error-restore")) {
+                Ok(plan) => {
+                    log::debug!(
+                        "Message::RestorePlanned({} files, {} conflicts)",
+                        plan.files.len(),
+                        plan.conflicts()
+                    );
+
+                    ajour.restore_state.plan = Some(plan);
+                }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.error = Some(error);
+                }
+            }
+        }
+        Message::Interaction(Interaction::Restore) => {
+            log::debug!("Interaction::Restore");
+
+            if let Some(plan) = ajour.restore_state.plan.take() {
+                ajour.restore_state.restoring = true;
+
+                return Ok(Command::perform(
+                    restore(plan, ajour.backup_state.passphrase()),
+                    Message::Restored,
+                ));
+            }
+        }
+        Message::Restored(result) => {
+            ajour.restore_state.restoring = false;
+
+            match result.context(localized_string("error-restore")) {
+                Ok(summary) => {
+                    log::debug!(
+                        "Message::Restored({} restored, {} skipped, {} renamed)",
+                        summary.restored,
+                        summary.skipped,
+                        summary.renamed
+                    );
+
+                    if ajour.user_data.merge(summary.user_data.clone()) > 0 {
+                        let _ = ajour.user_data.save();
+                        ajour.user_data.apply(&mut ajour.songs);
+                        apply_smart_filters(ajour);
+                    }
+
+                    let mut commands = vec![];
+
+                    if summary.folders.contains(&config_dir()) {
+                        // The settings and user data on disk were replaced, reload
+                        // them before anything saves over them. Reloading the
+                        // settings rescans every library.
+                        commands.push(Command::perform(
+                            load_config(),
+                            Message::RestoredConfigLoaded,
+                        ));
+                        commands.push(Command::perform(load_user_data(), Message::UserDataLoaded));
+                    } else {
+                        for root in ajour.config.songs.directories.clone() {
+                            if summary.folders.contains(&root) {
+                                ajour.state.insert(Mode::MySongs, State::Loading);
+                                commands.extend(read_song_root(ajour, root));
+                            }
+                        }
+                    }
+
+                    ajour.restore_state.summary = Some(summary);
+
+                    return Ok(Command::batch(commands));
+                }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.error = Some(error);
+                }
+            }
+        }
+        Message::RestoredConfigLoaded(result) => {
+            match result.context(localized_string("error-restore")) {
+                Ok(config) => {
+                    log::debug!("Message::RestoredConfigLoaded");
+
+                    // Stay on the current screen.
+                    let mode = ajour.mode.clone();
+                    apply_config(ajour, config);
+                    ajour.mode = mode;
+
+                    ajour.expanded_type = ExpandType::None;
+                    ajour.expanded_song = None;
+                    ajour.addons = HashMap::new();
+                    ajour.songs = vec![];
+                    apply_smart_filters(ajour);
+
+                    return Ok(Command::perform(async {}, Message::Parse));
+                }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.error = Some(error);
+                }
+            }
+        }
+        Message::Interaction(Interaction::ToggleColumn(is_checked, key)) => {
+            // We can't untoggle the addon title column
+            if key == ColumnKey::Title {
+                return Ok(Command::none());
+            }
+
+            log::debug!("Interaction::ToggleColumn({}, {:?})", is_checked, key);
+
+            if is_checked {
+                if let Some(column) = ajour.header_state.columns.iter_mut().find(|c| c.key == key) {
+                    column.hidden = false;
+                }
+            } else if let Some(column) =
+                ajour.header_state.columns.iter_mut().find(|c| c.key == key)
             {
-                ajour.catalog_header_state.columns.swap(idx, idx + 1);
+                column.hidden = true;
+            }
+
+            // Persist changes to config
+            save_column_configs(ajour);
+        }
+        Message::Interaction(Interaction::MoveColumnLeft(key)) => {
+            log::debug!("Interaction::MoveColumnLeft({:?})", key);
+
+            // Update header state ordering and save to config
+            if let Some(idx) = ajour.header_state.columns.iter().position(|c| c.key == key) {
+                ajour.header_state.columns.swap(idx, idx - 1);
 
                 ajour
-                    .catalog_header_state
+                    .header_state
                     .columns
                     .iter_mut()
                     .enumerate()
@@ -1559,477 +1996,2137 @@ pub fn handle_message(ajour: &mut Ajour, message: Message) -> Result<Command<Mes
 
             // Update column ordering in settings
             if let Some(idx) = ajour
-                .catalog_column_settings
+                .column_settings
                 .columns
                 .iter()
                 .position(|c| c.key == key)
             {
-                ajour.catalog_column_settings.columns.swap(idx, idx + 1);
+                ajour.column_settings.columns.swap(idx, idx - 1);
             }
         }
-        Message::CatalogDownloaded(Ok(catalog)) => {
-            log::debug!(
-                "Message::CatalogDownloaded({} addons in catalog)",
-                catalog.addons.len()
-            );
+        Message::Interaction(Interaction::MoveColumnRight(key)) => {
+            log::debug!("Interaction::MoveColumnRight({:?})", key);
 
-            ajour.catalog_last_updated = Some(Utc::now());
+            // Update header state ordering and save to config
+            if let Some(idx) = ajour.header_state.columns.iter().position(|c| c.key == key) {
+                ajour.header_state.columns.swap(idx, idx + 1);
 
-            let mut categories_per_source =
-                catalog
-                    .addons
-                    .iter()
-                    .fold(HashMap::new(), |mut map, addon| {
-                        map.entry(addon.source.to_string())
-                            .or_insert_with(Vec::new)
-                            .append(
-                                &mut addon
-                                    .categories
-                                    .clone()
-                                    .iter()
-                                    .map(|c| CatalogCategory::Choice(c.to_string()))
-                                    .collect(),
-                            );
-                        map
-                    });
-            categories_per_source.iter_mut().for_each(move |s| {
-                s.1.sort();
-                s.1.dedup();
-                s.1.insert(0, CatalogCategory::All);
-            });
+                ajour
+                    .header_state
+                    .columns
+                    .iter_mut()
+                    .enumerate()
+                    .for_each(|(idx, column)| column.order = idx);
 
-            ajour.catalog_categories_per_source_cache = categories_per_source;
-            let catalog_source_choice = ajour
-                .config
-                .catalog_source
-                .map(CatalogSource::Choice)
-                .unwrap_or(CatalogSource::All);
+                // Persist changes to config
+                save_column_configs(ajour);
+            }
 
-            ajour.catalog_search_state.categories = ajour
-                .catalog_categories_per_source_cache
-                .get(&catalog_source_choice.to_string())
-                .cloned()
-                .unwrap_or_default();
+            // Update column ordering in settings
+            if let Some(idx) = ajour
+                .column_settings
+                .columns
+                .iter()
+                .position(|c| c.key == key)
+            {
+                ajour.column_settings.columns.swap(idx, idx + 1);
+            }
+        }
+        Message::Interaction(Interaction::ToggleCatalogColumn(is_checked, key)) => {
+            // We can't untoggle the addon title column
+            if key == CatalogColumnKey::Title {
+                return Ok(Command::none());
+            }
 
-            ajour.catalog = Some(catalog);
+            log::debug!(
+                "Interaction::ToggleCatalogColumn({}, {:?})",
+                is_checked,
+                key
+            );
+
+            if is_checked {
+                if let Some(column) = ajour
+                    .catalog_header_state
+                    .columns
+                    .iter_mut()
+                    .find(|c| c.key == key)
+                {
+                    column.hidden = false;
+                }
+            } else if let Some(column) = ajour
+                .catalog_header_state
+                .columns
+                .iter_mut()
+                .find(|c| c.key == key)
+            {
+                column.hidden = true;
+            }
+
+            // Persist changes to config
+            save_column_configs(ajour);
+        }
+        Message::Interaction(Interaction::MoveCatalogColumnLeft(key)) => {
+            log::debug!("Interaction::MoveCatalogColumnLeft({:?})", key);
+
+            // Update header state ordering and save to config
+            if let Some(idx) = ajour
+                .catalog_header_state
+                .columns
+                .iter()
+                .position(|c| c.key == key)
+            {
+                ajour.catalog_header_state.columns.swap(idx, idx - 1);
+
+                ajour
+                    .catalog_header_state
+                    .columns
+                    .iter_mut()
+                    .enumerate()
+                    .for_each(|(idx, column)| column.order = idx);
+
+                // Persist changes to config
+                save_column_configs(ajour);
+            }
+
+            // Update column ordering in settings
+            if let Some(idx) = ajour
+                .catalog_column_settings
+                .columns
+                .iter()
+                .position(|c| c.key == key)
+            {
+                ajour.catalog_column_settings.columns.swap(idx, idx - 1);
+            }
+        }
+        Message::Interaction(Interaction::MoveCatalogColumnRight(key)) => {
+            log::debug!("Interaction::MoveCatalogColumnRight({:?})", key);
+
+            // Update header state ordering and save to config
+            if let Some(idx) = ajour
+                .catalog_header_state
+                .columns
+                .iter()
+                .position(|c| c.key == key)
+            {
+                ajour.catalog_header_state.columns.swap(idx, idx + 1);
+
+                ajour
+                    .catalog_header_state
+                    .columns
+                    .iter_mut()
+                    .enumerate()
+                    .for_each(|(idx, column)| column.order = idx);
+
+                // Persist changes to config
+                save_column_configs(ajour);
+            }
+
+            // Update column ordering in settings
+            if let Some(idx) = ajour
+                .catalog_column_settings
+                .columns
+                .iter()
+                .position(|c| c.key == key)
+            {
+                ajour.catalog_column_settings.columns.swap(idx, idx + 1);
+            }
+        }
+        Message::CatalogDownloaded(Ok(catalog)) => {
+            log::debug!(
+                "Message::CatalogDownloaded({} addons in catalog)",
+                catalog.addons.len()
+            );
+
+            ajour.catalog_last_updated = Some(Utc::now());
+
+            let mut categories_per_source =
+                catalog
+                    .addons
+                    .iter()
+                    .fold(HashMap::new(), |mut map, addon| {
+                        map.entry(addon.source.to_string())
+                            .or_insert_with(Vec::new)
+                            .append(
+                                &mut addon
+                                    .categories
+                                    .clone()
+                                    .iter()
+                                    .map(|c| CatalogCategory::Choice(c.to_string()))
+                                    .collect(),
+                            );
+                        map
+                    });
+            categories_per_source.iter_mut().for_each(move |s| {
+                s.1.sort();
+                s.1.dedup();
+                s.1.insert(0, CatalogCategory::All);
+            });
+
+            ajour.catalog_categories_per_source_cache = categories_per_source;
+            let catalog_source_choice = ajour
+                .config
+                .catalog_source
+                .map(CatalogSource::Choice)
+                .unwrap_or(CatalogSource::All);
+
+            ajour.catalog_search_state.categories = ajour
+                .catalog_categories_per_source_cache
+                .get(&catalog_source_choice.to_string())
+                .cloned()
+                .unwrap_or_default();
+
+            ajour.catalog = Some(catalog);
+
+            ajour.state.insert(Mode::Catalog, State::Ready);
+
+            query_and_sort_catalog(ajour);
+        }
+        Message::Interaction(Interaction::AddonsQuery(query)) => {
+            // Addons search query
+            ajour.addons_search_state.query = if query.is_empty() { None } else { Some(query) };
+
+            // Increase penalty for gaps between matching characters
+            let fuzzy_match_config = SkimScoreConfig {
+                gap_start: -12,
+                gap_extension: -6,
+                ..Default::default()
+            };
+            let fuzzy_matcher = SkimMatcherV2::default().score_config(fuzzy_match_config);
+
+            let addons = ajour.addons.entry(ajour.config.wow.flavor).or_default();
+            let global_release_channel = ajour.config.addons.global_release_channel;
+
+            if let Some(query) = &ajour.addons_search_state.query {
+                addons.iter_mut().for_each(|a| {
+                    a.fuzzy_score.take();
+
+                    if let Some(score) = fuzzy_matcher.fuzzy_match(a.title(), query) {
+                        if score > 0 {
+                            a.fuzzy_score = Some(score);
+                        }
+                    }
+                });
+
+                // Sort the addons by score
+                sort_addons(
+                    addons,
+                    global_release_channel,
+                    SortDirection::Desc,
+                    ColumnKey::FuzzyScore,
+                );
+                ajour.header_state.previous_sort_direction = Some(SortDirection::Desc);
+                ajour.header_state.previous_column_key = Some(ColumnKey::FuzzyScore);
+            } else {
+                // Clear out the fuzzy scores
+                addons.iter_mut().for_each(|a| {
+                    a.fuzzy_score.take();
+                });
+
+                // Use default sort
+                sort_addons(
+                    addons,
+                    global_release_channel,
+                    SortDirection::Desc,
+                    ColumnKey::Status,
+                );
+                ajour.header_state.previous_sort_direction = Some(SortDirection::Desc);
+                ajour.header_state.previous_column_key = Some(ColumnKey::Status);
+            }
+        }
+        Message::Interaction(Interaction::CatalogQuery(query)) => {
+            // Catalog search query
+            ajour.catalog_search_state.query = if query.is_empty() {
+                None
+            } else {
+                // Always set sort config to None when a new character is typed
+                // so the sort will be off fuzzy match score.
+                ajour.catalog_header_state.previous_column_key.take();
+                ajour.catalog_header_state.previous_sort_direction.take();
+
+                Some(query)
+            };
+
+            query_and_sort_catalog(ajour);
+        }
+        Message::Interaction(Interaction::InstallAddon(flavor, id, kind)) => {
+            log::debug!("Interaction::InstallAddon({}, {:?})", flavor, &kind);
+
+            let install_addons = ajour.install_addons.entry(flavor).or_default();
+
+            // Remove any existing status for this addon since we are going
+            // to try and download it again. For InstallKind::Source, we should only
+            // ever have one entry here so we just remove it
+            install_addons.retain(|a| match kind {
+                InstallKind::Catalog { .. } | InstallKind::Import { .. } => {
+                    !(id == a.id && a.kind == kind)
+                }
+                InstallKind::Source => a.kind != kind,
+            });
+
+            // Add new status for this addon as Downloading
+            install_addons.push(InstallAddon {
+                id: id.clone(),
+                kind,
+                status: InstallStatus::Downloading,
+                addon: None,
+            });
+
+            return Ok(Command::perform(
+                perform_fetch_latest_addon(kind, id, flavor),
+                Message::InstallAddonFetched,
+            ));
+        }
+        Message::Interaction(Interaction::CatalogCategorySelected(category)) => {
+            log::debug!("Interaction::CatalogCategorySelected({})", &category);
+
+            // Select category
+            ajour.catalog_search_state.category = category;
+
+            query_and_sort_catalog(ajour);
+        }
+        Message::Interaction(Interaction::CatalogResultSizeSelected(size)) => {
+            log::debug!("Interaction::CatalogResultSizeSelected({:?})", &size);
+
+            // Catalog result size
+            ajour.catalog_search_state.result_size = size;
+
+            query_and_sort_catalog(ajour);
+        }
+        Message::Interaction(Interaction::CatalogSourceSelected(source)) => {
+            log::debug!("Interaction::CatalogSourceSelected({:?})", source);
+
+            // Save the specific source to the config, otherwise we set `None`
+            match source {
+                CatalogSource::All => {
+                    ajour.config.catalog_source = None;
+                }
+                CatalogSource::Choice(source) => {
+                    ajour.config.catalog_source = Some(source);
+                }
+            }
+
+            // Save to config
+            let _ = ajour.config.save();
+
+            ajour.catalog_search_state.categories = ajour
+                .catalog_categories_per_source_cache
+                .get(&source.to_string())
+                .cloned()
+                .unwrap_or_default();
+
+            ajour.catalog_search_state.category = CatalogCategory::All;
+
+            query_and_sort_catalog(ajour);
+        }
+        Message::InstallAddonFetched((flavor, id, result)) => {
+            let install_addons = ajour.install_addons.entry(flavor).or_default();
+
+            if let Some(install_addon) = install_addons.iter_mut().find(|a| a.id == id) {
+                match result {
+                    Ok(mut addon) => {
+                        log::debug!(
+                            "Message::CatalogInstallAddonFetched({:?}, {:?})",
+                            flavor,
+                            &id,
+                        );
+
+                        addon.state = AddonState::Downloading;
+                        install_addon.addon = Some(addon.clone());
+
+                        let global_release_channel = ajour.config.addons.global_release_channel;
+                        let to_directory = ajour
+                            .config
+                            .get_download_directory_for_flavor(flavor)
+                            .expect("Expected a valid path");
+
+                        return Ok(Command::perform(
+                            perform_download_addon(
+                                DownloadReason::Install,
+                                flavor,
+                                global_release_channel,
+                                addon,
+                                to_directory,
+                            ),
+                            Message::DownloadedAddon,
+                        ));
+                    }
+                    Err(error) => {
+                        // Dont use `context` here to convert to anyhow::Error since
+                        // we actually want to show the underlying RepositoryError
+                        // message
+                        let error = anyhow::Error::new(error);
+
+                        log_error(&error);
+
+                        match install_addon.kind {
+                            InstallKind::Catalog { .. } => {
+                                install_addon.status = InstallStatus::Unavailable;
+                            }
+                            InstallKind::Source | InstallKind::Import { .. } => {
+                                install_addon.status = InstallStatus::Error(error.to_string());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Message::AddonCacheUpdated(Ok(entry)) => {
+            log::debug!("Message::AddonCacheUpdated({})", entry.title);
+        }
+        Message::AddonCacheEntryRemoved(maybe_entry) => {
+            match maybe_entry.context(localized_string("error-remove-cache")) {
+                Ok(Some(entry)) => log::debug!("Message::AddonCacheEntryRemoved({})", entry.title),
+                Ok(None) => {}
+                Err(e) => {
+                    log_error(&e);
+                }
+            }
+        }
+        Message::Interaction(Interaction::InstallScmQuery(query)) => {
+            // install from scm search query
+            ajour.install_from_scm_state.query = Some(query);
+
+            // Remove the status if it's an error and user typed into
+            // text input
+            {
+                let install_addons = ajour
+                    .install_addons
+                    .entry(ajour.config.wow.flavor)
+                    .or_default();
+
+                if let Some((idx, install_addon)) = install_addons
+                    .iter()
+                    .enumerate()
+                    .find(|(_, a)| a.kind == InstallKind::Source)
+                {
+                    if matches!(install_addon.status, InstallStatus::Error(_)) {
+                        install_addons.remove(idx);
+                    }
+                }
+            }
+        }
+        Message::Interaction(Interaction::InstallScmUrl) => {
+            if let Some(url) = ajour.install_from_scm_state.query.clone() {
+                if !url.is_empty() {
+                    return handle_message(
+                        ajour,
+                        Message::Interaction(Interaction::InstallAddon(
+                            ajour.config.wow.flavor,
+                            url,
+                            InstallKind::Source,
+                        )),
+                    );
+                }
+            }
+        }
+        Message::RefreshCatalog(_) => {
+            if let Some(last_updated) = &ajour.catalog_last_updated {
+                let now = Utc::now();
+                let now_time = now.time();
+                let refresh_time = NaiveTime::from_hms_opt(2, 0, 0);
+
+                // TODO: using unwrap() here leads to the "old" behavior, panicing on null 
+                // this might not be what we desire, so I'll leave this todo for future reference
+                if last_updated.date_naive() < now.date_naive() && now_time > refresh_time.unwrap() {
+                    log::debug!("Message::RefreshCatalog: catalog needs to be refreshed");
+
+                    return Ok(Command::perform(
+                        catalog_download_latest_or_use_cache(),
+                        Message::CatalogDownloaded,
+                    ));
+                }
+            }
+        }
+        Message::Interaction(Interaction::ToggleHideIgnoredAddons(is_checked)) => {
+            log::debug!("Interaction::ToggleHideIgnoredAddons({})", is_checked);
+
+            ajour.config.hide_ignored_addons = is_checked;
+            let _ = ajour.config.save();
+        }
+        Message::Interaction(Interaction::ToggleDeleteSavedVariables(is_checked)) => {
+            log::debug!("Interaction::ToggleDeleteSavedVariables({})", is_checked);
+
+            ajour.config.addons.delete_saved_variables = is_checked;
+            let _ = ajour.config.save();
+        }
+        Message::CatalogDownloaded(error @ Err(_)) => {
+            let error = error.context("Failed to download catalog").unwrap_err();
+            log_error(&error);
+            ajour.state.insert(Mode::Catalog, State::Error(error));
+        }
+        Message::AddonCacheUpdated(error @ Err(_)) => {
+            let error = error.context("Failed to update addon cache").unwrap_err();
+            log_error(&error);
+            ajour.error = Some(error);
+        }
+        Message::Interaction(Interaction::PickLocalizationLanguage(lang)) => {
+            log::debug!("Interaction::PickLocalizationLanguage({:?})", lang);
+
+            // Update config.
+            ajour.config.language = lang;
+            let _ = ajour.config.save();
+
+            // Update global LANG refcell.
+            *LANG.get().expect("LANG not set").write().unwrap() = lang.language_code();
+        }
+        Message::Interaction(Interaction::PickGlobalReleaseChannel(channel)) => {
+            log::debug!("Interaction::PickGlobalReleaseChannel({:?})", channel);
+
+            // Update all addon states, expect ignored, if needed.
+            let flavors = &Flavor::ALL[..];
+            for flavor in flavors {
+                let ignored_ids = ajour.config.addons.ignored.entry(*flavor).or_default();
+                let mut addons: Vec<_> = ajour
+                    .addons
+                    .entry(*flavor)
+                    .or_default()
+                    .iter_mut()
+                    .filter(|a| !ignored_ids.iter().any(|i| i == &a.primary_folder_id))
+                    .collect();
+                for addon in addons.iter_mut() {
+                    // Check if addon is updatable.
+                    if let Some(package) = addon.relevant_release_package(channel) {
+                        if addon.is_updatable(&package) {
+                            addon.state = AddonState::Updatable;
+                        } else {
+                            addon.state = AddonState::Idle;
+                        }
+                    }
+                }
+            }
+
+            ajour.config.addons.global_release_channel = channel;
+            let _ = ajour.config.save();
+        }
+        Message::Interaction(Interaction::AlternatingRowColorToggled(is_set)) => {
+            log::debug!(
+                "Interaction::AlternatingRowColorToggled(is_set: {})",
+                is_set,
+            );
+
+            ajour.config.alternating_row_colors = is_set;
+            let _ = ajour.config.save();
+        }
+        Message::Interaction(Interaction::KeybindingsToggle(is_set)) => {
+            log::debug!("Interaction::KeybindingsToggle(is_set: {})", is_set,);
+
+            ajour.config.is_keybindings_enabled = is_set;
+            let _ = ajour.config.save();
+        }
+        Message::Interaction(Interaction::ExportAddons) => {
+            log::debug!("Interaction::ExportAddons");
+
+            return Ok(Command::perform(
+                select_export_file(),
+                Message::ExportAddons,
+            ));
+        }
+        Message::ExportAddons(path) => {
+            if let Some(path) = path {
+                log::debug!("Message::ExportAddons({:?})", &path);
+
+                let addons = ajour.addons.clone();
+
+                return Ok(Command::perform(
+                    async { share::export(addons, path) },
+                    Message::AddonsExported,
+                ));
+            }
+        }
+        Message::AddonsExported(result) => match result.context("Failed to export addons") {
+            Ok(_) => {
+                log::debug!("Message::AddonsExported");
+            }
+            Err(error) => {
+                log_error(&error);
+
+                ajour.error = Some(error);
+            }
+        },
+        Message::Interaction(Interaction::ImportAddons) => {
+            log::debug!("Interaction::ImportAddons");
+
+            return Ok(Command::perform(
+                select_import_file(),
+                Message::ImportAddons,
+            ));
+        }
+        Message::ImportAddons(path) => {
+            if let Some(path) = path {
+                log::debug!("Message::ImportAddons({:?})", &path);
+
+                let current_addons = ajour.addons.clone();
+
+                ajour.mode = Mode::MyAddons(ajour.config.wow.flavor);
+
+                return Ok(Command::perform(
+                    async { share::parse_only_needed(current_addons, path) },
+                    Message::ImportParsed,
+                ));
+            }
+        }
+        Message::ImportParsed(result) => match result.context("Failed to parse import file") {
+            Ok(parsed) => {
+                log::debug!("Message::ImportParsed");
+
+                let mut commands = vec![];
+
+                for (flavor, parsed) in parsed.into_iter() {
+                    for data in parsed.data {
+                        let id = data.id.clone();
+                        let repo_kind = data.repo_kind;
+                        let install_kind = InstallKind::Import { repo_kind };
+
+                        let command = Command::perform(
+                            async move { (flavor, id, install_kind) },
+                            |(a, b, c)| Message::Interaction(Interaction::InstallAddon(a, b, c)),
+                        );
+
+                        commands.push(command);
+                    }
+                }
+
+                return Ok(Command::batch(commands));
+            }
+            Err(error) => {
+                log_error(&error);
+
+                ajour.error = Some(error);
+            }
+        },
+        Message::Interaction(Interaction::ExportSongs) => {
+            log::debug!("Interaction::ExportSongs");
+
+            return Ok(Command::perform(
+                select_songs_export_file(),
+                Message::ExportSongs,
+            ));
+        }
+        Message::Interaction(Interaction::ToggleShareUserData(is_checked)) => {
+            log::debug!("Interaction::ToggleShareUserData({})", is_checked);
+
+            ajour.share_state.with_user_data = is_checked;
+        }
+        Message::ExportSongs(path) => {
+            if let Some(path) = path {
+                log::debug!("Message::ExportSongs({:?})", &path);
+
+                let songs = ajour.songs.clone();
+                let with_user_data = ajour.share_state.with_user_data;
+
+                return Ok(Command::perform(
+                    async move { share::export_songs(&songs, path, with_user_data) },
+                    Message::SongsExported,
+                ));
+            }
+        }
+        Message::SongsExported(result) => {
+            match result.context(localized_string("error-export-songs")) {
+                Ok(_) => {
+                    log::debug!("Message::SongsExported");
+                }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.error = Some(error);
+                }
+            }
+        }
+        Message::Interaction(Interaction::ExportSongReport) => {
+            log::debug!("Interaction::ExportSongReport");
+
+            return Ok(Command::perform(
+                select_report_export_file(),
+                Message::ExportSongReport,
+            ));
+        }
+        Message::ExportSongReport(path) => {
+            if let Some(path) = path {
+                log::debug!("Message::ExportSongReport({:?})", &path);
+
+                let format = ReportFormat::from_path(&path).unwrap_or(ReportFormat::Html);
+
+                // Exports the songs currently shown in My Songs.
+                let has_query = ajour.songs_search_state.query.is_some();
+                let play_filter = ajour.songs_search_state.play_filter;
+                let smart_filters_state = &ajour.smart_filters_state;
+
+                let songs: Vec<Song> = ajour
+                    .songs
+                    .iter()
+                    .filter(|s| !has_query || s.fuzzy_score.is_some())
+                    .filter(|s| play_filter.matches(s))
+                    .filter(|s| smart_filters_state.matches(s))
+                    .cloned()
+                    .collect();
+
+                return Ok(Command::perform(
+                    async move { export_report(&songs, format, path) },
+                    Message::SongReportExported,
+                ));
+            }
+        }
+        Message::SongReportExported(result) => {
+            match result.context(localized_string("error-export-song-report")) {
+                Ok(_) => {
+                    log::debug!("Message::SongReportExported");
+                }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.error = Some(error);
+                }
+            }
+        }
+        Message::Interaction(Interaction::ImportSongs) => {
+            log::debug!("Interaction::ImportSongs");
+
+            return Ok(Command::perform(
+                select_songs_import_file(),
+                Message::ImportSongs,
+            ));
+        }
+        Message::ImportSongs(path) => {
+            if let Some(path) = path {
+                log::debug!("Message::ImportSongs({:?})", &path);
+
+                let current_songs = ajour.songs.clone();
+                let with_user_data = ajour.share_state.with_user_data;
+
+                return Ok(Command::perform(
+                    async move { share::parse_songs_only_needed(&current_songs, path, with_user_data) },
+                    Message::ImportSongsParsed,
+                ));
+            }
+        }
+        Message::ImportSongsParsed(result) => {
+            match result.context(localized_string("error-import-songs")) {
+                Ok(parsed) => {
+                    log::debug!(
+                        "Message::ImportSongsParsed({} resolvable, {} unresolvable, {} ignored)",
+                        parsed.resolvable.len(),
+                        parsed.unresolvable.len(),
+                        parsed.ignored
+                    );
+
+                    ajour.share_state.unresolved_songs =
+                        parsed.unresolvable.iter().map(|data| data.name()).collect();
+
+                    // Tags and ratings of the shared songs if asked for, unless
+                    // we have our own.
+                    if ajour.user_data.merge(parsed.user_data.clone()) > 0 {
+                        let _ = ajour.user_data.save();
+                        ajour.user_data.apply(&mut ajour.songs);
+                        apply_smart_filters(ajour);
+                    }
+
+                    // Install into the library selected in My Songs, or the first one.
+                    let root = ajour
+                        .song_transfer_state
+                        .target
+                        .clone()
+                        .map(|SongDirectory(root)| root)
+                        .or_else(|| ajour.config.songs.directories.first().cloned());
+
+                    if let Some(root) = root {
+                        ajour.share_state.songs_pending += parsed.resolvable.len();
+
+                        let commands = parsed
+                            .resolvable
+                            .into_iter()
+                            .map(|data| {
+                                Command::perform(
+                                    install_shared_song(
+                                        ajour.song_cache.clone(),
+                                        data,
+                                        root.clone(),
+                                    ),
+                                    Message::SharedSongInstalled,
+                                )
+                            })
+                            .collect::<Vec<_>>();
+
+                        return Ok(Command::batch(commands));
+                    }
+                }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.error = Some(error);
+                }
+            }
+        }
+        Message::SharedSongInstalled(result) => {
+            ajour.share_state.songs_pending = ajour.share_state.songs_pending.saturating_sub(1);
+
+            match result.context(localized_string("error-install-shared-song")) {
+                Ok(mut song) => {
+                    log::debug!("Message::SharedSongInstalled({:?})", &song.path);
+
+                    ajour.score_data.apply(std::slice::from_mut(&mut song));
+                    ajour.user_data.apply(std::slice::from_mut(&mut song));
+                    ajour.songs.push(song);
+                    apply_smart_filters(ajour);
+
+                    let column_key = ajour
+                        .song_header_state
+                        .previous_column_key
+                        .unwrap_or(SongColumnKey::Title);
+                    let sort_direction = ajour
+                        .song_header_state
+                        .previous_sort_direction
+                        .unwrap_or(SortDirection::Asc);
+
+                    sort_songs(&mut ajour.songs, sort_direction, column_key);
+                }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.error = Some(error);
+                }
+            }
+        }
+        Message::ParsedSongs((root, result)) => {
+            ajour.state.insert(Mode::MySongs, State::Ready);
+
+            match result.context(localized_string("error-parse-songs")) {
+                Ok(mut songs) => {
+                    log::debug!("Message::ParsedSongs({:?}, {} songs)", &root, songs.len());
+
+                    // Join play history, user data and Clone Hero's load errors
+                    // onto the songs.
+                    ajour.score_data.apply(&mut songs);
+                    ajour.user_data.apply(&mut songs);
+                    ajour.bad_songs.apply(&mut songs);
+                    ajour.song_updates.apply(&mut songs);
+
+                    // Replace any songs previously parsed from this root.
+                    ajour.songs.retain(|s| s.root != root);
+                    ajour.songs.extend(songs);
+
+                    // Forget selected songs which are gone.
+                    let paths: HashSet<&PathBuf> = ajour.songs.iter().map(|s| &s.path).collect();
+                    ajour.transfer_selection.retain(|path| paths.contains(path));
+
+                    let column_key = ajour
+                        .song_header_state
+                        .previous_column_key
+                        .unwrap_or(SongColumnKey::Title);
+                    let sort_direction = ajour
+                        .song_header_state
+                        .previous_sort_direction
+                        .unwrap_or(SortDirection::Asc);
+
+                    sort_songs(&mut ajour.songs, sort_direction, column_key);
+
+                    // Score the new songs against the search query.
+                    apply_songs_query(ajour);
+                    apply_smart_filters(ajour);
+                }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.state.insert(Mode::MySongs, State::Error(error));
+                }
+            }
+        }
+        Message::ScoreDataLoaded(result) => {
+            match result.context(localized_string("error-load-score-data")) {
+                Ok(score_data) => {
+                    log::debug!("Message::ScoreDataLoaded({} songs)", score_data.songs.len());
+
+                    score_data.apply(&mut ajour.songs);
+                    ajour.score_data = score_data;
+                }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.error = Some(error);
+                }
+            }
+        }
+        Message::BadSongsLoaded(result) => {
+            match result.context(localized_string("error-load-bad-songs")) {
+                Ok(bad_songs) => {
+                    log::debug!("Message::BadSongsLoaded({} songs)", bad_songs.songs.len());
+
+                    bad_songs.apply(&mut ajour.songs);
+                    ajour.bad_songs = bad_songs;
+                }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.error = Some(error);
+                }
+            }
+        }
+        Message::Interaction(Interaction::ValidateSong(path)) => {
+            log::debug!("Interaction::ValidateSong({:?})", &path);
+
+            if let Some(song) = ajour.songs.iter().find(|s| s.path == path) {
+                return Ok(Command::perform(
+                    validate_song_folder(song.root.clone(), song.path.clone()),
+                    Message::SongValidated,
+                ));
+            }
+        }
+        Message::SongValidated(mut song) => {
+            log::debug!("Message::SongValidated({}, {:?})", &song.id, &song.state);
+
+            ajour.score_data.apply(std::slice::from_mut(&mut song));
+            ajour.user_data.apply(std::slice::from_mut(&mut song));
+
+            if let Some(existing) = ajour
+                .songs
+                .iter_mut()
+                .find(|s| s.root == song.root && s.id == song.id)
+            {
+                *existing = song;
+            }
+        }
+        Message::Interaction(Interaction::QuarantineSong) => {
+            log::debug!("Interaction::QuarantineSong");
+            ajour.pending_confirmation = Some(Confirm::QuarantineSong);
+        }
+        Message::Interaction(Interaction::ConfirmQuarantineSong(path)) => {
+            log::debug!("Interaction::ConfirmQuarantineSong({:?})", &path);
+
+            // Remove any pending confirms.
+            ajour.pending_confirmation = None;
+
+            if let Some(song) = ajour.songs.iter().find(|s| s.path == path).cloned() {
+                return Ok(Command::perform(
+                    perform_quarantine_song(song, ajour.config.songs.quarantine_directory()),
+                    Message::SongQuarantined,
+                ));
+            }
+        }
+        Message::SongQuarantined((song, result)) => {
+            match result.context(localized_string("error-quarantine-song")) {
+                Ok(path) => {
+                    log::debug!("Message::SongQuarantined({}, {:?})", &song.id, &path);
+
+                    // Close details if shown.
+                    ajour.expanded_song = None;
+
+                    // Remove from local state.
+                    ajour
+                        .songs
+                        .retain(|s| !(s.root == song.root && s.id == song.id));
+                    apply_smart_filters(ajour);
+
+                    // Remove song from cache
+                    if let Some(song_cache) = &ajour.song_cache {
+                        return Ok(Command::perform(
+                            remove_song_cache_entry(song_cache.clone(), song.root, song.path),
+                            Message::SongCacheEntryRemoved,
+                        ));
+                    }
+                }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.error = Some(error);
+                }
+            }
+        }
+        Message::SongCacheEntryRemoved(maybe_entry) => {
+            match maybe_entry.context(localized_string("error-remove-cache")) {
+                Ok(Some(entry)) => {
+                    log::debug!("Message::SongCacheEntryRemoved({:?})", entry.path)
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    log_error(&e);
+                }
+            }
+        }
+        Message::Interaction(Interaction::OrganizeTemplateChanged(template)) => {
+            ajour.song_settings_state.organize_template = template;
+
+            // The preview no longer matches the template.
+            ajour.song_settings_state.organize_plans = None;
+        }
+        Message::Interaction(Interaction::PreviewOrganize) => {
+            let template = ajour.song_settings_state.organize_template.clone();
+
+            log::debug!("Interaction::PreviewOrganize({})", &template);
+
+            let plans = ajour
+                .config
+                .songs
+                .directories
+                .iter()
+                .map(|root| plan_organize(&template, root, &ajour.songs))
+                .collect::<Result<Vec<_>, _>>();
+
+            match plans.context(localized_string("error-organize-template")) {
+                Ok(plans) => {
+                    ajour.song_settings_state.organize_plans =
+                        Some(plans.into_iter().flatten().collect());
+
+                    ajour.config.songs.organize_template = Some(template);
+                    let _ = ajour.config.save();
+                }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.error = Some(error);
+                }
+            }
+        }
+        Message::Interaction(Interaction::ApplyOrganize) => {
+            log::debug!("Interaction::ApplyOrganize");
+
+            if let Some(plans) = ajour.song_settings_state.organize_plans.take() {
+                return Ok(Command::perform(
+                    apply_rename_plans(ajour.song_cache.clone(), plans),
+                    Message::SongsOrganized,
+                ));
+            }
+        }
+        Message::SongsOrganized(result) => {
+            match result.context(localized_string("error-organize-songs")) {
+                Ok(result) => {
+                    log::debug!(
+                        "Message::SongsOrganized({} moved, {} failed)",
+                        result.renamed.len(),
+                        result.failed.len()
+                    );
+
+                    // Close details if shown, the id might have changed.
+                    ajour.expanded_song = None;
+
+                    // Update the moved songs in place instead of rescanning.
+                    for plan in result.renamed.iter() {
+                        if let Some(song) = ajour
+                            .songs
+                            .iter_mut()
+                            .find(|s| s.root == plan.root && s.path == plan.from)
+                        {
+                            song.id = plan.id();
+                            song.path = plan.to.clone();
+                        }
+                    }
+
+                    apply_smart_filters(ajour);
+
+                    if let Some((plan, reason)) = result.failed.first() {
+                        let error = anyhow::anyhow!("{:?}: {}", plan.from, reason)
+                            .context(localized_string("error-organize-songs"));
+                        log_error(&error);
+                        ajour.error = Some(error);
+                    }
+                }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.error = Some(error);
+                }
+            }
+        }
+        Message::Interaction(Interaction::SongTransferTargetSelected(target)) => {
+            log::debug!("Interaction::SongTransferTargetSelected({})", &target);
+
+            ajour.song_transfer_state.target = Some(target);
+
+            // Remove any pending confirms.
+            ajour.pending_confirmation = None;
+        }
+        Message::Interaction(Interaction::ToggleSongSelected(path, is_checked)) => {
+            log::debug!(
+                "Interaction::ToggleSongSelected({:?}, {})",
+                &path,
+                is_checked
+            );
+
+            if is_checked {
+                ajour.transfer_selection.insert(path);
+            } else {
+                ajour.transfer_selection.remove(&path);
+            }
+        }
+        Message::Interaction(Interaction::SelectShownSongs) => {
+            log::debug!("Interaction::SelectShownSongs");
+
+            let selection: HashSet<PathBuf> = ajour
+                .songs
+                .iter()
+                .filter(|s| is_song_shown(ajour, s))
+                .map(|s| s.path.clone())
+                .collect();
+
+            ajour.transfer_selection = selection;
+        }
+        Message::Interaction(Interaction::ClearSongSelection) => {
+            log::debug!("Interaction::ClearSongSelection");
+
+            ajour.transfer_selection.clear();
+        }
+        Message::Interaction(Interaction::TransferSongs(mode)) => {
+            log::debug!("Interaction::TransferSongs({})", mode);
+
+            // Moving songs has to be confirmed.
+            if mode == TransferMode::Move && ajour.pending_confirmation != Some(Confirm::MoveSongs)
+            {
+                ajour.pending_confirmation = Some(Confirm::MoveSongs);
+                return Ok(Command::none());
+            }
+
+            // Remove any pending confirms.
+            ajour.pending_confirmation = None;
+
+            let target = match ajour.song_transfer_state.target.clone() {
+                Some(SongDirectory(target)) => target,
+                None => return Ok(Command::none()),
+            };
+
+            // Transfers the selected songs, except those already in the
+            // target root.
+            let songs: Vec<Song> = ajour
+                .songs
+                .iter()
+                .filter(|s| ajour.transfer_selection.contains(&s.path))
+                .filter(|s| s.root != target)
+                .cloned()
+                .collect();
+
+            if songs.is_empty() {
+                return Ok(Command::none());
+            }
+
+            ajour.transfer_selection.clear();
+
+            ajour.song_transfer_state.progress = Some((0, songs.len()));
+            ajour.song_transfer_state.running = Some((mode, target.clone()));
+
+            let folders = songs.iter().map(|s| s.path.clone()).collect();
+
+            return Ok(Command::perform(
+                check_available_space(folders, target),
+                move |result| Message::SongTransferSpaceChecked((mode, songs, result)),
+            ));
+        }
+        Message::SongTransferSpaceChecked((mode, songs, result)) => {
+            match result.context(localized_string("error-transfer-songs")) {
+                Ok(required) => {
+                    log::debug!(
+                        "Message::SongTransferSpaceChecked({}, {} songs, {} bytes)",
+                        mode,
+                        songs.len(),
+                        required
+                    );
+
+                    ajour.song_transfer_state.queue = songs;
+
+                    if let Some(command) = next_song_transfer_batch(ajour) {
+                        return Ok(command);
+                    }
+                }
+                Err(error) => {
+                    ajour.song_transfer_state.running = None;
+                    ajour.song_transfer_state.progress = None;
+
+                    log_error(&error);
+                    ajour.error = Some(error);
+                }
+            }
+        }
+        Message::SongsTransferred((mode, result)) => {
+            match result.context(localized_string("error-transfer-songs")) {
+                Ok(result) => {
+                    log::debug!(
+                        "Message::SongsTransferred({}, {} done, {} failed)",
+                        mode,
+                        result.transferred.len(),
+                        result.failed.len()
+                    );
+
+                    // Close details if shown.
+                    ajour.expanded_song = None;
+
+                    if let Some((done, _)) = ajour.song_transfer_state.progress.as_mut() {
+                        *done += result.transferred.len() + result.failed.len();
+                    }
+
+                    // Update local state instead of rescanning both roots.
+                    for (from, to) in result.transferred {
+                        if mode == TransferMode::Move {
+                            ajour
+                                .songs
+                                .retain(|s| !(s.root == from.root && s.path == from.path));
+                        }
+
+                        ajour.songs.push(to);
+                    }
+
+                    apply_smart_filters(ajour);
+
+                    let column_key = ajour
+                        .song_header_state
+                        .previous_column_key
+                        .unwrap_or(SongColumnKey::Title);
+                    let sort_direction = ajour
+                        .song_header_state
+                        .previous_sort_direction
+                        .unwrap_or(SortDirection::Asc);
+
+                    sort_songs(&mut ajour.songs, sort_direction, column_key);
+
+                    if let Some((song, reason)) = result.failed.first() {
+                        let error = anyhow::anyhow!("{:?}: {}", song.path, reason)
+                            .context(localized_string("error-transfer-songs"));
+                        log_error(&error);
+                        ajour.error = Some(error);
+                    }
+
+                    if let Some(command) = next_song_transfer_batch(ajour) {
+                        return Ok(command);
+                    }
+                }
+                Err(error) => {
+                    ajour.song_transfer_state.queue.clear();
+                    ajour.song_transfer_state.running = None;
+                    ajour.song_transfer_state.progress = None;
+
+                    log_error(&error);
+                    ajour.error = Some(error);
+                }
+            }
+        }
+        Message::ParsedPacks((root, result)) => {
+            match result.context(localized_string("error-parse-packs")) {
+                Ok(packs) => {
+                    log::debug!("Message::ParsedPacks({:?}, {} packs)", &root, packs.len());
+
+                    // Replace any packs previously found in this root.
+                    ajour.packs.retain(|p| p.root != root);
+                    ajour.packs.extend(packs);
+                    ajour.packs.sort_by_key(|p| p.name().to_lowercase());
+                }
+                Err(error) => {
+                    log_error(&error);
+                }
+            }
+        }
+        Message::Interaction(Interaction::ExpandPack(path)) => {
+            log::debug!("Interaction::ExpandPack({:?})", &path);
+
+            if !ajour.expanded_packs.remove(&path) {
+                ajour.expanded_packs.insert(path);
+            }
+
+            // Remove any pending confirms.
+            ajour.pending_confirmation = None;
+        }
+        Message::Interaction(Interaction::InstallPack) => {
+            log::debug!("Interaction::InstallPack");
+
+            return Ok(Command::perform(select_pack_file(), Message::InstallPack));
+        }
+        Message::InstallPack(path) => {
+            if let Some(path) = path {
+                log::debug!("Message::InstallPack({:?})", &path);
+
+                // Install into the library selected in My Songs, or the first one.
+                let root = ajour
+                    .song_transfer_state
+                    .target
+                    .clone()
+                    .map(|SongDirectory(root)| root)
+                    .or_else(|| ajour.config.songs.directories.first().cloned());
+
+                if let Some(root) = root {
+                    return Ok(Command::perform(
+                        install_pack(
+                            ajour.song_cache.clone(),
+                            ajour.version_store.clone(),
+                            path.to_string_lossy().to_string(),
+                            root,
+                            None,
+                            None,
+                        ),
+                        Message::PackInstalled,
+                    ));
+                }
+            }
+        }
+        Message::Interaction(Interaction::UpdatePack(path)) => {
+            log::debug!("Interaction::UpdatePack({:?})", &path);
+
+            if let Some(pack) = ajour.packs.iter().find(|p| p.path == path).cloned() {
+                return Ok(Command::perform(
+                    update_pack(
+                        ajour.song_cache.clone(),
+                        ajour.version_store.clone(),
+                        pack,
+                        None,
+                    ),
+                    Message::PackInstalled,
+                ));
+            }
+        }
+        Message::PackInstalled(result) => {
+            match result.context(localized_string("error-install-pack")) {
+                Ok(pack) => {
+                    log::debug!(
+                        "Message::PackInstalled({}, {} songs)",
+                        pack.name(),
+                        pack.songs.len()
+                    );
+
+                    // Close details if shown.
+                    ajour.expanded_song = None;
+
+                    // Show the songs of the installed pack.
+                    ajour.expanded_packs.insert(pack.path.clone());
+
+                    // The songs of the pack are fresh from its source.
+                    ajour
+                        .song_updates
+                        .songs
+                        .retain(|path, _| !pack.contains(path));
+
+                    ajour.state.insert(Mode::MySongs, State::Loading);
+
+                    return Ok(Command::batch(read_song_root(ajour, pack.root)));
+                }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.error = Some(error);
+                }
+            }
+        }
+        Message::Interaction(Interaction::DeletePack()) => {
+            log::debug!("Interaction::DeletePack()");
+            ajour.pending_confirmation = Some(Confirm::DeletePack);
+        }
+        Message::Interaction(Interaction::ConfirmDeletePack(path)) => {
+            log::debug!("Interaction::ConfirmDeletePack({:?})", &path);
+
+            // Remove any pending confirms.
+            ajour.pending_confirmation = None;
+
+            if let Some(pack) = ajour.packs.iter().find(|p| p.path == path).cloned() {
+                return Ok(Command::perform(
+                    delete_pack(ajour.song_cache.clone(), pack),
+                    Message::PackDeleted,
+                ));
+            }
+        }
+        Message::PackDeleted(result) => {
+            match result.context(localized_string("error-delete-pack")) {
+                Ok(pack) => {
+                    log::debug!("Message::PackDeleted({})", pack.name());
 
-            ajour.state.insert(Mode::Catalog, State::Ready);
+                    // Close details if shown.
+                    ajour.expanded_song = None;
+                    ajour.expanded_packs.remove(&pack.path);
 
-            query_and_sort_catalog(ajour);
+                    // Remove the pack and its songs from local state.
+                    ajour.songs.retain(|s| !pack.contains(&s.path));
+                    ajour.packs.retain(|p| p.path != pack.path);
+                    apply_smart_filters(ajour);
+                }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.error = Some(error);
+                }
+            }
         }
-        Message::Interaction(Interaction::AddonsQuery(query)) => {
-            // Addons search query
-            ajour.addons_search_state.query = if query.is_empty() { None } else { Some(query) };
+        Message::SongsUpdated(result) => {
+            // Songs which were being updated go back to idle, the updated ones
+            // are replaced below.
+            for song in ajour.songs.iter_mut() {
+                if song.state == SongState::Downloading {
+                    song.state = SongState::Idle;
+                }
+            }
 
-            // Increase penalty for gaps between matching characters
-            let fuzzy_match_config = SkimScoreConfig {
-                gap_start: -12,
-                gap_extension: -6,
-                ..Default::default()
-            };
-            let fuzzy_matcher = SkimMatcherV2::default().score_config(fuzzy_match_config);
+            match result.context(localized_string("error-update-songs")) {
+                Ok(result) => {
+                    log::debug!(
+                        "Message::SongsUpdated({} songs, {} failed)",
+                        result.updated.len(),
+                        result.failed.len()
+                    );
 
-            let addons = ajour.addons.entry(ajour.config.wow.flavor).or_default();
-            let global_release_channel = ajour.config.addons.global_release_channel;
+                    let mut carried_over = false;
 
-            if let Some(query) = &ajour.addons_search_state.query {
-                addons.iter_mut().for_each(|a| {
-                    a.fuzzy_score.take();
+                    for mut song in result.updated {
+                        ajour.song_updates.songs.remove(&song.path);
+                        ajour.bad_songs.apply(std::slice::from_mut(&mut song));
 
-                    if let Some(score) = fuzzy_matcher.fuzzy_match(a.title(), query) {
-                        if score > 0 {
-                            a.fuzzy_score = Some(score);
+                        if let Some(existing) = ajour.songs.iter_mut().find(|s| s.path == song.path)
+                        {
+                            // Tags and ratings stay with the updated chart.
+                            if let (Some(from), Some(to)) = (&existing.hash, &song.hash) {
+                                carried_over |= ajour.user_data.carry_over(from, to);
+                            }
+                            ajour.user_data.apply(std::slice::from_mut(&mut song));
+
+                            *existing = song;
                         }
                     }
-                });
 
-                // Sort the addons by score
-                sort_addons(
-                    addons,
-                    global_release_channel,
-                    SortDirection::Desc,
-                    ColumnKey::FuzzyScore,
-                );
-                ajour.header_state.previous_sort_direction = Some(SortDirection::Desc);
-                ajour.header_state.previous_column_key = Some(ColumnKey::FuzzyScore);
-            } else {
-                // Clear out the fuzzy scores
-                addons.iter_mut().for_each(|a| {
-                    a.fuzzy_score.take();
-                });
+                    if carried_over {
+                        let _ = ajour.user_data.save();
+                    }
 
-                // Use default sort
-                sort_addons(
-                    addons,
-                    global_release_channel,
-                    SortDirection::Desc,
-                    ColumnKey::Status,
-                );
-                ajour.header_state.previous_sort_direction = Some(SortDirection::Desc);
-                ajour.header_state.previous_column_key = Some(ColumnKey::Status);
+                    apply_smart_filters(ajour);
+
+                    if let Some((song, reason)) = result.failed.first() {
+                        let error = anyhow::anyhow!("{:?}: {}", song.path, reason)
+                            .context(localized_string("error-update-songs"));
+                        log_error(&error);
+                        ajour.error = Some(error);
+                    }
+                }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.error = Some(error);
+                }
             }
-        }
-        Message::Interaction(Interaction::CatalogQuery(query)) => {
-            // Catalog search query
-            ajour.catalog_search_state.query = if query.is_empty() {
-                None
-            } else {
-                // Always set sort config to None when a new character is typed
-                // so the sort will be off fuzzy match score.
-                ajour.catalog_header_state.previous_column_key.take();
-                ajour.catalog_header_state.previous_sort_direction.take();
 
-                Some(query)
-            };
+            ajour.song_updates.apply(&mut ajour.songs);
+        }
+        Message::Interaction(Interaction::ToggleSongAutoUpdate(path, enabled)) => {
+            log::debug!(
+                "Interaction::ToggleSongAutoUpdate({:?}, {})",
+                &path,
+                enabled
+            );
 
-            query_and_sort_catalog(ajour);
+            ajour.config.songs.auto_update.set_enabled(path, enabled);
+            let _ = ajour.config.save();
         }
-        Message::Interaction(Interaction::InstallAddon(flavor, id, kind)) => {
-            log::debug!("Interaction::InstallAddon({}, {:?})", flavor, &kind);
+        Message::Interaction(Interaction::SongAutoUpdateIntervalSelected(UpdateInterval(
+            hours,
+        ))) => {
+            log::debug!("Interaction::SongAutoUpdateIntervalSelected({})", hours);
 
-            let install_addons = ajour.install_addons.entry(flavor).or_default();
+            ajour.config.songs.auto_update.interval_hours = Some(hours);
+            let _ = ajour.config.save();
+        }
+        Message::Interaction(Interaction::ToggleSongQuietHours(enabled)) => {
+            log::debug!("Interaction::ToggleSongQuietHours({})", enabled);
 
-            // Remove any existing status for this addon since we are going
-            // to try and download it again. For InstallKind::Source, we should only
-            // ever have one entry here so we just remove it
-            install_addons.retain(|a| match kind {
-                InstallKind::Catalog { .. } | InstallKind::Import { .. } => {
-                    !(id == a.id && a.kind == kind)
-                }
-                InstallKind::Source => a.kind != kind,
-            });
+            // Default to a quiet night.
+            ajour.config.songs.auto_update.quiet_hours = if enabled { Some((22, 7)) } else { None };
+            let _ = ajour.config.save();
+        }
+        Message::Interaction(Interaction::SongQuietHoursStartSelected(hour)) => {
+            log::debug!("Interaction::SongQuietHoursStartSelected({})", hour);
 
-            // Add new status for this addon as Downloading
-            install_addons.push(InstallAddon {
-                id: id.clone(),
-                kind,
-                status: InstallStatus::Downloading,
-                addon: None,
-            });
+            if let Some((start, _)) = &mut ajour.config.songs.auto_update.quiet_hours {
+                *start = hour.0;
+                let _ = ajour.config.save();
+            }
+        }
+        Message::Interaction(Interaction::SongQuietHoursEndSelected(hour)) => {
+            log::debug!("Interaction::SongQuietHoursEndSelected({})", hour);
 
-            return Ok(Command::perform(
-                perform_fetch_latest_addon(kind, id, flavor),
-                Message::InstallAddonFetched,
-            ));
+            if let Some((_, end)) = &mut ajour.config.songs.auto_update.quiet_hours {
+                *end = hour.0;
+                let _ = ajour.config.save();
+            }
         }
-        Message::Interaction(Interaction::CatalogCategorySelected(category)) => {
-            log::debug!("Interaction::CatalogCategorySelected({})", &category);
+        Message::AutoUpdateSongs(_) => {
+            let auto_update = &ajour.config.songs.auto_update;
 
-            // Select category
-            ajour.catalog_search_state.category = category;
+            if ajour.song_settings_state.auto_update_running || !auto_update.is_due(Local::now()) {
+                return Ok(Command::none());
+            }
 
-            query_and_sort_catalog(ajour);
-        }
-        Message::Interaction(Interaction::CatalogResultSizeSelected(size)) => {
-            log::debug!("Interaction::CatalogResultSizeSelected({:?})", &size);
+            // Only libraries which are still known.
+            let roots: Vec<_> = auto_update
+                .directories
+                .iter()
+                .filter(|dir| ajour.config.songs.directories.contains(dir))
+                .cloned()
+                .collect();
 
-            // Catalog result size
-            ajour.catalog_search_state.result_size = size;
+            log::debug!("Message::AutoUpdateSongs({} libraries)", roots.len());
 
-            query_and_sort_catalog(ajour);
+            if !roots.is_empty() {
+                ajour.song_settings_state.auto_update_running = true;
+
+                return Ok(Command::perform(
+                    plan_updates(ajour.song_cache.clone(), roots),
+                    Message::SongUpdatesPlanned,
+                ));
+            }
         }
-        Message::Interaction(Interaction::CatalogSourceSelected(source)) => {
-            log::debug!("Interaction::CatalogSourceSelected({:?})", source);
+        Message::SongUpdatesPlanned(result) => {
+            match result.context(localized_string("error-auto-update-songs")) {
+                Ok(plan) => {
+                    log::debug!(
+                        "Message::SongUpdatesPlanned({} updates)",
+                        plan.updates.len()
+                    );
 
-            // Save the specific source to the config, otherwise we set `None`
-            match source {
-                CatalogSource::All => {
-                    ajour.config.catalog_source = None;
+                    // Songs show as updatable until they are updated.
+                    for update in plan.updates.iter() {
+                        ajour.song_updates.songs.extend(
+                            update
+                                .changes
+                                .iter()
+                                .map(|change| (change.path.clone(), change.remote_hash.clone())),
+                        );
+                    }
+                    ajour.song_updates.apply(&mut ajour.songs);
+
+                    return Ok(Command::perform(
+                        apply_updates(ajour.song_cache.clone(), ajour.version_store.clone(), plan),
+                        Message::SongsAutoUpdated,
+                    ));
                 }
-                CatalogSource::Choice(source) => {
-                    ajour.config.catalog_source = Some(source);
+                Err(error) => {
+                    ajour.song_settings_state.auto_update_running = false;
+
+                    // Also record failed runs, so they aren't retried right away.
+                    ajour.config.songs.auto_update.last_run = Some(Utc::now());
+                    let _ = ajour.config.save();
+
+                    log_error(&error);
+                    ajour.error = Some(error);
                 }
             }
+        }
+        Message::SongsAutoUpdated(summary) => {
+            log::debug!(
+                "Message::SongsAutoUpdated({} songs, {} packs, {} failed)",
+                summary.songs.len(),
+                summary.packs.len(),
+                summary.failed.len()
+            );
 
-            // Save to config
+            ajour.song_settings_state.auto_update_running = false;
+
+            ajour.config.songs.auto_update.last_run = Some(Utc::now());
             let _ = ajour.config.save();
 
-            ajour.catalog_search_state.categories = ajour
-                .catalog_categories_per_source_cache
-                .get(&source.to_string())
-                .cloned()
-                .unwrap_or_default();
+            let changed = !summary.songs.is_empty() || !summary.packs.is_empty();
+            ajour.song_settings_state.auto_update_summary = Some(summary);
 
-            ajour.catalog_search_state.category = CatalogCategory::All;
+            // Read the updated libraries again. Songs which failed to update
+            // stay updatable, the others now match their origin.
+            if changed {
+                let roots: Vec<_> = ajour
+                    .config
+                    .songs
+                    .auto_update
+                    .directories
+                    .iter()
+                    .filter(|dir| ajour.config.songs.directories.contains(dir))
+                    .cloned()
+                    .collect();
+                let mut commands = vec![];
 
-            query_and_sort_catalog(ajour);
-        }
-        Message::InstallAddonFetched((flavor, id, result)) => {
-            let install_addons = ajour.install_addons.entry(flavor).or_default();
+                for root in roots {
+                    commands.extend(read_song_root(ajour, root));
+                }
 
-            if let Some(install_addon) = install_addons.iter_mut().find(|a| a.id == id) {
-                match result {
-                    Ok(mut addon) => {
-                        log::debug!(
-                            "Message::CatalogInstallAddonFetched({:?}, {:?})",
-                            flavor,
-                            &id,
-                        );
+                ajour.expanded_song = None;
+                ajour.state.insert(Mode::MySongs, State::Loading);
 
-                        addon.state = AddonState::Downloading;
-                        install_addon.addon = Some(addon.clone());
+                return Ok(Command::batch(commands));
+            }
+        }
+        Message::Interaction(Interaction::SortSongColumn(column_key)) => {
+            // Close details if shown.
+            ajour.expanded_song = None;
 
-                        let global_release_channel = ajour.config.addons.global_release_channel;
-                        let to_directory = ajour
-                            .config
-                            .get_download_directory_for_flavor(flavor)
-                            .expect("Expected a valid path");
+            // First time clicking a column should sort it in Ascending order, otherwise
+            // flip the sort direction.
+            let mut sort_direction = SortDirection::Asc;
 
-                        return Ok(Command::perform(
-                            perform_download_addon(
-                                DownloadReason::Install,
-                                flavor,
-                                global_release_channel,
-                                addon,
-                                to_directory,
-                            ),
-                            Message::DownloadedAddon,
-                        ));
+            if let Some(previous_column_key) = ajour.song_header_state.previous_column_key {
+                if column_key == previous_column_key {
+                    if let Some(previous_sort_direction) =
+                        ajour.song_header_state.previous_sort_direction
+                    {
+                        sort_direction = previous_sort_direction.toggle()
                     }
-                    Err(error) => {
-                        // Dont use `context` here to convert to anyhow::Error since
-                        // we actually want to show the underlying RepositoryError
-                        // message
-                        let error = anyhow::Error::new(error);
+                }
+            }
 
-                        log_error(&error);
+            log::debug!(
+                "Interaction::SortSongColumn({:?}, {:?})",
+                column_key,
+                sort_direction
+            );
+
+            sort_songs(&mut ajour.songs, sort_direction, column_key);
+
+            ajour.song_header_state.previous_sort_direction = Some(sort_direction);
+            ajour.song_header_state.previous_column_key = Some(column_key);
+        }
+        Message::Interaction(Interaction::SongsQuery(query)) => {
+            // Songs search query
+            ajour.songs_search_state.query = if query.is_empty() { None } else { Some(query) };
+
+            if ajour.songs_search_state.query.is_some() {
+                let fuzzy = apply_songs_query(ajour);
+
+                // Sort the songs by score, if there is free text to score.
+                if fuzzy {
+                    sort_songs(
+                        &mut ajour.songs,
+                        SortDirection::Desc,
+                        SongColumnKey::FuzzyScore,
+                    );
+                    ajour.song_header_state.previous_sort_direction = Some(SortDirection::Desc);
+                    ajour.song_header_state.previous_column_key = Some(SongColumnKey::FuzzyScore);
+                }
+            } else {
+                // Clear out the fuzzy scores
+                ajour.songs.iter_mut().for_each(|s| {
+                    s.fuzzy_score.take();
+                });
+
+                // Use default sort
+                sort_songs(&mut ajour.songs, SortDirection::Asc, SongColumnKey::Title);
+                ajour.song_header_state.previous_sort_direction = Some(SortDirection::Asc);
+                ajour.song_header_state.previous_column_key = Some(SongColumnKey::Title);
+            }
+        }
+        Message::Interaction(Interaction::PlayFilterSelected(play_filter)) => {
+            log::debug!("Interaction::PlayFilterSelected({:?})", play_filter);
+
+            ajour.songs_search_state.play_filter = play_filter;
+
+            // Most played is only meaningful when sorted by play count.
+            if play_filter == PlayFilter::MostPlayed {
+                sort_songs(
+                    &mut ajour.songs,
+                    SortDirection::Desc,
+                    SongColumnKey::PlayCount,
+                );
+                ajour.song_header_state.previous_sort_direction = Some(SortDirection::Desc);
+                ajour.song_header_state.previous_column_key = Some(SongColumnKey::PlayCount);
+            }
+        }
+        Message::Interaction(Interaction::SelectSmartFilter(idx)) => {
+            log::debug!("Interaction::SelectSmartFilter({:?})", idx);
+
+            ajour.smart_filters_state.selected = idx;
+            ajour.smart_filters_state.exported = None;
+
+            apply_smart_filters(ajour);
+        }
+        Message::Interaction(Interaction::SmartFilterNameChanged(name)) => {
+            ajour.smart_filters_state.name = name;
+        }
+        Message::Interaction(Interaction::SaveSmartFilter) => {
+            log::debug!("Interaction::SaveSmartFilter");
+
+            let name = ajour.smart_filters_state.name.trim().to_owned();
+            let query = match ajour.songs_search_state.query.clone() {
+                Some(query) if !name.is_empty() => query,
+                _ => return Ok(Command::none()),
+            };
+
+            if let Err(error) =
+                SongQuery::parse(&query).context(localized_string("error-save-smart-filter"))
+            {
+                log_error(&error);
+                ajour.error = Some(error);
+
+                return Ok(Command::none());
+            }
 
-                        match install_addon.kind {
-                            InstallKind::Catalog { .. } => {
-                                install_addon.status = InstallStatus::Unavailable;
-                            }
-                            InstallKind::Source | InstallKind::Import { .. } => {
-                                install_addon.status = InstallStatus::Error(error.to_string());
-                            }
-                        }
-                    }
+            // A filter with the same name is replaced.
+            let filters = &mut ajour.config.songs.smart_filters;
+            let filter = SmartFilter { name, query };
+            let idx = match filters.iter().position(|f| f.name == filter.name) {
+                Some(idx) => {
+                    filters[idx] = filter;
+                    idx
                 }
-            }
-        }
-        Message::AddonCacheUpdated(Ok(entry)) => {
-            log::debug!("Message::AddonCacheUpdated({})", entry.title);
+                None => {
+                    filters.push(filter);
+                    filters.len() - 1
+                }
+            };
+            let _ = ajour.config.save();
+
+            // Show the saved filter in place of the search.
+            ajour.smart_filters_state.name.clear();
+            ajour.smart_filters_state.selected = Some(idx);
+            ajour.smart_filters_state.exported = None;
+
+            apply_smart_filters(ajour);
+
+            return handle_message(
+                ajour,
+                Message::Interaction(Interaction::SongsQuery(String::new())),
+            );
         }
-        Message::AddonCacheEntryRemoved(maybe_entry) => {
-            match maybe_entry.context(localized_string("error-remove-cache")) {
-                Ok(Some(entry)) => log::debug!("Message::AddonCacheEntryRemoved({})", entry.title),
-                Ok(None) => {}
-                Err(e) => {
-                    log_error(&e);
+        Message::Interaction(Interaction::DeleteSmartFilter) => {
+            log::debug!("Interaction::DeleteSmartFilter");
+
+            if let Some(idx) = ajour.smart_filters_state.selected.take() {
+                if idx < ajour.config.songs.smart_filters.len() {
+                    ajour.config.songs.smart_filters.remove(idx);
+                    let _ = ajour.config.save();
                 }
+
+                ajour.smart_filters_state.exported = None;
+
+                apply_smart_filters(ajour);
             }
         }
-        Message::Interaction(Interaction::InstallScmQuery(query)) => {
-            // install from scm search query
-            ajour.install_from_scm_state.query = Some(query);
+        Message::Interaction(Interaction::ExportSmartFilter) => {
+            log::debug!("Interaction::ExportSmartFilter");
 
-            // Remove the status if it's an error and user typed into
-            // text input
+            let filter = match ajour
+                .smart_filters_state
+                .selected
+                .and_then(|idx| ajour.config.songs.smart_filters.get(idx))
             {
-                let install_addons = ajour
-                    .install_addons
-                    .entry(ajour.config.wow.flavor)
-                    .or_default();
+                Some(filter) => filter.clone(),
+                None => return Ok(Command::none()),
+            };
 
-                if let Some((idx, install_addon)) = install_addons
-                    .iter()
-                    .enumerate()
-                    .find(|(_, a)| a.kind == InstallKind::Source)
-                {
-                    if matches!(install_addon.status, InstallStatus::Error(_)) {
-                        install_addons.remove(idx);
-                    }
+            let data_directory = match ajour.config.songs.data_directory() {
+                Some(data_directory) => data_directory,
+                None => {
+                    let error = anyhow::anyhow!(localized_string("error-setlist-data-directory"));
+                    log_error(&error);
+                    ajour.error = Some(error);
+
+                    return Ok(Command::none());
                 }
-            }
+            };
+
+            // Songs are exported in the order they're shown in.
+            let smart_filters_state = &ajour.smart_filters_state;
+            let hashes: Vec<String> = ajour
+                .songs
+                .iter()
+                .filter(|s| smart_filters_state.matches(s))
+                .filter_map(|s| s.hash.clone())
+                .collect();
+
+            return Ok(Command::perform(
+                export_setlist(data_directory, filter.name, hashes),
+                Message::SmartFilterExported,
+            ));
         }
-        Message::Interaction(Interaction::InstallScmUrl) => {
-            if let Some(url) = ajour.install_from_scm_state.query.clone() {
-                if !url.is_empty() {
-                    return handle_message(
-                        ajour,
-                        Message::Interaction(Interaction::InstallAddon(
-                            ajour.config.wow.flavor,
-                            url,
-                            InstallKind::Source,
-                        )),
-                    );
+        Message::SmartFilterExported(result) => {
+            match result.context(localized_string("error-export-setlist")) {
+                Ok(path) => {
+                    log::debug!("Message::SmartFilterExported({:?})", &path);
+
+                    ajour.smart_filters_state.exported = Some(path);
+                }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.error = Some(error);
                 }
             }
         }
-        Message::RefreshCatalog(_) => {
-            if let Some(last_updated) = &ajour.catalog_last_updated {
-                let now = Utc::now();
-                let now_time = now.time();
-                let refresh_time = NaiveTime::from_hms_opt(2, 0, 0);
+        Message::Interaction(Interaction::ExpandSong(path)) => {
+            log::debug!("Interaction::ExpandSong({:?})", &path);
 
-                // TODO: using unwrap() here leads to the "old" behavior, panicing on null 
-                // this might not be what we desire, so I'll leave this todo for future reference
-                if last_updated.date_naive() < now.date_naive() && now_time > refresh_time.unwrap() {
-                    log::debug!("Message::RefreshCatalog: catalog needs to be refreshed");
+            // Remove any pending confirms.
+            ajour.pending_confirmation = None;
+
+            ajour.expanded_song_versions.clear();
+
+            // Notes which weren't saved with enter are saved when the song is
+            // collapsed.
+            if let Some(expanded) = ajour.expanded_song.clone() {
+                let notes = std::mem::take(&mut ajour.song_user_data_state.notes);
+                update_song_user_data(ajour, &expanded, |data| data.set_notes(notes));
+            }
+            ajour.song_user_data_state.tag.clear();
+
+            // Collapse if the same song is clicked again.
+            if ajour.expanded_song.as_ref() == Some(&path) {
+                ajour.expanded_song = None;
+            } else {
+                let song = ajour.songs.iter().find(|s| s.path == path).cloned();
+
+                ajour.song_user_data_state.notes = song
+                    .as_ref()
+                    .and_then(|s| s.user_data.notes.clone())
+                    .unwrap_or_default();
+                ajour.expanded_song = Some(path);
 
+                if let (Some(version_store), Some(song)) = (ajour.version_store.clone(), song) {
                     return Ok(Command::perform(
-                        catalog_download_latest_or_use_cache(),
-                        Message::CatalogDownloaded,
+                        song_versions(version_store, song),
+                        Message::SongVersionsLoaded,
                     ));
                 }
             }
         }
-        Message::Interaction(Interaction::ToggleHideIgnoredAddons(is_checked)) => {
-            log::debug!("Interaction::ToggleHideIgnoredAddons({})", is_checked);
+        Message::Interaction(Interaction::SongTagInputChanged(tag)) => {
+            ajour.song_user_data_state.tag = tag;
+        }
+        Message::Interaction(Interaction::AddSongTag(path)) => {
+            let tag = std::mem::take(&mut ajour.song_user_data_state.tag);
 
-            ajour.config.hide_ignored_addons = is_checked;
-            let _ = ajour.config.save();
+            log::debug!("Interaction::AddSongTag({:?}, {})", &path, &tag);
+
+            update_song_user_data(ajour, &path, |data| {
+                data.add_tag(&tag);
+            });
         }
-        Message::Interaction(Interaction::ToggleDeleteSavedVariables(is_checked)) => {
-            log::debug!("Interaction::ToggleDeleteSavedVariables({})", is_checked);
+        Message::Interaction(Interaction::RemoveSongTag(path, tag)) => {
+            log::debug!("Interaction::RemoveSongTag({:?}, {})", &path, &tag);
 
-            ajour.config.addons.delete_saved_variables = is_checked;
-            let _ = ajour.config.save();
+            update_song_user_data(ajour, &path, |data| data.remove_tag(&tag));
         }
-        Message::CatalogDownloaded(error @ Err(_)) => {
-            let error = error.context("Failed to download catalog").unwrap_err();
-            log_error(&error);
-            ajour.state.insert(Mode::Catalog, State::Error(error));
+        Message::Interaction(Interaction::ToggleSongFavorite(path, favorite)) => {
+            log::debug!("Interaction::ToggleSongFavorite({:?}, {})", &path, favorite);
+
+            update_song_user_data(ajour, &path, |data| data.favorite = favorite);
         }
-        Message::AddonCacheUpdated(error @ Err(_)) => {
-            let error = error.context("Failed to update addon cache").unwrap_err();
-            log_error(&error);
-            ajour.error = Some(error);
+        Message::Interaction(Interaction::SongRatingSelected(path, SongRating(rating))) => {
+            log::debug!("Interaction::SongRatingSelected({:?}, {:?})", &path, rating);
+
+            update_song_user_data(ajour, &path, |data| data.set_rating(rating));
         }
-        Message::Interaction(Interaction::PickLocalizationLanguage(lang)) => {
-            log::debug!("Interaction::PickLocalizationLanguage({:?})", lang);
+        Message::Interaction(Interaction::SongNotesChanged(notes)) => {
+            ajour.song_user_data_state.notes = notes;
+        }
+        Message::Interaction(Interaction::SaveSongNotes(path)) => {
+            log::debug!("Interaction::SaveSongNotes({:?})", &path);
 
-            // Update config.
-            ajour.config.language = lang;
-            let _ = ajour.config.save();
+            let notes = ajour.song_user_data_state.notes.clone();
+            update_song_user_data(ajour, &path, |data| data.set_notes(notes));
+        }
+        Message::VersionStoreLoaded(result) => {
+            match result.context(localized_string("error-load-versions")) {
+                Ok(version_store) => {
+                    log::debug!("Message::VersionStoreLoaded");
 
-            // Update global LANG refcell.
-            *LANG.get().expect("LANG not set").write().unwrap() = lang.language_code();
+                    ajour.version_store = Some(Arc::new(Mutex::new(version_store)));
+                }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.error = Some(error);
+                }
+            }
         }
-        Message::Interaction(Interaction::PickGlobalReleaseChannel(channel)) => {
-            log::debug!("Interaction::PickGlobalReleaseChannel({:?})", channel);
+        Message::UserDataLoaded(result) => {
+            match result.context(localized_string("error-load-user-data")) {
+                Ok(user_data) => {
+                    log::debug!("Message::UserDataLoaded({} songs)", user_data.songs.len());
 
-            // Update all addon states, expect ignored, if needed.
-            let flavors = &Flavor::ALL[..];
-            for flavor in flavors {
-                let ignored_ids = ajour.config.addons.ignored.entry(*flavor).or_default();
-                let mut addons: Vec<_> = ajour
-                    .addons
-                    .entry(*flavor)
-                    .or_default()
-                    .iter_mut()
-                    .filter(|a| !ignored_ids.iter().any(|i| i == &a.primary_folder_id))
+                    user_data.apply(&mut ajour.songs);
+                    ajour.user_data = user_data;
+
+                    apply_smart_filters(ajour);
+                }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.error = Some(error);
+                }
+            }
+        }
+        Message::SongVersionsLoaded((path, versions)) => {
+            log::debug!(
+                "Message::SongVersionsLoaded({:?}, {} versions)",
+                &path,
+                versions.len()
+            );
+
+            // Only list versions of the song which is still expanded.
+            if ajour.expanded_song.as_ref() == Some(&path) {
+                let score_data = &ajour.score_data;
+
+                ajour.expanded_song_versions = versions
+                    .into_iter()
+                    .map(|version| SongVersionState {
+                        play_count: version
+                            .hash
+                            .as_ref()
+                            .and_then(|hash| score_data.songs.get(hash))
+                            .map(|scores| scores.play_count)
+                            .unwrap_or_default(),
+                        version,
+                        rollback_btn_state: Default::default(),
+                    })
                     .collect();
-                for addon in addons.iter_mut() {
-                    // Check if addon is updatable.
-                    if let Some(package) = addon.relevant_release_package(channel) {
-                        if addon.is_updatable(&package) {
-                            addon.state = AddonState::Updatable;
-                        } else {
-                            addon.state = AddonState::Idle;
+            }
+        }
+        Message::Interaction(Interaction::RollbackSong(path, version)) => {
+            log::debug!("Interaction::RollbackSong({:?}, {})", &path, &version);
+
+            let song = ajour.songs.iter().find(|s| s.path == path).cloned();
+
+            if let (Some(version_store), Some(song)) = (ajour.version_store.clone(), song) {
+                return Ok(Command::perform(
+                    rollback_song(ajour.song_cache.clone(), version_store, song, version),
+                    Message::SongRolledBack,
+                ));
+            }
+        }
+        Message::SongRolledBack(result) => {
+            match result.context(localized_string("error-rollback-song")) {
+                Ok(mut song) => {
+                    log::debug!("Message::SongRolledBack({:?})", &song.path);
+
+                    // Scores are kept per chart hash, so the rolled back
+                    // version gets its own play history back.
+                    ajour.score_data.apply(std::slice::from_mut(&mut song));
+                    ajour.bad_songs.apply(std::slice::from_mut(&mut song));
+                    ajour.song_updates.apply(std::slice::from_mut(&mut song));
+
+                    let rolled_back = song.clone();
+
+                    if let Some(existing) = ajour.songs.iter_mut().find(|s| s.path == song.path) {
+                        // Tags and ratings stay with the rolled back chart.
+                        if let (Some(from), Some(to)) = (&existing.hash, &song.hash) {
+                            if ajour.user_data.carry_over(from, to) {
+                                let _ = ajour.user_data.save();
+                            }
                         }
+                        ajour.user_data.apply(std::slice::from_mut(&mut song));
+
+                        *existing = song;
+                    }
+
+                    apply_smart_filters(ajour);
+
+                    if let Some(version_store) = ajour.version_store.clone() {
+                        return Ok(Command::perform(
+                            song_versions(version_store, rolled_back),
+                            Message::SongVersionsLoaded,
+                        ));
                     }
                 }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.error = Some(error);
+                }
             }
+        }
+        Message::Interaction(Interaction::SelectSongDirectory) => {
+            log::debug!("Interaction::SelectSongDirectory");
 
-            ajour.config.addons.global_release_channel = channel;
-            let _ = ajour.config.save();
+            return Ok(Command::perform(
+                select_directory(),
+                Message::UpdateSongDirectory,
+            ));
         }
-        Message::Interaction(Interaction::AlternatingRowColorToggled(is_set)) => {
-            log::debug!(
-                "Interaction::AlternatingRowColorToggled(is_set: {})",
-                is_set,
-            );
+        Message::UpdateSongDirectory(path) => {
+            log::debug!("Message::UpdateSongDirectory({:?})", &path);
 
-            ajour.config.alternating_row_colors = is_set;
-            let _ = ajour.config.save();
+            if let Some(path) = path {
+                ajour.config.songs.add_directory(path.clone());
+                let _ = ajour.config.save();
+
+                if !ajour
+                    .song_settings_state
+                    .directories
+                    .iter()
+                    .any(|d| d.path == path)
+                {
+                    ajour
+                        .song_settings_state
+                        .directories
+                        .push(SongDirectoryState::new(path.clone()));
+                }
+
+                ajour.state.insert(Mode::MySongs, State::Loading);
+
+                let mut commands = read_song_root(ajour, path);
+
+                commands.extend(load_clone_hero_data(ajour.config.songs.data_directory()));
+
+                return Ok(Command::batch(commands));
+            }
         }
-        Message::Interaction(Interaction::KeybindingsToggle(is_set)) => {
-            log::debug!("Interaction::KeybindingsToggle(is_set: {})", is_set,);
+        Message::Interaction(Interaction::RemoveSongDirectory(path)) => {
+            log::debug!("Interaction::RemoveSongDirectory({:?})", &path);
 
-            ajour.config.is_keybindings_enabled = is_set;
+            ajour.config.songs.directories.retain(|d| d != &path);
+            ajour
+                .config
+                .songs
+                .auto_update
+                .set_enabled(path.clone(), false);
             let _ = ajour.config.save();
+
+            ajour
+                .song_settings_state
+                .directories
+                .retain(|d| d.path != path);
+            ajour.songs.retain(|s| s.root != path);
+            ajour.packs.retain(|p| p.root != path);
+            apply_smart_filters(ajour);
+
+            if ajour.config.songs.directories.is_empty() {
+                ajour.state.remove(&Mode::MySongs);
+            }
         }
-        Message::Interaction(Interaction::ExportAddons) => {
-            log::debug!("Interaction::ExportAddons");
+        Message::Interaction(Interaction::SelectCloneHeroDataDirectory) => {
+            log::debug!("Interaction::SelectCloneHeroDataDirectory");
 
             return Ok(Command::perform(
-                select_export_file(),
-                Message::ExportAddons,
+                select_directory(),
+                Message::UpdateCloneHeroDataDirectory,
             ));
         }
-        Message::ExportAddons(path) => {
-            if let Some(path) = path {
-                log::debug!("Message::ExportAddons({:?})", &path);
+        Message::UpdateCloneHeroDataDirectory(path) => {
+            log::debug!("Message::UpdateCloneHeroDataDirectory({:?})", &path);
 
-                let addons = ajour.addons.clone();
+            if let Some(path) = path {
+                ajour.config.songs.data_directory = Some(path.clone());
+                let _ = ajour.config.save();
 
-                return Ok(Command::perform(
-                    async { share::export(addons, path) },
-                    Message::AddonsExported,
-                ));
+                return Ok(Command::batch(load_clone_hero_data(Some(path))));
             }
         }
-        Message::AddonsExported(result) => match result.context("Failed to export addons") {
-            Ok(_) => {
-                log::debug!("Message::AddonsExported");
-            }
-            Err(error) => {
-                log_error(&error);
+        Message::Interaction(Interaction::SelectInboxDirectory) => {
+            log::debug!("Interaction::SelectInboxDirectory");
 
-                ajour.error = Some(error);
+            return Ok(Command::perform(
+                select_directory(),
+                Message::UpdateInboxDirectory,
+            ));
+        }
+        Message::UpdateInboxDirectory(path) => {
+            log::debug!("Message::UpdateInboxDirectory({:?})", &path);
+
+            if let Some(path) = path {
+                ajour.config.songs.inbox.directory = Some(path.clone());
+                let _ = ajour.config.save();
+
+                // The subscription starts watching the new inbox, but what's
+                // already in it has to be imported now.
+                return Ok(Command::batch(import_inbox_items(
+                    ajour,
+                    inbox_items(&path),
+                )));
             }
-        },
-        Message::Interaction(Interaction::ImportAddons) => {
-            log::debug!("Interaction::ImportAddons");
+        }
+        Message::Interaction(Interaction::SelectInboxLibrary) => {
+            log::debug!("Interaction::SelectInboxLibrary");
 
             return Ok(Command::perform(
-                select_import_file(),
-                Message::ImportAddons,
+                select_directory(),
+                Message::UpdateInboxLibrary,
             ));
         }
-        Message::ImportAddons(path) => {
+        Message::UpdateInboxLibrary(path) => {
+            log::debug!("Message::UpdateInboxLibrary({:?})", &path);
+
             if let Some(path) = path {
-                log::debug!("Message::ImportAddons({:?})", &path);
+                ajour.config.songs.inbox.library = Some(path);
+                let _ = ajour.config.save();
+            }
+        }
+        Message::InboxChanged(changes) => {
+            log::debug!("Message::InboxChanged({} paths)", changes.len());
 
-                let current_addons = ajour.addons.clone();
+            if let Some(inbox) = ajour.config.songs.inbox.directory.clone() {
+                let items = changed_inbox_items(&inbox, &changes);
 
-                ajour.mode = Mode::MyAddons(ajour.config.wow.flavor);
+                return Ok(Command::batch(import_inbox_items(ajour, items)));
+            }
+        }
+        Message::LibraryChanged((root, changes)) => {
+            log::debug!(
+                "Message::LibraryChanged({:?}, {} paths)",
+                &root,
+                changes.len()
+            );
+
+            let folders = changed_song_folders(&root, &changes);
 
+            if !folders.is_empty() {
                 return Ok(Command::perform(
-                    async { share::parse_only_needed(current_addons, path) },
-                    Message::ImportParsed,
+                    perform_reread_song_folders(ajour.song_cache.clone(), root, folders),
+                    Message::SongFoldersRead,
                 ));
             }
         }
-        Message::ImportParsed(result) => match result.context("Failed to parse import file") {
-            Ok(parsed) => {
-                log::debug!("Message::ImportParsed");
+        Message::SongFoldersRead((root, folders, result)) => {
+            match result.context(localized_string("error-parse-songs")) {
+                Ok(mut songs) => {
+                    log::debug!(
+                        "Message::SongFoldersRead({:?}, {} songs)",
+                        &root,
+                        songs.len()
+                    );
 
-                let mut commands = vec![];
+                    // Join play history, user data and Clone Hero's load errors
+                    // onto the songs.
+                    ajour.score_data.apply(&mut songs);
+                    ajour.user_data.apply(&mut songs);
+                    ajour.bad_songs.apply(&mut songs);
+                    ajour.song_updates.apply(&mut songs);
 
-                for (flavor, parsed) in parsed.into_iter() {
-                    for data in parsed.data {
-                        let id = data.id.clone();
-                        let repo_kind = data.repo_kind;
-                        let install_kind = InstallKind::Import { repo_kind };
+                    // Replace the songs previously read from the changed folders.
+                    ajour.songs.retain(|s| {
+                        s.root != root || !folders.iter().any(|folder| s.path.starts_with(folder))
+                    });
+                    ajour.songs.extend(songs);
+
+                    let column_key = ajour
+                        .song_header_state
+                        .previous_column_key
+                        .unwrap_or(SongColumnKey::Title);
+                    let sort_direction = ajour
+                        .song_header_state
+                        .previous_sort_direction
+                        .unwrap_or(SortDirection::Asc);
+
+                    sort_songs(&mut ajour.songs, sort_direction, column_key);
+
+                    // Score the new songs against the search query.
+                    apply_songs_query(ajour);
+                    apply_smart_filters(ajour);
+                }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.error = Some(error);
+                }
+            }
+        }
+        Message::InboxImported((item, result)) => {
+            ajour.song_settings_state.inbox_pending.remove(&item);
 
-                        let command = Command::perform(
-                            async move { (flavor, id, install_kind) },
-                            |(a, b, c)| Message::Interaction(Interaction::InstallAddon(a, b, c)),
-                        );
+            match result.context(localized_string("error-inbox-import")) {
+                Ok(import) => {
+                    log::debug!(
+                        "Message::InboxImported({}, {} songs)",
+                        &import.name,
+                        import.songs.len()
+                    );
 
-                        commands.push(command);
-                    }
-                }
+                    if !import.songs.is_empty() {
+                        let mut songs = import.songs.clone();
+                        ajour.score_data.apply(&mut songs);
+                        ajour.user_data.apply(&mut songs);
 
-                return Ok(Command::batch(commands));
-            }
-            Err(error) => {
-                log_error(&error);
+                        // The library watcher may have read them already.
+                        ajour
+                            .songs
+                            .retain(|s| !songs.iter().any(|song| song.path == s.path));
+                        ajour.songs.extend(songs);
+                        apply_smart_filters(ajour);
+
+                        let column_key = ajour
+                            .song_header_state
+                            .previous_column_key
+                            .unwrap_or(SongColumnKey::Title);
+                        let sort_direction = ajour
+                            .song_header_state
+                            .previous_sort_direction
+                            .unwrap_or(SortDirection::Asc);
+
+                        sort_songs(&mut ajour.songs, sort_direction, column_key);
+                    }
 
-                ajour.error = Some(error);
+                    let imports = &mut ajour.song_settings_state.inbox_imports;
+                    imports.push(import);
+                    if imports.len() > MAX_INBOX_IMPORTS {
+                        imports.remove(0);
+                    }
+                }
+                Err(error) => {
+                    log_error(&error);
+                    ajour.error = Some(error);
+                }
             }
-        },
+        }
         Message::Interaction(Interaction::CompressionLevelChanged(level)) => {
             ajour.config.zstd_compression_level = level;
             let _ = ajour.config.save();
@@ -2111,6 +4208,9 @@ pub fn handle_message(ajour: &mut Ajour, message: Message) -> Result<Command<Mes
                     Mode::MyAddons(_) => {
                         ajour.addons_search_state.query = None;
                     }
+                    Mode::MySongs => {
+                        ajour.songs_search_state.query = None;
+                    }
                     Mode::Catalog => {
                         ajour.catalog_search_state.query = None;
                     }
@@ -2277,6 +4377,49 @@ async fn select_import_file() -> Option<PathBuf> {
     dialog.pick_file().await.map(|f| f.path().to_path_buf())
 }
 
+#[cfg(not(target_os = "linux"))]
+async fn select_songs_export_file() -> Option<PathBuf> {
+    use rfd::AsyncFileDialog;
+
+    let dialog = AsyncFileDialog::new()
+        .set_file_name("chmon-songs.yml")
+        .add_filter("YML File", &["yml"])
+        .add_filter("JSON File", &["json"]);
+
+    dialog.save_file().await.map(|f| f.path().to_path_buf())
+}
+
+#[cfg(not(target_os = "linux"))]
+async fn select_songs_import_file() -> Option<PathBuf> {
+    use rfd::AsyncFileDialog;
+
+    let dialog = AsyncFileDialog::new().add_filter("Song list", &["yml", "yaml", "json"]);
+
+    dialog.pick_file().await.map(|f| f.path().to_path_buf())
+}
+
+#[cfg(not(target_os = "linux"))]
+async fn select_report_export_file() -> Option<PathBuf> {
+    use rfd::AsyncFileDialog;
+
+    let dialog = AsyncFileDialog::new()
+        .set_file_name("songs.html")
+        .add_filter("HTML File", &["html"])
+        .add_filter("CSV File", &["csv"])
+        .add_filter("JSON File", &["json"]);
+
+    dialog.save_file().await.map(|f| f.path().to_path_buf())
+}
+
+#[cfg(not(target_os = "linux"))]
+async fn select_pack_file() -> Option<PathBuf> {
+    use rfd::AsyncFileDialog;
+
+    let dialog = AsyncFileDialog::new().add_filter("ZIP File", &["zip"]);
+
+    dialog.pick_file().await.map(|f| f.path().to_path_buf())
+}
+
 #[cfg(target_os = "linux")]
 async fn select_directory() -> Option<PathBuf> {
     use native_dialog::FileDialog;
@@ -2321,6 +4464,49 @@ async fn select_import_file() -> Option<PathBuf> {
     dialog.show_open_single_file().ok().flatten()
 }
 
+#[cfg(target_os = "linux")]
+async fn select_songs_export_file() -> Option<PathBuf> {
+    use native_dialog::FileDialog;
+
+    let dialog = FileDialog::new()
+        .set_filename("chmon-songs.yml")
+        .add_filter("YML File", &["yml"])
+        .add_filter("JSON File", &["json"]);
+
+    dialog.show_save_single_file().ok().flatten()
+}
+
+#[cfg(target_os = "linux")]
+async fn select_songs_import_file() -> Option<PathBuf> {
+    use native_dialog::FileDialog;
+
+    let dialog = FileDialog::new().add_filter("Song list", &["yml", "yaml", "json"]);
+
+    dialog.show_open_single_file().ok().flatten()
+}
+
+#[cfg(target_os = "linux")]
+async fn select_report_export_file() -> Option<PathBuf> {
+    use native_dialog::FileDialog;
+
+    let dialog = FileDialog::new()
+        .set_filename("songs.html")
+        .add_filter("HTML File", &["html"])
+        .add_filter("CSV File", &["csv"])
+        .add_filter("JSON File", &["json"]);
+
+    dialog.show_save_single_file().ok().flatten()
+}
+
+#[cfg(target_os = "linux")]
+async fn select_pack_file() -> Option<PathBuf> {
+    use native_dialog::FileDialog;
+
+    let dialog = FileDialog::new().add_filter("ZIP File", &["zip"]);
+
+    dialog.show_open_single_file().ok().flatten()
+}
+
 async fn perform_read_addon_directory(
     addon_cache: Option<Arc<Mutex<AddonCache>>>,
     fingerprint_cache: Option<Arc<Mutex<FingerprintCache>>>,
@@ -2333,6 +4519,500 @@ async fn perform_read_addon_directory(
     )
 }
 
+async fn perform_read_song_directory(
+    song_cache: Option<Arc<Mutex<SongCache>>>,
+    root_dir: PathBuf,
+    data_directory: Option<PathBuf>,
+) -> (PathBuf, Result<Vec<Song>, ParseError>) {
+    // Seed the cache from Clone Hero's own scan the first time a root is
+    // read. Failing to do so only makes the scan slower.
+    if let (Some(song_cache), Some(data_directory)) = (song_cache.clone(), data_directory) {
+        if let Err(error) = seed_song_cache(song_cache, &root_dir, data_directory).await {
+            log::error!("failed to seed song cache: {}", error);
+        }
+    }
+
+    (
+        root_dir.clone(),
+        read_song_directory(song_cache, root_dir).await,
+    )
+}
+
+async fn perform_reread_song_folders(
+    song_cache: Option<Arc<Mutex<SongCache>>>,
+    root_dir: PathBuf,
+    folders: Vec<PathBuf>,
+) -> (PathBuf, Vec<PathBuf>, Result<Vec<Song>, ParseError>) {
+    let result = reread_song_folders(song_cache, root_dir.clone(), folders.clone()).await;
+
+    (root_dir, folders, result)
+}
+
+async fn perform_quarantine_song(
+    song: Song,
+    quarantine_directory: PathBuf,
+) -> (Song, Result<PathBuf, FilesystemError>) {
+    let result = quarantine_song(&song, &quarantine_directory).await;
+
+    (song, result)
+}
+
+async fn perform_transfer_songs(
+    song_cache: Option<Arc<Mutex<SongCache>>>,
+    songs: Vec<Song>,
+    target_root: PathBuf,
+    mode: TransferMode,
+) -> (TransferMode, Result<TransferResult, FilesystemError>) {
+    (
+        mode,
+        transfer_songs(song_cache, songs, target_root, mode, |_, _, _| {}).await,
+    )
+}
+
+/// Returns the command transferring the next batch of queued songs. Songs
+/// are transferred in batches so progress can be shown in between.
+///
+/// Finishes the transfer if no songs are left.
+fn next_song_transfer_batch(ajour: &mut Ajour) -> Option<Command<Message>> {
+    let state = &mut ajour.song_transfer_state;
+
+    let (mode, target) = match state.running.clone() {
+        Some(running) if !state.queue.is_empty() => running,
+        _ => {
+            state.running = None;
+            state.progress = None;
+            return None;
+        }
+    };
+
+    let batch_size = state.queue.len().min(SONG_TRANSFER_BATCH_SIZE);
+    let batch: Vec<Song> = state.queue.drain(..batch_size).collect();
+
+    Some(Command::perform(
+        perform_transfer_songs(ajour.song_cache.clone(), batch, target, mode),
+        Message::SongsTransferred,
+    ))
+}
+
+/// Starts a backup of the folders selected in the backup settings. Returns
+/// `None` if no backup directory is set or nothing is selected.
+fn start_backup(ajour: &mut Ajour) -> Option<Command<Message>> {
+    start_backup_of(ajour, true, true)
+}
+
+/// Like `start_backup`, but only backs up the folders saved to an archive if
+/// `archive` is set and the song libraries if `songs` is set.
+fn start_backup_of(ajour: &mut Ajour, archive: bool, songs: bool) -> Option<Command<Message>> {
+    let dest = ajour.config.backup_directory.clone()?;
+
+    // This will disable our backup button and show a message that the
+    // app is processing the backup. We will unflag this on completion.
+    ajour.backup_state.backing_up = true;
+
+    let mut src_folders = vec![];
+
+    if archive {
+        // Backup WTF & AddOn directories for flavor if it exist
+        for flavor in Flavor::ALL.iter() {
+            if let Some(wow_dir) = ajour.config.get_root_directory_for_flavor(flavor) {
+                if ajour.config.backup_addons {
+                    let addon_dir = ajour.config.get_addon_directory_for_flavor(flavor).unwrap();
+
+                    // Backup starting with `Interface` folder as some users save
+                    // custom data here that they would like retained
+                    if let Some(interface_dir) = addon_dir.parent() {
+                        if interface_dir.exists() {
+                            src_folders.push(BackupFolder::new(interface_dir, &wow_dir));
+                        }
+                    }
+                }
+
+                if ajour.config.backup_wtf {
+                    let wtf_dir = ajour.config.get_wtf_directory_for_flavor(flavor).unwrap();
+
+                    if wtf_dir.exists() {
+                        src_folders.push(BackupFolder::new(&wtf_dir, &wow_dir));
+                    }
+                }
+
+                if ajour.config.backup_screenshots {
+                    let screenshot_dir = ajour
+                        .config
+                        .get_screenshots_directory_for_flavor(flavor)
+                        .unwrap();
+                    if screenshot_dir.exists() {
+                        src_folders.push(BackupFolder::new(&screenshot_dir, &wow_dir));
+                    }
+                }
+
+                if ajour.config.backup_fonts {
+                    let fonts_dir = ajour.config.get_fonts_directory_for_flavor(flavor).unwrap();
+                    if fonts_dir.exists() {
+                        src_folders.push(BackupFolder::new(&fonts_dir, &wow_dir));
+                    }
+                }
+            }
+        }
+
+        // Backup Ajour config.
+        if ajour.config.backup_config {
+            let config_path = ajour_core::fs::config_dir();
+            if let Some(config_prefix) = config_path.parent() {
+                src_folders.push(BackupFolder::new(&config_path, config_prefix));
+            }
+        }
+    }
+
+    let mut commands = vec![];
+
+    let passphrase = if ajour.config.backup_encrypt {
+        ajour.backup_state.passphrase()
+    } else {
+        None
+    };
+
+    if !src_folders.is_empty() && ajour.config.backup_encrypt && passphrase.is_none() {
+        // The passphrase isn't saved, so it has to be entered after each start.
+        let error = anyhow::anyhow!(localized_string("error-backup-passphrase"));
+        log_error(&error);
+        ajour.error = Some(error);
+
+        ajour.backup_state.backing_up = false;
+    } else if !src_folders.is_empty() {
+        ajour.backup_state.last_attempt = Some(Local::now().naive_local());
+
+        commands.push(Command::perform(
+            backup_folders(
+                src_folders,
+                dest.to_owned(),
+                ajour.config.compression_format,
+                ajour.config.zstd_compression_level,
+                passphrase,
+            ),
+            Message::BackupFinished,
+        ));
+    } else {
+        ajour.backup_state.backing_up = false;
+    }
+
+    // Song libraries are backed up incrementally next to the archives.
+    if songs && ajour.config.backup_songs && !ajour.config.songs.directories.is_empty() {
+        ajour.backup_state.backing_up_songs = true;
+        ajour.backup_state.last_snapshot_attempt = Some(Local::now().naive_local());
+
+        commands.push(Command::perform(
+            backup_songs(
+                dest.to_owned(),
+                ajour.config.songs.directories.clone(),
+                ajour.user_data.clone(),
+            ),
+            Message::SongsBackedUp,
+        ));
+    }
+
+    if commands.is_empty() {
+        None
+    } else {
+        Some(Command::batch(commands))
+    }
+}
+
+/// Returns true if `message` updates or deletes several songs or addons at
+/// once.
+fn is_bulk_change(ajour: &Ajour, message: &Message) -> bool {
+    match message {
+        Message::Interaction(Interaction::UpdateAll(Mode::MyAddons(flavor))) => ajour
+            .addons
+            .get(flavor)
+            .map(|addons| addons.iter().any(|a| a.state == AddonState::Updatable))
+            .unwrap_or_default(),
+        Message::Interaction(Interaction::UpdateAll(Mode::MySongs)) => ajour
+            .songs
+            .iter()
+            .any(|song| song.state == SongState::Updatable),
+        Message::Interaction(Interaction::ConfirmDeletePack(_))
+        | Message::Interaction(Interaction::TransferSongs(TransferMode::Move))
+        | Message::Interaction(Interaction::ApplyOrganize) => true,
+        Message::AutoUpdateSongs(_) => {
+            !ajour.song_settings_state.auto_update_running
+                && ajour.config.songs.auto_update.is_due(Local::now())
+        }
+        _ => false,
+    }
+}
+
+/// Handles the changes which waited for a backup, once all backups finished.
+fn run_deferred(ajour: &mut Ajour) -> Result<Command<Message>> {
+    if ajour.backup_state.backing_up || ajour.backup_state.backing_up_songs {
+        return Ok(Command::none());
+    }
+
+    let deferred = std::mem::take(&mut ajour.backup_state.deferred);
+
+    ajour.backup_state.running_deferred = true;
+    let commands = deferred
+        .into_iter()
+        .map(|message| handle_message(ajour, message))
+        .collect::<Result<Vec<_>>>();
+    ajour.backup_state.running_deferred = false;
+
+    Ok(Command::batch(commands?))
+}
+
+/// Drops the changes which waited for a backup which failed.
+fn cancel_deferred(ajour: &mut Ajour) {
+    for message in ajour.backup_state.deferred.drain(..) {
+        log::debug!("cancelled {:?} since the backup failed", &message);
+    }
+}
+
+/// Sets a retention rule from its text input. Returns false if `value` isn't
+/// empty or a number, leaving the rule unchanged.
+fn parse_retention_rule<T: std::str::FromStr>(value: &str, rule: &mut Option<T>) -> bool {
+    if value.is_empty() {
+        *rule = None;
+    } else if let Ok(parsed) = value.parse() {
+        *rule = Some(parsed);
+    } else {
+        return false;
+    }
+
+    true
+}
+
+/// Returns the commands parsing every song library root and loading the
+/// play history from the Clone Hero data directory.
+fn parse_song_directories(ajour: &mut Ajour) -> Vec<Command<Message>> {
+    let mut commands = vec![];
+
+    if ajour.config.songs.directories.is_empty() {
+        log::debug!("no song directories set");
+        return commands;
+    }
+
+    ajour.state.insert(Mode::MySongs, State::Loading);
+
+    for root_dir in ajour.config.songs.directories.clone() {
+        log::debug!("preparing to parse songs in {:?}", root_dir.display());
+
+        commands.extend(read_song_root(ajour, root_dir));
+    }
+
+    commands.extend(load_clone_hero_data(ajour.config.songs.data_directory()));
+
+    commands
+}
+
+/// Scores the songs against the My Songs search query. Songs matching every
+/// field of the query get a score, fuzzily matched against their free text.
+/// A query which can't be parsed is matched as free text as a whole.
+///
+/// Returns true if the query has free text, so the scores are meaningful.
+fn apply_songs_query(ajour: &mut Ajour) -> bool {
+    let query = match &ajour.songs_search_state.query {
+        Some(query) => query.clone(),
+        None => return false,
+    };
+
+    let (song_query, text) = match SongQuery::parse(&query) {
+        Ok(song_query) => {
+            let text = song_query.text();
+            (song_query, text)
+        }
+        Err(error) => {
+            log::debug!("matching {:?} as free text: {}", &query, error);
+            (SongQuery::default(), Some(query))
+        }
+    };
+
+    // Increase penalty for gaps between matching characters
+    let fuzzy_match_config = SkimScoreConfig {
+        gap_start: -12,
+        gap_extension: -6,
+        ..Default::default()
+    };
+    let fuzzy_matcher = SkimMatcherV2::default().score_config(fuzzy_match_config);
+
+    ajour.songs.iter_mut().for_each(|s| {
+        s.fuzzy_score.take();
+
+        if !song_query.matches_fields(s) {
+            return;
+        }
+
+        match &text {
+            Some(text) => {
+                let haystack = format!("{} {}", s.title(), s.artist().unwrap_or_default());
+
+                if let Some(score) = fuzzy_matcher.fuzzy_match(&haystack, text) {
+                    if score > 0 {
+                        s.fuzzy_score = Some(score);
+                    }
+                }
+            }
+            None => s.fuzzy_score = Some(0),
+        }
+    });
+
+    text.is_some()
+}
+
+/// Returns true if the song is shown in My Songs with the current search query,
+/// play filter and smart filter.
+fn is_song_shown(ajour: &Ajour, song: &Song) -> bool {
+    (ajour.songs_search_state.query.is_none() || song.fuzzy_score.is_some())
+        && ajour.songs_search_state.play_filter.matches(song)
+        && ajour.smart_filters_state.matches(song)
+}
+
+/// Changes the user data of a song and every other copy of it, and saves the
+/// store. Songs without a chart hash can't have user data.
+fn update_song_user_data<F>(ajour: &mut Ajour, path: &Path, f: F)
+where
+    F: FnOnce(&mut SongUserData),
+{
+    let hash = match ajour.songs.iter().find(|s| s.path == path) {
+        Some(Song {
+            hash: Some(hash), ..
+        }) => hash.clone(),
+        _ => return,
+    };
+
+    let mut data = ajour.user_data.get(&hash).cloned().unwrap_or_default();
+    f(&mut data);
+
+    if ajour.user_data.get(&hash).cloned().unwrap_or_default() == data {
+        return;
+    }
+
+    for song in ajour
+        .songs
+        .iter_mut()
+        .filter(|s| s.hash.as_ref() == Some(&hash))
+    {
+        song.user_data = data.clone();
+    }
+
+    ajour.user_data.set(&hash, data);
+
+    if let Err(error) = ajour
+        .user_data
+        .save()
+        .context(localized_string("error-save-user-data"))
+    {
+        log_error(&error);
+        ajour.error = Some(error);
+    }
+
+    apply_smart_filters(ajour);
+}
+
+/// Counts the songs each smart filter matches, and collects the songs of the
+/// selected one. A filter whose query can't be parsed matches no songs.
+fn apply_smart_filters(ajour: &mut Ajour) {
+    let filters = &ajour.config.songs.smart_filters;
+    let state = &mut ajour.smart_filters_state;
+
+    state.filters.resize_with(filters.len(), Default::default);
+    if state.selected.filter(|idx| *idx < filters.len()).is_none() {
+        state.selected = None;
+    }
+    state.selected_songs.clear();
+
+    for (idx, (filter, filter_state)) in filters.iter().zip(state.filters.iter_mut()).enumerate() {
+        let query = match SongQuery::parse(&filter.query) {
+            Ok(query) => query,
+            Err(error) => {
+                log::debug!("smart filter {:?}: {}", &filter.name, error);
+                filter_state.count = 0;
+                continue;
+            }
+        };
+
+        let songs = ajour.songs.iter().filter(|s| query.matches(s));
+
+        if state.selected == Some(idx) {
+            state.selected_songs = songs.map(|s| s.path.clone()).collect();
+            filter_state.count = state.selected_songs.len();
+        } else {
+            filter_state.count = songs.count();
+        }
+    }
+}
+
+/// Returns the commands importing `items` from the inbox, skipping the ones
+/// which are already being imported.
+fn import_inbox_items(ajour: &mut Ajour, items: Vec<PathBuf>) -> Vec<Command<Message>> {
+    let (inbox, library) = match (
+        ajour.config.songs.inbox.directory.clone(),
+        ajour.config.songs.inbox_library(),
+    ) {
+        (Some(inbox), Some(library)) => (inbox, library),
+        _ => return vec![],
+    };
+
+    let song_cache = ajour.song_cache.clone();
+    let pending = &mut ajour.song_settings_state.inbox_pending;
+
+    items
+        .into_iter()
+        .filter(|item| pending.insert(item.clone()))
+        .map(|item| {
+            log::debug!("importing {:?} from the inbox", &item);
+
+            Command::perform(
+                perform_import_inbox_item(song_cache.clone(), inbox.clone(), item, library.clone()),
+                Message::InboxImported,
+            )
+        })
+        .collect()
+}
+
+async fn perform_import_inbox_item(
+    song_cache: Option<Arc<Mutex<SongCache>>>,
+    inbox: PathBuf,
+    item: PathBuf,
+    library: PathBuf,
+) -> (PathBuf, Result<InboxImport, FilesystemError>) {
+    let result = import_inbox_item(song_cache, inbox, item.clone(), library).await;
+
+    (item, result)
+}
+
+/// Returns the commands parsing the songs and packs of a library root.
+fn read_song_root(ajour: &Ajour, root_dir: PathBuf) -> Vec<Command<Message>> {
+    vec![
+        Command::perform(
+            perform_read_song_directory(
+                ajour.song_cache.clone(),
+                root_dir.clone(),
+                ajour.config.songs.data_directory(),
+            ),
+            Message::ParsedSongs,
+        ),
+        Command::perform(perform_read_packs(root_dir), Message::ParsedPacks),
+    ]
+}
+
+async fn perform_read_packs(root_dir: PathBuf) -> (PathBuf, Result<Vec<Pack>, ParseError>) {
+    (root_dir.clone(), read_packs(root_dir).await)
+}
+
+/// Returns the commands loading the play history and the songs Clone Hero
+/// failed to load from its data directory.
+fn load_clone_hero_data(data_directory: Option<PathBuf>) -> Vec<Command<Message>> {
+    match data_directory {
+        Some(data_directory) => vec![
+            Command::perform(
+                load_score_data(data_directory.clone()),
+                Message::ScoreDataLoaded,
+            ),
+            Command::perform(load_bad_songs(data_directory), Message::BadSongsLoaded),
+        ],
+        None => vec![],
+    }
+}
+
 /// Downloads the newest version of the addon.
 /// This is for now only downloading from warcraftinterface.
 async fn perform_download_addon(
@@ -2802,6 +5482,56 @@ fn query_and_sort_catalog(ajour: &mut Ajour) {
     }
 }
 
+fn compare_songs(a: &Song, b: &Song, column_key: SongColumnKey) -> Ordering {
+    let title = |s: &Song| s.title().to_lowercase();
+    let lowercase = |s: Option<&String>| s.map(|s| s.to_lowercase());
+
+    let ordering = match column_key {
+        SongColumnKey::Title => Ordering::Equal,
+        SongColumnKey::Artist => a
+            .artist()
+            .map(str::to_lowercase)
+            .cmp(&b.artist().map(str::to_lowercase)),
+        SongColumnKey::Album => {
+            lowercase(a.metadata.album.as_ref()).cmp(&lowercase(b.metadata.album.as_ref()))
+        }
+        SongColumnKey::Genre => {
+            lowercase(a.metadata.genre.as_ref()).cmp(&lowercase(b.metadata.genre.as_ref()))
+        }
+        SongColumnKey::Year => a.metadata.year.cmp(&b.metadata.year),
+        SongColumnKey::Charter => a
+            .charter()
+            .map(str::to_lowercase)
+            .cmp(&b.charter().map(str::to_lowercase)),
+        SongColumnKey::Length => a.metadata.song_length.cmp(&b.metadata.song_length),
+        SongColumnKey::PlayCount => a.play_count().cmp(&b.play_count()),
+        SongColumnKey::BestScore => a.best_score().cmp(&b.best_score()),
+        SongColumnKey::Stars => a.stars().cmp(&b.stars()),
+        SongColumnKey::Accuracy => a
+            .accuracy()
+            .partial_cmp(&b.accuracy())
+            .unwrap_or(Ordering::Equal),
+        SongColumnKey::Tags => a
+            .user_data
+            .tags
+            .join(", ")
+            .to_lowercase()
+            .cmp(&b.user_data.tags.join(", ").to_lowercase()),
+        SongColumnKey::Rating => (a.user_data.rating, a.user_data.favorite)
+            .cmp(&(b.user_data.rating, b.user_data.favorite)),
+        SongColumnKey::FuzzyScore => a.fuzzy_score.cmp(&b.fuzzy_score),
+    };
+
+    ordering.then_with(|| title(a).cmp(&title(b)))
+}
+
+fn sort_songs(songs: &mut [Song], sort_direction: SortDirection, column_key: SongColumnKey) {
+    match sort_direction {
+        SortDirection::Asc => songs.sort_by(|a, b| compare_songs(a, b, column_key)),
+        SortDirection::Desc => songs.sort_by(|a, b| compare_songs(a, b, column_key).reverse()),
+    }
+}
+
 fn save_column_configs(ajour: &mut Ajour) {
     let _my_addons_columns: Vec<_> = ajour
         .header_state
@@ -12,8 +12,10 @@ mod process;
 #[cfg(target_os = "windows")]
 mod tray;
 
+use ajour_core::backup::BackupRetention;
 use ajour_core::config::Config;
 use ajour_core::fs::{PersistentData, CONFIG_DIR};
+use ajour_core::transfer::TransferMode;
 
 #[cfg(target_os = "linux")]
 use std::env;
@@ -66,25 +68,117 @@ pub fn main() {
         Some(command) => {
             // Process the command and exit
             if let Err(e) = match command {
+                cli::Command::Backup {
+                    action: Some(cli::BackupAction::Verify { archive }),
+                    ..
+                } => command::verify_backups(archive),
+                cli::Command::Backup {
+                    action: Some(cli::BackupAction::ListRemote),
+                    ..
+                } => command::list_remote(),
                 cli::Command::Backup {
                     backup_folder,
                     destination,
                     flavors,
                     compression_format,
                     level,
+                    if_due,
+                    encrypt,
+                    action: None,
                 } => command::backup(
                     backup_folder,
                     destination,
                     flavors,
                     compression_format,
                     level,
+                    if_due,
+                    encrypt,
+                ),
+                cli::Command::Restore {
+                    backup_directory,
+                    backup,
+                    browse,
+                    only,
+                    config_only,
+                    to,
+                    conflict,
+                    dry_run,
+                } => command::restore(
+                    backup_directory,
+                    backup,
+                    browse,
+                    only,
+                    config_only,
+                    to,
+                    conflict,
+                    dry_run,
+                ),
+                cli::Command::PruneBackups {
+                    backup_directory,
+                    keep_last,
+                    keep_daily,
+                    keep_weekly,
+                    max_size,
+                    dry_run,
+                } => command::prune_backups(
+                    backup_directory,
+                    BackupRetention {
+                        keep_last,
+                        keep_daily,
+                        keep_weekly,
+                        max_size_mb: max_size,
+                    },
+                    dry_run,
+                ),
+                cli::Command::ExportSnapshot {
+                    backup_directory,
+                    snapshot,
+                    output,
+                    compression_format,
+                    level,
+                } => command::snapshot_export(
+                    backup_directory,
+                    snapshot,
+                    output,
+                    compression_format,
+                    level,
                 ),
-                cli::Command::Update => command::update_both(),
+                cli::Command::Update { dry_run } => command::update_both(dry_run),
                 cli::Command::UpdateAddons => command::update_all_addons(),
                 cli::Command::Install { url, flavor } => command::install_from_source(url, flavor),
                 cli::Command::PathAdd { path, flavor } => command::path_add(path, flavor),
+                cli::Command::Move {
+                    from,
+                    to,
+                    songs,
+                    add_library,
+                } => command::transfer_songs(from, to, songs, TransferMode::Move, add_library),
+                cli::Command::Copy {
+                    from,
+                    to,
+                    songs,
+                    add_library,
+                } => command::transfer_songs(from, to, songs, TransferMode::Copy, add_library),
+                cli::Command::Export {
+                    format,
+                    directory,
+                    query,
+                    output,
+                } => command::export_songs(format, directory, query, output),
+                cli::Command::List { filter } => command::list_songs(
+                    filter.to_filter(),
+                    Default::default(),
+                    filter.directory,
+                    filter.json,
+                ),
+                cli::Command::Search { query, filter } => {
+                    command::list_songs(filter.to_filter(), query, filter.directory, filter.json)
+                }
+                cli::Command::Stats { directory, json } => command::print_stats(directory, json),
+                cli::Command::Watch { inbox, library } => command::watch_inbox(inbox, library),
             } {
                 log_error(&e);
+                std::process::exit(1);
             }
         }
         None => {
@@ -133,7 +227,11 @@ fn setup_logger(is_cli: bool, is_debug: bool) -> Result<()> {
         logger = logger.level_for("ajour_core", log::LevelFilter::Trace);
     }
 
-    if is_cli || is_debug {
+    // Commands print their output to stdout, so logs go to stderr to keep it
+    // parseable.
+    if is_cli {
+        logger = logger.chain(std::io::stderr());
+    } else if is_debug {
         logger = logger.chain(std::io::stdout());
     }
 